@@ -0,0 +1,37 @@
+-- Create treatment types catalogue
+CREATE TABLE IF NOT EXISTS treatment_types (
+    id TEXT PRIMARY KEY NOT NULL,
+    name_he TEXT NOT NULL,
+    name_en TEXT NOT NULL,
+    default_duration_minutes INTEGER NOT NULL,
+    default_price_cents INTEGER NOT NULL DEFAULT 0,
+    color TEXT NOT NULL DEFAULT '#3b82f6',
+    active BOOLEAN NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL
+);
+
+-- Rebuild treatments with the type link and actual session length.
+-- Migration 006 renamed patients away and back, which left the treatments
+-- foreign key pointing at the dropped patients_old table, so the table is
+-- recreated here with the reference restored.
+CREATE TABLE treatments_new (
+    id TEXT PRIMARY KEY NOT NULL,
+    patient_id TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    date TEXT NOT NULL,
+    treatment_type_id TEXT,
+    duration_minutes INTEGER,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE,
+    FOREIGN KEY (treatment_type_id) REFERENCES treatment_types(id) ON DELETE SET NULL
+);
+INSERT INTO treatments_new (id, patient_id, summary, date, created_at, updated_at)
+    SELECT id, patient_id, summary, date, created_at, updated_at FROM treatments;
+DROP TABLE treatments;
+ALTER TABLE treatments_new RENAME TO treatments;
+
+-- Recreate indexes
+CREATE INDEX IF NOT EXISTS idx_treatments_patient_id ON treatments(patient_id);
+CREATE INDEX IF NOT EXISTS idx_treatments_date ON treatments(date);
+CREATE INDEX IF NOT EXISTS idx_treatments_treatment_type_id ON treatments(treatment_type_id);
@@ -0,0 +1,110 @@
+-- Sequential document numbering. Numbers are only taken inside the
+-- transaction that issues the document, so each series stays gap-free.
+CREATE TABLE IF NOT EXISTS document_sequences (
+    series TEXT PRIMARY KEY NOT NULL,
+    next_number INTEGER NOT NULL
+);
+
+INSERT OR IGNORE INTO document_sequences (series, next_number) VALUES ('invoice', 1);
+INSERT OR IGNORE INTO document_sequences (series, next_number) VALUES ('credit_note', 1);
+INSERT OR IGNORE INTO document_sequences (series, next_number) VALUES ('receipt', 1);
+
+-- Create invoices table (credit notes are invoices of kind 'credit_note')
+CREATE TABLE IF NOT EXISTS invoices (
+    id TEXT PRIMARY KEY NOT NULL,
+    kind TEXT NOT NULL DEFAULT 'invoice',
+    number INTEGER,
+    patient_id TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'draft',
+    credited_invoice_id TEXT,
+    currency TEXT NOT NULL DEFAULT 'ILS',
+    vat_rate REAL NOT NULL DEFAULT 0,
+    subtotal_cents INTEGER NOT NULL DEFAULT 0,
+    vat_cents INTEGER NOT NULL DEFAULT 0,
+    total_cents INTEGER NOT NULL DEFAULT 0,
+    notes TEXT NOT NULL DEFAULT '',
+    due_date TEXT,
+    created_at TEXT NOT NULL,
+    issued_at TEXT,
+    FOREIGN KEY (patient_id) REFERENCES patients(id),
+    FOREIGN KEY (credited_invoice_id) REFERENCES invoices(id),
+    UNIQUE (kind, number)
+);
+
+CREATE INDEX IF NOT EXISTS idx_invoices_patient_id ON invoices(patient_id);
+CREATE INDEX IF NOT EXISTS idx_invoices_status ON invoices(status);
+CREATE INDEX IF NOT EXISTS idx_invoices_credited_invoice_id ON invoices(credited_invoice_id);
+
+-- Create invoice lines table
+CREATE TABLE IF NOT EXISTS invoice_lines (
+    id TEXT PRIMARY KEY NOT NULL,
+    invoice_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    treatment_id TEXT,
+    description TEXT NOT NULL,
+    quantity INTEGER NOT NULL DEFAULT 1,
+    unit_price_cents INTEGER NOT NULL,
+    line_total_cents INTEGER NOT NULL,
+    FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE CASCADE,
+    FOREIGN KEY (treatment_id) REFERENCES treatments(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_invoice_lines_invoice_id ON invoice_lines(invoice_id);
+CREATE INDEX IF NOT EXISTS idx_invoice_lines_treatment_id ON invoice_lines(treatment_id);
+
+-- Create payments table; every payment gets its own receipt number
+CREATE TABLE IF NOT EXISTS payments (
+    id TEXT PRIMARY KEY NOT NULL,
+    invoice_id TEXT NOT NULL,
+    patient_id TEXT NOT NULL,
+    receipt_number INTEGER NOT NULL UNIQUE,
+    amount_cents INTEGER NOT NULL,
+    method TEXT NOT NULL,
+    reference TEXT NOT NULL DEFAULT '',
+    paid_at TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (invoice_id) REFERENCES invoices(id),
+    FOREIGN KEY (patient_id) REFERENCES patients(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_payments_invoice_id ON payments(invoice_id);
+CREATE INDEX IF NOT EXISTS idx_payments_patient_id ON payments(patient_id);
+
+-- Issued documents are immutable: only the status may move forward
+CREATE TRIGGER IF NOT EXISTS trg_invoices_immutable
+BEFORE UPDATE OF kind, number, patient_id, credited_invoice_id, currency, vat_rate,
+                 subtotal_cents, vat_cents, total_cents, notes, due_date, issued_at
+ON invoices
+WHEN OLD.status != 'draft'
+BEGIN
+    SELECT RAISE(ABORT, 'issued invoices are immutable');
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_invoices_no_delete
+BEFORE DELETE ON invoices
+WHEN OLD.status != 'draft'
+BEGIN
+    SELECT RAISE(ABORT, 'issued invoices cannot be deleted');
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_invoice_lines_immutable_insert
+BEFORE INSERT ON invoice_lines
+WHEN (SELECT status FROM invoices WHERE id = NEW.invoice_id) != 'draft'
+BEGIN
+    SELECT RAISE(ABORT, 'issued invoices are immutable');
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_invoice_lines_immutable_update
+BEFORE UPDATE OF invoice_id, position, description, quantity, unit_price_cents, line_total_cents
+ON invoice_lines
+WHEN (SELECT status FROM invoices WHERE id = OLD.invoice_id) != 'draft'
+BEGIN
+    SELECT RAISE(ABORT, 'issued invoices are immutable');
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_invoice_lines_immutable_delete
+BEFORE DELETE ON invoice_lines
+WHEN (SELECT status FROM invoices WHERE id = OLD.invoice_id) != 'draft'
+BEGIN
+    SELECT RAISE(ABORT, 'issued invoices are immutable');
+END;
@@ -0,0 +1,44 @@
+-- Create prepaid session packages (punch cards)
+CREATE TABLE IF NOT EXISTS session_packages (
+    id TEXT PRIMARY KEY NOT NULL,
+    patient_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    sessions_purchased INTEGER NOT NULL,
+    sessions_used INTEGER NOT NULL DEFAULT 0,
+    price_cents INTEGER NOT NULL DEFAULT 0,
+    purchased_at TEXT NOT NULL,
+    expires_at TEXT,
+    notes TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_session_packages_patient_id ON session_packages(patient_id);
+
+-- Link treatments to the package they were drawn from
+ALTER TABLE treatments ADD COLUMN package_id TEXT REFERENCES session_packages(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_treatments_package_id ON treatments(package_id);
+
+-- Keep the used-session counter in step with linked treatments
+CREATE TRIGGER IF NOT EXISTS trg_treatments_package_insert
+AFTER INSERT ON treatments
+WHEN NEW.package_id IS NOT NULL
+BEGIN
+    UPDATE session_packages SET sessions_used = sessions_used + 1 WHERE id = NEW.package_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_treatments_package_delete
+AFTER DELETE ON treatments
+WHEN OLD.package_id IS NOT NULL
+BEGIN
+    UPDATE session_packages SET sessions_used = MAX(sessions_used - 1, 0) WHERE id = OLD.package_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_treatments_package_update
+AFTER UPDATE OF package_id ON treatments
+WHEN OLD.package_id IS NOT NEW.package_id
+BEGIN
+    UPDATE session_packages SET sessions_used = MAX(sessions_used - 1, 0) WHERE id = OLD.package_id;
+    UPDATE session_packages SET sessions_used = sessions_used + 1 WHERE id = NEW.package_id;
+END;
@@ -0,0 +1,5 @@
+-- Record which user (therapist) logged each treatment, for workload reporting.
+-- Treatments created before this migration stay unassigned.
+ALTER TABLE treatments ADD COLUMN therapist_id TEXT REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_treatments_therapist_id ON treatments(therapist_id);
@@ -0,0 +1,11 @@
+-- User-editable export layouts, one body per template name and output format
+CREATE TABLE IF NOT EXISTS export_templates (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    format TEXT NOT NULL CHECK (format IN ('rtf', 'pdf', 'docx')),
+    description TEXT NOT NULL DEFAULT '',
+    body TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE (name, format)
+);
@@ -0,0 +1,2 @@
+-- Preferred language for API messages; NULL follows the client's Accept-Language
+ALTER TABLE users ADD COLUMN language TEXT;
@@ -0,0 +1,5 @@
+-- Normalized E.164 form of phone_number (e.g. +972501234567) for search and duplicate checks.
+-- Existing rows are filled in at startup, since parsing depends on DEFAULT_PHONE_COUNTRY.
+ALTER TABLE patients ADD COLUMN phone_e164 TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_patients_phone_e164 ON patients(phone_e164);
@@ -0,0 +1,20 @@
+-- Duplicate records folded into another patient. A duplicate with issued invoices
+-- cannot be deleted, so it is kept inactive and points at the surviving record.
+ALTER TABLE patients ADD COLUMN merged_into TEXT REFERENCES patients(id);
+
+-- Audit trail of merges; merged_patient is a JSON snapshot of the duplicate before the merge
+CREATE TABLE IF NOT EXISTS patient_merges (
+    id TEXT PRIMARY KEY NOT NULL,
+    surviving_patient_id TEXT NOT NULL,
+    merged_patient_id TEXT NOT NULL,
+    merged_patient TEXT NOT NULL,
+    treatments_moved INTEGER NOT NULL,
+    packages_moved INTEGER NOT NULL,
+    invoices_moved INTEGER NOT NULL,
+    merged_patient_retained BOOLEAN NOT NULL,
+    merged_by TEXT,
+    merged_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_patient_merges_surviving ON patient_merges(surviving_patient_id);
+CREATE INDEX IF NOT EXISTS idx_patient_merges_merged ON patient_merges(merged_patient_id);
@@ -0,0 +1,10 @@
+-- Demographics and identifiers; all optional
+ALTER TABLE patients ADD COLUMN date_of_birth TEXT;
+ALTER TABLE patients ADD COLUMN gender TEXT;
+ALTER TABLE patients ADD COLUMN national_id TEXT;
+ALTER TABLE patients ADD COLUMN address TEXT;
+ALTER TABLE patients ADD COLUMN preferred_language TEXT;
+ALTER TABLE patients ADD COLUMN referral_source TEXT;
+
+-- Israeli ID numbers, stored as 9 digits; one patient per number
+CREATE UNIQUE INDEX IF NOT EXISTS idx_patients_national_id ON patients(national_id) WHERE national_id IS NOT NULL;
@@ -0,0 +1,23 @@
+-- People related to a patient: parents and guardians of minors, spouses, emergency contacts
+CREATE TABLE IF NOT EXISTS patient_contacts (
+    id TEXT PRIMARY KEY NOT NULL,
+    patient_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    relationship TEXT NOT NULL CHECK (relationship IN ('parent', 'guardian', 'spouse', 'emergency')),
+    phone_number TEXT NOT NULL DEFAULT '',
+    phone_e164 TEXT,
+    email TEXT,
+    notes TEXT NOT NULL DEFAULT '',
+    -- Clinical information may be shared with this contact
+    may_receive_information BOOLEAN NOT NULL DEFAULT 0,
+    -- Legally entitled to consent on the patient's behalf
+    legal_guardian BOOLEAN NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_patient_contacts_patient_id ON patient_contacts(patient_id);
+
+-- Rows moved per table for patient tables added after merges were introduced, as a JSON object
+ALTER TABLE patient_merges ADD COLUMN records_moved TEXT NOT NULL DEFAULT '{}';
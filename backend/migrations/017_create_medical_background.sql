@@ -0,0 +1,48 @@
+-- Medical background of a patient; dates are calendar days (YYYY-MM-DD), an open end_date means ongoing
+CREATE TABLE IF NOT EXISTS patient_diagnoses (
+    id TEXT PRIMARY KEY NOT NULL,
+    patient_id TEXT NOT NULL,
+    -- ICD-10 code in canonical form, e.g. M54.5
+    icd10_code TEXT,
+    description TEXT NOT NULL,
+    start_date TEXT,
+    end_date TEXT,
+    notes TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_patient_diagnoses_patient_id ON patient_diagnoses(patient_id);
+
+CREATE TABLE IF NOT EXISTS patient_allergies (
+    id TEXT PRIMARY KEY NOT NULL,
+    patient_id TEXT NOT NULL,
+    allergen TEXT NOT NULL,
+    reaction TEXT NOT NULL DEFAULT '',
+    severity TEXT NOT NULL CHECK (severity IN ('mild', 'moderate', 'severe', 'life_threatening')),
+    start_date TEXT,
+    end_date TEXT,
+    notes TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_patient_allergies_patient_id ON patient_allergies(patient_id);
+
+CREATE TABLE IF NOT EXISTS patient_medications (
+    id TEXT PRIMARY KEY NOT NULL,
+    patient_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    dosage TEXT NOT NULL DEFAULT '',
+    frequency TEXT NOT NULL DEFAULT '',
+    start_date TEXT,
+    end_date TEXT,
+    notes TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_patient_medications_patient_id ON patient_medications(patient_id);
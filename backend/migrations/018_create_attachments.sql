@@ -0,0 +1,22 @@
+-- Files attached to a patient, optionally to one of their treatments.
+-- Content lives in the blob store under its SHA-256, shared by attachments with identical content.
+CREATE TABLE IF NOT EXISTS attachments (
+    id TEXT PRIMARY KEY NOT NULL,
+    patient_id TEXT NOT NULL,
+    treatment_id TEXT,
+    file_name TEXT NOT NULL,
+    -- Sniffed from the content, not taken from the upload
+    content_type TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    uploaded_by TEXT,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE,
+    -- Deleting a treatment keeps its files on the patient
+    FOREIGN KEY (treatment_id) REFERENCES treatments(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_attachments_patient_id ON attachments(patient_id);
+CREATE INDEX IF NOT EXISTS idx_attachments_treatment_id ON attachments(treatment_id);
+CREATE INDEX IF NOT EXISTS idx_attachments_sha256 ON attachments(sha256);
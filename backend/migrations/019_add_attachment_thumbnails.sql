@@ -0,0 +1,3 @@
+-- Whether a thumbnail could be made of the attachment. Thumbnails are stored in the
+-- blob store next to the content; attachments uploaded before get theirs on first request.
+ALTER TABLE attachments ADD COLUMN has_thumbnail INTEGER NOT NULL DEFAULT 0;
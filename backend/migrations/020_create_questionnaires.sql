@@ -0,0 +1,45 @@
+-- Admin-defined questionnaires; questions are a JSON array (see models/questionnaire.rs)
+CREATE TABLE IF NOT EXISTS questionnaires (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    questions TEXT NOT NULL,
+    scoring TEXT NOT NULL DEFAULT 'none' CHECK (scoring IN ('none', 'sum', 'mean', 'percent')),
+    higher_is_better BOOLEAN NOT NULL DEFAULT 0,
+    active BOOLEAN NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- A patient's answers, a JSON object keyed by question ID, with the score at the time
+CREATE TABLE IF NOT EXISTS questionnaire_responses (
+    id TEXT PRIMARY KEY NOT NULL,
+    patient_id TEXT NOT NULL,
+    questionnaire_id TEXT NOT NULL,
+    treatment_id TEXT,
+    answers TEXT NOT NULL,
+    score REAL,
+    notes TEXT NOT NULL DEFAULT '',
+    completed_at TEXT NOT NULL,
+    recorded_by TEXT,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE,
+    -- Questionnaires with responses are deactivated rather than deleted
+    FOREIGN KEY (questionnaire_id) REFERENCES questionnaires(id),
+    FOREIGN KEY (treatment_id) REFERENCES treatments(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_questionnaire_responses_patient_id ON questionnaire_responses(patient_id, completed_at);
+CREATE INDEX IF NOT EXISTS idx_questionnaire_responses_questionnaire_id ON questionnaire_responses(questionnaire_id);
+
+-- Standard pain and outcome measures, ready to use
+INSERT INTO questionnaires (id, name, description, questions, scoring, higher_is_better, created_at, updated_at) VALUES
+    ('3f2b9c1e-6a4d-4e8f-9b21-0c5d7e8f1a01', 'Pain intensity (NRS)', 'Numeric Rating Scale, 0–10',
+     '[{"id": "pain", "text": "Rate your pain right now, from 0 (no pain) to 10 (worst pain imaginable)", "kind": "scale", "required": true, "scored": true, "scale_min": 0, "scale_max": 10, "scale_min_label": "No pain", "scale_max_label": "Worst pain imaginable"}]',
+     'sum', 0, '2026-01-01T00:00:00+00:00', '2026-01-01T00:00:00+00:00'),
+    ('3f2b9c1e-6a4d-4e8f-9b21-0c5d7e8f1a02', 'Pain intensity (VAS)', 'Visual Analogue Scale, 0–100 mm',
+     '[{"id": "pain", "text": "Mark your pain on the line, from 0 mm (no pain) to 100 mm (worst pain imaginable)", "kind": "scale", "required": true, "scored": true, "scale_min": 0, "scale_max": 100, "scale_min_label": "No pain", "scale_max_label": "Worst pain imaginable"}]',
+     'sum', 0, '2026-01-01T00:00:00+00:00', '2026-01-01T00:00:00+00:00'),
+    ('3f2b9c1e-6a4d-4e8f-9b21-0c5d7e8f1a03', 'Patient-Specific Functional Scale (PSFS)', 'Up to three activities the patient chooses, each rated 0–10; the score is the mean rating',
+     '[{"id": "activity_1", "text": "Activity 1 you are unable to do or have difficulty with because of your problem", "kind": "text", "required": true}, {"id": "rating_1", "text": "How able are you to perform activity 1 today?", "kind": "scale", "required": true, "scored": true, "scale_min": 0, "scale_max": 10, "scale_min_label": "Unable to perform", "scale_max_label": "Able to perform at the level before the problem"}, {"id": "activity_2", "text": "Activity 2 you are unable to do or have difficulty with because of your problem", "kind": "text", "required": false}, {"id": "rating_2", "text": "How able are you to perform activity 2 today?", "kind": "scale", "required": false, "scored": true, "scale_min": 0, "scale_max": 10, "scale_min_label": "Unable to perform", "scale_max_label": "Able to perform at the level before the problem"}, {"id": "activity_3", "text": "Activity 3 you are unable to do or have difficulty with because of your problem", "kind": "text", "required": false}, {"id": "rating_3", "text": "How able are you to perform activity 3 today?", "kind": "scale", "required": false, "scored": true, "scale_min": 0, "scale_max": 10, "scale_min_label": "Unable to perform", "scale_max_label": "Able to perform at the level before the problem"}]',
+     'mean', 1, '2026-01-01T00:00:00+00:00', '2026-01-01T00:00:00+00:00');
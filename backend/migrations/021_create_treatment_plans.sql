@@ -0,0 +1,43 @@
+-- Treatment plans set by the therapist; dates are calendar days (YYYY-MM-DD)
+CREATE TABLE IF NOT EXISTS treatment_plans (
+    id TEXT PRIMARY KEY NOT NULL,
+    patient_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    planned_sessions INTEGER,
+    -- Free text, e.g. "twice a week"
+    frequency TEXT NOT NULL DEFAULT '',
+    start_date TEXT,
+    target_end_date TEXT,
+    status TEXT NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'on_hold', 'completed', 'discontinued')),
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_treatment_plans_patient_id ON treatment_plans(patient_id);
+
+-- Goals of a plan, kept in the order they were set
+CREATE TABLE IF NOT EXISTS treatment_plan_goals (
+    id TEXT PRIMARY KEY NOT NULL,
+    plan_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    target_date TEXT,
+    status TEXT NOT NULL DEFAULT 'not_started'
+        CHECK (status IN ('not_started', 'in_progress', 'achieved', 'discontinued')),
+    -- Percent of the way to the goal, 0-100
+    progress INTEGER NOT NULL DEFAULT 0,
+    achieved_date TEXT,
+    notes TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (plan_id) REFERENCES treatment_plans(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_treatment_plan_goals_plan_id ON treatment_plan_goals(plan_id);
+
+-- Link treatments to the plan they were given under
+ALTER TABLE treatments ADD COLUMN plan_id TEXT REFERENCES treatment_plans(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_treatments_plan_id ON treatments(plan_id);
@@ -0,0 +1,69 @@
+-- Consent form templates; `body` and `version` are those of the current text
+CREATE TABLE IF NOT EXISTS consent_forms (
+    id TEXT PRIMARY KEY NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('treatment', 'privacy', 'other')),
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    version INTEGER NOT NULL DEFAULT 1,
+    -- Patients must have signed the current version before they are treated
+    required INTEGER NOT NULL DEFAULT 1,
+    active INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- Every text a form has had, so a signature can always be traced to the wording signed
+CREATE TABLE IF NOT EXISTS consent_form_versions (
+    form_id TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_by TEXT,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (form_id, version),
+    FOREIGN KEY (form_id) REFERENCES consent_forms(id) ON DELETE CASCADE
+);
+
+-- Signed consents, with the form as rendered for the patient at signing
+CREATE TABLE IF NOT EXISTS consent_signatures (
+    id TEXT PRIMARY KEY NOT NULL,
+    patient_id TEXT NOT NULL,
+    form_id TEXT NOT NULL,
+    form_version INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    title TEXT NOT NULL,
+    rendered_body TEXT NOT NULL,
+    rendered_sha256 TEXT NOT NULL,
+    signer_name TEXT NOT NULL,
+    -- Set when a contact, e.g. a parent, signed on the patient's behalf
+    signer_contact_id TEXT,
+    signature_image BLOB NOT NULL,
+    signature_content_type TEXT NOT NULL,
+    signed_at TEXT NOT NULL,
+    ip_address TEXT,
+    user_agent TEXT,
+    recorded_by TEXT,
+    revoked_at TEXT,
+    revoke_reason TEXT NOT NULL DEFAULT '',
+    FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE,
+    FOREIGN KEY (form_id, form_version) REFERENCES consent_form_versions(form_id, version)
+);
+
+CREATE INDEX IF NOT EXISTS idx_consent_signatures_patient_id ON consent_signatures(patient_id);
+CREATE INDEX IF NOT EXISTS idx_consent_signatures_form_id ON consent_signatures(form_id);
+
+-- What was signed cannot change; only revoking, and moving to another patient on merge, may update a signature
+CREATE TRIGGER IF NOT EXISTS consent_signatures_immutable
+BEFORE UPDATE OF id, form_id, form_version, kind, title, rendered_body, rendered_sha256, signer_name,
+    signer_contact_id, signature_image, signature_content_type, signed_at, ip_address, user_agent, recorded_by
+ON consent_signatures
+BEGIN
+    SELECT RAISE(ABORT, 'signed consents cannot be changed');
+END;
+
+CREATE TRIGGER IF NOT EXISTS consent_signatures_revoke_once
+BEFORE UPDATE OF revoked_at ON consent_signatures
+WHEN OLD.revoked_at IS NOT NULL
+BEGIN
+    SELECT RAISE(ABORT, 'consent has already been revoked');
+END;
@@ -0,0 +1,3 @@
+-- Body chart annotations of a treatment, a JSON array of
+-- {region, side, view, x, y, intensity, note}; see models/body_chart.rs
+ALTER TABLE treatments ADD COLUMN annotations TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,13 @@
+-- Client address reported by a trusted proxy, kept apart from the connecting address in ip_address
+ALTER TABLE consent_signatures ADD COLUMN forwarded_for TEXT;
+
+DROP TRIGGER IF EXISTS consent_signatures_immutable;
+
+CREATE TRIGGER IF NOT EXISTS consent_signatures_immutable
+BEFORE UPDATE OF id, form_id, form_version, kind, title, rendered_body, rendered_sha256, signer_name,
+    signer_contact_id, signature_image, signature_content_type, signed_at, ip_address, forwarded_for, user_agent,
+    recorded_by
+ON consent_signatures
+BEGIN
+    SELECT RAISE(ABORT, 'signed consents cannot be changed');
+END;
@@ -0,0 +1,16 @@
+-- Refuse to draw a session from a package that has none left, so concurrent bookings cannot overdraw it
+CREATE TRIGGER IF NOT EXISTS trg_treatments_package_limit_insert
+BEFORE INSERT ON treatments
+WHEN NEW.package_id IS NOT NULL
+    AND (SELECT sessions_used >= sessions_purchased FROM session_packages WHERE id = NEW.package_id)
+BEGIN
+    SELECT RAISE(ABORT, 'session package exhausted');
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_treatments_package_limit_update
+BEFORE UPDATE OF package_id ON treatments
+WHEN NEW.package_id IS NOT NULL AND OLD.package_id IS NOT NEW.package_id
+    AND (SELECT sessions_used >= sessions_purchased FROM session_packages WHERE id = NEW.package_id)
+BEGIN
+    SELECT RAISE(ABORT, 'session package exhausted');
+END;
@@ -11,7 +11,7 @@ impl JwtUtils {
         env::var("JWT_SECRET").unwrap_or_else(|_| "your-secret-key".to_string())
     }
 
-    pub fn create_token(user_id: &str, email: &str) -> Result<String> {
+    pub fn create_token(user_id: &str, email: &str, language: Option<&str>) -> Result<String> {
         let now = Utc::now();
         let exp = (now + Duration::hours(24)).timestamp() as usize;
         let iat = now.timestamp() as usize;
@@ -21,6 +21,7 @@ impl JwtUtils {
             email: email.to_string(),
             exp,
             iat,
+            lang: language.map(str::to_string),
         };
 
         let token = encode(
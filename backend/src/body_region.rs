@@ -0,0 +1,82 @@
+use serde::Serialize;
+
+use crate::i18n::Localizer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegionGroup {
+    HeadNeck,
+    Trunk,
+    UpperLimb,
+    LowerLimb,
+}
+
+/// An anatomical region body chart annotations are filed under
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BodyRegion {
+    pub code: &'static str,
+    pub group: RegionGroup,
+    /// Found on both sides of the body, so annotations should say which
+    pub paired: bool,
+    /// Locale key of the region's name
+    #[serde(skip)]
+    key: &'static str,
+}
+
+macro_rules! region {
+    ($code:literal, $group:ident, $paired:literal) => {
+        BodyRegion { code: $code, group: RegionGroup::$group, paired: $paired, key: concat!("body_regions.", $code) }
+    };
+}
+
+/// The fixed list of regions, head to toe; names are in the `body_regions` section of the locale files
+pub const REGIONS: &[BodyRegion] = &[
+    region!("head", HeadNeck, false),
+    region!("face", HeadNeck, false),
+    region!("jaw", HeadNeck, true),
+    region!("neck", HeadNeck, false),
+    region!("cervical_spine", HeadNeck, false),
+    region!("shoulder", UpperLimb, true),
+    region!("upper_arm", UpperLimb, true),
+    region!("elbow", UpperLimb, true),
+    region!("forearm", UpperLimb, true),
+    region!("wrist", UpperLimb, true),
+    region!("hand", UpperLimb, true),
+    region!("fingers", UpperLimb, true),
+    region!("scapula", Trunk, true),
+    region!("thoracic_spine", Trunk, false),
+    region!("chest", Trunk, false),
+    region!("ribs", Trunk, true),
+    region!("abdomen", Trunk, false),
+    region!("lumbar_spine", Trunk, false),
+    region!("sacrum", Trunk, false),
+    region!("sacroiliac_joint", Trunk, true),
+    region!("coccyx", Trunk, false),
+    region!("pelvis", Trunk, false),
+    region!("groin", Trunk, true),
+    region!("buttock", Trunk, true),
+    region!("hip", LowerLimb, true),
+    region!("thigh", LowerLimb, true),
+    region!("knee", LowerLimb, true),
+    region!("shin", LowerLimb, true),
+    region!("calf", LowerLimb, true),
+    region!("ankle", LowerLimb, true),
+    region!("heel", LowerLimb, true),
+    region!("foot", LowerLimb, true),
+    region!("toes", LowerLimb, true),
+];
+
+pub fn lookup(code: &str) -> Option<&'static BodyRegion> {
+    REGIONS.iter().find(|region| region.code == code)
+}
+
+impl BodyRegion {
+    pub fn name<'a>(&self, localizer: &'a Localizer) -> &'a str {
+        localizer.text(self.key)
+    }
+}
+
+/// Name of a region code in the localizer's language; codes no longer on the list are shown as they are
+pub fn region_name<'a>(code: &'a str, localizer: &'a Localizer) -> &'a str {
+    lookup(code).map_or(code, |region| region.name(localizer))
+}
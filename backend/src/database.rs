@@ -1,9 +1,18 @@
-use sqlx::{SqlitePool, Row};
+use sqlx::{SqlitePool, Row, Sqlite, Transaction, sqlite::SqliteRow};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
-use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
 
-use crate::models::{Patient, Treatment};
+use crate::models::{
+    Gender, Patient, PatientContact, ContactRelationship, PatientMerge,
+    MedicalBackground, Diagnosis, Allergy, AllergySeverity, Medication, Attachment,
+    Questionnaire, QuestionnaireResponse, ScoringMethod, ConsentForm, ConsentFormVersion, ConsentKind, ConsentSignature, TreatmentPlan, PlanGoal, PlanStatus, GoalStatus, Treatment, TreatmentOutcome, TreatmentType, TreatmentTypeBreakdown,
+    Invoice, InvoiceKind, InvoiceLine, InvoiceStatus, IssueOutcome, Payment, PaymentOutcome, PaymentMethod, SessionPackage,
+    ReportGrouping, SessionsReportRow, RevenueReportRow, PatientActivityRow, TherapistWorkloadRow, ReportSummary,
+    ExportFormat, ExportTemplate,
+};
+use crate::phone;
 
 #[derive(Clone)]
 pub struct Database {
@@ -16,8 +25,10 @@ impl Database {
         
         // Run migrations
         sqlx::migrate!("./migrations").run(&pool).await?;
-        
-        Ok(Database { pool })
+
+        let db = Database { pool };
+        db.normalize_patient_phones().await?;
+        Ok(db)
     }
 
     pub fn pool(&self) -> &SqlitePool {
@@ -27,101 +38,79 @@ impl Database {
     pub async fn create_patient(&self, patient: &Patient) -> Result<()> {
         sqlx::query(
             r#"
-            INSERT INTO patients (id, name, email, phone_number, description, date, active)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO patients (id, name, email, phone_number, phone_e164, description, date, active,
+                                  date_of_birth, gender, national_id, address, preferred_language, referral_source)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#
         )
         .bind(patient.id.to_string())
         .bind(&patient.name)
         .bind(&patient.email)
         .bind(&patient.phone_number)
+        .bind(&patient.phone_e164)
         .bind(&patient.description)
         .bind(patient.date.to_rfc3339())
         .bind(patient.active)
+        .bind(patient.date_of_birth.map(|d| d.to_string()))
+        .bind(patient.gender.map(|g| g.as_str()))
+        .bind(&patient.national_id)
+        .bind(&patient.address)
+        .bind(&patient.preferred_language)
+        .bind(&patient.referral_source)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
-    pub async fn get_all_patients(&self) -> Result<Vec<Patient>> {
-        let rows = sqlx::query(
-            "SELECT id, name, email, phone_number, description, date, active FROM patients ORDER BY date DESC"
-        )
+    /// All patients, or only those whose number matches `phone_e164`; merged records are left out
+    pub async fn get_all_patients(&self, phone_e164: Option<&str>) -> Result<Vec<Patient>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {PATIENT_COLUMNS} FROM patients
+             WHERE merged_into IS NULL
+               AND (?1 IS NULL OR phone_e164 = ?1)
+             ORDER BY date DESC"
+        ))
+        .bind(phone_e164)
         .fetch_all(&self.pool)
         .await?;
 
-        let mut patients = Vec::new();
-        for row in rows {
-            let id_str: String = row.get("id");
-            let name: String = row.get("name");
-            let email: Option<String> = row.try_get("email").ok();
-            let phone_number: String = row.get("phone_number");
-            let description: String = row.get("description");
-            let date_str: String = row.get("date");
-            let active: bool = row.get("active");
-
-            let patient = Patient {
-                id: Uuid::parse_str(&id_str)?,
-                name,
-                email,
-                phone_number,
-                description,
-                date: DateTime::parse_from_rfc3339(&date_str)?.with_timezone(&Utc),
-                active,
-            };
-            patients.push(patient);
-        }
-
-        Ok(patients)
+        rows.iter().map(patient_from_row).collect()
     }
 
     pub async fn get_patient_by_id(&self, id: Uuid) -> Result<Option<Patient>> {
-        let row = sqlx::query(
-            "SELECT id, name, email, phone_number, description, date, active FROM patients WHERE id = ?"
-        )
+        let row = sqlx::query(&format!(
+            "SELECT {PATIENT_COLUMNS} FROM patients WHERE id = ?"
+        ))
         .bind(id.to_string())
         .fetch_optional(&self.pool)
         .await?;
 
-        if let Some(row) = row {
-            let id_str: String = row.get("id");
-            let name: String = row.get("name");
-            let email: String = row.get("email");
-            let phone_number: String = row.get("phone_number");
-            let description: String = row.get("description");
-            let date_str: String = row.get("date");
-            let active: bool = row.get("active");
-
-            let patient = Patient {
-                id: Uuid::parse_str(&id_str)?,
-                name,
-                email: Some(email),
-                phone_number,
-                description,
-                date: DateTime::parse_from_rfc3339(&date_str)?.with_timezone(&Utc),
-                active,
-            };
-            Ok(Some(patient))
-        } else {
-            Ok(None)
-        }
+        row.as_ref().map(patient_from_row).transpose()
     }
 
     pub async fn update_patient(&self, id: Uuid, patient: &Patient) -> Result<bool> {
         let result = sqlx::query(
             r#"
             UPDATE patients 
-            SET name = ?, email = ?, phone_number = ?, description = ?, date = ?, active = ?
+            SET name = ?, email = ?, phone_number = ?, phone_e164 = ?, description = ?, date = ?, active = ?,
+                date_of_birth = ?, gender = ?, national_id = ?, address = ?, preferred_language = ?, referral_source = ?
             WHERE id = ?
             "#
         )
         .bind(&patient.name)
         .bind(&patient.email)
         .bind(&patient.phone_number)
+        .bind(&patient.phone_e164)
         .bind(&patient.description)
         .bind(patient.date.to_rfc3339())
         .bind(patient.active)
+        .bind(patient.date_of_birth.map(|d| d.to_string()))
+        .bind(patient.gender.map(|g| g.as_str()))
+        .bind(&patient.national_id)
+        .bind(&patient.address)
+        .bind(&patient.preferred_language)
+        .bind(&patient.referral_source)
         .bind(id.to_string())
         .execute(&self.pool)
         .await?;
@@ -129,124 +118,581 @@ impl Database {
         Ok(result.rows_affected() > 0)
     }
 
+    pub async fn find_patient_by_national_id(&self, national_id: &str) -> Result<Option<Uuid>> {
+        let id: Option<String> = sqlx::query_scalar("SELECT id FROM patients WHERE national_id = ?")
+            .bind(national_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(id.map(|id| Uuid::parse_str(&id)).transpose()?)
+    }
+
+    /// Fill in `phone_e164` for rows written before the column existed.
+    ///
+    /// Numbers that still cannot be parsed keep `NULL` and are retried on the next start.
+    async fn normalize_patient_phones(&self) -> Result<()> {
+        let rows = sqlx::query("SELECT id, phone_number FROM patients WHERE phone_e164 IS NULL")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut tx = self.pool.begin().await?;
+        for row in rows {
+            let phone_number: String = row.get("phone_number");
+            if let Some(e164) = phone::normalize(&phone_number) {
+                sqlx::query("UPDATE patients SET phone_e164 = ? WHERE id = ?")
+                    .bind(e164)
+                    .bind(row.get::<String, _>("id"))
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
     pub async fn delete_patient(&self, id: Uuid) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        // Draft invoices have no legal standing and go with the patient
+        sqlx::query(
+            "DELETE FROM invoices WHERE patient_id = ? AND status = 'draft'"
+        )
+        .bind(id.to_string())
+        .execute(&mut *tx)
+        .await?;
+
         let result = sqlx::query(
             "DELETE FROM patients WHERE id = ?"
         )
         .bind(id.to_string())
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        tx.commit().await?;
         Ok(result.rows_affected() > 0)
     }
 
-    // Treatment methods
-    pub async fn create_treatment(&self, treatment: &Treatment) -> Result<()> {
+    /// Fold `duplicate` into `survivor` in one transaction and record the merge.
+    ///
+    /// Treatments, session packages, draft invoices and the records in
+    /// `PATIENT_RECORD_TABLES` move to the survivor, which
+    /// is saved as given (see `Patient::absorb`). Issued invoices and their payments
+    /// cannot change patient; if there are any, the duplicate is kept inactive with
+    /// `merged_into` set, otherwise it is deleted.
+    pub async fn merge_patients(&self, survivor: &Patient, duplicate: &Patient, merged_by: Option<String>) -> Result<PatientMerge> {
+        let mut tx = self.pool.begin().await?;
+        let survivor_id = survivor.id.to_string();
+        let duplicate_id = duplicate.id.to_string();
+
+        let treatments_moved = sqlx::query("UPDATE treatments SET patient_id = ? WHERE patient_id = ?")
+            .bind(&survivor_id)
+            .bind(&duplicate_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        let packages_moved = sqlx::query("UPDATE session_packages SET patient_id = ? WHERE patient_id = ?")
+            .bind(&survivor_id)
+            .bind(&duplicate_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        let invoices_moved = sqlx::query(
+            "UPDATE invoices SET patient_id = ? WHERE patient_id = ? AND status = 'draft'"
+        )
+        .bind(&survivor_id)
+        .bind(&duplicate_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        let mut records_moved = BTreeMap::new();
+        for table in PATIENT_RECORD_TABLES {
+            let moved = sqlx::query(&format!("UPDATE {table} SET patient_id = ? WHERE patient_id = ?"))
+                .bind(&survivor_id)
+                .bind(&duplicate_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            records_moved.insert(table.to_string(), moved as i64);
+        }
+
+        let issued: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM invoices WHERE patient_id = ?")
+            .bind(&duplicate_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        let retained = issued > 0;
+        if retained {
+            // The survivor may take over the ID number, which must stay unique
+            sqlx::query("UPDATE patients SET active = 0, merged_into = ?, national_id = NULL WHERE id = ?")
+                .bind(&survivor_id)
+                .bind(&duplicate_id)
+                .execute(&mut *tx)
+                .await?;
+        } else {
+            sqlx::query("DELETE FROM patients WHERE id = ?")
+                .bind(&duplicate_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
         sqlx::query(
             r#"
-            INSERT INTO treatments (id, patient_id, summary, date)
-            VALUES (?, ?, ?, ?)
+            UPDATE patients
+            SET email = ?, phone_number = ?, phone_e164 = ?, description = ?, date = ?,
+                date_of_birth = ?, gender = ?, national_id = ?, address = ?, preferred_language = ?, referral_source = ?
+            WHERE id = ?
+            "#
+        )
+        .bind(&survivor.email)
+        .bind(&survivor.phone_number)
+        .bind(&survivor.phone_e164)
+        .bind(&survivor.description)
+        .bind(survivor.date.to_rfc3339())
+        .bind(survivor.date_of_birth.map(|d| d.to_string()))
+        .bind(survivor.gender.map(|g| g.as_str()))
+        .bind(&survivor.national_id)
+        .bind(&survivor.address)
+        .bind(&survivor.preferred_language)
+        .bind(&survivor.referral_source)
+        .bind(&survivor_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let merge = PatientMerge {
+            id: Uuid::new_v4(),
+            surviving_patient_id: survivor.id,
+            merged_patient_id: duplicate.id,
+            merged_patient: duplicate.clone(),
+            treatments_moved: treatments_moved as i64,
+            packages_moved: packages_moved as i64,
+            invoices_moved: invoices_moved as i64,
+            records_moved,
+            merged_patient_retained: retained,
+            merged_by,
+            merged_at: Utc::now(),
+        };
+        sqlx::query(&format!(
+            "INSERT INTO patient_merges ({PATIENT_MERGE_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        ))
+        .bind(merge.id.to_string())
+        .bind(&survivor_id)
+        .bind(&duplicate_id)
+        .bind(serde_json::to_string(duplicate)?)
+        .bind(merge.treatments_moved)
+        .bind(merge.packages_moved)
+        .bind(merge.invoices_moved)
+        .bind(serde_json::to_string(&merge.records_moved)?)
+        .bind(merge.merged_patient_retained)
+        .bind(&merge.merged_by)
+        .bind(merge.merged_at.to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(merge)
+    }
+
+    /// Merges the patient took part in, either as survivor or as the merged record
+    pub async fn get_patient_merges(&self, patient_id: Uuid) -> Result<Vec<PatientMerge>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {PATIENT_MERGE_COLUMNS} FROM patient_merges
+             WHERE surviving_patient_id = ?1 OR merged_patient_id = ?1
+             ORDER BY merged_at DESC"
+        ))
+        .bind(patient_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(patient_merge_from_row).collect()
+    }
+
+    // Treatment methods
+    pub async fn create_treatment(&self, treatment: &Treatment) -> Result<TreatmentOutcome> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO treatments (id, patient_id, summary, date, treatment_type_id, duration_minutes, package_id, plan_id,
+                                    therapist_id, annotations)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#
         )
         .bind(treatment.id.to_string())
         .bind(treatment.patient_id.to_string())
         .bind(&treatment.summary)
         .bind(treatment.date.to_rfc3339())
+        .bind(treatment.treatment_type_id.map(|id| id.to_string()))
+        .bind(treatment.duration_minutes)
+        .bind(treatment.package_id.map(|id| id.to_string()))
+        .bind(treatment.plan_id.map(|id| id.to_string()))
+        .bind(&treatment.therapist_id)
+        .bind(serde_json::to_string(&treatment.annotations)?)
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(_) => Ok(TreatmentOutcome::Saved),
+            Err(e) if is_package_exhausted(&e) => Ok(TreatmentOutcome::PackageExhausted),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn get_treatments_for_patient(&self, patient_id: Uuid) -> Result<Vec<Treatment>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {TREATMENT_COLUMNS} FROM treatments WHERE patient_id = ? ORDER BY date DESC"
+        ))
+        .bind(patient_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(treatment_from_row).collect()
+    }
+
+    pub async fn get_all_treatments(&self, treatment_type_id: Option<Uuid>) -> Result<Vec<Treatment>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {TREATMENT_COLUMNS} FROM treatments
+             WHERE (?1 IS NULL OR treatment_type_id = ?1)
+             ORDER BY date DESC"
+        ))
+        .bind(treatment_type_id.map(|id| id.to_string()))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(treatment_from_row).collect()
+    }
+
+    pub async fn get_treatment_by_id(&self, id: Uuid) -> Result<Option<Treatment>> {
+        let row = sqlx::query(&format!(
+            "SELECT {TREATMENT_COLUMNS} FROM treatments WHERE id = ?"
+        ))
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(treatment_from_row).transpose()
+    }
+
+    /// Treatments of a patient with body chart annotations, oldest first; only those marking `region` if given
+    pub async fn get_annotated_treatments(&self, patient_id: Uuid, region: Option<&str>) -> Result<Vec<Treatment>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {TREATMENT_COLUMNS} FROM treatments
+             WHERE patient_id = ?1
+               AND EXISTS (SELECT 1 FROM json_each(treatments.annotations) a
+                           WHERE ?2 IS NULL OR json_extract(a.value, '$.region') = ?2)
+             ORDER BY date"
+        ))
+        .bind(patient_id.to_string())
+        .bind(region)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(treatment_from_row).collect()
+    }
+
+    pub async fn update_treatment(&self, id: Uuid, treatment: &Treatment) -> Result<TreatmentOutcome> {
+        let result = sqlx::query(
+            r#"
+            UPDATE treatments 
+            SET summary = ?, date = ?, treatment_type_id = ?, duration_minutes = ?, package_id = ?, plan_id = ?,
+                annotations = ?
+            WHERE id = ?
+            "#
+        )
+        .bind(&treatment.summary)
+        .bind(treatment.date.to_rfc3339())
+        .bind(treatment.treatment_type_id.map(|id| id.to_string()))
+        .bind(treatment.duration_minutes)
+        .bind(treatment.package_id.map(|id| id.to_string()))
+        .bind(treatment.plan_id.map(|id| id.to_string()))
+        .bind(serde_json::to_string(&treatment.annotations)?)
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(result) if result.rows_affected() > 0 => Ok(TreatmentOutcome::Saved),
+            Ok(_) => Ok(TreatmentOutcome::NotFound),
+            Err(e) if is_package_exhausted(&e) => Ok(TreatmentOutcome::PackageExhausted),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn delete_treatment(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM treatments WHERE id = ?"
+        )
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Treatment type methods
+    pub async fn create_treatment_type(&self, treatment_type: &TreatmentType) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO treatment_types (id, name_he, name_en, default_duration_minutes, default_price_cents, color, active, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(treatment_type.id.to_string())
+        .bind(&treatment_type.name_he)
+        .bind(&treatment_type.name_en)
+        .bind(treatment_type.default_duration_minutes)
+        .bind(treatment_type.default_price_cents)
+        .bind(&treatment_type.color)
+        .bind(treatment_type.active)
+        .bind(treatment_type.created_at.to_rfc3339())
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
-    pub async fn get_treatments_for_patient(&self, patient_id: Uuid) -> Result<Vec<Treatment>> {
+    pub async fn get_treatment_types(&self, include_inactive: bool) -> Result<Vec<TreatmentType>> {
         let rows = sqlx::query(
-            "SELECT id, patient_id, summary, date FROM treatments WHERE patient_id = ? ORDER BY date DESC"
+            r#"
+            SELECT id, name_he, name_en, default_duration_minutes, default_price_cents, color, active, created_at
+            FROM treatment_types
+            WHERE active = 1 OR ?
+            ORDER BY name_en
+            "#
         )
-        .bind(patient_id.to_string())
+        .bind(include_inactive)
         .fetch_all(&self.pool)
         .await?;
 
-        let mut treatments = Vec::new();
-        for row in rows {
-            let id_str: String = row.get("id");
-            let patient_id_str: String = row.get("patient_id");
-            let summary: String = row.get("summary");
-            let date_str: String = row.get("date");
+        rows.iter().map(treatment_type_from_row).collect()
+    }
 
-            let treatment = Treatment {
-                id: Uuid::parse_str(&id_str)?,
-                patient_id: Uuid::parse_str(&patient_id_str)?,
-                summary,
-                date: DateTime::parse_from_rfc3339(&date_str)?.with_timezone(&Utc),
-            };
-            treatments.push(treatment);
-        }
+    pub async fn get_treatment_type_by_id(&self, id: Uuid) -> Result<Option<TreatmentType>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, name_he, name_en, default_duration_minutes, default_price_cents, color, active, created_at
+            FROM treatment_types WHERE id = ?
+            "#
+        )
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(treatment_type_from_row).transpose()
+    }
+
+    pub async fn update_treatment_type(&self, id: Uuid, treatment_type: &TreatmentType) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE treatment_types
+            SET name_he = ?, name_en = ?, default_duration_minutes = ?, default_price_cents = ?, color = ?, active = ?
+            WHERE id = ?
+            "#
+        )
+        .bind(&treatment_type.name_he)
+        .bind(&treatment_type.name_en)
+        .bind(treatment_type.default_duration_minutes)
+        .bind(treatment_type.default_price_cents)
+        .bind(&treatment_type.color)
+        .bind(treatment_type.active)
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_treatment_type(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM treatment_types WHERE id = ?"
+        )
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
 
-        Ok(treatments)
+        Ok(result.rows_affected() > 0)
     }
 
-    pub async fn get_all_treatments(&self) -> Result<Vec<Treatment>> {
+    pub async fn get_treatment_type_breakdown(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<TreatmentTypeBreakdown>> {
         let rows = sqlx::query(
-            "SELECT id, patient_id, summary, date FROM treatments ORDER BY date DESC"
+            r#"
+            SELECT t.treatment_type_id, tt.name_he, tt.name_en, tt.color,
+                   COUNT(*) AS sessions,
+                   COALESCE(SUM(COALESCE(t.duration_minutes, tt.default_duration_minutes, 0)), 0) AS total_minutes
+            FROM treatments t
+            LEFT JOIN treatment_types tt ON tt.id = t.treatment_type_id
+            WHERE (?1 IS NULL OR julianday(t.date) >= julianday(?1))
+              AND (?2 IS NULL OR julianday(t.date) < julianday(?2))
+            GROUP BY t.treatment_type_id
+            ORDER BY sessions DESC
+            "#
         )
+        .bind(from.map(|d| d.to_rfc3339()))
+        .bind(to.map(|d| d.to_rfc3339()))
         .fetch_all(&self.pool)
         .await?;
 
-        let mut treatments = Vec::new();
+        let mut breakdown = Vec::new();
         for row in rows {
-            let id_str: String = row.get("id");
-            let patient_id_str: String = row.get("patient_id");
-            let summary: String = row.get("summary");
-            let date_str: String = row.get("date");
-
-            let treatment = Treatment {
-                id: Uuid::parse_str(&id_str)?,
-                patient_id: Uuid::parse_str(&patient_id_str)?,
-                summary,
-                date: DateTime::parse_from_rfc3339(&date_str)?.with_timezone(&Utc),
-            };
-            treatments.push(treatment);
+            let type_id: Option<String> = row.get("treatment_type_id");
+            breakdown.push(TreatmentTypeBreakdown {
+                treatment_type_id: type_id.map(|id| Uuid::parse_str(&id)).transpose()?,
+                name_he: row.get("name_he"),
+                name_en: row.get("name_en"),
+                color: row.get("color"),
+                sessions: row.get("sessions"),
+                total_minutes: row.get("total_minutes"),
+            });
         }
 
-        Ok(treatments)
+        Ok(breakdown)
     }
 
-    pub async fn get_treatment_by_id(&self, id: Uuid) -> Result<Option<Treatment>> {
-        let row = sqlx::query(
-            "SELECT id, patient_id, summary, date FROM treatments WHERE id = ?"
+    // Billing methods
+    pub async fn create_invoice(&self, invoice: &Invoice, lines: &[InvoiceLine]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO invoices (id, kind, number, patient_id, status, credited_invoice_id, currency, vat_rate,
+                                  subtotal_cents, vat_cents, total_cents, notes, due_date, created_at, issued_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
         )
+        .bind(invoice.id.to_string())
+        .bind(invoice.kind.as_str())
+        .bind(invoice.number)
+        .bind(invoice.patient_id.to_string())
+        .bind(invoice.status.as_str())
+        .bind(invoice.credited_invoice_id.map(|id| id.to_string()))
+        .bind(&invoice.currency)
+        .bind(invoice.vat_rate)
+        .bind(invoice.subtotal_cents)
+        .bind(invoice.vat_cents)
+        .bind(invoice.total_cents)
+        .bind(&invoice.notes)
+        .bind(invoice.due_date.map(|d| d.to_rfc3339()))
+        .bind(invoice.created_at.to_rfc3339())
+        .bind(invoice.issued_at.map(|d| d.to_rfc3339()))
+        .execute(&mut *tx)
+        .await?;
+
+        insert_invoice_lines(&mut tx, lines).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_invoices(
+        &self,
+        patient_id: Option<Uuid>,
+        status: Option<InvoiceStatus>,
+        kind: Option<InvoiceKind>,
+    ) -> Result<Vec<Invoice>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {INVOICE_COLUMNS} FROM invoices
+             WHERE (?1 IS NULL OR patient_id = ?1)
+               AND (?2 IS NULL OR status = ?2)
+               AND (?3 IS NULL OR kind = ?3)
+             ORDER BY created_at DESC"
+        ))
+        .bind(patient_id.map(|id| id.to_string()))
+        .bind(status.map(|s| s.as_str()))
+        .bind(kind.map(|k| k.as_str()))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(invoice_from_row).collect()
+    }
+
+    pub async fn get_invoice_by_id(&self, id: Uuid) -> Result<Option<Invoice>> {
+        let row = sqlx::query(&format!(
+            "SELECT {INVOICE_COLUMNS} FROM invoices WHERE id = ?"
+        ))
         .bind(id.to_string())
         .fetch_optional(&self.pool)
         .await?;
 
-        if let Some(row) = row {
+        row.as_ref().map(invoice_from_row).transpose()
+    }
+
+    pub async fn get_invoice_lines(&self, invoice_id: Uuid) -> Result<Vec<InvoiceLine>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, invoice_id, position, treatment_id, description, quantity, unit_price_cents, line_total_cents
+            FROM invoice_lines WHERE invoice_id = ? ORDER BY position
+            "#
+        )
+        .bind(invoice_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut lines = Vec::new();
+        for row in rows {
             let id_str: String = row.get("id");
-            let patient_id_str: String = row.get("patient_id");
-            let summary: String = row.get("summary");
-            let date_str: String = row.get("date");
+            let invoice_id_str: String = row.get("invoice_id");
+            let treatment_id: Option<String> = row.get("treatment_id");
 
-            let treatment = Treatment {
+            lines.push(InvoiceLine {
                 id: Uuid::parse_str(&id_str)?,
-                patient_id: Uuid::parse_str(&patient_id_str)?,
-                summary,
-                date: DateTime::parse_from_rfc3339(&date_str)?.with_timezone(&Utc),
-            };
-            Ok(Some(treatment))
-        } else {
-            Ok(None)
+                invoice_id: Uuid::parse_str(&invoice_id_str)?,
+                position: row.get("position"),
+                treatment_id: treatment_id.map(|id| Uuid::parse_str(&id)).transpose()?,
+                description: row.get("description"),
+                quantity: row.get("quantity"),
+                unit_price_cents: row.get("unit_price_cents"),
+                line_total_cents: row.get("line_total_cents"),
+            });
         }
+
+        Ok(lines)
     }
 
-    pub async fn update_treatment(&self, id: Uuid, treatment: &Treatment) -> Result<bool> {
+    /// Update a draft invoice, replacing its lines when given. Returns false if the invoice is no longer a draft.
+    pub async fn update_draft_invoice(&self, invoice: &Invoice, lines: Option<&[InvoiceLine]>) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
         let result = sqlx::query(
             r#"
-            UPDATE treatments 
-            SET summary = ?, date = ?
-            WHERE id = ?
+            UPDATE invoices
+            SET vat_rate = ?, subtotal_cents = ?, vat_cents = ?, total_cents = ?, notes = ?, due_date = ?
+            WHERE id = ? AND status = 'draft'
             "#
         )
-        .bind(&treatment.summary)
-        .bind(treatment.date.to_rfc3339())
+        .bind(invoice.vat_rate)
+        .bind(invoice.subtotal_cents)
+        .bind(invoice.vat_cents)
+        .bind(invoice.total_cents)
+        .bind(&invoice.notes)
+        .bind(invoice.due_date.map(|d| d.to_rfc3339()))
+        .bind(invoice.id.to_string())
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            tx.rollback().await?;
+            return Ok(false);
+        }
+
+        if let Some(lines) = lines {
+            sqlx::query("DELETE FROM invoice_lines WHERE invoice_id = ?")
+                .bind(invoice.id.to_string())
+                .execute(&mut *tx)
+                .await?;
+            insert_invoice_lines(&mut tx, lines).await?;
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    pub async fn delete_draft_invoice(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM invoices WHERE id = ? AND status = 'draft'"
+        )
         .bind(id.to_string())
         .execute(&self.pool)
         .await?;
@@ -254,9 +700,9 @@ impl Database {
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn delete_treatment(&self, id: Uuid) -> Result<bool> {
+    pub async fn void_draft_invoice(&self, id: Uuid) -> Result<bool> {
         let result = sqlx::query(
-            "DELETE FROM treatments WHERE id = ?"
+            "UPDATE invoices SET status = 'void' WHERE id = ? AND status = 'draft'"
         )
         .bind(id.to_string())
         .execute(&self.pool)
@@ -264,4 +710,2148 @@ impl Database {
 
         Ok(result.rows_affected() > 0)
     }
+
+    /// Issue a draft, taking the next number of its series in the same transaction.
+    ///
+    /// A credit note is checked against what is left on the original invoice
+    /// inside the transaction too: taking the number locks the database for
+    /// writing, so two credit notes issued at once cannot both pass the check.
+    pub async fn issue_invoice(&self, invoice: &Invoice) -> Result<IssueOutcome> {
+        let mut tx = self.pool.begin().await?;
+
+        let number: i64 = sqlx::query_scalar(
+            "UPDATE document_sequences SET next_number = next_number + 1 WHERE series = ? RETURNING next_number - 1"
+        )
+        .bind(invoice.kind.as_str())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if let Some(original_id) = invoice.credited_invoice_id {
+            let within_total: Option<bool> = sqlx::query_scalar(
+                r#"
+                SELECT (SELECT total_cents FROM invoices WHERE id = ?1) + COALESCE(SUM(total_cents), 0)
+                       <= (SELECT total_cents FROM invoices WHERE id = ?2)
+                FROM invoices
+                WHERE credited_invoice_id = ?2 AND kind = 'credit_note' AND status = 'issued'
+                "#
+            )
+            .bind(invoice.id.to_string())
+            .bind(original_id.to_string())
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if within_total != Some(true) {
+                tx.rollback().await?;
+                return Ok(IssueOutcome::ExceedsCredit);
+            }
+        }
+
+        let result = sqlx::query(
+            "UPDATE invoices SET number = ?, status = 'issued', issued_at = ? WHERE id = ? AND status = 'draft'"
+        )
+        .bind(number)
+        .bind(Utc::now().to_rfc3339())
+        .bind(invoice.id.to_string())
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            tx.rollback().await?;
+            return Ok(IssueOutcome::NotDraft);
+        }
+
+        tx.commit().await?;
+        Ok(IssueOutcome::Issued(number))
+    }
+
+    /// Total of issued credit notes raised against an invoice
+    pub async fn get_credited_cents(&self, invoice_id: Uuid) -> Result<i64> {
+        let credited: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(total_cents), 0) FROM invoices
+            WHERE credited_invoice_id = ? AND kind = 'credit_note' AND status = 'issued'
+            "#
+        )
+        .bind(invoice_id.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(credited)
+    }
+
+    /// Whether a treatment already appears on an invoice that has not been voided
+    pub async fn is_treatment_billed(&self, treatment_id: Uuid, exclude_invoice_id: Option<Uuid>) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM invoice_lines l
+            JOIN invoices i ON i.id = l.invoice_id
+            WHERE l.treatment_id = ?1 AND i.kind = 'invoice' AND i.status != 'void'
+              AND (?2 IS NULL OR i.id != ?2)
+            "#
+        )
+        .bind(treatment_id.to_string())
+        .bind(exclude_invoice_id.map(|id| id.to_string()))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    pub async fn patient_has_invoices(&self, patient_id: Uuid) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM invoices WHERE patient_id = ? AND status != 'draft'"
+        )
+        .bind(patient_id.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    /// Record a payment with the next receipt number and mark the invoice paid once nothing is owed.
+    ///
+    /// The invoice's status and amount due are read after the receipt number is
+    /// taken, which locks the database for writing, so concurrent payments
+    /// cannot overpay it or pay one voided in the meantime.
+    pub async fn create_payment(&self, payment: &Payment) -> Result<PaymentOutcome> {
+        let mut tx = self.pool.begin().await?;
+
+        let receipt_number: i64 = sqlx::query_scalar(
+            "UPDATE document_sequences SET next_number = next_number + 1 WHERE series = 'receipt' RETURNING next_number - 1"
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let due: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT total_cents
+                   - (SELECT COALESCE(SUM(total_cents), 0) FROM invoices c
+                      WHERE c.credited_invoice_id = ?1 AND c.kind = 'credit_note' AND c.status = 'issued')
+                   - (SELECT COALESCE(SUM(amount_cents), 0) FROM payments p WHERE p.invoice_id = ?1)
+            FROM invoices
+            WHERE id = ?1 AND kind = 'invoice' AND status = 'issued'
+            "#
+        )
+        .bind(payment.invoice_id.to_string())
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(due) = due else {
+            tx.rollback().await?;
+            return Ok(PaymentOutcome::NotAllowed);
+        };
+        if payment.amount_cents > due {
+            tx.rollback().await?;
+            return Ok(PaymentOutcome::ExceedsDue(due.max(0)));
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO payments (id, invoice_id, patient_id, receipt_number, amount_cents, method, reference, paid_at, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(payment.id.to_string())
+        .bind(payment.invoice_id.to_string())
+        .bind(payment.patient_id.to_string())
+        .bind(receipt_number)
+        .bind(payment.amount_cents)
+        .bind(payment.method.as_str())
+        .bind(&payment.reference)
+        .bind(payment.paid_at.to_rfc3339())
+        .bind(payment.created_at.to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE invoices SET status = 'paid'
+            WHERE id = ?1 AND status = 'issued'
+              AND total_cents
+                  - (SELECT COALESCE(SUM(total_cents), 0) FROM invoices c
+                     WHERE c.credited_invoice_id = ?1 AND c.kind = 'credit_note' AND c.status = 'issued')
+                  - (SELECT COALESCE(SUM(amount_cents), 0) FROM payments p WHERE p.invoice_id = ?1) <= 0
+            "#
+        )
+        .bind(payment.invoice_id.to_string())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(PaymentOutcome::Recorded(receipt_number))
+    }
+
+    pub async fn get_payments_for_invoice(&self, invoice_id: Uuid) -> Result<Vec<Payment>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {PAYMENT_COLUMNS} FROM payments WHERE invoice_id = ? ORDER BY paid_at"
+        ))
+        .bind(invoice_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(payment_from_row).collect()
+    }
+
+    pub async fn get_payment_by_id(&self, id: Uuid) -> Result<Option<Payment>> {
+        let row = sqlx::query(&format!(
+            "SELECT {PAYMENT_COLUMNS} FROM payments WHERE id = ?"
+        ))
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(payment_from_row).transpose()
+    }
+
+    // Session package methods
+    pub async fn create_package(&self, package: &SessionPackage) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO session_packages (id, patient_id, name, sessions_purchased, sessions_used, price_cents,
+                                          purchased_at, expires_at, notes, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(package.id.to_string())
+        .bind(package.patient_id.to_string())
+        .bind(&package.name)
+        .bind(package.sessions_purchased)
+        .bind(package.sessions_used)
+        .bind(package.price_cents)
+        .bind(package.purchased_at.to_rfc3339())
+        .bind(package.expires_at.map(|d| d.to_rfc3339()))
+        .bind(&package.notes)
+        .bind(package.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_packages_for_patient(&self, patient_id: Uuid) -> Result<Vec<SessionPackage>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {PACKAGE_COLUMNS} FROM session_packages WHERE patient_id = ? ORDER BY purchased_at DESC"
+        ))
+        .bind(patient_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(package_from_row).collect()
+    }
+
+    pub async fn get_package_by_id(&self, id: Uuid) -> Result<Option<SessionPackage>> {
+        let row = sqlx::query(&format!(
+            "SELECT {PACKAGE_COLUMNS} FROM session_packages WHERE id = ?"
+        ))
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(package_from_row).transpose()
+    }
+
+    pub async fn update_package(&self, id: Uuid, package: &SessionPackage) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE session_packages
+            SET name = ?, sessions_purchased = ?, price_cents = ?, expires_at = ?, notes = ?
+            WHERE id = ?
+            "#
+        )
+        .bind(&package.name)
+        .bind(package.sessions_purchased)
+        .bind(package.price_cents)
+        .bind(package.expires_at.map(|d| d.to_rfc3339()))
+        .bind(&package.notes)
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_package(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM session_packages WHERE id = ?"
+        )
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Patient contact methods
+    pub async fn create_contact(&self, contact: &PatientContact) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO patient_contacts ({CONTACT_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        ))
+        .bind(contact.id.to_string())
+        .bind(contact.patient_id.to_string())
+        .bind(&contact.name)
+        .bind(contact.relationship.as_str())
+        .bind(&contact.phone_number)
+        .bind(&contact.phone_e164)
+        .bind(&contact.email)
+        .bind(&contact.notes)
+        .bind(contact.may_receive_information)
+        .bind(contact.legal_guardian)
+        .bind(contact.created_at.to_rfc3339())
+        .bind(contact.updated_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Legal guardians first, then by name
+    pub async fn get_contacts_for_patient(&self, patient_id: Uuid) -> Result<Vec<PatientContact>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {CONTACT_COLUMNS} FROM patient_contacts WHERE patient_id = ? ORDER BY legal_guardian DESC, name"
+        ))
+        .bind(patient_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(contact_from_row).collect()
+    }
+
+    pub async fn get_contact_by_id(&self, id: Uuid) -> Result<Option<PatientContact>> {
+        let row = sqlx::query(&format!(
+            "SELECT {CONTACT_COLUMNS} FROM patient_contacts WHERE id = ?"
+        ))
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(contact_from_row).transpose()
+    }
+
+    pub async fn update_contact(&self, id: Uuid, contact: &PatientContact) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE patient_contacts
+            SET name = ?, relationship = ?, phone_number = ?, phone_e164 = ?, email = ?, notes = ?,
+                may_receive_information = ?, legal_guardian = ?, updated_at = ?
+            WHERE id = ?
+            "#
+        )
+        .bind(&contact.name)
+        .bind(contact.relationship.as_str())
+        .bind(&contact.phone_number)
+        .bind(&contact.phone_e164)
+        .bind(&contact.email)
+        .bind(&contact.notes)
+        .bind(contact.may_receive_information)
+        .bind(contact.legal_guardian)
+        .bind(contact.updated_at.to_rfc3339())
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_contact(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM patient_contacts WHERE id = ?"
+        )
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Medical background methods
+    pub async fn get_medical_background(&self, patient_id: Uuid) -> Result<MedicalBackground> {
+        Ok(MedicalBackground {
+            diagnoses: self.get_diagnoses_for_patient(patient_id).await?,
+            allergies: self.get_allergies_for_patient(patient_id).await?,
+            medications: self.get_medications_for_patient(patient_id).await?,
+        })
+    }
+
+    pub async fn create_diagnosis(&self, diagnosis: &Diagnosis) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO patient_diagnoses ({DIAGNOSIS_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        ))
+        .bind(diagnosis.id.to_string())
+        .bind(diagnosis.patient_id.to_string())
+        .bind(&diagnosis.icd10_code)
+        .bind(&diagnosis.description)
+        .bind(diagnosis.start_date.map(|d| d.to_string()))
+        .bind(diagnosis.end_date.map(|d| d.to_string()))
+        .bind(&diagnosis.notes)
+        .bind(diagnosis.created_at.to_rfc3339())
+        .bind(diagnosis.updated_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Ongoing diagnoses first, then the most recent
+    pub async fn get_diagnoses_for_patient(&self, patient_id: Uuid) -> Result<Vec<Diagnosis>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {DIAGNOSIS_COLUMNS} FROM patient_diagnoses WHERE patient_id = ? \
+             ORDER BY end_date IS NOT NULL, start_date DESC, created_at DESC"
+        ))
+        .bind(patient_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(diagnosis_from_row).collect()
+    }
+
+    pub async fn get_diagnosis_by_id(&self, id: Uuid) -> Result<Option<Diagnosis>> {
+        let row = sqlx::query(&format!(
+            "SELECT {DIAGNOSIS_COLUMNS} FROM patient_diagnoses WHERE id = ?"
+        ))
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(diagnosis_from_row).transpose()
+    }
+
+    pub async fn update_diagnosis(&self, id: Uuid, diagnosis: &Diagnosis) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE patient_diagnoses
+            SET icd10_code = ?, description = ?, start_date = ?, end_date = ?, notes = ?, updated_at = ?
+            WHERE id = ?
+            "#
+        )
+        .bind(&diagnosis.icd10_code)
+        .bind(&diagnosis.description)
+        .bind(diagnosis.start_date.map(|d| d.to_string()))
+        .bind(diagnosis.end_date.map(|d| d.to_string()))
+        .bind(&diagnosis.notes)
+        .bind(diagnosis.updated_at.to_rfc3339())
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_diagnosis(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM patient_diagnoses WHERE id = ?"
+        )
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn create_allergy(&self, allergy: &Allergy) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO patient_allergies ({ALLERGY_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        ))
+        .bind(allergy.id.to_string())
+        .bind(allergy.patient_id.to_string())
+        .bind(&allergy.allergen)
+        .bind(&allergy.reaction)
+        .bind(allergy.severity.as_str())
+        .bind(allergy.start_date.map(|d| d.to_string()))
+        .bind(allergy.end_date.map(|d| d.to_string()))
+        .bind(&allergy.notes)
+        .bind(allergy.created_at.to_rfc3339())
+        .bind(allergy.updated_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Most severe first, then by allergen
+    pub async fn get_allergies_for_patient(&self, patient_id: Uuid) -> Result<Vec<Allergy>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {ALLERGY_COLUMNS} FROM patient_allergies WHERE patient_id = ? \
+             ORDER BY CASE severity WHEN 'life_threatening' THEN 0 WHEN 'severe' THEN 1 WHEN 'moderate' THEN 2 ELSE 3 END, allergen"
+        ))
+        .bind(patient_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(allergy_from_row).collect()
+    }
+
+    pub async fn get_allergy_by_id(&self, id: Uuid) -> Result<Option<Allergy>> {
+        let row = sqlx::query(&format!(
+            "SELECT {ALLERGY_COLUMNS} FROM patient_allergies WHERE id = ?"
+        ))
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(allergy_from_row).transpose()
+    }
+
+    pub async fn update_allergy(&self, id: Uuid, allergy: &Allergy) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE patient_allergies
+            SET allergen = ?, reaction = ?, severity = ?, start_date = ?, end_date = ?, notes = ?, updated_at = ?
+            WHERE id = ?
+            "#
+        )
+        .bind(&allergy.allergen)
+        .bind(&allergy.reaction)
+        .bind(allergy.severity.as_str())
+        .bind(allergy.start_date.map(|d| d.to_string()))
+        .bind(allergy.end_date.map(|d| d.to_string()))
+        .bind(&allergy.notes)
+        .bind(allergy.updated_at.to_rfc3339())
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_allergy(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM patient_allergies WHERE id = ?"
+        )
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn create_medication(&self, medication: &Medication) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO patient_medications ({MEDICATION_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        ))
+        .bind(medication.id.to_string())
+        .bind(medication.patient_id.to_string())
+        .bind(&medication.name)
+        .bind(&medication.dosage)
+        .bind(&medication.frequency)
+        .bind(medication.start_date.map(|d| d.to_string()))
+        .bind(medication.end_date.map(|d| d.to_string()))
+        .bind(&medication.notes)
+        .bind(medication.created_at.to_rfc3339())
+        .bind(medication.updated_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Medications still taken first, then by name
+    pub async fn get_medications_for_patient(&self, patient_id: Uuid) -> Result<Vec<Medication>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {MEDICATION_COLUMNS} FROM patient_medications WHERE patient_id = ? \
+             ORDER BY end_date IS NOT NULL, name"
+        ))
+        .bind(patient_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(medication_from_row).collect()
+    }
+
+    pub async fn get_medication_by_id(&self, id: Uuid) -> Result<Option<Medication>> {
+        let row = sqlx::query(&format!(
+            "SELECT {MEDICATION_COLUMNS} FROM patient_medications WHERE id = ?"
+        ))
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(medication_from_row).transpose()
+    }
+
+    pub async fn update_medication(&self, id: Uuid, medication: &Medication) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE patient_medications
+            SET name = ?, dosage = ?, frequency = ?, start_date = ?, end_date = ?, notes = ?, updated_at = ?
+            WHERE id = ?
+            "#
+        )
+        .bind(&medication.name)
+        .bind(&medication.dosage)
+        .bind(&medication.frequency)
+        .bind(medication.start_date.map(|d| d.to_string()))
+        .bind(medication.end_date.map(|d| d.to_string()))
+        .bind(&medication.notes)
+        .bind(medication.updated_at.to_rfc3339())
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_medication(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM patient_medications WHERE id = ?"
+        )
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Attachment methods
+    pub async fn create_attachment(&self, attachment: &Attachment) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO attachments ({ATTACHMENT_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        ))
+        .bind(attachment.id.to_string())
+        .bind(attachment.patient_id.to_string())
+        .bind(attachment.treatment_id.map(|id| id.to_string()))
+        .bind(&attachment.file_name)
+        .bind(&attachment.content_type)
+        .bind(attachment.size_bytes)
+        .bind(&attachment.sha256)
+        .bind(&attachment.description)
+        .bind(&attachment.uploaded_by)
+        .bind(attachment.has_thumbnail)
+        .bind(attachment.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Newest first, optionally only those of one treatment
+    pub async fn get_attachments_for_patient(&self, patient_id: Uuid, treatment_id: Option<Uuid>) -> Result<Vec<Attachment>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {ATTACHMENT_COLUMNS} FROM attachments \
+             WHERE patient_id = ? AND (? IS NULL OR treatment_id = ?) ORDER BY created_at DESC"
+        ))
+        .bind(patient_id.to_string())
+        .bind(treatment_id.map(|id| id.to_string()))
+        .bind(treatment_id.map(|id| id.to_string()))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(attachment_from_row).collect()
+    }
+
+    pub async fn get_attachment_by_id(&self, id: Uuid) -> Result<Option<Attachment>> {
+        let row = sqlx::query(&format!(
+            "SELECT {ATTACHMENT_COLUMNS} FROM attachments WHERE id = ?"
+        ))
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(attachment_from_row).transpose()
+    }
+
+    /// The same content already attached to the patient at the same place
+    pub async fn find_attachment_by_hash(&self, patient_id: Uuid, treatment_id: Option<Uuid>, sha256: &str) -> Result<Option<Attachment>> {
+        let row = sqlx::query(&format!(
+            "SELECT {ATTACHMENT_COLUMNS} FROM attachments WHERE patient_id = ? AND treatment_id IS ? AND sha256 = ?"
+        ))
+        .bind(patient_id.to_string())
+        .bind(treatment_id.map(|id| id.to_string()))
+        .bind(sha256)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(attachment_from_row).transpose()
+    }
+
+    pub async fn update_attachment(&self, id: Uuid, attachment: &Attachment) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE attachments SET treatment_id = ?, file_name = ?, description = ? WHERE id = ?"
+        )
+        .bind(attachment.treatment_id.map(|id| id.to_string()))
+        .bind(&attachment.file_name)
+        .bind(&attachment.description)
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Record that a thumbnail was made of every attachment with this content
+    pub async fn set_attachment_thumbnail(&self, sha256: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE attachments SET has_thumbnail = 1 WHERE sha256 = ?"
+        )
+        .bind(sha256)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_attachment(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM attachments WHERE id = ?"
+        )
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Content hashes of a patient's attachments, to release their blobs once the patient is gone
+    pub async fn get_attachment_hashes_for_patient(&self, patient_id: Uuid) -> Result<Vec<String>> {
+        let hashes = sqlx::query_scalar(
+            "SELECT DISTINCT sha256 FROM attachments WHERE patient_id = ?"
+        )
+        .bind(patient_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(hashes)
+    }
+
+    /// Whether any attachment still refers to this content
+    pub async fn attachment_hash_in_use(&self, sha256: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM attachments WHERE sha256 = ?"
+        )
+        .bind(sha256)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    // Questionnaire methods
+    pub async fn get_questionnaires(&self, include_inactive: bool) -> Result<Vec<Questionnaire>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {QUESTIONNAIRE_COLUMNS} FROM questionnaires WHERE active = 1 OR ? ORDER BY name"
+        ))
+        .bind(include_inactive)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(questionnaire_from_row).collect()
+    }
+
+    pub async fn get_questionnaire_by_id(&self, id: Uuid) -> Result<Option<Questionnaire>> {
+        let row = sqlx::query(&format!(
+            "SELECT {QUESTIONNAIRE_COLUMNS} FROM questionnaires WHERE id = ?"
+        ))
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(questionnaire_from_row).transpose()
+    }
+
+    pub async fn create_questionnaire(&self, questionnaire: &Questionnaire) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO questionnaires ({QUESTIONNAIRE_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        ))
+        .bind(questionnaire.id.to_string())
+        .bind(&questionnaire.name)
+        .bind(&questionnaire.description)
+        .bind(serde_json::to_string(&questionnaire.questions)?)
+        .bind(questionnaire.scoring.as_str())
+        .bind(questionnaire.higher_is_better)
+        .bind(questionnaire.active)
+        .bind(questionnaire.created_at.to_rfc3339())
+        .bind(questionnaire.updated_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_questionnaire(&self, id: Uuid, questionnaire: &Questionnaire) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE questionnaires SET name = ?, description = ?, questions = ?, scoring = ?, higher_is_better = ?, \
+             active = ?, updated_at = ? WHERE id = ?"
+        )
+        .bind(&questionnaire.name)
+        .bind(&questionnaire.description)
+        .bind(serde_json::to_string(&questionnaire.questions)?)
+        .bind(questionnaire.scoring.as_str())
+        .bind(questionnaire.higher_is_better)
+        .bind(questionnaire.active)
+        .bind(questionnaire.updated_at.to_rfc3339())
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn questionnaire_has_responses(&self, id: Uuid) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM questionnaire_responses WHERE questionnaire_id = ?"
+        )
+        .bind(id.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    pub async fn delete_questionnaire(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM questionnaires WHERE id = ?"
+        )
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn create_questionnaire_response(&self, response: &QuestionnaireResponse) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO questionnaire_responses (id, patient_id, questionnaire_id, treatment_id, answers, score, notes, \
+             completed_at, recorded_by, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(response.id.to_string())
+        .bind(response.patient_id.to_string())
+        .bind(response.questionnaire_id.to_string())
+        .bind(response.treatment_id.map(|id| id.to_string()))
+        .bind(serde_json::to_string(&response.answers)?)
+        .bind(response.score)
+        .bind(&response.notes)
+        .bind(response.completed_at.to_rfc3339())
+        .bind(&response.recorded_by)
+        .bind(response.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Oldest first, optionally only those to one questionnaire
+    pub async fn get_questionnaire_responses_for_patient(
+        &self,
+        patient_id: Uuid,
+        questionnaire_id: Option<Uuid>,
+    ) -> Result<Vec<QuestionnaireResponse>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {QUESTIONNAIRE_RESPONSE_COLUMNS} FROM questionnaire_responses r \
+             JOIN questionnaires q ON q.id = r.questionnaire_id \
+             WHERE r.patient_id = ? AND (? IS NULL OR r.questionnaire_id = ?) ORDER BY r.completed_at"
+        ))
+        .bind(patient_id.to_string())
+        .bind(questionnaire_id.map(|id| id.to_string()))
+        .bind(questionnaire_id.map(|id| id.to_string()))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(questionnaire_response_from_row).collect()
+    }
+
+    pub async fn get_questionnaire_response_by_id(&self, id: Uuid) -> Result<Option<QuestionnaireResponse>> {
+        let row = sqlx::query(&format!(
+            "SELECT {QUESTIONNAIRE_RESPONSE_COLUMNS} FROM questionnaire_responses r \
+             JOIN questionnaires q ON q.id = r.questionnaire_id WHERE r.id = ?"
+        ))
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(questionnaire_response_from_row).transpose()
+    }
+
+    pub async fn delete_questionnaire_response(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM questionnaire_responses WHERE id = ?"
+        )
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Consent form methods
+    /// Create a form together with its first version
+    pub async fn create_consent_form(&self, form: &ConsentForm, version: &ConsentFormVersion) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(&format!(
+            "INSERT INTO consent_forms ({CONSENT_FORM_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        ))
+        .bind(form.id.to_string())
+        .bind(form.kind.as_str())
+        .bind(&form.title)
+        .bind(&form.body)
+        .bind(form.version)
+        .bind(form.required)
+        .bind(form.active)
+        .bind(form.created_at.to_rfc3339())
+        .bind(form.updated_at.to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+        insert_consent_form_version(&mut tx, version).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_consent_forms(&self, include_inactive: bool) -> Result<Vec<ConsentForm>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {CONSENT_FORM_COLUMNS} FROM consent_forms WHERE active = 1 OR ? ORDER BY kind, title"
+        ))
+        .bind(include_inactive)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(consent_form_from_row).collect()
+    }
+
+    /// Active forms patients must sign before treatment
+    pub async fn get_required_consent_forms(&self) -> Result<Vec<ConsentForm>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {CONSENT_FORM_COLUMNS} FROM consent_forms WHERE active = 1 AND required = 1 ORDER BY kind, title"
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(consent_form_from_row).collect()
+    }
+
+    pub async fn get_consent_form_by_id(&self, id: Uuid) -> Result<Option<ConsentForm>> {
+        let row = sqlx::query(&format!(
+            "SELECT {CONSENT_FORM_COLUMNS} FROM consent_forms WHERE id = ?"
+        ))
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(consent_form_from_row).transpose()
+    }
+
+    /// Save a form; `new_version` is its new text when that changed, and is added to the history
+    pub async fn update_consent_form(&self, id: Uuid, form: &ConsentForm, new_version: Option<&ConsentFormVersion>) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "UPDATE consent_forms SET title = ?, body = ?, version = ?, required = ?, active = ?, updated_at = ? WHERE id = ?"
+        )
+        .bind(&form.title)
+        .bind(&form.body)
+        .bind(form.version)
+        .bind(form.required)
+        .bind(form.active)
+        .bind(form.updated_at.to_rfc3339())
+        .bind(id.to_string())
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        if let Some(version) = new_version {
+            insert_consent_form_version(&mut tx, version).await?;
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    pub async fn consent_form_has_signatures(&self, id: Uuid) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM consent_signatures WHERE form_id = ?"
+        )
+        .bind(id.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    pub async fn delete_consent_form(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM consent_forms WHERE id = ?"
+        )
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Newest first
+    pub async fn get_consent_form_versions(&self, form_id: Uuid) -> Result<Vec<ConsentFormVersion>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {CONSENT_FORM_VERSION_COLUMNS} FROM consent_form_versions WHERE form_id = ? ORDER BY version DESC"
+        ))
+        .bind(form_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(consent_form_version_from_row).collect()
+    }
+
+    pub async fn get_consent_form_version(&self, form_id: Uuid, version: i64) -> Result<Option<ConsentFormVersion>> {
+        let row = sqlx::query(&format!(
+            "SELECT {CONSENT_FORM_VERSION_COLUMNS} FROM consent_form_versions WHERE form_id = ? AND version = ?"
+        ))
+        .bind(form_id.to_string())
+        .bind(version)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(consent_form_version_from_row).transpose()
+    }
+
+    // Consent signature methods
+    pub async fn create_consent_signature(&self, signature: &ConsentSignature, image: &[u8]) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO consent_signatures ({CONSENT_SIGNATURE_COLUMNS}, signature_image) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        ))
+        .bind(signature.id.to_string())
+        .bind(signature.patient_id.to_string())
+        .bind(signature.form_id.to_string())
+        .bind(signature.form_version)
+        .bind(signature.kind.as_str())
+        .bind(&signature.title)
+        .bind(&signature.rendered_body)
+        .bind(&signature.rendered_sha256)
+        .bind(&signature.signer_name)
+        .bind(signature.signer_contact_id.map(|id| id.to_string()))
+        .bind(&signature.signature_content_type)
+        .bind(signature.signed_at.to_rfc3339())
+        .bind(&signature.ip_address)
+        .bind(&signature.forwarded_for)
+        .bind(&signature.user_agent)
+        .bind(&signature.recorded_by)
+        .bind(signature.revoked_at.map(|d| d.to_rfc3339()))
+        .bind(&signature.revoke_reason)
+        .bind(image)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Newest first, revoked ones included
+    pub async fn get_consent_signatures_for_patient(&self, patient_id: Uuid) -> Result<Vec<ConsentSignature>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {CONSENT_SIGNATURE_COLUMNS} FROM consent_signatures WHERE patient_id = ? ORDER BY signed_at DESC"
+        ))
+        .bind(patient_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(consent_signature_from_row).collect()
+    }
+
+    pub async fn get_consent_signature_by_id(&self, id: Uuid) -> Result<Option<ConsentSignature>> {
+        let row = sqlx::query(&format!(
+            "SELECT {CONSENT_SIGNATURE_COLUMNS} FROM consent_signatures WHERE id = ?"
+        ))
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(consent_signature_from_row).transpose()
+    }
+
+    pub async fn get_consent_signature_image(&self, id: Uuid) -> Result<Option<Vec<u8>>> {
+        let image = sqlx::query_scalar(
+            "SELECT signature_image FROM consent_signatures WHERE id = ?"
+        )
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(image)
+    }
+
+    /// Revoke a signature that has not been revoked yet
+    pub async fn revoke_consent_signature(&self, id: Uuid, revoked_at: DateTime<Utc>, reason: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE consent_signatures SET revoked_at = ?, revoke_reason = ? WHERE id = ? AND revoked_at IS NULL"
+        )
+        .bind(revoked_at.to_rfc3339())
+        .bind(reason)
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Treatment plan methods
+    pub async fn create_treatment_plan(&self, plan: &TreatmentPlan) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO treatment_plans (id, patient_id, title, description, planned_sessions, frequency,
+                                         start_date, target_end_date, status, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(plan.id.to_string())
+        .bind(plan.patient_id.to_string())
+        .bind(&plan.title)
+        .bind(&plan.description)
+        .bind(plan.planned_sessions)
+        .bind(&plan.frequency)
+        .bind(plan.start_date.map(|d| d.to_string()))
+        .bind(plan.target_end_date.map(|d| d.to_string()))
+        .bind(plan.status.as_str())
+        .bind(plan.created_at.to_rfc3339())
+        .bind(plan.updated_at.to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+
+        for (position, goal) in plan.goals.iter().enumerate() {
+            insert_plan_goal(&mut tx, goal, position as i64 + 1).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Newest first, each with its goals
+    pub async fn get_treatment_plans_for_patient(&self, patient_id: Uuid) -> Result<Vec<TreatmentPlan>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {TREATMENT_PLAN_COLUMNS} FROM treatment_plans p WHERE p.patient_id = ? ORDER BY p.created_at DESC"
+        ))
+        .bind(patient_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut plans = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let mut plan = treatment_plan_from_row(row)?;
+            plan.goals = self.get_goals_for_plan(plan.id).await?;
+            plans.push(plan);
+        }
+        Ok(plans)
+    }
+
+    pub async fn get_treatment_plan_by_id(&self, id: Uuid) -> Result<Option<TreatmentPlan>> {
+        let row = sqlx::query(&format!(
+            "SELECT {TREATMENT_PLAN_COLUMNS} FROM treatment_plans p WHERE p.id = ?"
+        ))
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let mut plan = treatment_plan_from_row(&row)?;
+        plan.goals = self.get_goals_for_plan(plan.id).await?;
+        Ok(Some(plan))
+    }
+
+    /// Saves the plan's own fields; goals are changed one at a time
+    pub async fn update_treatment_plan(&self, id: Uuid, plan: &TreatmentPlan) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE treatment_plans
+            SET title = ?, description = ?, planned_sessions = ?, frequency = ?, start_date = ?,
+                target_end_date = ?, status = ?, updated_at = ?
+            WHERE id = ?
+            "#
+        )
+        .bind(&plan.title)
+        .bind(&plan.description)
+        .bind(plan.planned_sessions)
+        .bind(&plan.frequency)
+        .bind(plan.start_date.map(|d| d.to_string()))
+        .bind(plan.target_end_date.map(|d| d.to_string()))
+        .bind(plan.status.as_str())
+        .bind(plan.updated_at.to_rfc3339())
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Goals go with the plan; linked treatments are kept and unlinked
+    pub async fn delete_treatment_plan(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM treatment_plans WHERE id = ?"
+        )
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_goals_for_plan(&self, plan_id: Uuid) -> Result<Vec<PlanGoal>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {PLAN_GOAL_COLUMNS} FROM treatment_plan_goals WHERE plan_id = ? ORDER BY position"
+        ))
+        .bind(plan_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(plan_goal_from_row).collect()
+    }
+
+    /// Adds the goal after the plan's existing ones
+    pub async fn create_plan_goal(&self, goal: &PlanGoal) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let position: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(MAX(position), 0) + 1 FROM treatment_plan_goals WHERE plan_id = ?"
+        )
+        .bind(goal.plan_id.to_string())
+        .fetch_one(&mut *tx)
+        .await?;
+        insert_plan_goal(&mut tx, goal, position).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_plan_goal_by_id(&self, id: Uuid) -> Result<Option<PlanGoal>> {
+        let row = sqlx::query(&format!(
+            "SELECT {PLAN_GOAL_COLUMNS} FROM treatment_plan_goals WHERE id = ?"
+        ))
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(plan_goal_from_row).transpose()
+    }
+
+    pub async fn update_plan_goal(&self, id: Uuid, goal: &PlanGoal) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE treatment_plan_goals
+            SET description = ?, target_date = ?, status = ?, progress = ?, achieved_date = ?, notes = ?, updated_at = ?
+            WHERE id = ?
+            "#
+        )
+        .bind(&goal.description)
+        .bind(goal.target_date.map(|d| d.to_string()))
+        .bind(goal.status.as_str())
+        .bind(goal.progress)
+        .bind(goal.achieved_date.map(|d| d.to_string()))
+        .bind(&goal.notes)
+        .bind(goal.updated_at.to_rfc3339())
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_plan_goal(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM treatment_plan_goals WHERE id = ?"
+        )
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Report methods
+    pub async fn report_sessions(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        grouping: ReportGrouping,
+    ) -> Result<Vec<SessionsReportRow>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT strftime(?3, t.date) AS period,
+                   COUNT(*) AS sessions,
+                   COUNT(DISTINCT t.patient_id) AS patients,
+                   COALESCE(SUM(COALESCE(t.duration_minutes, tt.default_duration_minutes, 0)), 0) AS total_minutes
+            FROM treatments t
+            LEFT JOIN treatment_types tt ON tt.id = t.treatment_type_id
+            WHERE (?1 IS NULL OR julianday(t.date) >= julianday(?1))
+              AND (?2 IS NULL OR julianday(t.date) < julianday(?2))
+            GROUP BY period
+            ORDER BY period
+            "#
+        )
+        .bind(from.map(|d| d.to_rfc3339()))
+        .bind(to.map(|d| d.to_rfc3339()))
+        .bind(grouping.strftime_format())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| SessionsReportRow {
+                period: row.get("period"),
+                sessions: row.get("sessions"),
+                patients: row.get("patients"),
+                total_minutes: row.get("total_minutes"),
+            })
+            .collect())
+    }
+
+    pub async fn report_revenue(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        grouping: ReportGrouping,
+    ) -> Result<Vec<RevenueReportRow>> {
+        let invoiced = sqlx::query(
+            r#"
+            SELECT strftime(?3, issued_at) AS period,
+                   COALESCE(SUM(CASE WHEN kind = 'invoice' THEN total_cents ELSE 0 END), 0) AS invoiced_cents,
+                   COALESCE(SUM(CASE WHEN kind = 'credit_note' THEN total_cents ELSE 0 END), 0) AS credited_cents
+            FROM invoices
+            WHERE status IN ('issued', 'paid') AND issued_at IS NOT NULL
+              AND (?1 IS NULL OR julianday(issued_at) >= julianday(?1))
+              AND (?2 IS NULL OR julianday(issued_at) < julianday(?2))
+            GROUP BY period
+            "#
+        )
+        .bind(from.map(|d| d.to_rfc3339()))
+        .bind(to.map(|d| d.to_rfc3339()))
+        .bind(grouping.strftime_format())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let collected = sqlx::query(
+            r#"
+            SELECT strftime(?3, paid_at) AS period, COALESCE(SUM(amount_cents), 0) AS collected_cents
+            FROM payments
+            WHERE (?1 IS NULL OR julianday(paid_at) >= julianday(?1))
+              AND (?2 IS NULL OR julianday(paid_at) < julianday(?2))
+            GROUP BY period
+            "#
+        )
+        .bind(from.map(|d| d.to_rfc3339()))
+        .bind(to.map(|d| d.to_rfc3339()))
+        .bind(grouping.strftime_format())
+        .fetch_all(&self.pool)
+        .await?;
+
+        // Invoices and payments fall in different periods, so merge the two series by label
+        let mut periods: BTreeMap<String, RevenueReportRow> = BTreeMap::new();
+        for row in &invoiced {
+            let period: String = row.get("period");
+            let entry = periods.entry(period.clone()).or_insert_with(|| RevenueReportRow { period, ..Default::default() });
+            entry.invoiced_cents = row.get("invoiced_cents");
+            entry.credited_cents = row.get("credited_cents");
+        }
+        for row in &collected {
+            let period: String = row.get("period");
+            let entry = periods.entry(period.clone()).or_insert_with(|| RevenueReportRow { period, ..Default::default() });
+            entry.collected_cents = row.get("collected_cents");
+        }
+
+        Ok(periods
+            .into_values()
+            .map(|mut row| {
+                row.net_invoiced_cents = row.invoiced_cents - row.credited_cents;
+                row
+            })
+            .collect())
+    }
+
+    pub async fn report_patient_activity(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        grouping: ReportGrouping,
+    ) -> Result<Vec<PatientActivityRow>> {
+        let rows = sqlx::query(
+            r#"
+            WITH first_visits AS (
+                SELECT patient_id, MIN(julianday(date)) AS first_day
+                FROM treatments
+                GROUP BY patient_id
+            ),
+            visits AS (
+                SELECT DISTINCT strftime(?3, date) AS period, patient_id
+                FROM treatments
+                WHERE (?1 IS NULL OR julianday(date) >= julianday(?1))
+                  AND (?2 IS NULL OR julianday(date) < julianday(?2))
+            )
+            SELECT v.period,
+                   SUM(CASE WHEN strftime(?3, f.first_day) = v.period THEN 1 ELSE 0 END) AS new_patients,
+                   SUM(CASE WHEN strftime(?3, f.first_day) = v.period THEN 0 ELSE 1 END) AS returning_patients
+            FROM visits v
+            JOIN first_visits f ON f.patient_id = v.patient_id
+            GROUP BY v.period
+            ORDER BY v.period
+            "#
+        )
+        .bind(from.map(|d| d.to_rfc3339()))
+        .bind(to.map(|d| d.to_rfc3339()))
+        .bind(grouping.strftime_format())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| PatientActivityRow {
+                period: row.get("period"),
+                new_patients: row.get("new_patients"),
+                returning_patients: row.get("returning_patients"),
+            })
+            .collect())
+    }
+
+    pub async fn report_therapist_workload(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<TherapistWorkloadRow>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT t.therapist_id, u.name AS therapist_name,
+                   COUNT(*) AS sessions,
+                   COUNT(DISTINCT t.patient_id) AS patients,
+                   COALESCE(SUM(COALESCE(t.duration_minutes, tt.default_duration_minutes, 0)), 0) AS total_minutes
+            FROM treatments t
+            LEFT JOIN users u ON u.id = t.therapist_id
+            LEFT JOIN treatment_types tt ON tt.id = t.treatment_type_id
+            WHERE (?1 IS NULL OR julianday(t.date) >= julianday(?1))
+              AND (?2 IS NULL OR julianday(t.date) < julianday(?2))
+            GROUP BY t.therapist_id
+            ORDER BY sessions DESC
+            "#
+        )
+        .bind(from.map(|d| d.to_rfc3339()))
+        .bind(to.map(|d| d.to_rfc3339()))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| TherapistWorkloadRow {
+                therapist_id: row.get("therapist_id"),
+                therapist_name: row.get("therapist_name"),
+                sessions: row.get("sessions"),
+                patients: row.get("patients"),
+                total_minutes: row.get("total_minutes"),
+            })
+            .collect())
+    }
+
+    pub async fn report_summary(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<ReportSummary> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM patients WHERE merged_into IS NULL) AS total_patients,
+                (SELECT COUNT(*) FROM patients WHERE active = 1 AND merged_into IS NULL) AS active_patients,
+                (SELECT COUNT(*) FROM treatments
+                 WHERE (?1 IS NULL OR julianday(date) >= julianday(?1))
+                   AND (?2 IS NULL OR julianday(date) < julianday(?2))) AS sessions,
+                (SELECT COUNT(DISTINCT patient_id) FROM treatments
+                 WHERE (?1 IS NULL OR julianday(date) >= julianday(?1))
+                   AND (?2 IS NULL OR julianday(date) < julianday(?2))) AS treated_patients,
+                (SELECT COALESCE(SUM(CASE WHEN kind = 'credit_note' THEN -total_cents ELSE total_cents END), 0)
+                 FROM invoices
+                 WHERE status IN ('issued', 'paid') AND issued_at IS NOT NULL
+                   AND (?1 IS NULL OR julianday(issued_at) >= julianday(?1))
+                   AND (?2 IS NULL OR julianday(issued_at) < julianday(?2))) AS net_invoiced_cents,
+                (SELECT COALESCE(SUM(amount_cents), 0) FROM payments
+                 WHERE (?1 IS NULL OR julianday(paid_at) >= julianday(?1))
+                   AND (?2 IS NULL OR julianday(paid_at) < julianday(?2))) AS collected_cents
+            "#
+        )
+        .bind(from.map(|d| d.to_rfc3339()))
+        .bind(to.map(|d| d.to_rfc3339()))
+        .fetch_one(&self.pool)
+        .await?;
+
+        let total_patients: i64 = row.get("total_patients");
+        let active_patients: i64 = row.get("active_patients");
+        let sessions: i64 = row.get("sessions");
+        let treated_patients: i64 = row.get("treated_patients");
+
+        Ok(ReportSummary {
+            from,
+            to,
+            total_patients,
+            active_patients,
+            inactive_patients: total_patients - active_patients,
+            sessions,
+            treated_patients,
+            average_sessions_per_patient: if treated_patients > 0 {
+                sessions as f64 / treated_patients as f64
+            } else {
+                0.0
+            },
+            net_invoiced_cents: row.get("net_invoiced_cents"),
+            collected_cents: row.get("collected_cents"),
+        })
+    }
+
+    // Export template methods
+    pub async fn create_export_template(&self, template: &ExportTemplate) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO export_templates (id, name, format, description, body, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(template.id.to_string())
+        .bind(&template.name)
+        .bind(template.format.as_str())
+        .bind(&template.description)
+        .bind(&template.body)
+        .bind(template.created_at.to_rfc3339())
+        .bind(template.updated_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_export_templates(&self) -> Result<Vec<ExportTemplate>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {EXPORT_TEMPLATE_COLUMNS} FROM export_templates ORDER BY name, format"
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(export_template_from_row).collect()
+    }
+
+    pub async fn get_export_template_by_id(&self, id: Uuid) -> Result<Option<ExportTemplate>> {
+        let row = sqlx::query(&format!(
+            "SELECT {EXPORT_TEMPLATE_COLUMNS} FROM export_templates WHERE id = ?"
+        ))
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(export_template_from_row).transpose()
+    }
+
+    pub async fn get_export_template_by_name(&self, name: &str, format: ExportFormat) -> Result<Option<ExportTemplate>> {
+        let row = sqlx::query(&format!(
+            "SELECT {EXPORT_TEMPLATE_COLUMNS} FROM export_templates WHERE name = ? AND format = ?"
+        ))
+        .bind(name)
+        .bind(format.as_str())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(export_template_from_row).transpose()
+    }
+
+    pub async fn update_export_template(&self, id: Uuid, template: &ExportTemplate) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE export_templates
+            SET name = ?, format = ?, description = ?, body = ?, updated_at = ?
+            WHERE id = ?
+            "#
+        )
+        .bind(&template.name)
+        .bind(template.format.as_str())
+        .bind(&template.description)
+        .bind(&template.body)
+        .bind(template.updated_at.to_rfc3339())
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_export_template(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM export_templates WHERE id = ?"
+        )
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Whether a write was refused by the trigger guarding session package limits
+fn is_package_exhausted(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .is_some_and(|e| e.message().contains("session package exhausted"))
+}
+
+const PATIENT_COLUMNS: &str = "id, name, email, phone_number, phone_e164, description, date, active, \
+    date_of_birth, gender, national_id, address, preferred_language, referral_source, merged_into";
+
+fn patient_from_row(row: &SqliteRow) -> Result<Patient> {
+    let id_str: String = row.get("id");
+    let date_str: String = row.get("date");
+    let date_of_birth: Option<String> = row.get("date_of_birth");
+    let gender: Option<String> = row.get("gender");
+    let merged_into: Option<String> = row.get("merged_into");
+
+    Ok(Patient {
+        id: Uuid::parse_str(&id_str)?,
+        name: row.get("name"),
+        email: row.get("email"),
+        phone_number: row.get("phone_number"),
+        phone_e164: row.get("phone_e164"),
+        description: row.get("description"),
+        date: DateTime::parse_from_rfc3339(&date_str)?.with_timezone(&Utc),
+        active: row.get("active"),
+        date_of_birth: date_of_birth.map(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d")).transpose()?,
+        gender: gender.as_deref().and_then(Gender::parse),
+        national_id: row.get("national_id"),
+        address: row.get("address"),
+        preferred_language: row.get("preferred_language"),
+        referral_source: row.get("referral_source"),
+        merged_into: merged_into.map(|id| Uuid::parse_str(&id)).transpose()?,
+    })
+}
+
+const PATIENT_MERGE_COLUMNS: &str = "id, surviving_patient_id, merged_patient_id, merged_patient, \
+    treatments_moved, packages_moved, invoices_moved, records_moved, merged_patient_retained, merged_by, merged_at";
+
+/// Tables of records that belong to one patient and follow it in a merge, keyed by `patient_id`
+const PATIENT_RECORD_TABLES: &[&str] = &[
+    "patient_contacts",
+    "patient_diagnoses",
+    "patient_allergies",
+    "patient_medications",
+    "attachments",
+    "questionnaire_responses",
+    "treatment_plans",
+    "consent_signatures",
+];
+
+fn patient_merge_from_row(row: &SqliteRow) -> Result<PatientMerge> {
+    let id_str: String = row.get("id");
+    let surviving_str: String = row.get("surviving_patient_id");
+    let merged_str: String = row.get("merged_patient_id");
+    let snapshot: String = row.get("merged_patient");
+    let records_moved: String = row.get("records_moved");
+    let merged_at_str: String = row.get("merged_at");
+
+    Ok(PatientMerge {
+        id: Uuid::parse_str(&id_str)?,
+        surviving_patient_id: Uuid::parse_str(&surviving_str)?,
+        merged_patient_id: Uuid::parse_str(&merged_str)?,
+        merged_patient: serde_json::from_str(&snapshot)?,
+        treatments_moved: row.get("treatments_moved"),
+        packages_moved: row.get("packages_moved"),
+        invoices_moved: row.get("invoices_moved"),
+        records_moved: serde_json::from_str(&records_moved)?,
+        merged_patient_retained: row.get("merged_patient_retained"),
+        merged_by: row.get("merged_by"),
+        merged_at: DateTime::parse_from_rfc3339(&merged_at_str)?.with_timezone(&Utc),
+    })
+}
+
+const TREATMENT_COLUMNS: &str = "id, patient_id, summary, date, treatment_type_id, duration_minutes, package_id, plan_id, \
+    therapist_id, annotations";
+
+fn treatment_from_row(row: &SqliteRow) -> Result<Treatment> {
+    let id_str: String = row.get("id");
+    let patient_id_str: String = row.get("patient_id");
+    let date_str: String = row.get("date");
+    let treatment_type_id: Option<String> = row.get("treatment_type_id");
+    let package_id: Option<String> = row.get("package_id");
+    let plan_id: Option<String> = row.get("plan_id");
+    let annotations: String = row.get("annotations");
+
+    Ok(Treatment {
+        id: Uuid::parse_str(&id_str)?,
+        patient_id: Uuid::parse_str(&patient_id_str)?,
+        summary: row.get("summary"),
+        date: DateTime::parse_from_rfc3339(&date_str)?.with_timezone(&Utc),
+        treatment_type_id: treatment_type_id.map(|id| Uuid::parse_str(&id)).transpose()?,
+        duration_minutes: row.get("duration_minutes"),
+        package_id: package_id.map(|id| Uuid::parse_str(&id)).transpose()?,
+        plan_id: plan_id.map(|id| Uuid::parse_str(&id)).transpose()?,
+        therapist_id: row.get("therapist_id"),
+        annotations: serde_json::from_str(&annotations)?,
+    })
+}
+
+fn treatment_type_from_row(row: &SqliteRow) -> Result<TreatmentType> {
+    let id_str: String = row.get("id");
+    let created_at_str: String = row.get("created_at");
+
+    Ok(TreatmentType {
+        id: Uuid::parse_str(&id_str)?,
+        name_he: row.get("name_he"),
+        name_en: row.get("name_en"),
+        default_duration_minutes: row.get("default_duration_minutes"),
+        default_price_cents: row.get("default_price_cents"),
+        color: row.get("color"),
+        active: row.get("active"),
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+    })
+}
+
+const INVOICE_COLUMNS: &str = "id, kind, number, patient_id, status, credited_invoice_id, currency, vat_rate, \
+    subtotal_cents, vat_cents, total_cents, notes, due_date, created_at, issued_at";
+
+const PAYMENT_COLUMNS: &str = "id, invoice_id, patient_id, receipt_number, amount_cents, method, reference, paid_at, created_at";
+
+async fn insert_invoice_lines(tx: &mut Transaction<'_, Sqlite>, lines: &[InvoiceLine]) -> Result<()> {
+    for line in lines {
+        sqlx::query(
+            r#"
+            INSERT INTO invoice_lines (id, invoice_id, position, treatment_id, description, quantity, unit_price_cents, line_total_cents)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(line.id.to_string())
+        .bind(line.invoice_id.to_string())
+        .bind(line.position)
+        .bind(line.treatment_id.map(|id| id.to_string()))
+        .bind(&line.description)
+        .bind(line.quantity)
+        .bind(line.unit_price_cents)
+        .bind(line.line_total_cents)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+fn invoice_from_row(row: &SqliteRow) -> Result<Invoice> {
+    let id_str: String = row.get("id");
+    let kind_str: String = row.get("kind");
+    let patient_id_str: String = row.get("patient_id");
+    let status_str: String = row.get("status");
+    let credited_invoice_id: Option<String> = row.get("credited_invoice_id");
+    let due_date: Option<String> = row.get("due_date");
+    let created_at_str: String = row.get("created_at");
+    let issued_at: Option<String> = row.get("issued_at");
+
+    Ok(Invoice {
+        id: Uuid::parse_str(&id_str)?,
+        kind: InvoiceKind::parse(&kind_str).ok_or_else(|| anyhow!("Unknown invoice kind: {kind_str}"))?,
+        number: row.get("number"),
+        patient_id: Uuid::parse_str(&patient_id_str)?,
+        status: InvoiceStatus::parse(&status_str).ok_or_else(|| anyhow!("Unknown invoice status: {status_str}"))?,
+        credited_invoice_id: credited_invoice_id.map(|id| Uuid::parse_str(&id)).transpose()?,
+        currency: row.get("currency"),
+        vat_rate: row.get("vat_rate"),
+        subtotal_cents: row.get("subtotal_cents"),
+        vat_cents: row.get("vat_cents"),
+        total_cents: row.get("total_cents"),
+        notes: row.get("notes"),
+        due_date: due_date.map(|d| DateTime::parse_from_rfc3339(&d).map(|d| d.with_timezone(&Utc))).transpose()?,
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+        issued_at: issued_at.map(|d| DateTime::parse_from_rfc3339(&d).map(|d| d.with_timezone(&Utc))).transpose()?,
+    })
+}
+
+fn payment_from_row(row: &SqliteRow) -> Result<Payment> {
+    let id_str: String = row.get("id");
+    let invoice_id_str: String = row.get("invoice_id");
+    let patient_id_str: String = row.get("patient_id");
+    let method_str: String = row.get("method");
+    let paid_at_str: String = row.get("paid_at");
+    let created_at_str: String = row.get("created_at");
+
+    Ok(Payment {
+        id: Uuid::parse_str(&id_str)?,
+        invoice_id: Uuid::parse_str(&invoice_id_str)?,
+        patient_id: Uuid::parse_str(&patient_id_str)?,
+        receipt_number: row.get("receipt_number"),
+        amount_cents: row.get("amount_cents"),
+        method: PaymentMethod::parse(&method_str).ok_or_else(|| anyhow!("Unknown payment method: {method_str}"))?,
+        reference: row.get("reference"),
+        paid_at: DateTime::parse_from_rfc3339(&paid_at_str)?.with_timezone(&Utc),
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+    })
+}
+
+const PACKAGE_COLUMNS: &str = "id, patient_id, name, sessions_purchased, sessions_used, price_cents, \
+    purchased_at, expires_at, notes, created_at";
+
+fn package_from_row(row: &SqliteRow) -> Result<SessionPackage> {
+    let id_str: String = row.get("id");
+    let patient_id_str: String = row.get("patient_id");
+    let purchased_at_str: String = row.get("purchased_at");
+    let expires_at: Option<String> = row.get("expires_at");
+    let created_at_str: String = row.get("created_at");
+
+    Ok(SessionPackage {
+        id: Uuid::parse_str(&id_str)?,
+        patient_id: Uuid::parse_str(&patient_id_str)?,
+        name: row.get("name"),
+        sessions_purchased: row.get("sessions_purchased"),
+        sessions_used: row.get("sessions_used"),
+        price_cents: row.get("price_cents"),
+        purchased_at: DateTime::parse_from_rfc3339(&purchased_at_str)?.with_timezone(&Utc),
+        expires_at: expires_at.map(|d| DateTime::parse_from_rfc3339(&d).map(|d| d.with_timezone(&Utc))).transpose()?,
+        notes: row.get("notes"),
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+    })
+}
+
+const CONTACT_COLUMNS: &str = "id, patient_id, name, relationship, phone_number, phone_e164, email, notes, \
+    may_receive_information, legal_guardian, created_at, updated_at";
+
+fn contact_from_row(row: &SqliteRow) -> Result<PatientContact> {
+    let id_str: String = row.get("id");
+    let patient_id_str: String = row.get("patient_id");
+    let relationship: String = row.get("relationship");
+    let created_at_str: String = row.get("created_at");
+    let updated_at_str: String = row.get("updated_at");
+
+    Ok(PatientContact {
+        id: Uuid::parse_str(&id_str)?,
+        patient_id: Uuid::parse_str(&patient_id_str)?,
+        name: row.get("name"),
+        relationship: ContactRelationship::parse(&relationship)
+            .ok_or_else(|| anyhow!("Unknown contact relationship: {relationship}"))?,
+        phone_number: row.get("phone_number"),
+        phone_e164: row.get("phone_e164"),
+        email: row.get("email"),
+        notes: row.get("notes"),
+        may_receive_information: row.get("may_receive_information"),
+        legal_guardian: row.get("legal_guardian"),
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at_str)?.with_timezone(&Utc),
+    })
+}
+
+/// Calendar days are stored as `YYYY-MM-DD`
+fn optional_date(row: &SqliteRow, column: &str) -> Result<Option<NaiveDate>> {
+    let value: Option<String> = row.get(column);
+    Ok(value.map(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d")).transpose()?)
+}
+
+const DIAGNOSIS_COLUMNS: &str = "id, patient_id, icd10_code, description, start_date, end_date, notes, created_at, updated_at";
+
+fn diagnosis_from_row(row: &SqliteRow) -> Result<Diagnosis> {
+    let id_str: String = row.get("id");
+    let patient_id_str: String = row.get("patient_id");
+    let created_at_str: String = row.get("created_at");
+    let updated_at_str: String = row.get("updated_at");
+
+    Ok(Diagnosis {
+        id: Uuid::parse_str(&id_str)?,
+        patient_id: Uuid::parse_str(&patient_id_str)?,
+        icd10_code: row.get("icd10_code"),
+        description: row.get("description"),
+        start_date: optional_date(row, "start_date")?,
+        end_date: optional_date(row, "end_date")?,
+        notes: row.get("notes"),
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at_str)?.with_timezone(&Utc),
+    })
+}
+
+const ALLERGY_COLUMNS: &str = "id, patient_id, allergen, reaction, severity, start_date, end_date, notes, created_at, updated_at";
+
+fn allergy_from_row(row: &SqliteRow) -> Result<Allergy> {
+    let id_str: String = row.get("id");
+    let patient_id_str: String = row.get("patient_id");
+    let severity: String = row.get("severity");
+    let created_at_str: String = row.get("created_at");
+    let updated_at_str: String = row.get("updated_at");
+
+    Ok(Allergy {
+        id: Uuid::parse_str(&id_str)?,
+        patient_id: Uuid::parse_str(&patient_id_str)?,
+        allergen: row.get("allergen"),
+        reaction: row.get("reaction"),
+        severity: AllergySeverity::parse(&severity).ok_or_else(|| anyhow!("Unknown allergy severity: {severity}"))?,
+        start_date: optional_date(row, "start_date")?,
+        end_date: optional_date(row, "end_date")?,
+        notes: row.get("notes"),
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at_str)?.with_timezone(&Utc),
+    })
+}
+
+const MEDICATION_COLUMNS: &str = "id, patient_id, name, dosage, frequency, start_date, end_date, notes, created_at, updated_at";
+
+fn medication_from_row(row: &SqliteRow) -> Result<Medication> {
+    let id_str: String = row.get("id");
+    let patient_id_str: String = row.get("patient_id");
+    let created_at_str: String = row.get("created_at");
+    let updated_at_str: String = row.get("updated_at");
+
+    Ok(Medication {
+        id: Uuid::parse_str(&id_str)?,
+        patient_id: Uuid::parse_str(&patient_id_str)?,
+        name: row.get("name"),
+        dosage: row.get("dosage"),
+        frequency: row.get("frequency"),
+        start_date: optional_date(row, "start_date")?,
+        end_date: optional_date(row, "end_date")?,
+        notes: row.get("notes"),
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at_str)?.with_timezone(&Utc),
+    })
+}
+
+const ATTACHMENT_COLUMNS: &str = "id, patient_id, treatment_id, file_name, content_type, size_bytes, sha256, description, \
+    uploaded_by, has_thumbnail, created_at";
+
+fn attachment_from_row(row: &SqliteRow) -> Result<Attachment> {
+    let id_str: String = row.get("id");
+    let patient_id_str: String = row.get("patient_id");
+    let treatment_id: Option<String> = row.get("treatment_id");
+    let created_at_str: String = row.get("created_at");
+
+    Ok(Attachment {
+        id: Uuid::parse_str(&id_str)?,
+        patient_id: Uuid::parse_str(&patient_id_str)?,
+        treatment_id: treatment_id.map(|id| Uuid::parse_str(&id)).transpose()?,
+        file_name: row.get("file_name"),
+        content_type: row.get("content_type"),
+        size_bytes: row.get("size_bytes"),
+        sha256: row.get("sha256"),
+        description: row.get("description"),
+        uploaded_by: row.get("uploaded_by"),
+        has_thumbnail: row.get("has_thumbnail"),
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+    })
+}
+
+const QUESTIONNAIRE_COLUMNS: &str = "id, name, description, questions, scoring, higher_is_better, active, created_at, updated_at";
+
+fn questionnaire_from_row(row: &SqliteRow) -> Result<Questionnaire> {
+    let id_str: String = row.get("id");
+    let questions: String = row.get("questions");
+    let scoring: String = row.get("scoring");
+    let created_at_str: String = row.get("created_at");
+    let updated_at_str: String = row.get("updated_at");
+
+    Ok(Questionnaire {
+        id: Uuid::parse_str(&id_str)?,
+        name: row.get("name"),
+        description: row.get("description"),
+        questions: serde_json::from_str(&questions)?,
+        scoring: ScoringMethod::parse(&scoring).ok_or_else(|| anyhow!("Unknown scoring method: {scoring}"))?,
+        higher_is_better: row.get("higher_is_better"),
+        active: row.get("active"),
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at_str)?.with_timezone(&Utc),
+    })
+}
+
+/// Response columns, with the questionnaire's name joined in as `q`
+const QUESTIONNAIRE_RESPONSE_COLUMNS: &str = "r.id, r.patient_id, r.questionnaire_id, q.name AS questionnaire_name, \
+    r.treatment_id, r.answers, r.score, r.notes, r.completed_at, r.recorded_by, r.created_at";
+
+fn questionnaire_response_from_row(row: &SqliteRow) -> Result<QuestionnaireResponse> {
+    let id_str: String = row.get("id");
+    let patient_id_str: String = row.get("patient_id");
+    let questionnaire_id_str: String = row.get("questionnaire_id");
+    let treatment_id: Option<String> = row.get("treatment_id");
+    let answers: String = row.get("answers");
+    let completed_at_str: String = row.get("completed_at");
+    let created_at_str: String = row.get("created_at");
+
+    Ok(QuestionnaireResponse {
+        id: Uuid::parse_str(&id_str)?,
+        patient_id: Uuid::parse_str(&patient_id_str)?,
+        questionnaire_id: Uuid::parse_str(&questionnaire_id_str)?,
+        questionnaire_name: row.get("questionnaire_name"),
+        treatment_id: treatment_id.map(|id| Uuid::parse_str(&id)).transpose()?,
+        answers: serde_json::from_str(&answers)?,
+        score: row.get("score"),
+        notes: row.get("notes"),
+        completed_at: DateTime::parse_from_rfc3339(&completed_at_str)?.with_timezone(&Utc),
+        recorded_by: row.get("recorded_by"),
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+    })
+}
+
+const TREATMENT_PLAN_COLUMNS: &str = "p.id, p.patient_id, p.title, p.description, p.planned_sessions, p.frequency, \
+    p.start_date, p.target_end_date, p.status, p.created_at, p.updated_at, \
+    (SELECT COUNT(*) FROM treatments t WHERE t.plan_id = p.id) AS sessions_done";
+
+/// Goals are left empty and loaded separately
+fn treatment_plan_from_row(row: &SqliteRow) -> Result<TreatmentPlan> {
+    let id_str: String = row.get("id");
+    let patient_id_str: String = row.get("patient_id");
+    let status: String = row.get("status");
+    let created_at_str: String = row.get("created_at");
+    let updated_at_str: String = row.get("updated_at");
+
+    Ok(TreatmentPlan {
+        id: Uuid::parse_str(&id_str)?,
+        patient_id: Uuid::parse_str(&patient_id_str)?,
+        title: row.get("title"),
+        description: row.get("description"),
+        planned_sessions: row.get("planned_sessions"),
+        frequency: row.get("frequency"),
+        start_date: optional_date(row, "start_date")?,
+        target_end_date: optional_date(row, "target_end_date")?,
+        status: PlanStatus::parse(&status).ok_or_else(|| anyhow!("Unknown plan status: {status}"))?,
+        sessions_done: row.get("sessions_done"),
+        goals: Vec::new(),
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at_str)?.with_timezone(&Utc),
+    })
+}
+
+const PLAN_GOAL_COLUMNS: &str = "id, plan_id, description, target_date, status, progress, achieved_date, notes, \
+    created_at, updated_at";
+
+async fn insert_plan_goal(tx: &mut Transaction<'_, Sqlite>, goal: &PlanGoal, position: i64) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO treatment_plan_goals (id, plan_id, position, description, target_date, status, progress,
+                                          achieved_date, notes, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#
+    )
+    .bind(goal.id.to_string())
+    .bind(goal.plan_id.to_string())
+    .bind(position)
+    .bind(&goal.description)
+    .bind(goal.target_date.map(|d| d.to_string()))
+    .bind(goal.status.as_str())
+    .bind(goal.progress)
+    .bind(goal.achieved_date.map(|d| d.to_string()))
+    .bind(&goal.notes)
+    .bind(goal.created_at.to_rfc3339())
+    .bind(goal.updated_at.to_rfc3339())
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+fn plan_goal_from_row(row: &SqliteRow) -> Result<PlanGoal> {
+    let id_str: String = row.get("id");
+    let plan_id_str: String = row.get("plan_id");
+    let status: String = row.get("status");
+    let created_at_str: String = row.get("created_at");
+    let updated_at_str: String = row.get("updated_at");
+
+    Ok(PlanGoal {
+        id: Uuid::parse_str(&id_str)?,
+        plan_id: Uuid::parse_str(&plan_id_str)?,
+        description: row.get("description"),
+        target_date: optional_date(row, "target_date")?,
+        status: GoalStatus::parse(&status).ok_or_else(|| anyhow!("Unknown goal status: {status}"))?,
+        progress: row.get("progress"),
+        achieved_date: optional_date(row, "achieved_date")?,
+        notes: row.get("notes"),
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at_str)?.with_timezone(&Utc),
+    })
+}
+
+const CONSENT_FORM_COLUMNS: &str = "id, kind, title, body, version, required, active, created_at, updated_at";
+
+fn consent_form_from_row(row: &SqliteRow) -> Result<ConsentForm> {
+    let id_str: String = row.get("id");
+    let kind: String = row.get("kind");
+    let created_at_str: String = row.get("created_at");
+    let updated_at_str: String = row.get("updated_at");
+
+    Ok(ConsentForm {
+        id: Uuid::parse_str(&id_str)?,
+        kind: ConsentKind::parse(&kind).ok_or_else(|| anyhow!("Unknown consent kind: {kind}"))?,
+        title: row.get("title"),
+        body: row.get("body"),
+        version: row.get("version"),
+        required: row.get("required"),
+        active: row.get("active"),
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at_str)?.with_timezone(&Utc),
+    })
+}
+
+const CONSENT_FORM_VERSION_COLUMNS: &str = "form_id, version, title, body, created_by, created_at";
+
+async fn insert_consent_form_version(tx: &mut Transaction<'_, Sqlite>, version: &ConsentFormVersion) -> Result<()> {
+    sqlx::query(&format!(
+        "INSERT INTO consent_form_versions ({CONSENT_FORM_VERSION_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?)"
+    ))
+    .bind(version.form_id.to_string())
+    .bind(version.version)
+    .bind(&version.title)
+    .bind(&version.body)
+    .bind(&version.created_by)
+    .bind(version.created_at.to_rfc3339())
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+fn consent_form_version_from_row(row: &SqliteRow) -> Result<ConsentFormVersion> {
+    let form_id_str: String = row.get("form_id");
+    let created_at_str: String = row.get("created_at");
+
+    Ok(ConsentFormVersion {
+        form_id: Uuid::parse_str(&form_id_str)?,
+        version: row.get("version"),
+        title: row.get("title"),
+        body: row.get("body"),
+        created_by: row.get("created_by"),
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+    })
+}
+
+/// Everything but the signature image, which is only read when asked for
+const CONSENT_SIGNATURE_COLUMNS: &str = "id, patient_id, form_id, form_version, kind, title, rendered_body, \
+    rendered_sha256, signer_name, signer_contact_id, signature_content_type, signed_at, ip_address, forwarded_for, \
+    user_agent, recorded_by, revoked_at, revoke_reason";
+
+fn consent_signature_from_row(row: &SqliteRow) -> Result<ConsentSignature> {
+    let id_str: String = row.get("id");
+    let patient_id_str: String = row.get("patient_id");
+    let form_id_str: String = row.get("form_id");
+    let kind: String = row.get("kind");
+    let signer_contact_id: Option<String> = row.get("signer_contact_id");
+    let signed_at_str: String = row.get("signed_at");
+    let revoked_at: Option<String> = row.get("revoked_at");
+
+    Ok(ConsentSignature {
+        id: Uuid::parse_str(&id_str)?,
+        patient_id: Uuid::parse_str(&patient_id_str)?,
+        form_id: Uuid::parse_str(&form_id_str)?,
+        form_version: row.get("form_version"),
+        kind: ConsentKind::parse(&kind).ok_or_else(|| anyhow!("Unknown consent kind: {kind}"))?,
+        title: row.get("title"),
+        rendered_body: row.get("rendered_body"),
+        rendered_sha256: row.get("rendered_sha256"),
+        signer_name: row.get("signer_name"),
+        signer_contact_id: signer_contact_id.map(|id| Uuid::parse_str(&id)).transpose()?,
+        signature_content_type: row.get("signature_content_type"),
+        signed_at: DateTime::parse_from_rfc3339(&signed_at_str)?.with_timezone(&Utc),
+        ip_address: row.get("ip_address"),
+        forwarded_for: row.get("forwarded_for"),
+        user_agent: row.get("user_agent"),
+        recorded_by: row.get("recorded_by"),
+        revoked_at: revoked_at
+            .map(|d| DateTime::parse_from_rfc3339(&d).map(|d| d.with_timezone(&Utc)))
+            .transpose()?,
+        revoke_reason: row.get("revoke_reason"),
+    })
+}
+
+const EXPORT_TEMPLATE_COLUMNS: &str = "id, name, format, description, body, created_at, updated_at";
+
+fn export_template_from_row(row: &SqliteRow) -> Result<ExportTemplate> {
+    let id_str: String = row.get("id");
+    let format_str: String = row.get("format");
+    let created_at_str: String = row.get("created_at");
+    let updated_at_str: String = row.get("updated_at");
+
+    Ok(ExportTemplate {
+        id: Uuid::parse_str(&id_str)?,
+        name: row.get("name"),
+        format: ExportFormat::parse(&format_str).ok_or_else(|| anyhow!("Unknown export format: {format_str}"))?,
+        description: row.get("description"),
+        body: row.get("body"),
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at_str)?.with_timezone(&Utc),
+    })
 }
@@ -0,0 +1,224 @@
+use chrono::Utc;
+
+use super::rtf::RtfDocument;
+use super::DocumentWriter;
+use super::ClinicInfo;
+use crate::i18n::Localizer;
+use crate::models::{Invoice, InvoiceDetail, InvoiceKind, InvoiceStatus, Patient, Payment, PaymentMethod};
+
+struct BillingLabels<'a> {
+    invoice: &'a str,
+    credit_note: &'a str,
+    receipt: &'a str,
+    draft: &'a str,
+    number: &'a str,
+    date: &'a str,
+    due_date: &'a str,
+    billed_to: &'a str,
+    phone: &'a str,
+    description: &'a str,
+    quantity: &'a str,
+    unit_price: &'a str,
+    amount: &'a str,
+    subtotal: &'a str,
+    vat: &'a str,
+    total: &'a str,
+    paid: &'a str,
+    credited: &'a str,
+    balance_due: &'a str,
+    credit_for_invoice: &'a str,
+    for_invoice: &'a str,
+    amount_received: &'a str,
+    payment_method: &'a str,
+    cash: &'a str,
+    card: &'a str,
+    bank_transfer: &'a str,
+    reference: &'a str,
+    notes: &'a str,
+    tax_id: &'a str,
+    generated_on: &'a str,
+}
+
+fn get_billing_labels(l: &Localizer) -> BillingLabels<'_> {
+    BillingLabels {
+        invoice: l.text("billing.invoice"),
+        credit_note: l.text("billing.credit_note"),
+        receipt: l.text("billing.receipt"),
+        draft: l.text("billing.draft"),
+        number: l.text("billing.number"),
+        date: l.text("billing.date"),
+        due_date: l.text("billing.due_date"),
+        billed_to: l.text("billing.billed_to"),
+        phone: l.text("billing.phone"),
+        description: l.text("billing.description"),
+        quantity: l.text("billing.quantity"),
+        unit_price: l.text("billing.unit_price"),
+        amount: l.text("billing.amount"),
+        subtotal: l.text("billing.subtotal"),
+        vat: l.text("billing.vat"),
+        total: l.text("billing.total"),
+        paid: l.text("billing.paid"),
+        credited: l.text("billing.credited"),
+        balance_due: l.text("billing.balance_due"),
+        credit_for_invoice: l.text("billing.credit_for_invoice"),
+        for_invoice: l.text("billing.for_invoice"),
+        amount_received: l.text("billing.amount_received"),
+        payment_method: l.text("billing.payment_method"),
+        cash: l.text("billing.cash"),
+        card: l.text("billing.card"),
+        bank_transfer: l.text("billing.bank_transfer"),
+        reference: l.text("billing.reference"),
+        notes: l.text("billing.notes"),
+        tax_id: l.text("billing.tax_id"),
+        generated_on: l.text("billing.generated_on"),
+    }
+}
+
+/// Document number as printed, e.g. `000042`, or the draft marker before issue
+fn document_number(invoice: &Invoice, labels: &BillingLabels) -> String {
+    match invoice.number {
+        Some(number) => format!("{number:06}"),
+        None => labels.draft.to_string(),
+    }
+}
+
+/// File name for a billing document, carrying its number when it has one
+pub fn invoice_filename(invoice: &Invoice) -> String {
+    let prefix = match invoice.kind {
+        InvoiceKind::Invoice => "invoice",
+        InvoiceKind::CreditNote => "credit_note",
+    };
+    match invoice.number {
+        Some(number) => format!("{prefix}_{number:06}.rtf"),
+        None => format!("{prefix}_draft_{}.rtf", &invoice.id.simple().to_string()[..8]),
+    }
+}
+
+pub fn receipt_filename(payment: &Payment) -> String {
+    format!("receipt_{:06}.rtf", payment.receipt_number)
+}
+
+/// Render an invoice or credit note with its lines, VAT and payment summary
+pub fn render_invoice(
+    detail: &InvoiceDetail,
+    credited_invoice: Option<&Invoice>,
+    patient: &Patient,
+    clinic: &ClinicInfo,
+    language: &str,
+) -> String {
+    let l = Localizer::new(language);
+    let labels = get_billing_labels(&l);
+    let invoice = &detail.invoice;
+    let money = |cents: i64| l.format_money(cents, &invoice.currency);
+
+    let mut doc = RtfDocument::new(l.code());
+    clinic.write_letterhead(&mut doc, &l, labels.tax_id);
+
+    let title = match invoice.kind {
+        InvoiceKind::Invoice => labels.invoice,
+        InvoiceKind::CreditNote => labels.credit_note,
+    };
+    doc.title(&format!("{title} {} {}", labels.number, document_number(invoice, &labels)));
+
+    if invoice.status == InvoiceStatus::Draft {
+        doc.bold(labels.draft);
+    }
+
+    doc.field(labels.date, &l.format_short_date(&invoice.issued_at.unwrap_or(invoice.created_at)));
+    if let Some(due_date) = &invoice.due_date {
+        doc.field(labels.due_date, &l.format_short_date(due_date));
+    }
+    if let Some(original) = credited_invoice {
+        doc.field(labels.credit_for_invoice, &document_number(original, &labels));
+    }
+    doc.blank_line();
+
+    doc.heading(labels.billed_to);
+    doc.paragraph(&patient.name);
+    if !patient.phone_number.is_empty() {
+        doc.field(labels.phone, &patient.phone_number);
+    }
+    doc.blank_line();
+
+    let rows: Vec<Vec<String>> = detail.lines.iter().map(|line| vec![
+        line.description.clone(),
+        line.quantity.to_string(),
+        money(line.unit_price_cents),
+        money(line.line_total_cents),
+    ]).collect();
+    doc.table(
+        &[labels.description, labels.quantity, labels.unit_price, labels.amount],
+        &rows,
+        &[4600, 900, 1800, 1800],
+    );
+    doc.blank_line();
+
+    doc.field(labels.subtotal, &money(invoice.subtotal_cents));
+    doc.field(
+        &format!("{} ({}%)", labels.vat, format_vat_rate(invoice.vat_rate)),
+        &money(invoice.vat_cents),
+    );
+    doc.field(labels.total, &money(invoice.total_cents));
+
+    if invoice.kind == InvoiceKind::Invoice && invoice.status != InvoiceStatus::Draft {
+        if detail.credited_cents > 0 {
+            doc.field(labels.credited, &money(detail.credited_cents));
+        }
+        doc.field(labels.paid, &money(detail.paid_cents));
+        doc.field(labels.balance_due, &money(detail.amount_due_cents));
+    }
+
+    if !invoice.notes.is_empty() {
+        doc.blank_line();
+        doc.field(labels.notes, &invoice.notes);
+    }
+
+    doc.separator();
+    doc.footer(&format!("{}: {}", labels.generated_on, l.format_short_date(&Utc::now())));
+    doc.finish()
+}
+
+/// Render the receipt issued for a single payment
+pub fn render_receipt(
+    payment: &Payment,
+    invoice: &Invoice,
+    patient: &Patient,
+    clinic: &ClinicInfo,
+    language: &str,
+) -> String {
+    let l = Localizer::new(language);
+    let labels = get_billing_labels(&l);
+
+    let mut doc = RtfDocument::new(l.code());
+    clinic.write_letterhead(&mut doc, &l, labels.tax_id);
+
+    doc.title(&format!("{} {} {:06}", labels.receipt, labels.number, payment.receipt_number));
+    doc.field(labels.date, &l.format_short_date(&payment.paid_at));
+    doc.field(labels.billed_to, &patient.name);
+    doc.field(labels.for_invoice, &document_number(invoice, &labels));
+    doc.blank_line();
+
+    let method = match payment.method {
+        PaymentMethod::Cash => labels.cash,
+        PaymentMethod::Card => labels.card,
+        PaymentMethod::BankTransfer => labels.bank_transfer,
+    };
+    doc.field(labels.amount_received, &l.format_money(payment.amount_cents, &invoice.currency));
+    doc.field(labels.payment_method, method);
+    if !payment.reference.is_empty() {
+        doc.field(labels.reference, &payment.reference);
+    }
+
+    doc.separator();
+    doc.footer(&format!("{}: {}", labels.generated_on, l.format_short_date(&Utc::now())));
+    doc.finish()
+}
+
+fn format_vat_rate(rate: f64) -> String {
+    let percent = rate * 100.0;
+    if percent.fract().abs() < f64::EPSILON {
+        format!("{percent:.0}")
+    } else {
+        format!("{percent:.1}")
+    }
+}
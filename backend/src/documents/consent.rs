@@ -0,0 +1,105 @@
+use chrono::{DateTime, NaiveTime, Utc};
+use serde_json::{json, Value};
+
+use super::rtf::RtfDocument;
+use super::template::write_markup;
+use super::{ClinicInfo, DocumentWriter};
+use crate::i18n::Localizer;
+use crate::models::{ConsentSignature, Patient};
+
+/// Widest the signature is drawn, in twips (3 inches)
+const SIGNATURE_WIDTH: u32 = 4320;
+
+struct ConsentLabels<'a> {
+    tax_id: &'a str,
+    signature: &'a str,
+    signed_by: &'a str,
+    signed_at: &'a str,
+    ip_address: &'a str,
+    forwarded_for: &'a str,
+    form_version: &'a str,
+    document_hash: &'a str,
+    revoked: &'a str,
+}
+
+fn get_consent_labels(l: &Localizer) -> ConsentLabels<'_> {
+    ConsentLabels {
+        tax_id: l.text("letter.tax_id"),
+        signature: l.text("consent.signature"),
+        signed_by: l.text("consent.signed_by"),
+        signed_at: l.text("consent.signed_at"),
+        ip_address: l.text("consent.ip_address"),
+        forwarded_for: l.text("consent.forwarded_for"),
+        form_version: l.text("consent.form_version"),
+        document_hash: l.text("consent.document_hash"),
+        revoked: l.text("consent.revoked"),
+    }
+}
+
+/// Values a consent form body can refer to, e.g. `{{patient.name}}` or `{{date}}`
+pub fn consent_context(patient: &Patient, signer_name: &str, clinic: &ClinicInfo, localizer: &Localizer) -> Value {
+    json!({
+        "patient": {
+            "name": patient.name,
+            "national_id": patient.national_id,
+            "date_of_birth": patient.date_of_birth
+                .map(|d| localizer.format_short_date(&d.and_time(NaiveTime::MIN).and_utc())),
+            "address": patient.address,
+            "phone": patient.phone_number,
+        },
+        "signer": { "name": signer_name },
+        "clinic": {
+            "name": clinic.name_for(localizer.code()),
+            "address": clinic.address_for(localizer.code()),
+        },
+        "date": localizer.format_short_date(&Utc::now()),
+    })
+}
+
+fn timestamp(localizer: &Localizer, at: &DateTime<Utc>) -> String {
+    format!("{} {}", localizer.format_short_date(at), at.format("%H:%M UTC"))
+}
+
+/// The signed form as an RTF document: the text as rendered at signing, then the signature and how it was made
+pub fn render_consent(
+    signature: &ConsentSignature,
+    image: &[u8],
+    image_size: Option<(u32, u32)>,
+    clinic: &ClinicInfo,
+    language: &str,
+) -> String {
+    let l = Localizer::new(language);
+    let labels = get_consent_labels(&l);
+
+    let mut doc = RtfDocument::new(l.code());
+    clinic.write_letterhead(&mut doc, &l, labels.tax_id);
+
+    doc.title(&signature.title);
+    write_markup(&mut doc, &signature.rendered_body);
+    doc.blank_line();
+
+    doc.heading(labels.signature);
+    if let Some(size) = image_size {
+        doc.picture(image, &signature.signature_content_type, size, SIGNATURE_WIDTH);
+    }
+    doc.field(labels.signed_by, &signature.signer_name);
+    doc.field(labels.signed_at, &timestamp(&l, &signature.signed_at));
+    if let Some(ip_address) = &signature.ip_address {
+        doc.field(labels.ip_address, ip_address);
+    }
+    if let Some(forwarded_for) = &signature.forwarded_for {
+        doc.field(labels.forwarded_for, forwarded_for);
+    }
+    doc.field(labels.form_version, &signature.form_version.to_string());
+    doc.footer(&format!("{}: {}", labels.document_hash, signature.rendered_sha256));
+
+    if let Some(revoked_at) = &signature.revoked_at {
+        doc.blank_line();
+        let mut revoked = format!("{}: {}", labels.revoked, timestamp(&l, revoked_at));
+        if !signature.revoke_reason.is_empty() {
+            revoked.push_str(&format!(" - {}", signature.revoke_reason));
+        }
+        doc.bold(&revoked);
+    }
+    doc.finish()
+}
@@ -0,0 +1,332 @@
+use anyhow::Result;
+use chrono::Utc;
+
+use super::zip::ZipWriter;
+use super::DocumentWriter;
+use crate::i18n::{Direction, Localizer};
+
+const XML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+const WORDML_NS: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+
+/// A4 page with 2 cm side margins, in twips
+const PAGE_WIDTH: u32 = 11906;
+const PAGE_HEIGHT: u32 = 16838;
+const MARGIN_SIDE: u32 = 1134;
+const MARGIN_VERTICAL: u32 = 1440;
+
+/// Printable width available to tables, in twips
+pub const TEXT_WIDTH: u32 = PAGE_WIDTH - 2 * MARGIN_SIDE;
+
+#[derive(Clone, Copy)]
+struct RunStyle {
+    bold: bool,
+    italic: bool,
+}
+
+const PLAIN: RunStyle = RunStyle { bold: false, italic: false };
+const BOLD: RunStyle = RunStyle { bold: true, italic: false };
+const ITALIC: RunStyle = RunStyle { bold: false, italic: true };
+
+/// Office Open XML (DOCX) writer for patient exports.
+///
+/// Word handles Hebrew itself once paragraphs are marked right-to-left, so
+/// unlike the PDF writer no bidi reordering happens here: paragraphs carry
+/// `w:bidi`, tables `w:bidiVisual`, and the default run language is set so
+/// spell checking and complex-script fonts pick the right script.
+pub struct DocxDocument {
+    body: String,
+    rtl: bool,
+    language_tag: String,
+    title: String,
+    author: String,
+}
+
+impl DocxDocument {
+    pub fn new(language: &str) -> Self {
+        let localizer = Localizer::new(language);
+
+        Self {
+            body: String::new(),
+            rtl: localizer.direction() == Direction::Rtl,
+            language_tag: localizer.tag().to_string(),
+            title: String::new(),
+            author: String::new(),
+        }
+    }
+
+    /// Core properties shown in Word's document info panel
+    pub fn set_metadata(&mut self, title: &str, author: &str) {
+        self.title = title.to_string();
+        self.author = author.to_string();
+    }
+
+    fn push_paragraph(&mut self, style: Option<&str>, runs: &[(&str, RunStyle)]) {
+        self.body.push_str("<w:p>");
+        self.body.push_str(&self.paragraph_properties(style, ""));
+        for (text, run_style) in runs {
+            self.body.push_str(&run(text, *run_style));
+        }
+        self.body.push_str("</w:p>");
+    }
+
+    fn paragraph_properties(&self, style: Option<&str>, extra: &str) -> String {
+        let mut properties = String::from("<w:pPr>");
+        if let Some(style) = style {
+            properties.push_str(&format!("<w:pStyle w:val=\"{style}\"/>"));
+        }
+        properties.push_str(extra);
+        if self.rtl {
+            properties.push_str("<w:bidi/>");
+        }
+        properties.push_str("</w:pPr>");
+        properties
+    }
+
+    /// Bordered table with a repeating header row; `widths` are column widths
+    /// in twips, given in reading order
+    pub fn table(&mut self, header: &[&str], rows: &[Vec<String>], widths: &[u32]) {
+        self.body.push_str("<w:tbl><w:tblPr><w:tblStyle w:val=\"TableGrid\"/>");
+        if self.rtl {
+            self.body.push_str("<w:bidiVisual/>");
+        }
+        self.body.push_str(&format!(
+            "<w:tblW w:w=\"{}\" w:type=\"dxa\"/><w:tblLayout w:type=\"fixed\"/></w:tblPr><w:tblGrid>",
+            widths.iter().sum::<u32>()
+        ));
+        for width in widths {
+            self.body.push_str(&format!("<w:gridCol w:w=\"{width}\"/>"));
+        }
+        self.body.push_str("</w:tblGrid>");
+
+        let header = header.iter().map(|h| h.to_string()).collect::<Vec<_>>();
+        self.table_row(&header, widths, true);
+        for row in rows {
+            self.table_row(row, widths, false);
+        }
+        self.body.push_str("</w:tbl>");
+    }
+
+    fn table_row(&mut self, cells: &[String], widths: &[u32], header: bool) {
+        self.body.push_str("<w:tr>");
+        if header {
+            self.body.push_str("<w:trPr><w:tblHeader/></w:trPr>");
+        }
+        for (cell, width) in cells.iter().zip(widths) {
+            self.body.push_str(&format!("<w:tc><w:tcPr><w:tcW w:w=\"{width}\" w:type=\"dxa\"/>"));
+            if header {
+                self.body.push_str("<w:shd w:val=\"clear\" w:color=\"auto\" w:fill=\"E7E6E6\"/>");
+            }
+            self.body.push_str("</w:tcPr><w:p>");
+            self.body.push_str(&self.paragraph_properties(Some("TableText"), ""));
+            self.body.push_str(&run(cell, if header { BOLD } else { PLAIN }));
+            self.body.push_str("</w:p></w:tc>");
+        }
+        self.body.push_str("</w:tr>");
+    }
+
+    /// Package the document parts into a DOCX file
+    pub fn finish(self) -> Result<Vec<u8>> {
+        let mut zip = ZipWriter::new(Vec::new());
+        zip.add_file("[Content_Types].xml", CONTENT_TYPES.as_bytes())?;
+        zip.add_file("_rels/.rels", PACKAGE_RELS.as_bytes())?;
+        zip.add_file("docProps/core.xml", self.core_properties().as_bytes())?;
+        zip.add_file("docProps/app.xml", APP_PROPERTIES.as_bytes())?;
+        zip.add_file("word/_rels/document.xml.rels", DOCUMENT_RELS.as_bytes())?;
+        zip.add_file("word/styles.xml", self.styles().as_bytes())?;
+        zip.add_file("word/document.xml", self.document().as_bytes())?;
+        zip.finish()
+    }
+
+    fn document(&self) -> String {
+        format!(
+            "{XML_HEADER}<w:document xmlns:w=\"{WORDML_NS}\"><w:body>{}\
+             <w:sectPr><w:pgSz w:w=\"{PAGE_WIDTH}\" w:h=\"{PAGE_HEIGHT}\"/>\
+             <w:pgMar w:top=\"{MARGIN_VERTICAL}\" w:right=\"{MARGIN_SIDE}\" w:bottom=\"{MARGIN_VERTICAL}\" \
+             w:left=\"{MARGIN_SIDE}\" w:header=\"708\" w:footer=\"708\" w:gutter=\"0\"/>{}</w:sectPr>\
+             </w:body></w:document>",
+            self.body,
+            if self.rtl { "<w:bidi/>" } else { "" }
+        )
+    }
+
+    fn styles(&self) -> String {
+        let bidi = if self.rtl { "<w:bidi/>" } else { "" };
+        let language_tag = &self.language_tag;
+        // Complex-script runs default to Hebrew unless the document is in another RTL language
+        let bidi_tag = if self.rtl { language_tag.as_str() } else { "he-IL" };
+
+        format!(
+            "{XML_HEADER}<w:styles xmlns:w=\"{WORDML_NS}\">\
+             <w:docDefaults><w:rPrDefault><w:rPr>\
+             <w:rFonts w:ascii=\"Arial\" w:hAnsi=\"Arial\" w:eastAsia=\"Arial\" w:cs=\"Arial\"/>\
+             <w:sz w:val=\"22\"/><w:szCs w:val=\"22\"/>\
+             <w:lang w:val=\"{language_tag}\" w:eastAsia=\"en-US\" w:bidi=\"{bidi_tag}\"/>\
+             </w:rPr></w:rPrDefault>\
+             <w:pPrDefault><w:pPr>{bidi}<w:spacing w:after=\"120\" w:line=\"264\" w:lineRule=\"auto\"/></w:pPr></w:pPrDefault>\
+             </w:docDefaults>\
+             <w:style w:type=\"paragraph\" w:default=\"1\" w:styleId=\"Normal\"><w:name w:val=\"Normal\"/><w:qFormat/></w:style>\
+             <w:style w:type=\"paragraph\" w:styleId=\"Title\"><w:name w:val=\"Title\"/><w:basedOn w:val=\"Normal\"/>\
+             <w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:spacing w:after=\"240\"/></w:pPr>\
+             <w:rPr><w:b/><w:bCs/><w:sz w:val=\"36\"/><w:szCs w:val=\"36\"/></w:rPr></w:style>\
+             <w:style w:type=\"paragraph\" w:styleId=\"Heading1\"><w:name w:val=\"heading 1\"/><w:basedOn w:val=\"Normal\"/>\
+             <w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before=\"240\" w:after=\"120\"/>\
+             <w:outlineLvl w:val=\"0\"/></w:pPr>\
+             <w:rPr><w:b/><w:bCs/><w:color w:val=\"1F3864\"/><w:sz w:val=\"28\"/><w:szCs w:val=\"28\"/></w:rPr></w:style>\
+             <w:style w:type=\"paragraph\" w:styleId=\"TableText\"><w:name w:val=\"Table Text\"/><w:basedOn w:val=\"Normal\"/>\
+             <w:pPr><w:spacing w:before=\"40\" w:after=\"40\"/></w:pPr><w:rPr><w:sz w:val=\"20\"/><w:szCs w:val=\"20\"/></w:rPr></w:style>\
+             <w:style w:type=\"paragraph\" w:styleId=\"Note\"><w:name w:val=\"Note\"/><w:basedOn w:val=\"Normal\"/>\
+             <w:rPr><w:i/><w:iCs/><w:color w:val=\"595959\"/><w:sz w:val=\"16\"/><w:szCs w:val=\"16\"/></w:rPr></w:style>\
+             <w:style w:type=\"table\" w:default=\"1\" w:styleId=\"TableNormal\"><w:name w:val=\"Normal Table\"/>\
+             <w:tblPr><w:tblInd w:w=\"0\" w:type=\"dxa\"/><w:tblCellMar><w:top w:w=\"0\" w:type=\"dxa\"/>\
+             <w:left w:w=\"108\" w:type=\"dxa\"/><w:bottom w:w=\"0\" w:type=\"dxa\"/><w:right w:w=\"108\" w:type=\"dxa\"/>\
+             </w:tblCellMar></w:tblPr></w:style>\
+             <w:style w:type=\"table\" w:styleId=\"TableGrid\"><w:name w:val=\"Table Grid\"/><w:basedOn w:val=\"TableNormal\"/>\
+             <w:tblPr><w:tblBorders><w:top w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>\
+             <w:left w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>\
+             <w:bottom w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>\
+             <w:right w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>\
+             <w:insideH w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>\
+             <w:insideV w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/></w:tblBorders></w:tblPr></w:style>\
+             </w:styles>"
+        )
+    }
+
+    fn core_properties(&self) -> String {
+        let now = Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+
+        format!(
+            "{XML_HEADER}<cp:coreProperties \
+             xmlns:cp=\"http://schemas.openxmlformats.org/package/2006/metadata/core-properties\" \
+             xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:dcterms=\"http://purl.org/dc/terms/\" \
+             xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\
+             <dc:title>{title}</dc:title><dc:creator>{author}</dc:creator>\
+             <cp:lastModifiedBy>{author}</cp:lastModifiedBy><dc:language>{language}</dc:language>\
+             <dcterms:created xsi:type=\"dcterms:W3CDTF\">{now}</dcterms:created>\
+             <dcterms:modified xsi:type=\"dcterms:W3CDTF\">{now}</dcterms:modified>\
+             </cp:coreProperties>",
+            title = escape(&self.title),
+            author = escape(&self.author),
+            language = escape(&self.language_tag),
+        )
+    }
+}
+
+impl DocumentWriter for DocxDocument {
+    fn title(&mut self, text: &str) {
+        self.push_paragraph(Some("Title"), &[(text, PLAIN)]);
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.push_paragraph(Some("Heading1"), &[(text, PLAIN)]);
+    }
+
+    fn field(&mut self, label: &str, value: &str) {
+        self.push_paragraph(None, &[(&format!("{label}: "), BOLD), (value, PLAIN)]);
+    }
+
+    fn paragraph(&mut self, text: &str) {
+        self.push_paragraph(None, &[(text, PLAIN)]);
+    }
+
+    fn bold(&mut self, text: &str) {
+        self.push_paragraph(None, &[(text, BOLD)]);
+    }
+
+    fn italic(&mut self, text: &str) {
+        self.push_paragraph(None, &[(text, ITALIC)]);
+    }
+
+    fn blank_line(&mut self) {
+        self.push_paragraph(None, &[]);
+    }
+
+    fn separator(&mut self) {
+        let border = "<w:pBdr><w:bottom w:val=\"single\" w:sz=\"6\" w:space=\"1\" w:color=\"auto\"/></w:pBdr>";
+        self.body.push_str("<w:p>");
+        self.body.push_str(&self.paragraph_properties(None, border));
+        self.body.push_str("</w:p>");
+    }
+
+    /// Small italic line at the end of the document
+    fn footer(&mut self, text: &str) {
+        self.push_paragraph(Some("Note"), &[(text, PLAIN)]);
+    }
+}
+
+/// A run of text; line breaks inside the text become `w:br`
+fn run(text: &str, style: RunStyle) -> String {
+    let mut out = String::from("<w:r>");
+    if style.bold || style.italic {
+        out.push_str("<w:rPr>");
+        if style.bold {
+            out.push_str("<w:b/><w:bCs/>");
+        }
+        if style.italic {
+            out.push_str("<w:i/><w:iCs/>");
+        }
+        out.push_str("</w:rPr>");
+    }
+
+    for (index, line) in text.split('\n').enumerate() {
+        if index > 0 {
+            out.push_str("<w:br/>");
+        }
+        out.push_str(&format!("<w:t xml:space=\"preserve\">{}</w:t>", escape(line.trim_end_matches('\r'))));
+    }
+
+    out.push_str("</w:r>");
+    out
+}
+
+/// Escape text for XML content and attribute values, dropping characters XML 1.0 cannot carry
+pub fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            '\t' | '\n' | '\r' => result.push(ch),
+            c if (c as u32) < 0x20 || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+const CONTENT_TYPES: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+<Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+<Override PartName=\"/word/document.xml\" \
+ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml\"/>\
+<Override PartName=\"/word/styles.xml\" \
+ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml\"/>\
+<Override PartName=\"/docProps/core.xml\" ContentType=\"application/vnd.openxmlformats-package.core-properties+xml\"/>\
+<Override PartName=\"/docProps/app.xml\" \
+ContentType=\"application/vnd.openxmlformats-officedocument.extended-properties+xml\"/>\
+</Types>";
+
+const PACKAGE_RELS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+<Relationship Id=\"rId1\" \
+Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" Target=\"word/document.xml\"/>\
+<Relationship Id=\"rId2\" \
+Type=\"http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties\" Target=\"docProps/core.xml\"/>\
+<Relationship Id=\"rId3\" \
+Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/extended-properties\" Target=\"docProps/app.xml\"/>\
+</Relationships>";
+
+const DOCUMENT_RELS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+<Relationship Id=\"rId1\" \
+Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles\" Target=\"styles.xml\"/>\
+</Relationships>";
+
+const APP_PROPERTIES: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Properties xmlns=\"http://schemas.openxmlformats.org/officeDocument/2006/extended-properties\">\
+<Application>Treatment Manager</Application></Properties>";
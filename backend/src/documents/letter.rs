@@ -0,0 +1,53 @@
+use chrono::Utc;
+
+use super::rtf::RtfDocument;
+use super::DocumentWriter;
+use super::ClinicInfo;
+use crate::i18n::Localizer;
+use crate::models::{LetterRequest, Patient};
+
+struct LetterLabels<'a> {
+    date: &'a str,
+    to: &'a str,
+    regarding: &'a str,
+    tax_id: &'a str,
+    sincerely: &'a str,
+}
+
+fn get_letter_labels(l: &Localizer) -> LetterLabels<'_> {
+    LetterLabels {
+        date: l.text("letter.date"),
+        to: l.text("letter.to"),
+        regarding: l.text("letter.regarding"),
+        tax_id: l.text("letter.tax_id"),
+        sincerely: l.text("letter.sincerely"),
+    }
+}
+
+/// Render a letter about a patient on the clinic letterhead
+pub fn render_letter(letter: &LetterRequest, patient: &Patient, clinic: &ClinicInfo, language: &str) -> String {
+    let l = Localizer::new(language);
+    let labels = get_letter_labels(&l);
+
+    let mut doc = RtfDocument::new(l.code());
+    clinic.write_letterhead(&mut doc, &l, labels.tax_id);
+
+    doc.field(labels.date, &l.format_short_date(&Utc::now()));
+    if let Some(recipient) = letter.recipient.as_deref().filter(|r| !r.is_empty()) {
+        doc.field(labels.to, recipient);
+    }
+    doc.blank_line();
+
+    doc.title(&letter.title);
+    doc.field(labels.regarding, &patient.name);
+    doc.blank_line();
+
+    for paragraph in letter.body.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        doc.paragraph(paragraph);
+        doc.blank_line();
+    }
+
+    doc.paragraph(labels.sincerely);
+    doc.bold(letter.signature.as_deref().unwrap_or_else(|| clinic.name_for(l.code())));
+    doc.finish()
+}
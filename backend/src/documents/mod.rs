@@ -0,0 +1,101 @@
+pub mod rtf;
+pub mod billing;
+pub mod letter;
+pub mod consent;
+pub mod pdf;
+pub mod docx;
+pub mod zip;
+pub mod template;
+
+use std::env;
+
+use crate::i18n::Localizer;
+use rtf::RtfDocument;
+
+/// Block-level operations shared by the document writers, so a layout written
+/// once can be rendered to any export format
+pub trait DocumentWriter {
+    fn title(&mut self, text: &str);
+    fn heading(&mut self, text: &str);
+    fn field(&mut self, label: &str, value: &str);
+    fn paragraph(&mut self, text: &str);
+    fn bold(&mut self, text: &str);
+    fn italic(&mut self, text: &str);
+    fn blank_line(&mut self);
+    fn separator(&mut self);
+    /// Small italic line at the end of the document
+    fn footer(&mut self, text: &str);
+}
+
+/// Clinic letterhead details, read from the `CLINIC_*` environment variables
+#[derive(Debug, Clone)]
+pub struct ClinicInfo {
+    pub name: String,
+    pub name_he: Option<String>,
+    pub address: String,
+    pub address_he: Option<String>,
+    pub phone: String,
+    pub email: String,
+    pub tax_id: String,
+}
+
+impl ClinicInfo {
+    pub fn from_env() -> Self {
+        let var = |key: &str| env::var(key).ok().filter(|v| !v.trim().is_empty());
+
+        Self {
+            name: var("CLINIC_NAME").unwrap_or_else(|| "Treatment Clinic".to_string()),
+            name_he: var("CLINIC_NAME_HE"),
+            address: var("CLINIC_ADDRESS").unwrap_or_default(),
+            address_he: var("CLINIC_ADDRESS_HE"),
+            phone: var("CLINIC_PHONE").unwrap_or_default(),
+            email: var("CLINIC_EMAIL").unwrap_or_default(),
+            tax_id: var("CLINIC_TAX_ID").unwrap_or_default(),
+        }
+    }
+
+    pub fn name_for(&self, language: &str) -> &str {
+        match (language, &self.name_he) {
+            ("he", Some(name_he)) => name_he,
+            _ => &self.name,
+        }
+    }
+
+    pub fn address_for(&self, language: &str) -> &str {
+        match (language, &self.address_he) {
+            ("he", Some(address_he)) => address_he,
+            _ => &self.address,
+        }
+    }
+
+    /// Write the clinic name and contact lines at the top of a document
+    pub fn write_letterhead(&self, doc: &mut RtfDocument, localizer: &Localizer, tax_id_label: &str) {
+        doc.centered(self.name_for(localizer.code()), 32, true);
+
+        let address = self.address_for(localizer.code());
+        if !address.is_empty() {
+            doc.centered(address, 18, false);
+        }
+
+        let contact: Vec<&str> = [self.phone.as_str(), self.email.as_str()]
+            .into_iter()
+            .filter(|v| !v.is_empty())
+            .collect();
+        if !contact.is_empty() {
+            doc.centered(&contact.join(" | "), 18, false);
+        }
+
+        if !self.tax_id.is_empty() {
+            doc.centered(&format!("{tax_id_label}: {}", self.tax_id), 18, false);
+        }
+
+        doc.separator();
+    }
+}
+
+pub fn sanitize_filename(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' { c } else { '_' })
+        .collect::<String>()
+        .replace(' ', "_")
+}
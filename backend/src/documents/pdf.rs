@@ -0,0 +1,598 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::ops::Range;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{Datelike, Timelike, Utc};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use pdf_writer::types::{CidFontType, Direction, FontFlags, SystemInfo, UnicodeCmap};
+use pdf_writer::{Content, Date, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use unicode_bidi::{Level, ParagraphBidiInfo};
+
+use super::DocumentWriter;
+use crate::i18n::{self, Localizer};
+
+// A4 in points
+const PAGE_WIDTH: f32 = 595.28;
+const PAGE_HEIGHT: f32 = 841.89;
+const MARGIN_X: f32 = 56.0;
+const MARGIN_TOP: f32 = 72.0;
+const MARGIN_BOTTOM: f32 = 64.0;
+
+const BODY_SIZE: f32 = 11.0;
+const LINE_SPACING: f32 = 1.45;
+/// Horizontal shear for italics; only upright faces are embedded
+const ITALIC_SKEW: f32 = 0.2;
+
+const DEFAULT_FONT_PATH: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+const DEFAULT_BOLD_FONT_PATH: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSans-Bold.ttf";
+
+const IDENTITY: SystemInfo = SystemInfo {
+    registry: Str(b"Adobe"),
+    ordering: Str(b"Identity"),
+    supplement: 0,
+};
+
+/// TrueType font embedded as a CID font. Glyph use is tracked so the
+/// embedded copy only carries the outlines the document needs.
+pub struct PdfFont {
+    data: Vec<u8>,
+    base_name: String,
+    units_per_em: f32,
+    ascender: i16,
+    descender: i16,
+    cap_height: i16,
+    bbox: ttf_parser::Rect,
+    /// Glyph id and advance (in 1/1000 em) per character
+    glyphs: HashMap<char, (u16, f32)>,
+    used: BTreeMap<u16, (char, f32)>,
+}
+
+impl PdfFont {
+    pub fn load(path: &str) -> Result<Self> {
+        let data = fs::read(path).with_context(|| format!("Failed to read PDF font {path}"))?;
+        let face = ttf_parser::Face::parse(&data, 0).map_err(|e| anyhow!("Invalid PDF font {path}: {e}"))?;
+
+        let base_name = face
+            .names()
+            .into_iter()
+            .find(|name| name.name_id == ttf_parser::name_id::POST_SCRIPT_NAME)
+            .and_then(|name| name.to_string())
+            .unwrap_or_else(|| "EmbeddedFont".to_string());
+        let units_per_em = f32::from(face.units_per_em());
+        let ascender = face.ascender();
+        let descender = face.descender();
+        let cap_height = face.capital_height().unwrap_or(ascender);
+        let bbox = face.global_bounding_box();
+
+        Ok(Self {
+            data,
+            base_name,
+            units_per_em,
+            ascender,
+            descender,
+            cap_height,
+            bbox,
+            glyphs: HashMap::new(),
+            used: BTreeMap::new(),
+        })
+    }
+
+    fn glyph(&mut self, c: char) -> (u16, f32) {
+        if let Some(glyph) = self.glyphs.get(&c) {
+            return *glyph;
+        }
+
+        // The face was validated in `load`, so parsing again cannot fail in practice
+        let glyph = match ttf_parser::Face::parse(&self.data, 0) {
+            Ok(face) => {
+                let id = face.glyph_index(c).unwrap_or(ttf_parser::GlyphId(0));
+                let advance = face.glyph_hor_advance(id).unwrap_or(0);
+                (id.0, f32::from(advance) * 1000.0 / self.units_per_em)
+            }
+            Err(_) => (0, 0.0),
+        };
+        self.glyphs.insert(c, glyph);
+        glyph
+    }
+
+    fn width(&mut self, c: char, size: f32) -> f32 {
+        self.glyph(c).1 * size / 1000.0
+    }
+
+    /// Record a glyph as used and return its Identity-H code
+    fn use_glyph(&mut self, c: char) -> (u16, f32) {
+        let (id, advance) = self.glyph(c);
+        self.used.entry(id).or_insert((c, advance));
+        (id, advance)
+    }
+
+    fn scale(&self, value: i16) -> f32 {
+        f32::from(value) * 1000.0 / self.units_per_em
+    }
+}
+
+/// Regular and bold faces, read from `PDF_FONT_PATH` and `PDF_FONT_BOLD_PATH`
+pub struct PdfFonts {
+    regular: PdfFont,
+    bold: PdfFont,
+}
+
+impl PdfFonts {
+    pub fn from_env() -> Result<Self> {
+        let regular_path = env::var("PDF_FONT_PATH").unwrap_or_else(|_| DEFAULT_FONT_PATH.to_string());
+        let bold_path = env::var("PDF_FONT_BOLD_PATH").unwrap_or_else(|_| DEFAULT_BOLD_FONT_PATH.to_string());
+
+        let regular = PdfFont::load(&regular_path)?;
+        // Without a bold face headings fall back to the regular one
+        let bold = PdfFont::load(&bold_path).or_else(|_| PdfFont::load(&regular_path))?;
+
+        Ok(Self { regular, bold })
+    }
+
+    fn get(&mut self, style: TextStyle) -> &mut PdfFont {
+        if style.bold { &mut self.bold } else { &mut self.regular }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TextStyle {
+    size: f32,
+    bold: bool,
+    italic: bool,
+    gray: f32,
+}
+
+impl TextStyle {
+    const fn new(size: f32, bold: bool) -> Self {
+        Self { size, bold, italic: false, gray: 0.0 }
+    }
+}
+
+const REGULAR: TextStyle = TextStyle::new(BODY_SIZE, false);
+const BOLD: TextStyle = TextStyle::new(BODY_SIZE, true);
+
+#[derive(Debug, Clone, Copy)]
+enum Align {
+    Start,
+    Center,
+}
+
+/// Incremental PDF writer with the same block operations as `RtfDocument`.
+///
+/// Text is wrapped in logical order and each line is then reordered with the
+/// Unicode bidi algorithm, so Hebrew and mixed Hebrew/English lines come out
+/// in the right visual order. Pages get a running header and a "page x of y"
+/// footer once the page count is known.
+pub struct PdfDocument {
+    rtl: bool,
+    language: String,
+    fonts: PdfFonts,
+    pages: Vec<Content>,
+    y: f32,
+    title: String,
+    author: String,
+    header: String,
+    page_label: String,
+}
+
+impl PdfDocument {
+    pub fn new(language: &str, fonts: PdfFonts) -> Self {
+        Self {
+            rtl: i18n::is_rtl(language),
+            language: Localizer::new(language).tag().to_string(),
+            fonts,
+            pages: Vec::new(),
+            y: 0.0,
+            title: String::new(),
+            author: String::new(),
+            header: String::new(),
+            page_label: String::new(),
+        }
+    }
+
+    /// Title and author recorded in the document properties
+    pub fn set_metadata(&mut self, title: &str, author: &str) {
+        self.title = title.to_string();
+        self.author = author.to_string();
+    }
+
+    /// Text repeated at the top of every page, and the footer label where
+    /// `{page}` and `{pages}` are replaced with the page number and count
+    pub fn set_running_text(&mut self, header: &str, page_label: &str) {
+        self.header = header.to_string();
+        self.page_label = page_label.to_string();
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Content::new());
+        self.y = PAGE_HEIGHT - MARGIN_TOP;
+    }
+
+    fn ensure_space(&mut self, height: f32) {
+        if self.pages.is_empty() || self.y - height < MARGIN_BOTTOM {
+            self.new_page();
+        }
+    }
+
+    fn char_width(&mut self, c: char, style: TextStyle) -> f32 {
+        self.fonts.get(style).width(c, style.size)
+    }
+
+    /// Break a line into byte ranges that fit `max_width`, preferring spaces
+    fn wrap(&mut self, text: &str, styles: &[TextStyle], max_width: f32) -> Vec<Range<usize>> {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        let mut width = 0.0;
+        let mut last_break: Option<(usize, f32)> = None;
+
+        for (i, c) in text.char_indices() {
+            let w = self.char_width(c, styles[i]);
+            if width + w > max_width && i > start {
+                match last_break.filter(|(at, _)| *at > start) {
+                    Some((at, width_at)) => {
+                        lines.push(start..at);
+                        start = at;
+                        width -= width_at;
+                    }
+                    None => {
+                        lines.push(start..i);
+                        start = i;
+                        width = 0.0;
+                    }
+                }
+                last_break = None;
+            }
+
+            width += w;
+            if c == ' ' {
+                last_break = Some((i + 1, width));
+            }
+        }
+
+        lines.push(start..text.len());
+        lines
+    }
+
+    /// Draw one line of `text` at baseline `y`, in visual order
+    #[allow(clippy::too_many_arguments)]
+    fn draw_line(
+        &mut self,
+        page: usize,
+        y: f32,
+        text: &str,
+        styles: &[TextStyle],
+        bidi: &ParagraphBidiInfo,
+        line: Range<usize>,
+        align: Align,
+    ) {
+        let (levels, runs) = bidi.visual_runs(line);
+
+        // Consecutive glyphs with the same style are shown with one operator
+        let mut segments: Vec<(TextStyle, Vec<u8>, f32)> = Vec::new();
+        let mut total_width = 0.0;
+
+        for run in runs {
+            let rtl = levels[run.start].is_rtl();
+            let mut chars: Vec<(usize, char)> = text[run.clone()]
+                .char_indices()
+                .map(|(i, c)| (run.start + i, c))
+                .collect();
+            if rtl {
+                chars.reverse();
+            }
+
+            for (i, c) in chars {
+                let style = styles[i];
+                let c = if rtl { mirror(c) } else { c };
+                let (id, advance) = self.fonts.get(style).use_glyph(c);
+                let width = advance * style.size / 1000.0;
+
+                match segments.last_mut() {
+                    Some((last, bytes, w)) if *last == style => {
+                        bytes.extend(id.to_be_bytes());
+                        *w += width;
+                    }
+                    _ => segments.push((style, id.to_be_bytes().to_vec(), width)),
+                }
+                total_width += width;
+            }
+        }
+
+        let mut x = match align {
+            Align::Start if self.rtl => PAGE_WIDTH - MARGIN_X - total_width,
+            Align::Start => MARGIN_X,
+            Align::Center => (PAGE_WIDTH - total_width) / 2.0,
+        };
+
+        let content = &mut self.pages[page];
+        content.begin_text();
+        for (style, bytes, width) in &segments {
+            let font: &[u8] = if style.bold { b"F2" } else { b"F1" };
+            let skew = if style.italic { ITALIC_SKEW } else { 0.0 };
+            content.set_fill_gray(style.gray);
+            content.set_font(Name(font), style.size);
+            content.set_text_matrix([1.0, 0.0, skew, 1.0, x, y]);
+            content.show(Str(bytes));
+            x += width;
+        }
+        content.end_text();
+    }
+
+    /// Lay out a paragraph made of styled spans, wrapping and breaking pages as needed
+    fn write_block(&mut self, spans: &[(&str, TextStyle)], align: Align) {
+        let mut text = String::new();
+        let mut styles = Vec::new();
+        for (span, style) in spans {
+            let span = span.replace('\r', "");
+            styles.extend(std::iter::repeat_n(*style, span.len()));
+            text.push_str(&span);
+        }
+
+        let size = spans.iter().map(|(_, style)| style.size).fold(BODY_SIZE, f32::max);
+        let line_height = size * LINE_SPACING;
+        let base_level = if self.rtl { Level::rtl() } else { Level::ltr() };
+        let max_width = PAGE_WIDTH - 2.0 * MARGIN_X;
+
+        let mut offset = 0;
+        for hard_line in text.split('\n') {
+            let line_styles = &styles[offset..offset + hard_line.len()];
+            offset += hard_line.len() + 1;
+
+            let bidi = ParagraphBidiInfo::new(hard_line, Some(base_level));
+            for range in self.wrap(hard_line, line_styles, max_width) {
+                let range = trim_spaces(hard_line, range);
+                self.ensure_space(line_height);
+                let baseline = self.y - size;
+                if !range.is_empty() {
+                    let page = self.pages.len() - 1;
+                    self.draw_line(page, baseline, hard_line, line_styles, &bidi, range, align);
+                }
+                self.y -= line_height;
+            }
+        }
+    }
+
+    /// Single unwrapped line at a fixed position, used for page headers and footers
+    fn draw_fixed(&mut self, page: usize, y: f32, text: &str, style: TextStyle, align: Align) {
+        let styles = vec![style; text.len()];
+        let base_level = if self.rtl { Level::rtl() } else { Level::ltr() };
+        let bidi = ParagraphBidiInfo::new(text, Some(base_level));
+        self.draw_line(page, y, text, &styles, &bidi, 0..text.len(), align);
+    }
+
+    fn rule(&mut self, page: usize, y: f32, gray: f32) {
+        let content = &mut self.pages[page];
+        content.set_stroke_gray(gray);
+        content.set_line_width(0.5);
+        content.move_to(MARGIN_X, y);
+        content.line_to(PAGE_WIDTH - MARGIN_X, y);
+        content.stroke();
+    }
+
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        if self.pages.is_empty() {
+            self.new_page();
+        }
+
+        let page_count = self.pages.len();
+        let small = TextStyle { gray: 0.4, ..TextStyle::new(8.0, false) };
+        for page in 0..page_count {
+            if !self.header.is_empty() {
+                let header = self.header.clone();
+                self.draw_fixed(page, PAGE_HEIGHT - 40.0, &header, small, Align::Start);
+                self.rule(page, PAGE_HEIGHT - 46.0, 0.6);
+            }
+            if !self.page_label.is_empty() {
+                let label = self.page_label
+                    .replace("{page}", &(page + 1).to_string())
+                    .replace("{pages}", &page_count.to_string());
+                self.draw_fixed(page, 32.0, &label, small, Align::Center);
+            }
+        }
+
+        let catalog_id = Ref::new(1);
+        let page_tree_id = Ref::new(2);
+        let info_id = Ref::new(3);
+        let mut ids = (4..).map(Ref::new);
+
+        let mut pdf = Pdf::new();
+
+        let mut catalog = pdf.catalog(catalog_id);
+        catalog.pages(page_tree_id);
+        catalog.pair(Name(b"Lang"), TextStr(&self.language));
+        if self.rtl {
+            catalog.viewer_preferences().direction(Direction::R2L);
+        }
+        catalog.finish();
+
+        let now = Utc::now();
+        pdf.document_info(info_id)
+            .title(TextStr(&self.title))
+            .author(TextStr(&self.author))
+            .producer(TextStr("Treatment Manager"))
+            .creation_date(
+                Date::new(now.year() as u16)
+                    .month(now.month() as u8)
+                    .day(now.day() as u8)
+                    .hour(now.hour() as u8)
+                    .minute(now.minute() as u8)
+                    .second(now.second() as u8),
+            );
+
+        let regular_id = write_font(&mut pdf, &self.fonts.regular, &mut ids, "AAAAAA")?;
+        let bold_id = write_font(&mut pdf, &self.fonts.bold, &mut ids, "AAAAAB")?;
+
+        let mut page_ids = Vec::with_capacity(page_count);
+        for content in self.pages {
+            let page_id = ids.next().unwrap_or(Ref::new(i32::MAX));
+            let content_id = ids.next().unwrap_or(Ref::new(i32::MAX));
+            page_ids.push(page_id);
+
+            pdf.stream(content_id, &deflate(&content.finish())?)
+                .filter(Filter::FlateDecode);
+
+            let mut page = pdf.page(page_id);
+            page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+                .parent(page_tree_id)
+                .contents(content_id);
+            let mut resources = page.resources();
+            let mut fonts = resources.fonts();
+            fonts.pair(Name(b"F1"), regular_id);
+            fonts.pair(Name(b"F2"), bold_id);
+            fonts.finish();
+            resources.finish();
+            page.finish();
+        }
+
+        pdf.pages(page_tree_id)
+            .kids(page_ids.iter().copied())
+            .count(page_count as i32);
+
+        Ok(pdf.finish())
+    }
+}
+
+impl DocumentWriter for PdfDocument {
+    fn title(&mut self, text: &str) {
+        self.write_block(&[(text, TextStyle::new(18.0, true))], Align::Start);
+        self.y -= 8.0;
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.y -= 4.0;
+        self.write_block(&[(text, TextStyle::new(13.0, true))], Align::Start);
+    }
+
+    fn field(&mut self, label: &str, value: &str) {
+        let label = format!("{label}: ");
+        self.write_block(&[(&label, BOLD), (value, REGULAR)], Align::Start);
+    }
+
+    fn paragraph(&mut self, text: &str) {
+        self.write_block(&[(text, REGULAR)], Align::Start);
+    }
+
+    fn bold(&mut self, text: &str) {
+        self.write_block(&[(text, BOLD)], Align::Start);
+    }
+
+    fn italic(&mut self, text: &str) {
+        self.write_block(&[(text, TextStyle { italic: true, ..REGULAR })], Align::Start);
+    }
+
+    fn blank_line(&mut self) {
+        self.y -= BODY_SIZE * LINE_SPACING * 0.6;
+    }
+
+    fn separator(&mut self) {
+        self.ensure_space(16.0);
+        self.y -= 8.0;
+        let page = self.pages.len() - 1;
+        let y = self.y;
+        self.rule(page, y, 0.7);
+        self.y -= 8.0;
+    }
+
+    fn footer(&mut self, text: &str) {
+        let style = TextStyle { italic: true, gray: 0.35, ..TextStyle::new(8.0, false) };
+        self.write_block(&[(text, style)], Align::Start);
+    }
+}
+
+/// Embed a font as a Type0/CIDFontType2 pair with Identity-H encoding and a ToUnicode map
+fn write_font(pdf: &mut Pdf, font: &PdfFont, ids: &mut impl Iterator<Item = Ref>, tag: &str) -> Result<Ref> {
+    let mut next = || ids.next().ok_or_else(|| anyhow!("Ran out of PDF object ids"));
+    let type0_id = next()?;
+    let cid_id = next()?;
+    let descriptor_id = next()?;
+    let file_id = next()?;
+    let cmap_id = next()?;
+
+    // Subset fonts are named with a six-letter tag
+    let base_name = format!("{tag}+{}", font.base_name);
+    let name = Name(base_name.as_bytes());
+
+    pdf.type0_font(type0_id)
+        .base_font(name)
+        .encoding_predefined(Name(b"Identity-H"))
+        .descendant_font(cid_id)
+        .to_unicode(cmap_id);
+
+    let mut cid = pdf.cid_font(cid_id);
+    cid.subtype(CidFontType::Type2)
+        .base_font(name)
+        .system_info(IDENTITY)
+        .font_descriptor(descriptor_id)
+        .default_width(0.0)
+        .cid_to_gid_map_predefined(Name(b"Identity"));
+    let mut widths = cid.widths();
+    for (id, (_, advance)) in &font.used {
+        widths.consecutive(*id, [*advance]);
+    }
+    widths.finish();
+    cid.finish();
+
+    pdf.font_descriptor(descriptor_id)
+        .name(name)
+        .flags(FontFlags::SYMBOLIC)
+        .bbox(Rect::new(
+            font.scale(font.bbox.x_min),
+            font.scale(font.bbox.y_min),
+            font.scale(font.bbox.x_max),
+            font.scale(font.bbox.y_max),
+        ))
+        .italic_angle(0.0)
+        .ascent(font.scale(font.ascender))
+        .descent(font.scale(font.descender))
+        .cap_height(font.scale(font.cap_height))
+        .stem_v(80.0)
+        .font_file2(file_id);
+
+    let glyphs: Vec<u16> = std::iter::once(0).chain(font.used.keys().copied()).collect();
+    let data = subsetter::subset(&font.data, 0, subsetter::Profile::pdf(&glyphs))
+        .unwrap_or_else(|_| font.data.clone());
+    pdf.stream(file_id, &deflate(&data)?)
+        .filter(Filter::FlateDecode);
+
+    let mut cmap = UnicodeCmap::new(Name(b"Custom"), IDENTITY);
+    for (id, (c, _)) in &font.used {
+        cmap.pair(*id, *c);
+    }
+    pdf.cmap(cmap_id, &cmap.finish());
+
+    Ok(type0_id)
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+fn trim_spaces(text: &str, range: Range<usize>) -> Range<usize> {
+    let line = &text[range.clone()];
+    let start = range.start + (line.len() - line.trim_start_matches(' ').len());
+    let end = range.start + line.trim_end_matches(' ').len();
+    start..end.max(start)
+}
+
+/// Mirrored counterpart of paired punctuation inside right-to-left runs
+fn mirror(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        _ => c,
+    }
+}
@@ -0,0 +1,191 @@
+use super::DocumentWriter;
+use crate::i18n;
+
+/// Incremental RTF writer shared by patient exports, billing documents and letters.
+///
+/// Right-to-left languages (Hebrew, Arabic) are written right-to-left; every paragraph carries the
+/// direction and alignment so viewers that ignore the document default still
+/// lay it out correctly.
+pub struct RtfDocument {
+    out: String,
+    rtl: bool,
+}
+
+impl RtfDocument {
+    pub fn new(language: &str) -> Self {
+        let rtl = i18n::is_rtl(language);
+        let mut out = String::new();
+
+        // RTF header with enhanced Hebrew support and RTL when needed
+        out.push_str("{\\rtf1\\ansi\\ansicpg1255\\deff0\\deflang1033\\deflangfe1037 ");
+        out.push_str("{\\fonttbl{\\f0\\fswiss\\fcharset177\\*\\fname Arial;}{\\f1\\froman\\fcharset0 Times New Roman;}}");
+        out.push_str("{\\colortbl;\\red0\\green0\\blue0;}");
+
+        if rtl {
+            // Hebrew: Right-to-left document
+            out.push_str("\\uc1\\pard\\rtlpar\\qr\\cf1\\lang1037\\f0\\fs22 ");
+        } else {
+            // English/Default: Left-to-right document
+            out.push_str("\\uc1\\pard\\ltrpar\\cf1\\lang1037\\f0\\fs22 ");
+        }
+
+        Self { out, rtl }
+    }
+
+    /// Paragraph direction and alignment for the document language
+    fn align(&self) -> &'static str {
+        if self.rtl { "\\rtlpar\\qr" } else { "\\ltrpar\\ql" }
+    }
+
+    fn direction(&self) -> &'static str {
+        if self.rtl { "\\rtlpar" } else { "\\ltrpar" }
+    }
+
+    /// Centered line, used for letterheads
+    pub fn centered(&mut self, text: &str, font_size: u32, bold: bool) {
+        let (b_on, b_off) = if bold { ("\\b ", "\\b0") } else { ("", "") };
+        self.out.push_str(&format!(
+            "{}\\qc\\fs{font_size}{b_on}{}{b_off}\\fs18\\par",
+            self.direction(),
+            escape(text)
+        ));
+    }
+
+    /// Simple bordered table; `widths` are column widths in twips, given in reading order
+    pub fn table(&mut self, header: &[&str], rows: &[Vec<String>], widths: &[u32]) {
+        self.table_row(&header.iter().map(|h| h.to_string()).collect::<Vec<_>>(), widths, true);
+        for row in rows {
+            self.table_row(row, widths, false);
+        }
+        self.out.push_str("\\pard");
+        self.out.push_str(self.align());
+    }
+
+    fn table_row(&mut self, cells: &[String], widths: &[u32], bold: bool) {
+        self.out.push_str("\\trowd\\trgaph108");
+        if self.rtl {
+            self.out.push_str("\\rtlrow");
+        }
+
+        let mut edge = 0;
+        for width in widths {
+            edge += width;
+            self.out.push_str(&format!(
+                "\\clbrdrt\\brdrs\\clbrdrl\\brdrs\\clbrdrb\\brdrs\\clbrdrr\\brdrs\\cellx{edge}"
+            ));
+        }
+
+        for cell in cells {
+            let text = if bold { format!("\\b {}\\b0", escape(cell)) } else { escape(cell) };
+            self.out.push_str(&format!("\\pard\\intbl{} {}\\cell", self.align(), text));
+        }
+        self.out.push_str("\\row");
+    }
+
+    /// PNG or JPEG picture on a line of its own, scaled down to at most `max_width` twips wide
+    pub fn picture(&mut self, data: &[u8], content_type: &str, (width, height): (u32, u32), max_width: u32) {
+        let blip = if content_type == "image/png" { "\\pngblip" } else { "\\jpegblip" };
+        // Pixels at 96 DPI are 15 twips
+        let scale = (max_width as f64 / (width.max(1) * 15) as f64).min(1.0);
+        let goal = |pixels: u32| (pixels as f64 * 15.0 * scale).round() as u32;
+
+        let mut hex = String::with_capacity(data.len() * 2);
+        for (i, byte) in data.iter().enumerate() {
+            if i > 0 && i % 64 == 0 {
+                hex.push('\n');
+            }
+            hex.push_str(&format!("{byte:02x}"));
+        }
+        self.out.push_str(&format!(
+            "{}{{\\pict{blip}\\picw{width}\\pich{height}\\picwgoal{}\\pichgoal{}\n{hex}}}\\par",
+            self.align(),
+            goal(width),
+            goal(height)
+        ));
+    }
+
+    pub fn finish(mut self) -> String {
+        // RTF footer
+        self.out.push('}');
+        self.out
+    }
+}
+
+impl DocumentWriter for RtfDocument {
+    fn title(&mut self, text: &str) {
+        self.out.push_str(&format!("{}\\f1\\fs28\\b {} \\b0\\fs24\\par\\par", self.align(), escape(text)));
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.out.push_str(&format!("{}\\fs20\\b {} \\b0\\fs18\\par", self.align(), escape(text)));
+    }
+
+    fn field(&mut self, label: &str, value: &str) {
+        self.out.push_str(&format!("{}\\b {}: \\b0 {}\\par", self.align(), escape(label), escape(value)));
+    }
+
+    fn paragraph(&mut self, text: &str) {
+        self.out.push_str(&format!("{}{}\\par", self.align(), escape(text)));
+    }
+
+    fn bold(&mut self, text: &str) {
+        self.out.push_str(&format!("{}\\b {}\\b0\\par", self.align(), escape(text)));
+    }
+
+    fn italic(&mut self, text: &str) {
+        self.out.push_str(&format!("{}\\i {}\\i0\\par", self.align(), escape(text)));
+    }
+
+    fn blank_line(&mut self) {
+        self.out.push_str("\\par");
+    }
+
+    fn separator(&mut self) {
+        self.out.push_str("\\par---\\par");
+    }
+
+    /// Small italic line at the end of the document
+    fn footer(&mut self, text: &str) {
+        self.out.push_str(&format!("{}\\fs16\\i {}\\i0\\fs18\\par", self.align(), escape(text)));
+    }
+}
+
+/// Escape text for RTF, writing everything outside Latin-1 as `\uN?` escapes
+pub fn escape(text: &str) -> String {
+    let mut result = String::new();
+
+    for ch in text.chars() {
+        match ch {
+            '\\' => result.push_str("\\\\"),
+            '{' => result.push_str("\\{"),
+            '}' => result.push_str("\\}"),
+            '\n' => result.push_str("\\par "),
+            '\r' => {}, // Skip carriage returns
+            // Handle Hebrew characters (Unicode range 0x0590-0x05FF) with signed conversion
+            c if (c as u32) >= 0x0590 && (c as u32) <= 0x05FF => {
+                let unicode_val = c as u32;
+                if unicode_val > 32767 {
+                    // For values > 32767, use negative representation
+                    let signed_val = unicode_val as i32 - 65536;
+                    result.push_str(&format!("\\u{signed_val}?"));
+                } else {
+                    result.push_str(&format!("\\u{unicode_val}?"));
+                }
+            },
+            // Handle other Unicode characters
+            c if (c as u32) > 255 => {
+                let unicode_val = c as u32;
+                if unicode_val > 32767 {
+                    let signed_val = unicode_val as i32 - 65536;
+                    result.push_str(&format!("\\u{signed_val}?"));
+                } else {
+                    result.push_str(&format!("\\u{unicode_val}?"));
+                }
+            },
+            // Regular ASCII characters
+            c => result.push(c),
+        }
+    }
+
+    result
+}
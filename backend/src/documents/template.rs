@@ -0,0 +1,434 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use serde_json::Value;
+
+use super::DocumentWriter;
+
+/// Substituted values keep their line breaks as U+2028 so a multi-line summary
+/// stays one markup line and cannot start a heading or separator of its own
+const VALUE_LINE_BREAK: char = '\u{2028}';
+
+/// Deepest nesting of `{{#each}}`/`{{#if}}` blocks; parsing and rendering recurse once per level
+const MAX_NESTING: usize = 32;
+
+/// Problem found while parsing a template, with the 1-based line it starts on
+#[derive(Debug)]
+pub struct TemplateError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Value(String),
+    Each { path: String, body: Vec<Node> },
+    If { path: String, negate: bool, then: Vec<Node>, otherwise: Vec<Node> },
+}
+
+#[derive(Debug)]
+enum Token {
+    Text(String),
+    Tag { content: String, line: usize },
+}
+
+/// Handlebars-style text template for user-editable exports.
+///
+/// Supports `{{path.to.value}}`, `{{#each list}}…{{/each}}` (with `this`,
+/// `@index`, `@number`, `@first`, `@last` and `../` for the enclosing scope),
+/// `{{#if path}}…{{else}}…{{/if}}`, `{{#unless path}}…{{/unless}}` and
+/// `{{! comments }}`. Names are looked up in the innermost scope first, then
+/// outwards, and anything missing renders as an empty string. Block tags on a
+/// line of their own take the whole line with them, as in Handlebars.
+#[derive(Debug)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let tokens = strip_standalone(tokenize(source)?);
+        let mut tokens = tokens.into_iter();
+        let (nodes, end) = parse_nodes(&mut tokens, None, 0)?;
+        if let Some((tag, line)) = end {
+            return Err(TemplateError { line, message: format!("unexpected {{{{{tag}}}}}") });
+        }
+        Ok(Self { nodes })
+    }
+
+    pub fn render(&self, context: &Value) -> String {
+        let mut out = String::new();
+        let scopes = [Scope { value: context, position: None }];
+        render_nodes(&self.nodes, &scopes, &mut out);
+        out
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, TemplateError> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    let mut line = 1;
+
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            tokens.push(Token::Text(rest[..start].to_string()));
+        }
+        line += rest[..start].matches('\n').count();
+
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            return Err(TemplateError { line, message: "unclosed {{ tag".to_string() });
+        };
+        let content = after[..end].trim().to_string();
+        tokens.push(Token::Tag { content, line });
+        line += after[..end].matches('\n').count();
+        rest = &after[end + 2..];
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest.to_string()));
+    }
+
+    Ok(tokens)
+}
+
+fn is_block_tag(content: &str) -> bool {
+    content.starts_with('#') || content.starts_with('/') || content.starts_with('!') || content == "else"
+}
+
+/// Drop the indentation and line break around block tags that sit alone on a line
+fn strip_standalone(mut tokens: Vec<Token>) -> Vec<Token> {
+    let standalone: Vec<usize> = (0..tokens.len())
+        .filter(|&i| {
+            let Token::Tag { content, .. } = &tokens[i] else { return false };
+            if !is_block_tag(content) {
+                return false;
+            }
+            let starts_line = match i.checked_sub(1).map(|j| &tokens[j]) {
+                None => true,
+                Some(Token::Text(text)) => {
+                    let tail = &text[text.rfind('\n').map_or(0, |p| p + 1)..];
+                    tail.trim().is_empty() && (text.contains('\n') || i == 1)
+                }
+                Some(Token::Tag { .. }) => false,
+            };
+            let ends_line = match tokens.get(i + 1) {
+                None => true,
+                Some(Token::Text(text)) => text[..text.find('\n').unwrap_or(text.len())].trim().is_empty(),
+                Some(Token::Tag { .. }) => false,
+            };
+            starts_line && ends_line
+        })
+        .collect();
+
+    for i in standalone {
+        if let Some(Token::Text(text)) = i.checked_sub(1).map(|j| &mut tokens[j]) {
+            let keep = text.rfind('\n').map_or(0, |p| p + 1);
+            text.truncate(keep);
+        }
+        if let Some(Token::Text(text)) = tokens.get_mut(i + 1) {
+            let skip = text.find('\n').map_or(text.len(), |p| p + 1);
+            text.drain(..skip);
+        }
+    }
+
+    tokens
+}
+
+/// Closing or `else` tag that ended a run of nodes, with its line
+type EndTag = Option<(String, usize)>;
+
+/// Parse until the end of input or a closing/`else` tag, which is returned to the caller;
+/// `depth` is the number of blocks the run is nested in
+fn parse_nodes(
+    tokens: &mut impl Iterator<Item = Token>,
+    block: Option<(&str, usize)>,
+    depth: usize,
+) -> Result<(Vec<Node>, EndTag), TemplateError> {
+    let mut nodes = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        let (content, line) = match token {
+            Token::Text(text) => {
+                if !text.is_empty() {
+                    nodes.push(Node::Text(text));
+                }
+                continue;
+            }
+            Token::Tag { content, line } => (content, line),
+        };
+
+        if content.starts_with('!') {
+            continue;
+        }
+        if content.starts_with('/') || content == "else" {
+            return Ok((nodes, Some((content, line))));
+        }
+
+        if let Some(open) = content.strip_prefix('#') {
+            let (helper, path) = open.split_once(char::is_whitespace).unwrap_or((open, ""));
+            let path = path.trim().to_string();
+            if path.is_empty() {
+                return Err(TemplateError { line, message: format!("{{{{#{helper}}}}} needs a value to test") });
+            }
+
+            if depth >= MAX_NESTING {
+                return Err(TemplateError { line, message: format!("blocks are nested more than {MAX_NESTING} deep") });
+            }
+
+            let (then, end) = parse_nodes(tokens, Some((helper, line)), depth + 1)?;
+            let (otherwise, end) = match end {
+                Some((tag, else_line)) if tag == "else" => {
+                    if helper == "each" {
+                        return Err(TemplateError { line: else_line, message: "{{else}} is not supported in {{#each}}".to_string() });
+                    }
+                    parse_nodes(tokens, Some((helper, line)), depth + 1)?
+                }
+                end => (Vec::new(), end),
+            };
+            match end {
+                Some((tag, _)) if tag.strip_prefix('/').map(str::trim) == Some(helper) => {}
+                Some((tag, close_line)) => {
+                    return Err(TemplateError {
+                        line: close_line,
+                        message: format!("{{{{{tag}}}}} does not close {{{{#{helper}}}}} from line {line}"),
+                    });
+                }
+                None => {
+                    return Err(TemplateError { line, message: format!("{{{{#{helper}}}}} is never closed") });
+                }
+            }
+
+            nodes.push(match helper {
+                "each" => Node::Each { path, body: then },
+                "if" => Node::If { path, negate: false, then, otherwise },
+                "unless" => Node::If { path, negate: true, then, otherwise },
+                _ => return Err(TemplateError { line, message: format!("unknown block {{{{#{helper}}}}}") }),
+            });
+            continue;
+        }
+
+        if content.is_empty() {
+            return Err(TemplateError { line, message: "empty {{}} tag".to_string() });
+        }
+        nodes.push(Node::Value(content));
+    }
+
+    match block {
+        Some((helper, line)) => Err(TemplateError { line, message: format!("{{{{#{helper}}}}} is never closed") }),
+        None => Ok((nodes, None)),
+    }
+}
+
+struct Scope<'a> {
+    value: &'a Value,
+    /// Index and length of the enclosing `#each` loop
+    position: Option<(usize, usize)>,
+}
+
+fn render_nodes(nodes: &[Node], scopes: &[Scope], out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Value(path) => {
+                let text = match lookup(scopes, path).as_deref() {
+                    Some(Value::String(s)) => s.clone(),
+                    Some(Value::Number(n)) => n.to_string(),
+                    Some(Value::Bool(b)) => b.to_string(),
+                    _ => String::new(),
+                };
+                out.extend(text.chars().filter(|c| *c != '\r').map(|c| if c == '\n' { VALUE_LINE_BREAK } else { c }));
+            }
+            Node::Each { path, body } => {
+                if let Some(Value::Array(items)) = lookup(scopes, path).as_deref() {
+                    for (index, item) in items.iter().enumerate() {
+                        let mut inner: Vec<Scope> = scopes.iter().map(|s| Scope { value: s.value, position: s.position }).collect();
+                        inner.push(Scope { value: item, position: Some((index, items.len())) });
+                        render_nodes(body, &inner, out);
+                    }
+                }
+            }
+            Node::If { path, negate, then, otherwise } => {
+                if truthy(lookup(scopes, path)) != *negate {
+                    render_nodes(then, scopes, out);
+                } else {
+                    render_nodes(otherwise, scopes, out);
+                }
+            }
+        }
+    }
+}
+
+fn lookup<'a>(scopes: &[Scope<'a>], path: &str) -> Option<Cow<'a, Value>> {
+    let mut path = path;
+    let mut depth = scopes.len();
+    while let Some(rest) = path.strip_prefix("../") {
+        depth = depth.saturating_sub(1).max(1);
+        path = rest;
+    }
+    let scopes = &scopes[..depth];
+    let current = scopes.last()?;
+
+    if let Some(variable) = path.strip_prefix('@') {
+        let (index, len) = current.position?;
+        let value = match variable {
+            "index" => Value::from(index),
+            "number" => Value::from(index + 1),
+            "first" => Value::Bool(index == 0),
+            "last" => Value::Bool(index + 1 == len),
+            _ => return None,
+        };
+        return Some(Cow::Owned(value));
+    }
+    if path == "this" || path == "." {
+        return Some(Cow::Borrowed(current.value));
+    }
+    let path = path.strip_prefix("this.").unwrap_or(path);
+
+    let mut segments = path.split('.');
+    let first = segments.next()?;
+    let mut value = scopes.iter().rev().find_map(|scope| scope.value.get(first))?;
+    for segment in segments {
+        value = match value {
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => value.get(segment)?,
+        };
+    }
+    Some(Cow::Borrowed(value))
+}
+
+fn truthy(value: Option<Cow<Value>>) -> bool {
+    match value.as_deref() {
+        None | Some(Value::Null) => false,
+        Some(Value::Bool(b)) => *b,
+        Some(Value::String(s)) => !s.is_empty(),
+        Some(Value::Number(n)) => n.as_f64() != Some(0.0),
+        Some(Value::Array(items)) => !items.is_empty(),
+        Some(Value::Object(_)) => true,
+    }
+}
+
+/// Draw rendered template output with a document writer, one line per block:
+///
+/// - `# text` title, `## text` heading, `---` separator
+/// - `**Label:** value` labelled field, `**text**` bold line, `_text_` italic line
+/// - `> text` small footer line, an empty line a blank line, anything else a paragraph
+pub fn write_markup<W: DocumentWriter>(doc: &mut W, markup: &str) {
+    for line in markup.lines() {
+        let line = line.trim_end();
+        let text = |s: &str| s.trim().replace(VALUE_LINE_BREAK, "\n");
+
+        if line.trim().is_empty() {
+            doc.blank_line();
+        } else if line.trim() == "---" {
+            doc.separator();
+        } else if let Some(rest) = line.strip_prefix("## ") {
+            doc.heading(&text(rest));
+        } else if let Some(rest) = line.strip_prefix("# ") {
+            doc.title(&text(rest));
+        } else if let Some(rest) = line.strip_prefix("> ") {
+            doc.footer(&text(rest));
+        } else if let Some((label, value)) = line.strip_prefix("**").and_then(|rest| rest.split_once(":**")) {
+            doc.field(&text(label), &text(value));
+        } else if let Some(bold) = line.strip_prefix("**").and_then(|rest| rest.strip_suffix("**")) {
+            doc.bold(&text(bold));
+        } else if let Some(italic) = line.strip_prefix('_').and_then(|rest| rest.strip_suffix('_')).filter(|s| !s.is_empty()) {
+            doc.italic(&text(italic));
+        } else {
+            doc.paragraph(&text(line));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn render(source: &str, context: Value) -> String {
+        Template::parse(source).unwrap().render(&context)
+    }
+
+    fn nested_ifs(depth: usize) -> String {
+        format!("{}x{}", "{{#if a}}".repeat(depth), "{{/if}}".repeat(depth))
+    }
+
+    #[test]
+    fn substitutes_values() {
+        let context = json!({ "patient": { "name": "Dana" }, "count": 3, "active": true });
+        assert_eq!(render("{{patient.name}} {{count}} {{active}} [{{missing}}]", context), "Dana 3 true []");
+    }
+
+    #[test]
+    fn value_line_breaks_stay_on_one_line() {
+        assert_eq!(render("{{summary}}", json!({ "summary": "a\r\nb" })), "a\u{2028}b");
+    }
+
+    #[test]
+    fn if_else_and_unless() {
+        let source = "{{#if on}}yes{{else}}no{{/if}}/{{#unless on}}off{{/unless}}";
+        assert_eq!(render(source, json!({ "on": true })), "yes/");
+        assert_eq!(render(source, json!({ "on": "" })), "no/off");
+        assert_eq!(render(source, json!({ "on": [] })), "no/off");
+    }
+
+    #[test]
+    fn each_with_position_and_parent_scope() {
+        let source = "{{#each items}}{{@number}}.{{name}}{{../sep}}{{#if @last}}!{{/if}}{{/each}}";
+        let context = json!({ "sep": ";", "items": [{ "name": "a" }, { "name": "b" }] });
+        assert_eq!(render(source, context), "1.a;2.b;!");
+    }
+
+    #[test]
+    fn standalone_block_tags_take_their_line() {
+        let source = "start\n{{#if on}}\nmiddle\n{{/if}}\nend\n";
+        assert_eq!(render(source, json!({ "on": true })), "start\nmiddle\nend\n");
+        assert_eq!(render(source, json!({ "on": false })), "start\nend\n");
+    }
+
+    #[test]
+    fn comments_render_nothing() {
+        assert_eq!(render("a{{! note }}b", json!({})), "ab");
+    }
+
+    #[test]
+    fn reports_unclosed_and_mismatched_blocks() {
+        let error = Template::parse("one\n{{#if a}}").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(error.message.contains("never closed"));
+
+        let error = Template::parse("{{#if a}}\n{{/each}}").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(Template::parse("{{/if}}").is_err());
+        assert!(Template::parse("{{#each items}}{{else}}{{/each}}").is_err());
+        assert!(Template::parse("{{#with a}}{{/with}}").is_err());
+        assert!(Template::parse("{{#if}}{{/if}}").is_err());
+        assert!(Template::parse("{{name").is_err());
+        assert!(Template::parse("{{}}").is_err());
+    }
+
+    #[test]
+    fn nesting_up_to_the_limit_is_accepted() {
+        assert_eq!(render(&nested_ifs(MAX_NESTING), json!({ "a": true })), "x");
+    }
+
+    #[test]
+    fn nesting_past_the_limit_is_rejected() {
+        let error = Template::parse(&nested_ifs(MAX_NESTING + 1)).unwrap_err();
+        assert!(error.message.contains("nested"));
+    }
+
+    #[test]
+    fn deeply_nested_template_fails_without_overflowing() {
+        assert!(Template::parse(&nested_ifs(6000)).is_err());
+    }
+}
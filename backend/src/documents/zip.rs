@@ -0,0 +1,167 @@
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use chrono::{Datelike, Timelike, Utc};
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+
+const VERSION: u16 = 20;
+/// General purpose flag bit 11: file names are UTF-8
+const UTF8_NAMES: u16 = 1 << 11;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+struct CentralEntry {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Minimal ZIP writer for generated files (DOCX packages, bulk exports).
+///
+/// Each entry is compressed in memory and written in one piece, so sizes and
+/// CRCs are known before the local header goes out and the output never has to
+/// be seekable. The same writer can fill a buffer or feed a streaming response.
+/// Archives are limited to the classic (non-ZIP64) 4 GB / 65535 entry format.
+pub struct ZipWriter<W: Write> {
+    out: W,
+    offset: u64,
+    entries: Vec<CentralEntry>,
+    dos_time: u16,
+    dos_date: u16,
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(out: W) -> Self {
+        let now = Utc::now();
+        let dos_time = ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16;
+        let dos_date = (((now.year() - 1980).max(0) as u32) << 9 | (now.month() << 5) | now.day()) as u16;
+
+        Self {
+            out,
+            offset: 0,
+            entries: Vec::new(),
+            dos_time,
+            dos_date,
+        }
+    }
+
+    /// Add a file, deflated unless compression would make it larger
+    pub fn add_file(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let deflated = encoder.finish()?;
+
+        let (method, payload) = if deflated.len() < data.len() {
+            (METHOD_DEFLATED, deflated.as_slice())
+        } else {
+            (METHOD_STORED, data)
+        };
+
+        let (Ok(offset), Ok(size), Ok(compressed_size)) = (
+            u32::try_from(self.offset),
+            u32::try_from(data.len()),
+            u32::try_from(payload.len()),
+        ) else {
+            bail!("ZIP archive exceeds 4 GB");
+        };
+        if self.entries.len() >= usize::from(u16::MAX) {
+            bail!("ZIP archive exceeds {} entries", u16::MAX);
+        }
+
+        let entry = CentralEntry {
+            name: name.to_string(),
+            method,
+            crc: crc32fast::hash(data),
+            compressed_size,
+            size,
+            offset,
+        };
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        put_u32(&mut header, LOCAL_HEADER_SIGNATURE);
+        put_u16(&mut header, VERSION);
+        put_u16(&mut header, UTF8_NAMES);
+        put_u16(&mut header, entry.method);
+        put_u16(&mut header, self.dos_time);
+        put_u16(&mut header, self.dos_date);
+        put_u32(&mut header, entry.crc);
+        put_u32(&mut header, entry.compressed_size);
+        put_u32(&mut header, entry.size);
+        put_u16(&mut header, name.len() as u16);
+        put_u16(&mut header, 0);
+        header.extend_from_slice(name.as_bytes());
+
+        self.out.write_all(&header)?;
+        self.out.write_all(payload)?;
+        self.offset += (header.len() + payload.len()) as u64;
+        self.entries.push(entry);
+
+        Ok(())
+    }
+
+    /// The underlying writer; streaming callers drain a buffer here between entries
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    /// Write the central directory and return the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            put_u32(&mut directory, CENTRAL_HEADER_SIGNATURE);
+            put_u16(&mut directory, VERSION);
+            put_u16(&mut directory, VERSION);
+            put_u16(&mut directory, UTF8_NAMES);
+            put_u16(&mut directory, entry.method);
+            put_u16(&mut directory, self.dos_time);
+            put_u16(&mut directory, self.dos_date);
+            put_u32(&mut directory, entry.crc);
+            put_u32(&mut directory, entry.compressed_size);
+            put_u32(&mut directory, entry.size);
+            put_u16(&mut directory, entry.name.len() as u16);
+            put_u16(&mut directory, 0); // extra field length
+            put_u16(&mut directory, 0); // comment length
+            put_u16(&mut directory, 0); // disk number
+            put_u16(&mut directory, 0); // internal attributes
+            put_u32(&mut directory, 0); // external attributes
+            put_u32(&mut directory, entry.offset);
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+
+        let (Ok(directory_offset), Ok(directory_size)) =
+            (u32::try_from(self.offset), u32::try_from(directory.len()))
+        else {
+            bail!("ZIP archive exceeds 4 GB");
+        };
+
+        let count = self.entries.len() as u16;
+        put_u32(&mut directory, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u16(&mut directory, 0);
+        put_u16(&mut directory, 0);
+        put_u16(&mut directory, count);
+        put_u16(&mut directory, count);
+        put_u32(&mut directory, directory_size);
+        put_u32(&mut directory, directory_offset);
+        put_u16(&mut directory, 0);
+
+        self.out.write_all(&directory)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
@@ -0,0 +1,316 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::models::{DuplicateCandidate, Patient};
+
+/// Weights of the evidence that two records describe the same person; the total is capped at 1.
+/// Same-name pairs reach 0.5 on their own, a shared phone or email only together with a similar name.
+const NAME_WEIGHT: f64 = 0.5;
+const PHONE_WEIGHT: f64 = 0.3;
+const EMAIL_WEIGHT: f64 = 0.3;
+
+/// Names in the same script that only match by sound are discounted, so "Dan" and "Dana" stay apart
+const SAME_SCRIPT_SOUND_FACTOR: f64 = 0.9;
+
+/// Default `min_score` for candidate lists
+pub const DEFAULT_MIN_SCORE: f64 = 0.5;
+
+/// Lowest `min_score` accepted; below it nearly every pair would be listed
+pub const MIN_SCORE_FLOOR: f64 = 0.3;
+
+/// What is compared, computed once per patient
+struct MatchKeys {
+    /// Lowercased name with its words sorted
+    plain: String,
+    /// Consonant skeletons of the name, one per reading of ambiguous Hebrew letters
+    sounds: Vec<String>,
+    hebrew: bool,
+    phone: Option<String>,
+    email: Option<String>,
+    /// Groups the record is compared within: its phone, email, name and the sound of each word of it.
+    /// Records sharing none of them are never compared.
+    blocks: Vec<String>,
+}
+
+impl MatchKeys {
+    fn new(patient: &Patient) -> Self {
+        let plain = sorted_words(&patient.name, str::to_lowercase);
+        let phone = patient.phone_e164.clone();
+        let email = patient
+            .email
+            .as_deref()
+            .map(|email| email.trim().to_lowercase())
+            .filter(|email| !email.is_empty());
+
+        let mut blocks: Vec<String> = word_sounds(&patient.name).into_iter().map(|sound| format!("sound:{sound}")).collect();
+        if !plain.is_empty() {
+            blocks.push(format!("name:{plain}"));
+        }
+        blocks.extend(phone.iter().map(|phone| format!("phone:{phone}")));
+        blocks.extend(email.iter().map(|email| format!("email:{email}")));
+
+        Self {
+            sounds: sound_keys(&patient.name),
+            hebrew: patient.name.chars().any(is_hebrew),
+            plain,
+            phone,
+            email,
+            blocks,
+        }
+    }
+
+    fn shares_block(&self, other: &MatchKeys) -> bool {
+        self.blocks.iter().any(|block| other.blocks.contains(block))
+    }
+}
+
+/// How strongly two records match
+struct Evidence {
+    score: f64,
+    name_similarity: f64,
+    same_phone: bool,
+    same_email: bool,
+}
+
+impl Evidence {
+    fn candidate(self, patient: &Patient, duplicate: &Patient) -> DuplicateCandidate {
+        DuplicateCandidate {
+            patient: patient.clone(),
+            duplicate: duplicate.clone(),
+            score: self.score,
+            name_similarity: self.name_similarity,
+            same_phone: self.same_phone,
+            same_email: self.same_email,
+        }
+    }
+}
+
+/// Pairs of records scoring at least `min_score`, best matches first: at most `limit` of them,
+/// with the number found in all
+pub fn find_candidates(patients: &[Patient], min_score: f64, limit: usize) -> (Vec<DuplicateCandidate>, usize) {
+    let keys: Vec<MatchKeys> = patients.iter().map(MatchKeys::new).collect();
+    let mut blocks: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, record) in keys.iter().enumerate() {
+        for block in &record.blocks {
+            blocks.entry(block).or_default().push(index);
+        }
+    }
+    // Members are listed in index order, so each pair comes out as (lower, higher)
+    let pairs: BTreeSet<(usize, usize)> = blocks
+        .values()
+        .flat_map(|members| {
+            members.iter().enumerate().flat_map(move |(n, &i)| members[n + 1..].iter().map(move |&j| (i, j)))
+        })
+        .collect();
+
+    let mut matches: Vec<(usize, usize, Evidence)> = pairs
+        .into_iter()
+        .filter_map(|(i, j)| compare(&keys[i], &keys[j], min_score).map(|evidence| (i, j, evidence)))
+        .collect();
+    matches.sort_by(|a, b| b.2.score.total_cmp(&a.2.score));
+    let total = matches.len();
+    let candidates = matches
+        .into_iter()
+        .take(limit)
+        .map(|(i, j, evidence)| evidence.candidate(&patients[i], &patients[j]))
+        .collect();
+    (candidates, total)
+}
+
+/// Records among `others` that may be the same person as `patient`
+pub fn candidates_for(patient: &Patient, others: &[Patient], min_score: f64) -> Vec<DuplicateCandidate> {
+    let keys = MatchKeys::new(patient);
+    let mut candidates: Vec<DuplicateCandidate> = others
+        .iter()
+        .filter(|other| other.id != patient.id)
+        .filter_map(|other| {
+            let other_keys = MatchKeys::new(other);
+            if !keys.shares_block(&other_keys) {
+                return None;
+            }
+            compare(&keys, &other_keys, min_score).map(|evidence| evidence.candidate(patient, other))
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    candidates
+}
+
+fn compare(a: &MatchKeys, b: &MatchKeys, min_score: f64) -> Option<Evidence> {
+    let name = name_similarity(a, b);
+    let same_phone = a.phone.is_some() && a.phone == b.phone;
+    let same_email = a.email.is_some() && a.email == b.email;
+
+    let mut score = name * NAME_WEIGHT;
+    if same_phone {
+        score += PHONE_WEIGHT;
+    }
+    if same_email {
+        score += EMAIL_WEIGHT;
+    }
+    let score = round(score.min(1.0));
+
+    (score >= min_score).then_some(Evidence { score, name_similarity: round(name), same_phone, same_email })
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+fn name_similarity(a: &MatchKeys, b: &MatchKeys) -> f64 {
+    let factor = if a.hebrew == b.hebrew { SAME_SCRIPT_SOUND_FACTOR } else { 1.0 };
+    let sound = a
+        .sounds
+        .iter()
+        .flat_map(|x| b.sounds.iter().map(move |y| similarity(x, y)))
+        .fold(0.0, f64::max);
+    similarity(&a.plain, &b.plain).max(sound * factor)
+}
+
+/// 1 for equal strings, falling towards 0 with the edit distance
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 0.0;
+    }
+    1.0 - levenshtein(&a, &b) as f64 / longest as f64
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn words(name: &str) -> impl Iterator<Item = String> + '_ {
+    name.split(|c: char| c.is_whitespace() || c == '-')
+        .map(|word| word.chars().filter(|c| c.is_alphanumeric()).collect::<String>())
+}
+
+fn sorted_words(name: &str, key: impl Fn(&str) -> String) -> String {
+    let mut words: Vec<String> = words(name)
+        .map(|word| key(&word))
+        .filter(|word| !word.is_empty())
+        .collect();
+    words.sort();
+    words.join(" ")
+}
+
+fn is_hebrew(c: char) -> bool {
+    ('\u{05D0}'..='\u{05EA}').contains(&c)
+}
+
+/// How the name sounds, reduced to consonant classes shared by Hebrew and Latin spelling,
+/// so that "דוד כהן" and "David Cohen" both become "dbd khn".
+///
+/// Hebrew ו is a consonant in some names (דוד) and a vowel in others (יוסף), so a
+/// name containing it gets a key for each reading.
+fn sound_keys(name: &str) -> Vec<String> {
+    let mut keys = vec![sorted_words(name, |word| skeleton(word, false))];
+    if name.contains('ו') {
+        keys.push(sorted_words(name, |word| skeleton(word, true)));
+    }
+    keys.dedup();
+    keys
+}
+
+/// The sound of each word of the name on its own, in every reading of ו
+fn word_sounds(name: &str) -> Vec<String> {
+    let readings: &[bool] = if name.contains('ו') { &[false, true] } else { &[false] };
+    let mut sounds: Vec<String> = words(name)
+        .flat_map(|word| readings.iter().map(move |&vav_is_vowel| skeleton(&word, vav_is_vowel)))
+        .filter(|sound| !sound.is_empty())
+        .collect();
+    sounds.sort();
+    sounds.dedup();
+    sounds
+}
+
+fn skeleton(word: &str, vav_is_vowel: bool) -> String {
+    let mut chars: Vec<char> = word.to_lowercase().chars().collect();
+    // A final h is silent in both scripts (שרה, Sarah)
+    if chars.len() > 1 && matches!(chars.last(), Some('h' | 'ה')) {
+        chars.pop();
+    }
+
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let next = chars.get(i + 1).copied();
+        let (sound, consumed) = match (chars[i], next) {
+            ('s', Some('h')) => ("s", 2),
+            ('t', Some('z')) | ('t', Some('s')) => ("z", 2),
+            // Chaim, Khaim: the usual spellings of ח
+            ('c', Some('h')) | ('k', Some('h')) => ("h", 2),
+            ('c', Some('k')) => ("k", 2),
+            ('p', Some('h')) => ("p", 2),
+            ('t', Some('h')) => ("t", 2),
+            ('ו', Some('ו')) => ("b", 2),
+            (c, _) => match letter_sound(c, vav_is_vowel) {
+                Some(sound) => (sound, 1),
+                // Other scripts are compared as written
+                None => {
+                    out.push(c);
+                    i += 1;
+                    continue;
+                }
+            },
+        };
+        out.push_str(sound);
+        i += consumed;
+    }
+
+    // Doubled letters sound single
+    let mut collapsed: Vec<char> = out.chars().collect();
+    collapsed.dedup();
+    collapsed.into_iter().collect()
+}
+
+fn letter_sound(c: char, vav_is_vowel: bool) -> Option<&'static str> {
+    let sound = match c {
+        // Latin
+        'a' | 'e' | 'i' | 'o' | 'u' | 'y' => "",
+        'b' | 'v' | 'w' => "b",
+        'c' | 'k' | 'q' => "k",
+        'f' | 'p' => "p",
+        'j' | 'g' => "g",
+        'x' => "ks",
+        'd' => "d",
+        'h' => "h",
+        'l' => "l",
+        'm' => "m",
+        'n' => "n",
+        'r' => "r",
+        's' => "s",
+        't' => "t",
+        'z' => "z",
+        // Hebrew; final forms sound like their regular letters
+        'א' | 'ע' | 'י' => "",
+        'ו' if vav_is_vowel => "",
+        'ב' | 'ו' => "b",
+        'ג' => "g",
+        'ד' => "d",
+        'ה' => "h",
+        'ז' => "z",
+        'ח' => "h",
+        'כ' | 'ך' | 'ק' => "k",
+        'ט' | 'ת' => "t",
+        'ל' => "l",
+        'מ' | 'ם' => "m",
+        'נ' | 'ן' => "n",
+        'ס' | 'ש' => "s",
+        'פ' | 'ף' => "p",
+        'צ' | 'ץ' => "z",
+        'ר' => "r",
+        'à'..='å' | 'è'..='ë' | 'ì'..='ï' | 'ò'..='ö' | 'ù'..='ü' | 'ý' | 'ÿ' => "",
+        _ => return None,
+    };
+    Some(sound)
+}
@@ -0,0 +1,430 @@
+use std::fmt;
+
+use actix_multipart::MultipartError;
+use actix_web::error::{JsonPayloadError, PathError, QueryPayloadError};
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use serde_json::{json, Map, Value};
+
+use crate::i18n;
+use crate::request_context;
+use crate::validation::FieldError;
+
+/// Stable error codes returned to API clients.
+///
+/// Clients should branch on the code rather than the message; messages live in
+/// the `errors` section of the locale files (keyed by the lowercased code) and
+/// are translated into the request's language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    Internal,
+    InvalidRequest,
+    ValidationFailed,
+    AuthenticationRequired,
+    InvalidCredentials,
+    InvalidToken,
+    IncorrectPassword,
+    EmailTaken,
+    NoFieldsToUpdate,
+    UnsupportedLanguage,
+    UserNotFound,
+    PatientNotFound,
+    PatientHasInvoices,
+    PatientMerged,
+    MergeSamePatient,
+    NationalIdTaken,
+    NationalIdMismatch,
+    ContactNotFound,
+    DiagnosisNotFound,
+    AllergyNotFound,
+    MedicationNotFound,
+    AttachmentNotFound,
+    FileRequired,
+    FileTooLarge,
+    UnsupportedFileType,
+    ThumbnailUnavailable,
+    QuestionnaireNotFound,
+    QuestionnaireResponseNotFound,
+    QuestionnaireInUse,
+    QuestionnaireInactive,
+    DuplicateQuestionId,
+    InvalidQuestionOptions,
+    InvalidQuestionScale,
+    ConsentFormNotFound,
+    ConsentNotFound,
+    ConsentFormInUse,
+    ConsentFormInactive,
+    SignatureRequired,
+    InvalidSignatureImage,
+    SignerNotGuardian,
+    ConsentAlreadyRevoked,
+    TreatmentNotFound,
+    TreatmentNotForPatient,
+    TreatmentTypeNotFound,
+    InvalidTreatmentType,
+    PackageNotFound,
+    InvalidPackage,
+    PackageExhausted,
+    TreatmentPlanNotFound,
+    PlanGoalNotFound,
+    InvalidTreatmentPlan,
+    TooManyGoals,
+    InvalidSessionCount,
+    SessionsBelowUsed,
+    NegativePrice,
+    InvoiceNotFound,
+    InvoiceNotDraft,
+    InvoiceImmutable,
+    InvoiceEmpty,
+    InvoiceLineIncomplete,
+    InvoiceLineInvalid,
+    TreatmentAlreadyBilled,
+    CreditNoteNotAllowed,
+    CreditNoteExceedsInvoice,
+    PaymentNotFound,
+    PaymentNotAllowed,
+    PaymentAmountOutOfRange,
+    ExportTemplateNotFound,
+    ExportTemplateExists,
+    ExportTemplateInvalid,
+    InvalidTemplate,
+    TemplateNotForJson,
+    ExportFailed,
+    InvalidDateRange,
+    InvalidIdList,
+    NameRequired,
+    GithubNotConfigured,
+    GithubUnavailable,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::Internal => "INTERNAL_ERROR",
+            ErrorCode::InvalidRequest => "INVALID_REQUEST",
+            ErrorCode::ValidationFailed => "VALIDATION_FAILED",
+            ErrorCode::AuthenticationRequired => "AUTHENTICATION_REQUIRED",
+            ErrorCode::InvalidCredentials => "INVALID_CREDENTIALS",
+            ErrorCode::InvalidToken => "INVALID_TOKEN",
+            ErrorCode::IncorrectPassword => "INCORRECT_PASSWORD",
+            ErrorCode::EmailTaken => "EMAIL_TAKEN",
+            ErrorCode::NoFieldsToUpdate => "NO_FIELDS_TO_UPDATE",
+            ErrorCode::UnsupportedLanguage => "UNSUPPORTED_LANGUAGE",
+            ErrorCode::UserNotFound => "USER_NOT_FOUND",
+            ErrorCode::PatientNotFound => "PATIENT_NOT_FOUND",
+            ErrorCode::PatientHasInvoices => "PATIENT_HAS_INVOICES",
+            ErrorCode::PatientMerged => "PATIENT_MERGED",
+            ErrorCode::MergeSamePatient => "MERGE_SAME_PATIENT",
+            ErrorCode::NationalIdTaken => "NATIONAL_ID_TAKEN",
+            ErrorCode::NationalIdMismatch => "NATIONAL_ID_MISMATCH",
+            ErrorCode::ContactNotFound => "CONTACT_NOT_FOUND",
+            ErrorCode::DiagnosisNotFound => "DIAGNOSIS_NOT_FOUND",
+            ErrorCode::AllergyNotFound => "ALLERGY_NOT_FOUND",
+            ErrorCode::MedicationNotFound => "MEDICATION_NOT_FOUND",
+            ErrorCode::AttachmentNotFound => "ATTACHMENT_NOT_FOUND",
+            ErrorCode::FileRequired => "FILE_REQUIRED",
+            ErrorCode::FileTooLarge => "FILE_TOO_LARGE",
+            ErrorCode::UnsupportedFileType => "UNSUPPORTED_FILE_TYPE",
+            ErrorCode::ThumbnailUnavailable => "THUMBNAIL_UNAVAILABLE",
+            ErrorCode::QuestionnaireNotFound => "QUESTIONNAIRE_NOT_FOUND",
+            ErrorCode::QuestionnaireResponseNotFound => "QUESTIONNAIRE_RESPONSE_NOT_FOUND",
+            ErrorCode::QuestionnaireInUse => "QUESTIONNAIRE_IN_USE",
+            ErrorCode::QuestionnaireInactive => "QUESTIONNAIRE_INACTIVE",
+            ErrorCode::DuplicateQuestionId => "DUPLICATE_QUESTION_ID",
+            ErrorCode::InvalidQuestionOptions => "INVALID_QUESTION_OPTIONS",
+            ErrorCode::InvalidQuestionScale => "INVALID_QUESTION_SCALE",
+            ErrorCode::ConsentFormNotFound => "CONSENT_FORM_NOT_FOUND",
+            ErrorCode::ConsentNotFound => "CONSENT_NOT_FOUND",
+            ErrorCode::ConsentFormInUse => "CONSENT_FORM_IN_USE",
+            ErrorCode::ConsentFormInactive => "CONSENT_FORM_INACTIVE",
+            ErrorCode::SignatureRequired => "SIGNATURE_REQUIRED",
+            ErrorCode::InvalidSignatureImage => "INVALID_SIGNATURE_IMAGE",
+            ErrorCode::SignerNotGuardian => "SIGNER_NOT_GUARDIAN",
+            ErrorCode::ConsentAlreadyRevoked => "CONSENT_ALREADY_REVOKED",
+            ErrorCode::TreatmentNotFound => "TREATMENT_NOT_FOUND",
+            ErrorCode::TreatmentNotForPatient => "TREATMENT_NOT_FOR_PATIENT",
+            ErrorCode::TreatmentTypeNotFound => "TREATMENT_TYPE_NOT_FOUND",
+            ErrorCode::InvalidTreatmentType => "INVALID_TREATMENT_TYPE",
+            ErrorCode::PackageNotFound => "PACKAGE_NOT_FOUND",
+            ErrorCode::InvalidPackage => "INVALID_PACKAGE",
+            ErrorCode::PackageExhausted => "PACKAGE_EXHAUSTED",
+            ErrorCode::TreatmentPlanNotFound => "TREATMENT_PLAN_NOT_FOUND",
+            ErrorCode::PlanGoalNotFound => "PLAN_GOAL_NOT_FOUND",
+            ErrorCode::InvalidTreatmentPlan => "INVALID_TREATMENT_PLAN",
+            ErrorCode::TooManyGoals => "TOO_MANY_GOALS",
+            ErrorCode::InvalidSessionCount => "INVALID_SESSION_COUNT",
+            ErrorCode::SessionsBelowUsed => "SESSIONS_BELOW_USED",
+            ErrorCode::NegativePrice => "NEGATIVE_PRICE",
+            ErrorCode::InvoiceNotFound => "INVOICE_NOT_FOUND",
+            ErrorCode::InvoiceNotDraft => "INVOICE_NOT_DRAFT",
+            ErrorCode::InvoiceImmutable => "INVOICE_IMMUTABLE",
+            ErrorCode::InvoiceEmpty => "INVOICE_EMPTY",
+            ErrorCode::InvoiceLineIncomplete => "INVOICE_LINE_INCOMPLETE",
+            ErrorCode::InvoiceLineInvalid => "INVOICE_LINE_INVALID",
+            ErrorCode::TreatmentAlreadyBilled => "TREATMENT_ALREADY_BILLED",
+            ErrorCode::CreditNoteNotAllowed => "CREDIT_NOTE_NOT_ALLOWED",
+            ErrorCode::CreditNoteExceedsInvoice => "CREDIT_NOTE_EXCEEDS_INVOICE",
+            ErrorCode::PaymentNotFound => "PAYMENT_NOT_FOUND",
+            ErrorCode::PaymentNotAllowed => "PAYMENT_NOT_ALLOWED",
+            ErrorCode::PaymentAmountOutOfRange => "PAYMENT_AMOUNT_OUT_OF_RANGE",
+            ErrorCode::ExportTemplateNotFound => "EXPORT_TEMPLATE_NOT_FOUND",
+            ErrorCode::ExportTemplateExists => "EXPORT_TEMPLATE_EXISTS",
+            ErrorCode::ExportTemplateInvalid => "EXPORT_TEMPLATE_INVALID",
+            ErrorCode::InvalidTemplate => "INVALID_TEMPLATE",
+            ErrorCode::TemplateNotForJson => "TEMPLATE_NOT_FOR_JSON",
+            ErrorCode::ExportFailed => "EXPORT_FAILED",
+            ErrorCode::InvalidDateRange => "INVALID_DATE_RANGE",
+            ErrorCode::InvalidIdList => "INVALID_ID_LIST",
+            ErrorCode::NameRequired => "NAME_REQUIRED",
+            ErrorCode::GithubNotConfigured => "GITHUB_NOT_CONFIGURED",
+            ErrorCode::GithubUnavailable => "GITHUB_UNAVAILABLE",
+        }
+    }
+
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorCode::Internal
+            | ErrorCode::ExportTemplateInvalid
+            | ErrorCode::ExportFailed
+            | ErrorCode::GithubNotConfigured
+            | ErrorCode::GithubUnavailable => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::AuthenticationRequired
+            | ErrorCode::InvalidCredentials
+            | ErrorCode::InvalidToken
+            | ErrorCode::IncorrectPassword => StatusCode::UNAUTHORIZED,
+            ErrorCode::UserNotFound
+            | ErrorCode::PatientNotFound
+            | ErrorCode::ContactNotFound
+            | ErrorCode::DiagnosisNotFound
+            | ErrorCode::AllergyNotFound
+            | ErrorCode::MedicationNotFound
+            | ErrorCode::AttachmentNotFound
+            | ErrorCode::ThumbnailUnavailable
+            | ErrorCode::QuestionnaireNotFound
+            | ErrorCode::QuestionnaireResponseNotFound
+            | ErrorCode::ConsentFormNotFound
+            | ErrorCode::ConsentNotFound
+            | ErrorCode::TreatmentNotFound
+            | ErrorCode::TreatmentTypeNotFound
+            | ErrorCode::PackageNotFound
+            | ErrorCode::TreatmentPlanNotFound
+            | ErrorCode::PlanGoalNotFound
+            | ErrorCode::InvoiceNotFound
+            | ErrorCode::PaymentNotFound
+            | ErrorCode::ExportTemplateNotFound => StatusCode::NOT_FOUND,
+            ErrorCode::EmailTaken
+            | ErrorCode::PatientHasInvoices
+            | ErrorCode::PatientMerged
+            | ErrorCode::NationalIdTaken
+            | ErrorCode::NationalIdMismatch
+            | ErrorCode::PackageExhausted
+            | ErrorCode::InvoiceNotDraft
+            | ErrorCode::InvoiceImmutable
+            | ErrorCode::TreatmentAlreadyBilled
+            | ErrorCode::CreditNoteNotAllowed
+            | ErrorCode::CreditNoteExceedsInvoice
+            | ErrorCode::PaymentNotAllowed
+            | ErrorCode::ExportTemplateExists
+            | ErrorCode::QuestionnaireInUse
+            | ErrorCode::ConsentFormInUse
+            | ErrorCode::ConsentAlreadyRevoked => StatusCode::CONFLICT,
+            ErrorCode::FileTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorCode::UnsupportedFileType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ErrorCode::InvalidRequest
+            | ErrorCode::ValidationFailed
+            | ErrorCode::NoFieldsToUpdate
+            | ErrorCode::UnsupportedLanguage
+            | ErrorCode::MergeSamePatient
+            | ErrorCode::TreatmentNotForPatient
+            | ErrorCode::InvalidTreatmentType
+            | ErrorCode::InvalidPackage
+            | ErrorCode::InvalidTreatmentPlan
+            | ErrorCode::TooManyGoals
+            | ErrorCode::InvalidSessionCount
+            | ErrorCode::SessionsBelowUsed
+            | ErrorCode::NegativePrice
+            | ErrorCode::InvoiceEmpty
+            | ErrorCode::InvoiceLineIncomplete
+            | ErrorCode::InvoiceLineInvalid
+            | ErrorCode::PaymentAmountOutOfRange
+            | ErrorCode::InvalidTemplate
+            | ErrorCode::TemplateNotForJson
+            | ErrorCode::InvalidDateRange
+            | ErrorCode::InvalidIdList
+            | ErrorCode::NameRequired
+            | ErrorCode::FileRequired
+            | ErrorCode::QuestionnaireInactive
+            | ErrorCode::DuplicateQuestionId
+            | ErrorCode::InvalidQuestionOptions
+            | ErrorCode::InvalidQuestionScale
+            | ErrorCode::ConsentFormInactive
+            | ErrorCode::SignatureRequired
+            | ErrorCode::InvalidSignatureImage
+            | ErrorCode::SignerNotGuardian => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// An error code with the values its message refers to, e.g. the line number
+/// of an invalid invoice line. The values are also returned as `details`.
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    code: ErrorCode,
+    details: Vec<(&'static str, String)>,
+    fields: Vec<FieldError>,
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode) -> Self {
+        Self { code, details: Vec::new(), fields: Vec::new() }
+    }
+
+    pub fn with(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.details.push((name, value.to_string()));
+        self
+    }
+
+    /// Message in the current request's language
+    pub fn message(&self) -> String {
+        let key = format!("errors.{}", self.code.as_str().to_ascii_lowercase());
+        i18n::request_localizer().format_message(&key, &self.details)
+    }
+
+    /// JSON error body, see [`AppError`]
+    fn response(&self) -> HttpResponse {
+        let message = self.message();
+        let mut body = json!({
+            "code": self.code.as_str(),
+            "message": message,
+            "error": message,
+            "request_id": request_context::request_id(),
+        });
+        if !self.details.is_empty() {
+            let details: Map<String, Value> = self
+                .details
+                .iter()
+                .map(|(name, value)| (name.to_string(), Value::String(value.clone())))
+                .collect();
+            body["details"] = Value::Object(details);
+        }
+        if !self.fields.is_empty() {
+            body["fields"] = self.fields.iter().map(FieldError::to_json).collect();
+        }
+        HttpResponse::build(self.code.status()).json(body)
+    }
+}
+
+/// Error type returned by all handlers.
+///
+/// Every variant renders the same body:
+/// `{"code": CODE, "message": msg, "error": msg, "request_id": id, "details": {...}}`,
+/// and validation failures add `fields` with one entry per invalid field.
+/// `error` repeats the message for older clients. Database and other internal
+/// failures are logged with the request id and reported as `INTERNAL_ERROR`
+/// without their cause.
+#[derive(Debug)]
+pub enum AppError {
+    /// A catalogued client-facing error
+    Api(ApiError),
+    /// A body, query string or path that could not be parsed
+    InvalidRequest(String),
+    /// A body that parsed but broke its `Validate` rules
+    Validation(Vec<FieldError>),
+    Database(sqlx::Error),
+    Internal(anyhow::Error),
+}
+
+impl AppError {
+    fn api_error(&self) -> ApiError {
+        match self {
+            AppError::Api(e) => e.clone(),
+            AppError::InvalidRequest(reason) => {
+                ApiError::new(ErrorCode::InvalidRequest).with("reason", reason)
+            }
+            AppError::Validation(fields) => ApiError {
+                fields: fields.clone(),
+                ..ApiError::new(ErrorCode::ValidationFailed)
+            },
+            AppError::Database(_) | AppError::Internal(_) => ApiError::new(ErrorCode::Internal),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Api(e) => write!(f, "{}", e.code.as_str()),
+            AppError::InvalidRequest(reason) => write!(f, "Invalid request: {reason}"),
+            AppError::Validation(fields) => write!(f, "{} invalid fields", fields.len()),
+            AppError::Database(e) => write!(f, "Database error: {e}"),
+            AppError::Internal(e) => write!(f, "Internal error: {e:#}"),
+        }
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        self.api_error().code.status()
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if matches!(self, AppError::Database(_) | AppError::Internal(_)) {
+            let request_id = request_context::request_id().unwrap_or_default();
+            eprintln!("[{request_id}] {self}");
+        }
+        self.api_error().response()
+    }
+}
+
+impl From<ErrorCode> for AppError {
+    fn from(code: ErrorCode) -> Self {
+        AppError::Api(ApiError::new(code))
+    }
+}
+
+impl From<ApiError> for AppError {
+    fn from(error: ApiError) -> Self {
+        AppError::Api(error)
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(error: sqlx::Error) -> Self {
+        AppError::Database(error)
+    }
+}
+
+impl From<anyhow::Error> for AppError {
+    fn from(error: anyhow::Error) -> Self {
+        AppError::Internal(error)
+    }
+}
+
+impl From<JsonPayloadError> for AppError {
+    fn from(error: JsonPayloadError) -> Self {
+        AppError::InvalidRequest(error.to_string())
+    }
+}
+
+impl From<QueryPayloadError> for AppError {
+    fn from(error: QueryPayloadError) -> Self {
+        AppError::InvalidRequest(error.to_string())
+    }
+}
+
+impl From<MultipartError> for AppError {
+    fn from(error: MultipartError) -> Self {
+        AppError::InvalidRequest(error.to_string())
+    }
+}
+
+impl From<PathError> for AppError {
+    fn from(error: PathError) -> Self {
+        AppError::InvalidRequest(error.to_string())
+    }
+}
+
+/// Extractor error handler, so malformed input gets the standard error body
+pub fn extractor_error<E: Into<AppError>>(error: E, _req: &HttpRequest) -> actix_web::Error {
+    error.into().into()
+}
@@ -0,0 +1,392 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// EXIF tag pointing at the GPS IFD
+const GPS_INFO_TAG: u16 = 0x8825;
+
+/// `content` without the location a photo was taken at.
+///
+/// Phones record it in EXIF GPS tags and editors may copy it into XMP. Only
+/// the location is removed: orientation, dates and camera details are kept and
+/// the image itself is not re-encoded. Other files are returned unchanged.
+pub fn strip_location(content_type: &str, content: &[u8]) -> Vec<u8> {
+    match content_type {
+        "image/jpeg" => strip_jpeg(content),
+        "image/png" => strip_png(content),
+        "image/webp" => strip_webp(content),
+        "image/heic" => strip_heif(content),
+        "image/tiff" => {
+            let mut tiff = content.to_vec();
+            scrub_tiff(&mut tiff);
+            tiff
+        }
+        _ => content.to_vec(),
+    }
+}
+
+/// XMP packets are dropped only when they record a location
+fn is_xmp_with_location(packet: &[u8]) -> bool {
+    packet.windows(7).any(|window| window == b"exif:GP")
+}
+
+/// Scrub the EXIF segment in place and leave out XMP segments with a location
+fn strip_jpeg(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..2]);
+    let mut pos = 2;
+    // Metadata segments all come before the image data (start of scan)
+    while pos + 4 <= data.len() && data[pos] == 0xFF {
+        let marker = data[pos + 1];
+        if marker == 0xDA {
+            break;
+        }
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let end = pos + 2 + length;
+        if length < 2 || end > data.len() {
+            break;
+        }
+        let mut segment = data[pos..end].to_vec();
+        if marker == 0xE1 {
+            let payload = &mut segment[4..];
+            if payload.starts_with(b"Exif\0\0") {
+                scrub_tiff(&mut payload[6..]);
+            } else if payload.starts_with(b"http://ns.adobe.com/xap/1.0/\0") && is_xmp_with_location(payload) {
+                pos = end;
+                continue;
+            }
+        }
+        out.extend_from_slice(&segment);
+        pos = end;
+    }
+    out.extend_from_slice(&data[pos.min(data.len())..]);
+    out
+}
+
+/// Scrub the `eXIf` chunk and leave out an XMP `iTXt` chunk with a location
+fn strip_png(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..8]);
+    let mut pos = 8;
+    while pos + 12 <= data.len() {
+        let length = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
+        let end = pos + 12 + length;
+        if end > data.len() {
+            break;
+        }
+        let kind = &data[pos + 4..pos + 8];
+        let mut chunk = data[pos..end].to_vec();
+        if kind == b"eXIf" {
+            scrub_tiff(&mut chunk[8..8 + length]);
+            let crc = crc32fast::hash(&chunk[4..8 + length]);
+            chunk[8 + length..].copy_from_slice(&crc.to_be_bytes());
+        } else if kind == b"iTXt"
+            && chunk[8..].starts_with(b"XML:com.adobe.xmp\0")
+            && is_xmp_with_location(&chunk[8..8 + length])
+        {
+            pos = end;
+            continue;
+        }
+        out.extend_from_slice(&chunk);
+        pos = end;
+    }
+    out.extend_from_slice(&data[pos.min(data.len())..]);
+    out
+}
+
+/// Scrub the `EXIF` chunk and leave out an `XMP ` chunk with a location
+fn strip_webp(data: &[u8]) -> Vec<u8> {
+    let Some(header) = data.get(..12) else {
+        return data.to_vec();
+    };
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(header);
+    let mut pos = 12;
+    let mut dropped_xmp = false;
+    while pos + 8 <= data.len() {
+        let length = u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]) as usize;
+        // Chunks are padded to an even length
+        let end = (pos + 8 + length + (length & 1)).min(data.len());
+        if pos + 8 + length > data.len() {
+            break;
+        }
+        let kind = &data[pos..pos + 4];
+        let mut chunk = data[pos..end].to_vec();
+        if kind == b"EXIF" {
+            let payload = &mut chunk[8..8 + length];
+            let tiff_start = if payload.starts_with(b"Exif\0\0") { 6 } else { 0 };
+            scrub_tiff(&mut payload[tiff_start..]);
+        } else if kind == b"XMP " && is_xmp_with_location(&chunk[8..8 + length]) {
+            dropped_xmp = true;
+            pos = end;
+            continue;
+        }
+        out.extend_from_slice(&chunk);
+        pos = end;
+    }
+    out.extend_from_slice(&data[pos.min(data.len())..]);
+
+    if dropped_xmp {
+        let riff_size = (out.len() - 8) as u32;
+        if let Some(size) = out.get_mut(4..8) {
+            size.copy_from_slice(&riff_size.to_le_bytes());
+        }
+        // Clear the XMP flag of the extended header; the XMP chunk may have been all there was
+        if out.len() > 20 && &out[12..16] == b"VP8X" {
+            out[20] &= !0x04;
+        }
+    }
+    out
+}
+
+/// Scrub the `Exif` item and blank an XMP item with a location, in place.
+///
+/// HEIF files point at their metadata items by offset, so nothing is moved.
+fn strip_heif(data: &[u8]) -> Vec<u8> {
+    let mut out = data.to_vec();
+    for (kind, extents) in heif_metadata_items(data).unwrap_or_default() {
+        let mut item: Vec<u8> = extents.iter().flat_map(|range| data[range.clone()].iter().copied()).collect();
+        match kind {
+            HeifItem::Exif => {
+                // The TIFF header follows a 4-byte offset to it, usually past an "Exif\0\0" marker
+                let Some(header) = be_uint(&item, 0, 4).and_then(|offset| usize::try_from(offset + 4).ok()) else {
+                    continue;
+                };
+                if let Some(tiff) = item.get_mut(header..) {
+                    scrub_tiff(tiff);
+                }
+            }
+            HeifItem::Xmp if is_xmp_with_location(&item) => item.fill(b' '),
+            HeifItem::Xmp => continue,
+        }
+        let mut bytes = item.into_iter();
+        for range in extents {
+            for byte in &mut out[range] {
+                *byte = bytes.next().unwrap_or(b' ');
+            }
+        }
+    }
+    out
+}
+
+/// Metadata items a HEIF file can carry a location in
+#[derive(Clone, Copy)]
+enum HeifItem {
+    Exif,
+    Xmp,
+}
+
+/// The Exif and XMP items of a HEIF file, with the byte ranges each is stored in
+fn heif_metadata_items(data: &[u8]) -> Option<Vec<(HeifItem, Vec<Range<usize>>)>> {
+    let meta = heif_boxes(data, 0..data.len()).into_iter().find(|(kind, _)| kind == b"meta")?.1;
+    // `meta` is a full box: version and flags come before its children
+    let children = heif_boxes(data, meta.start + 4..meta.end);
+    let child = |name: &[u8; 4]| children.iter().find(|(kind, _)| kind == name).map(|(_, range)| range.clone());
+
+    let mut items = BTreeMap::new();
+    let iinf = child(b"iinf")?;
+    let entries_start = iinf.start + if data.get(iinf.start)? == &0 { 6 } else { 8 };
+    for (kind, infe) in heif_boxes(data, entries_start..iinf.end) {
+        if &kind != b"infe" {
+            continue;
+        }
+        let mut reader = Reader { data: &data[..infe.end], pos: infe.start };
+        let version = reader.uint(1)?;
+        reader.pos += 3;
+        // Older entries carry no item type
+        if version < 2 {
+            continue;
+        }
+        let id = reader.uint(if version == 2 { 2 } else { 4 })?;
+        reader.pos += 2;
+        let item_type = reader.bytes(4)?;
+        reader.c_str()?;
+        if item_type == b"Exif" {
+            items.insert(id, HeifItem::Exif);
+        } else if item_type == b"mime" && reader.c_str()? == b"application/rdf+xml" {
+            items.insert(id, HeifItem::Xmp);
+        }
+    }
+    if items.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let idat = child(b"idat");
+    let iloc = child(b"iloc")?;
+    let mut reader = Reader { data: &data[..iloc.end], pos: iloc.start };
+    let version = reader.uint(1)?;
+    reader.pos += 3;
+    let sizes = reader.uint(1)?;
+    let (offset_size, length_size) = ((sizes >> 4) as usize, (sizes & 0xF) as usize);
+    let sizes = reader.uint(1)?;
+    let base_offset_size = (sizes >> 4) as usize;
+    let index_size = if version == 0 { 0 } else { (sizes & 0xF) as usize };
+    let id_size = if version < 2 { 2 } else { 4 };
+
+    let mut found = Vec::new();
+    for _ in 0..reader.uint(id_size)? {
+        let id = reader.uint(id_size)?;
+        let construction_method = if version == 0 { 0 } else { reader.uint(2)? & 0xF };
+        reader.pos += 2;
+        let base_offset = reader.uint(base_offset_size)?;
+        let mut extents = Vec::new();
+        for _ in 0..reader.uint(2)? {
+            reader.pos += index_size;
+            let offset = reader.uint(offset_size)?;
+            let length = reader.uint(length_size)?;
+            // Offsets are into the file, or into the `idat` box for items stored inside `meta`
+            let (origin, end) = match (construction_method, &idat) {
+                (0, _) => (0, data.len()),
+                (1, Some(idat)) => (idat.start, idat.end),
+                _ => continue,
+            };
+            let start = usize::try_from(base_offset.checked_add(offset)?).ok()?.checked_add(origin)?;
+            let stop = if length == 0 { end } else { start.checked_add(usize::try_from(length).ok()?)? };
+            if start <= stop && stop <= end {
+                extents.push(start..stop);
+            }
+        }
+        if let Some(kind) = items.get(&id) {
+            found.push((*kind, extents));
+        }
+    }
+    Some(found)
+}
+
+/// Kind and payload range of each ISO BMFF box within `within`
+fn heif_boxes(data: &[u8], within: Range<usize>) -> Vec<([u8; 4], Range<usize>)> {
+    let mut boxes = Vec::new();
+    let mut pos = within.start;
+    while pos + 8 <= within.end {
+        let Some(size) = be_uint(data, pos, 4) else {
+            break;
+        };
+        let (header, size) = match size {
+            0 => (8, (within.end - pos) as u64),
+            1 => match be_uint(data, pos + 8, 8) {
+                Some(size) => (16, size),
+                None => break,
+            },
+            size => (8, size),
+        };
+        let Some(end) = usize::try_from(size).ok().and_then(|size| pos.checked_add(size)) else {
+            break;
+        };
+        if size < header as u64 || end > within.end {
+            break;
+        }
+        let mut kind = [0; 4];
+        kind.copy_from_slice(&data[pos + 4..pos + 8]);
+        boxes.push((kind, pos + header..end));
+        pos = end;
+    }
+    boxes
+}
+
+/// Big-endian unsigned integer of `size` bytes; HEIF allows sizes of 0 (absent) to 8
+fn be_uint(data: &[u8], offset: usize, size: usize) -> Option<u64> {
+    if size > 8 {
+        return None;
+    }
+    let bytes = data.get(offset..offset.checked_add(size)?)?;
+    Some(bytes.iter().fold(0, |value, byte| value << 8 | u64::from(*byte)))
+}
+
+/// Reads fields of a HEIF box one after another
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn uint(&mut self, size: usize) -> Option<u64> {
+        let value = be_uint(self.data, self.pos, size)?;
+        self.pos += size;
+        Some(value)
+    }
+
+    fn bytes(&mut self, length: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(length)?)?;
+        self.pos += length;
+        Some(bytes)
+    }
+
+    /// A NUL-terminated string, without the NUL
+    fn c_str(&mut self) -> Option<&'a [u8]> {
+        let rest = self.data.get(self.pos..)?;
+        let length = rest.iter().position(|byte| *byte == 0)?;
+        self.pos += length + 1;
+        Some(&rest[..length])
+    }
+}
+
+/// TIFF structure (the body of an EXIF block, or a whole TIFF file) in either byte order
+struct Tiff<'a> {
+    data: &'a mut [u8],
+    little_endian: bool,
+}
+
+impl Tiff<'_> {
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    }
+
+    fn zero(&mut self, offset: usize, length: usize) {
+        if let Some(bytes) = self.data.get_mut(offset..offset.saturating_add(length)) {
+            bytes.fill(0);
+        }
+    }
+
+    /// Offset of the GPS IFD, from the GPSInfo entry of the first IFD
+    fn gps_ifd(&self) -> Option<usize> {
+        let ifd = self.u32_at(4)? as usize;
+        let count = self.u16_at(ifd)? as usize;
+        (0..count)
+            .map(|i| ifd + 2 + i * 12)
+            .find(|entry| self.u16_at(*entry) == Some(GPS_INFO_TAG))
+            .and_then(|entry| self.u32_at(entry + 8))
+            .map(|offset| offset as usize)
+    }
+
+    /// Zero every GPS value and leave the GPS IFD empty
+    fn clear_ifd(&mut self, ifd: usize) -> Option<()> {
+        let count = self.u16_at(ifd)? as usize;
+        for i in 0..count {
+            let entry = ifd + 2 + i * 12;
+            let value_size = match self.u16_at(entry + 2)? {
+                1 | 2 | 6 | 7 => 1,
+                3 | 8 => 2,
+                4 | 9 | 11 | 13 => 4,
+                5 | 10 | 12 => 8,
+                _ => 0,
+            };
+            let size = value_size * self.u32_at(entry + 4)? as usize;
+            // Values longer than four bytes live elsewhere, at the offset in the entry
+            if size > 4 {
+                let offset = self.u32_at(entry + 8)? as usize;
+                self.zero(offset, size);
+            }
+            self.zero(entry, 12);
+        }
+        self.zero(ifd, 2);
+        Some(())
+    }
+}
+
+/// Remove the GPS tags from TIFF-structured metadata, in place
+fn scrub_tiff(data: &mut [u8]) {
+    let little_endian = match data.get(..4) {
+        Some(b"II*\0") => true,
+        Some(b"MM\0*") => false,
+        _ => return,
+    };
+    let mut tiff = Tiff { data, little_endian };
+    if let Some(gps_ifd) = tiff.gps_ifd() {
+        tiff.clear_ifd(gps_ifd);
+    }
+}
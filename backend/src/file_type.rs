@@ -0,0 +1,56 @@
+/// Content type of an uploaded file, told from its first bytes rather than
+/// trusting the name or the type the client sent.
+///
+/// Only the kinds of files a clinic attaches are recognized: documents,
+/// photos and scans. `None` means the file is of a type that is not accepted.
+pub fn sniff(data: &[u8], file_name: &str) -> Option<&'static str> {
+    let extension = file_name.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+    let starts = |magic: &[u8]| data.starts_with(magic);
+
+    if starts(b"%PDF-") {
+        Some("application/pdf")
+    } else if starts(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if starts(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if starts(b"GIF87a") || starts(b"GIF89a") {
+        Some("image/gif")
+    } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if starts(b"II*\0") || starts(b"MM\0*") {
+        Some("image/tiff")
+    } else if data.len() >= 12 && &data[4..8] == b"ftyp" && matches!(&data[8..12], b"heic" | b"heix" | b"mif1" | b"msf1") {
+        Some("image/heic")
+    } else if data.len() >= 132 && &data[128..132] == b"DICM" {
+        Some("application/dicom")
+    } else if starts(b"{\\rtf") {
+        Some("application/rtf")
+    } else if starts(b"PK\x03\x04") {
+        // Office documents are ZIP archives; only Word files are accepted
+        (extension == "docx" && contains(data, b"word/"))
+            .then_some("application/vnd.openxmlformats-officedocument.wordprocessingml.document")
+    } else if starts(&[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1]) {
+        (extension == "doc").then_some("application/msword")
+    } else if is_text(data) {
+        Some("text/plain; charset=utf-8")
+    } else {
+        None
+    }
+}
+
+/// Types shown by browsers rather than saved; everything else is sent as a download
+pub fn is_inline_safe(content_type: &str) -> bool {
+    matches!(content_type, "application/pdf" | "image/jpeg" | "image/png" | "image/gif" | "image/webp")
+}
+
+fn contains(data: &[u8], needle: &[u8]) -> bool {
+    data.windows(needle.len()).any(|window| window == needle)
+}
+
+/// UTF-8 without control characters other than whitespace
+fn is_text(data: &[u8]) -> bool {
+    !data.is_empty()
+        && std::str::from_utf8(data).is_ok_and(|text| {
+            text.chars().all(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t' | '\u{c}'))
+        })
+}
@@ -0,0 +1,380 @@
+use actix_multipart::{Field, Multipart};
+use actix_web::http::header::{
+    Charset, ContentDisposition, DispositionParam, DispositionType, EntityTag, ExtendedValue, IfNoneMatch,
+    CACHE_CONTROL, X_CONTENT_TYPE_OPTIONS,
+};
+use actix_web::body::SizedStream;
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web::http::header::Header;
+use futures_util::TryStreamExt;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::OwnedMutexGuard;
+use uuid::Uuid;
+
+use crate::errors::{ApiError, AppError, ErrorCode};
+use crate::validation::{ValidatedJson, Validator};
+use crate::database::Database;
+use crate::{exif, file_type, thumbnail};
+use crate::models::{
+    max_upload_bytes, Attachment, AttachmentDownloadQuery, AttachmentQuery, AttachmentUpload, Claims,
+    ThumbnailQuery, UpdateAttachmentRequest,
+};
+use crate::storage::{BlobStore, BlobStream};
+use crate::thumbnail::ThumbnailSize;
+
+/// Load an attachment and make sure it belongs to the patient in the path
+async fn load_attachment(db: &Database, patient_id: Uuid, attachment_id: Uuid) -> Result<Attachment, AppError> {
+    db.get_attachment_by_id(attachment_id)
+        .await?
+        .filter(|attachment| attachment.patient_id == patient_id)
+        .ok_or_else(|| ErrorCode::AttachmentNotFound.into())
+}
+
+/// Files may only be attached to the patient's own treatments
+async fn check_treatment(db: &Database, patient_id: Uuid, treatment_id: Uuid) -> Result<(), AppError> {
+    let treatment = db
+        .get_treatment_by_id(treatment_id)
+        .await?
+        .ok_or(ErrorCode::TreatmentNotFound)?;
+    if treatment.patient_id != patient_id {
+        return Err(ErrorCode::TreatmentNotForPatient.into());
+    }
+    Ok(())
+}
+
+/// Hold the content with this hash while it is stored or released.
+///
+/// An upload keeps it from storing the blob until its row is saved, so a
+/// release of the same content cannot find the hash unused and delete the
+/// blob in between.
+async fn lock_blob(hash: &str) -> OwnedMutexGuard<()> {
+    static LOCKS: OnceLock<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> = OnceLock::new();
+    let lock = {
+        let mut locks = LOCKS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+        // Locks nobody holds or waits for are dropped
+        locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+        locks.entry(hash.to_string()).or_default().clone()
+    };
+    lock.lock_owned().await
+}
+
+/// Remove stored content, and its thumbnails, no attachment refers to any more
+pub async fn release_blobs(db: &Database, store: &dyn BlobStore, hashes: &[String]) -> Result<(), AppError> {
+    for hash in hashes {
+        let _guard = lock_blob(hash).await;
+        if !db.attachment_hash_in_use(hash).await? {
+            store.delete(hash).await?;
+            for size in ThumbnailSize::ALL {
+                store.delete(&size.key(hash)).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The stored thumbnail, if one has been made
+async fn cached_thumbnail(store: &dyn BlobStore, attachment: &Attachment, size: ThumbnailSize) -> Result<Option<BlobStream>, AppError> {
+    Ok(store.get(&size.key(&attachment.sha256)).await?)
+}
+
+/// Make and store a thumbnail; `None` if the file has no preview
+async fn create_thumbnail(
+    store: &dyn BlobStore,
+    attachment: &Attachment,
+    content: Bytes,
+    size: ThumbnailSize,
+) -> Result<Option<Bytes>, AppError> {
+    let content_type = attachment.content_type.clone();
+    // Decoding a large photo takes a while, so it stays off the request threads
+    let generated = web::block(move || thumbnail::generate(&content_type, &content, size))
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let Some(generated) = generated.map(Bytes::from) else {
+        return Ok(None);
+    };
+    store.put(&size.key(&attachment.sha256), generated.clone()).await?;
+    Ok(Some(generated))
+}
+
+/// A text field of the form, such as the description
+pub async fn read_text(field: &mut Field) -> Result<String, AppError> {
+    let mut text = BytesMut::new();
+    while let Some(chunk) = field.try_next().await? {
+        // Form fields are short; anything longer is not a description
+        if text.len() + chunk.len() > 64 * 1024 {
+            return Err(AppError::InvalidRequest("form field is too long".to_string()));
+        }
+        text.extend_from_slice(&chunk);
+    }
+    String::from_utf8(text.to_vec()).map_err(|_| AppError::InvalidRequest("form field is not UTF-8".to_string()))
+}
+
+/// Upload a file as multipart form data: `file`, and optionally `description` and `treatment_id`
+pub async fn upload_attachment(
+    path: web::Path<Uuid>,
+    mut payload: Multipart,
+    claims: Option<web::ReqData<Claims>>,
+    db: web::Data<Database>,
+    store: web::Data<dyn BlobStore>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    if db.get_patient_by_id(patient_id).await?.is_none() {
+        return Err(ErrorCode::PatientNotFound.into());
+    }
+
+    let max_bytes = max_upload_bytes();
+    let mut file: Option<(String, Bytes)> = None;
+    let mut description = String::new();
+    let mut treatment_id = None;
+
+    while let Some(mut field) = payload.try_next().await? {
+        match field.name().unwrap_or_default() {
+            "file" => {
+                let file_name = field
+                    .content_disposition()
+                    .and_then(|cd| cd.get_filename())
+                    .unwrap_or_default()
+                    .to_string();
+                let mut data = BytesMut::new();
+                while let Some(chunk) = field.try_next().await? {
+                    if data.len() + chunk.len() > max_bytes {
+                        return Err(ApiError::new(ErrorCode::FileTooLarge)
+                            .with("max_mb", max_bytes / (1024 * 1024))
+                            .into());
+                    }
+                    data.extend_from_slice(&chunk);
+                }
+                file = Some((file_name, data.freeze()));
+            }
+            "description" => description = read_text(&mut field).await?.trim().to_string(),
+            "treatment_id" => {
+                let value = read_text(&mut field).await?;
+                let value = value.trim();
+                if !value.is_empty() {
+                    let id = Uuid::parse_str(value)
+                        .map_err(|e| AppError::InvalidRequest(format!("treatment_id: {e}")))?;
+                    treatment_id = Some(id);
+                }
+            }
+            // Unknown fields are drained and ignored
+            _ => while field.try_next().await?.is_some() {},
+        }
+    }
+
+    let (file_name, data) = file
+        .filter(|(_, data)| !data.is_empty())
+        .ok_or(ErrorCode::FileRequired)?;
+    if let Some(treatment_id) = treatment_id {
+        check_treatment(&db, patient_id, treatment_id).await?;
+    }
+    let content_type = file_type::sniff(&data, &file_name).ok_or(ErrorCode::UnsupportedFileType)?;
+
+    // Photos are stored without the place they were taken; the hash is of what is stored
+    let data = Bytes::from(exif::strip_location(content_type, &data));
+    let sha256 = format!("{:x}", Sha256::digest(&data));
+    let _guard = lock_blob(&sha256).await;
+
+    // Uploading the same file again to the same place returns the existing attachment
+    if let Some(existing) = db.find_attachment_by_hash(patient_id, treatment_id, &sha256).await? {
+        return Ok(HttpResponse::Ok().json(json!({
+            "message": "Attachment already exists",
+            "attachment": existing,
+            "duplicate": true
+        })));
+    }
+
+    let upload = AttachmentUpload {
+        treatment_id,
+        file_name,
+        content_type,
+        size_bytes: data.len(),
+        sha256,
+        description,
+        has_thumbnail: false,
+    };
+    Validator::check(&upload).map_err(AppError::Validation)?;
+    let mut attachment = Attachment::new(patient_id, upload, claims.map(|c| c.into_inner().sub));
+    store.put(&attachment.sha256, data.clone()).await?;
+    if thumbnail::is_supported(&attachment.content_type) {
+        let size = ThumbnailSize::default();
+        // Identical content uploaded elsewhere already has its thumbnail
+        attachment.has_thumbnail = cached_thumbnail(store.get_ref(), &attachment, size).await?.is_some()
+            || create_thumbnail(store.get_ref(), &attachment, data, size).await?.is_some();
+    }
+    db.create_attachment(&attachment).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Attachment uploaded successfully",
+        "attachment": attachment,
+        "duplicate": false
+    })))
+}
+
+pub async fn get_attachments_for_patient(
+    path: web::Path<Uuid>,
+    query: web::Query<AttachmentQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    let attachments = db.get_attachments_for_patient(patient_id, query.treatment_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "attachments": attachments,
+        "count": attachments.len()
+    })))
+}
+
+pub async fn get_attachment_by_id(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, attachment_id) = path.into_inner();
+
+    let attachment = load_attachment(&db, patient_id, attachment_id).await?;
+    Ok(HttpResponse::Ok().json(attachment))
+}
+
+/// Stream the file content, inline for types browsers display safely
+pub async fn download_attachment(
+    req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+    query: web::Query<AttachmentDownloadQuery>,
+    db: web::Data<Database>,
+    store: web::Data<dyn BlobStore>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, attachment_id) = path.into_inner();
+
+    let attachment = load_attachment(&db, patient_id, attachment_id).await?;
+
+    // Content never changes under a hash, so it is its own ETag
+    let etag = EntityTag::new_strong(attachment.sha256.clone());
+    if let Ok(IfNoneMatch::Items(tags)) = IfNoneMatch::parse(&req) {
+        if tags.iter().any(|tag| tag.strong_eq(&etag)) {
+            return Ok(HttpResponse::NotModified().insert_header(("ETag", etag.to_string())).finish());
+        }
+    }
+
+    let Some(content) = store.get(&attachment.sha256).await? else {
+        eprintln!("Content of attachment {} is missing from the store", attachment.id);
+        return Err(ErrorCode::AttachmentNotFound.into());
+    };
+
+    let inline = file_type::is_inline_safe(&attachment.content_type) && !query.download.unwrap_or(false);
+    let disposition = ContentDisposition {
+        disposition: if inline { DispositionType::Inline } else { DispositionType::Attachment },
+        parameters: vec![
+            // Plain ASCII for old clients, the real name in `filename*`
+            DispositionParam::Filename(
+                attachment.file_name.chars().map(|c| if c.is_ascii() { c } else { '_' }).collect(),
+            ),
+            DispositionParam::FilenameExt(ExtendedValue {
+                charset: Charset::Ext("UTF-8".to_string()),
+                language_tag: None,
+                value: attachment.file_name.clone().into_bytes(),
+            }),
+        ],
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(attachment.content_type.as_str())
+        .insert_header(disposition)
+        .insert_header(("ETag", etag.to_string()))
+        .insert_header((CACHE_CONTROL, "private, no-cache"))
+        .insert_header((X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .body(SizedStream::new(attachment.size_bytes as u64, content)))
+}
+
+/// A JPEG thumbnail of an image or scanned PDF, made on first request and then served from the store
+pub async fn get_attachment_thumbnail(
+    req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+    query: web::Query<ThumbnailQuery>,
+    db: web::Data<Database>,
+    store: web::Data<dyn BlobStore>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, attachment_id) = path.into_inner();
+
+    let attachment = load_attachment(&db, patient_id, attachment_id).await?;
+    if !thumbnail::is_supported(&attachment.content_type) {
+        return Err(ErrorCode::ThumbnailUnavailable.into());
+    }
+
+    // Attachment content never changes, so neither does its thumbnail
+    let etag = EntityTag::new_strong(query.size.key(&attachment.sha256));
+    let cached = |response: &mut actix_web::HttpResponseBuilder| {
+        response
+            .insert_header(("ETag", etag.to_string()))
+            .insert_header((CACHE_CONTROL, "private, max-age=31536000, immutable"));
+    };
+    if let Ok(IfNoneMatch::Items(tags)) = IfNoneMatch::parse(&req) {
+        if tags.iter().any(|tag| tag.strong_eq(&etag)) {
+            let mut response = HttpResponse::NotModified();
+            cached(&mut response);
+            return Ok(response.finish());
+        }
+    }
+
+    let mut response = HttpResponse::Ok();
+    response.content_type(thumbnail::CONTENT_TYPE);
+    cached(&mut response);
+
+    if let Some(stored) = cached_thumbnail(store.get_ref(), &attachment, query.size).await? {
+        return Ok(response.streaming(stored));
+    }
+
+    let Some(content) = store.get(&attachment.sha256).await? else {
+        eprintln!("Content of attachment {} is missing from the store", attachment.id);
+        return Err(ErrorCode::AttachmentNotFound.into());
+    };
+    let content: BytesMut = content.try_collect().await.map_err(anyhow::Error::from)?;
+    let generated = create_thumbnail(store.get_ref(), &attachment, content.freeze(), query.size)
+        .await?
+        .ok_or(ErrorCode::ThumbnailUnavailable)?;
+    if !attachment.has_thumbnail {
+        db.set_attachment_thumbnail(&attachment.sha256).await?;
+    }
+    Ok(response.body(generated))
+}
+
+pub async fn update_attachment(
+    path: web::Path<(Uuid, Uuid)>,
+    data: ValidatedJson<UpdateAttachmentRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, attachment_id) = path.into_inner();
+
+    let mut attachment = load_attachment(&db, patient_id, attachment_id).await?;
+    if let Some(treatment_id) = data.treatment_id {
+        check_treatment(&db, patient_id, treatment_id).await?;
+    }
+    attachment.update(data.into_inner());
+
+    if !db.update_attachment(attachment_id, &attachment).await? {
+        return Err(ErrorCode::AttachmentNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Attachment updated successfully",
+        "attachment": attachment
+    })))
+}
+
+pub async fn delete_attachment(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+    store: web::Data<dyn BlobStore>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, attachment_id) = path.into_inner();
+
+    let attachment = load_attachment(&db, patient_id, attachment_id).await?;
+    if !db.delete_attachment(attachment_id).await? {
+        return Err(ErrorCode::AttachmentNotFound.into());
+    }
+    release_blobs(&db, store.get_ref(), &[attachment.sha256]).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Attachment deleted successfully"
+    })))
+}
@@ -1,30 +1,34 @@
-use actix_web::{web, HttpResponse, Result, Error};
+use actix_web::{web, HttpResponse};
 use bcrypt::{hash, verify, DEFAULT_COST};
 use uuid::Uuid;
 use chrono::Utc;
 use std::env;
 use crate::{
     database::Database,
+    errors::{ApiError, AppError, ErrorCode},
+    i18n,
+    request_context,
     models::{LoginRequest, LoginResponse, User, UserInfo, CreateUserRequest, UpdateUserRequest, ChangePasswordRequest},
     auth::JwtUtils,
+    validation::ValidatedJson,
 };
 
 pub async fn login(
     db: web::Data<Database>,
     login_data: web::Json<LoginRequest>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, AppError> {
     log::info!("Login attempt for email: {}", login_data.email);
     
     // Check if user exists
     let user_result = sqlx::query_as::<_, User>(
-        "SELECT id, email, password_hash, name, created_at FROM users WHERE email = ?",
+        "SELECT id, email, password_hash, name, created_at, language FROM users WHERE email = ?",
     )
     .bind(&login_data.email)
     .fetch_optional(db.pool())
-    .await;
+    .await?;
 
     match user_result {
-        Ok(Some(user)) => {
+        Some(user) => {
             log::info!("User found in database: {}", user.email);
             log::info!("Input password length: {}", login_data.password.len());
             log::info!("Stored hash starts with: {}", &user.password_hash[..20]); // Just first 20 chars for debugging
@@ -36,22 +40,12 @@ pub async fn login(
             if password_matches {
                 log::info!("Password verification successful for user: {}", user.email);
                 // Create JWT token
-                match JwtUtils::create_token(&user.id, &user.email) {
-                    Ok(token) => {
-                        let response = LoginResponse {
-                            token,
-                            user: UserInfo {
-                                id: user.id,
-                                email: user.email,
-                                name: user.name,
-                            },
-                        };
-                        Ok(HttpResponse::Ok().json(response))
-                    }
-                    Err(_) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                        "error": "Failed to create token"
-                    }))),
-                }
+                let token = JwtUtils::create_token(&user.id, &user.email, user.language.as_deref())?;
+                let response = LoginResponse {
+                    token,
+                    user: UserInfo::from(user),
+                };
+                Ok(HttpResponse::Ok().json(response))
             } else {
                 log::warn!("Password verification failed for user: {}", user.email);
                 log::warn!("Input password had {} characters", login_data.password.len());
@@ -62,39 +56,35 @@ pub async fn login(
                 let has_whitespace = login_data.password.chars().any(|c| c.is_whitespace());
                 log::warn!("Password contains non-ASCII chars: {has_non_ascii}, whitespace: {has_whitespace}");
                 
-                Ok(HttpResponse::Unauthorized().json(serde_json::json!({
-                    "error": "Invalid credentials"
-                })))
+                Err(ErrorCode::InvalidCredentials.into())
             }
         }
-        Ok(None) => {
+        None => {
             log::warn!("User not found: {}", login_data.email);
-            Ok(HttpResponse::Unauthorized().json(serde_json::json!({
-                "error": "Invalid credentials"
-            })))
-        }
-        Err(e) => {
-            log::error!("Database error during login: {e}");
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Database error"
-            })))
+            Err(ErrorCode::InvalidCredentials.into())
         }
     }
 }
 
 pub async fn verify_token(
     token: web::Path<String>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, AppError> {
     match JwtUtils::verify_token(&token) {
         Ok(claims) => Ok(HttpResponse::Ok().json(serde_json::json!({
             "valid": true,
             "user_id": claims.sub,
             "email": claims.email
         }))),
-        Err(_) => Ok(HttpResponse::Unauthorized().json(serde_json::json!({
-            "valid": false,
-            "error": "Invalid token"
-        }))),
+        Err(_) => {
+            let message = ApiError::new(ErrorCode::InvalidToken).message();
+            Ok(HttpResponse::Unauthorized().json(serde_json::json!({
+                "valid": false,
+                "code": ErrorCode::InvalidToken.as_str(),
+                "message": message,
+                "error": message,
+                "request_id": request_context::request_id()
+            })))
+        }
     }
 }
 
@@ -179,7 +169,7 @@ pub async fn create_default_user(db: &Database) -> Result<(), sqlx::Error> {
         // Let's also show what users exist (for debugging)
         println!("🔍 Existing users in database:");
         match sqlx::query_as::<_, User>(
-            "SELECT id, email, password_hash, name, created_at FROM users LIMIT 5",
+            "SELECT id, email, password_hash, name, created_at, language FROM users LIMIT 5",
         )
         .fetch_all(db.pool())
         .await
@@ -203,7 +193,7 @@ pub async fn create_default_user(db: &Database) -> Result<(), sqlx::Error> {
 }
 
 // Debug function to check environment variables (REMOVE IN PRODUCTION!)
-pub async fn debug_env_vars() -> Result<HttpResponse> {
+pub async fn debug_env_vars() -> Result<HttpResponse, AppError> {
     let env_status = serde_json::json!({
         "environment_variables": {
             "DEFAULT_ADMIN_EMAIL": std::env::var("DEFAULT_ADMIN_EMAIL").unwrap_or_else(|_| "NOT_SET".to_string()),
@@ -220,7 +210,7 @@ pub async fn debug_env_vars() -> Result<HttpResponse> {
 }
 
 // Force recreate default user (DANGEROUS - REMOVE IN PRODUCTION!)
-pub async fn debug_force_create_user(db: web::Data<Database>) -> Result<HttpResponse> {
+pub async fn debug_force_create_user(db: web::Data<Database>) -> Result<HttpResponse, AppError> {
     println!("🚨 FORCE CREATING DEFAULT USER - THIS SHOULD ONLY BE USED FOR DEBUGGING!");
     
     // Get environment variables
@@ -286,18 +276,15 @@ pub async fn debug_force_create_user(db: web::Data<Database>) -> Result<HttpResp
         }
         Err(e) => {
             println!("❌ Failed to create user: {e}");
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": format!("Failed to create user: {}", e)
-            })))
+            Err(e.into())
         }
     }
 }
 
 // Debug function to list all users (remove in production)
-pub async fn debug_list_users(db: web::Data<Database>) -> Result<HttpResponse> {
+pub async fn debug_list_users(db: web::Data<Database>) -> Result<HttpResponse, AppError> {
     match sqlx::query_as::<_, User>(
-        "SELECT id, email, password_hash, name, created_at FROM users",
+        "SELECT id, email, password_hash, name, created_at, language FROM users",
     )
     .fetch_all(db.pool())
     .await
@@ -317,9 +304,7 @@ pub async fn debug_list_users(db: web::Data<Database>) -> Result<HttpResponse> {
         }
         Err(e) => {
             log::error!("Failed to fetch users: {e}");
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to fetch users"
-            })))
+            Err(e.into())
         }
     }
 }
@@ -327,64 +312,41 @@ pub async fn debug_list_users(db: web::Data<Database>) -> Result<HttpResponse> {
 // Get all users (admin function)
 pub async fn get_users(
     db: web::Data<Database>,
-) -> Result<HttpResponse> {
-    match sqlx::query_as::<_, User>(
-        "SELECT id, email, password_hash, name, created_at FROM users ORDER BY name",
+) -> Result<HttpResponse, AppError> {
+    let users = sqlx::query_as::<_, User>(
+        "SELECT id, email, password_hash, name, created_at, language FROM users ORDER BY name",
     )
     .fetch_all(db.pool())
-    .await
-    {
-        Ok(users) => {
-            let user_infos: Vec<UserInfo> = users.into_iter().map(UserInfo::from).collect();
-            Ok(HttpResponse::Ok().json(user_infos))
-        }
-        Err(_) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to fetch users"
-        }))),
-    }
+    .await?;
+
+    let user_infos: Vec<UserInfo> = users.into_iter().map(UserInfo::from).collect();
+    Ok(HttpResponse::Ok().json(user_infos))
 }
 
 // Create a new user
 pub async fn create_user(
     db: web::Data<Database>,
-    user_data: web::Json<CreateUserRequest>,
-) -> Result<HttpResponse> {
+    user_data: ValidatedJson<CreateUserRequest>,
+) -> Result<HttpResponse, AppError> {
     // Check if user already exists
     let existing_user = sqlx::query_scalar::<_, i64>(
         "SELECT COUNT(*) FROM users WHERE email = ?",
     )
     .bind(&user_data.email)
     .fetch_one(db.pool())
-    .await;
+    .await?;
 
-    match existing_user {
-        Ok(count) if count > 0 => {
-            return Ok(HttpResponse::Conflict().json(serde_json::json!({
-                "error": "User with this email already exists"
-            })));
-        }
-        Err(_) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Database error"
-            })));
-        }
-        _ => {}
+    if existing_user > 0 {
+        return Err(ErrorCode::EmailTaken.into());
     }
 
     // Hash password
-    let password_hash = match hash(&user_data.password, DEFAULT_COST) {
-        Ok(hash) => hash,
-        Err(_) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to hash password"
-            })));
-        }
-    };
+    let password_hash = hash(&user_data.password, DEFAULT_COST).map_err(anyhow::Error::from)?;
 
     let user_id = Uuid::new_v4().to_string();
     let now = Utc::now();
 
-    match sqlx::query(
+    sqlx::query(
         "INSERT INTO users (id, email, password_hash, name, created_at) VALUES (?, ?, ?, ?, ?)",
     )
     .bind(&user_id)
@@ -393,51 +355,35 @@ pub async fn create_user(
     .bind(&user_data.name)
     .bind(now)
     .execute(db.pool())
-    .await
-    {
-        Ok(_) => {
-            let user_info = UserInfo {
-                id: user_id,
-                email: user_data.email.clone(),
-                name: user_data.name.clone(),
-            };
-            Ok(HttpResponse::Created().json(user_info))
-        }
-        Err(_) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to create user"
-        }))),
-    }
+    .await?;
+
+    let user_info = UserInfo {
+        id: user_id,
+        email: user_data.email.clone(),
+        name: user_data.name.clone(),
+        language: None,
+    };
+    Ok(HttpResponse::Created().json(user_info))
 }
 
 // Update user details (not password)
 pub async fn update_user(
     db: web::Data<Database>,
     user_id: web::Path<String>,
-    user_data: web::Json<UpdateUserRequest>,
-) -> Result<HttpResponse> {
+    user_data: ValidatedJson<UpdateUserRequest>,
+) -> Result<HttpResponse, AppError> {
     // First check if user exists
-    let existing_user = match sqlx::query_as::<_, User>(
-        "SELECT id, email, password_hash, name, created_at FROM users WHERE id = ?",
+    let existing_user = sqlx::query_as::<_, User>(
+        "SELECT id, email, password_hash, name, created_at, language FROM users WHERE id = ?",
     )
     .bind(user_id.as_str())
     .fetch_optional(db.pool())
-    .await
-    {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "User not found"
-            })));
-        }
-        Err(_) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Database error"
-            })));
-        }
-    };
+    .await?
+    .ok_or(ErrorCode::UserNotFound)?;
 
     let mut updated_email = existing_user.email.clone();
     let mut updated_name = existing_user.name.clone();
+    let mut updated_language = existing_user.language.clone();
     let mut changes_made = false;
 
     // Check email update
@@ -450,24 +396,13 @@ pub async fn update_user(
             .bind(email)
             .bind(user_id.as_str())
             .fetch_one(db.pool())
-            .await;
+            .await?;
 
-            match email_check {
-                Ok(count) if count > 0 => {
-                    return Ok(HttpResponse::Conflict().json(serde_json::json!({
-                        "error": "Email already taken by another user"
-                    })));
-                }
-                Err(_) => {
-                    return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                        "error": "Database error"
-                    })));
-                }
-                _ => {
-                    updated_email = email.clone();
-                    changes_made = true;
-                }
+            if email_check > 0 {
+                return Err(ErrorCode::EmailTaken.into());
             }
+            updated_email = email.clone();
+            changes_made = true;
         }
     }
 
@@ -479,129 +414,103 @@ pub async fn update_user(
         }
     }
 
+    // Check language update; an empty value goes back to following Accept-Language
+    if let Some(language) = &user_data.language {
+        let language = Some(language.trim().to_lowercase()).filter(|l| !l.is_empty());
+        if let Some(code) = &language {
+            if !i18n::is_supported(code) {
+                return Err(ApiError::new(ErrorCode::UnsupportedLanguage).with("language", code).into());
+            }
+        }
+        if language != existing_user.language {
+            updated_language = language;
+            changes_made = true;
+        }
+    }
+
     if !changes_made {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "No fields to update"
-        })));
+        return Err(ErrorCode::NoFieldsToUpdate.into());
     }
 
     // Update the user
-    match sqlx::query("UPDATE users SET email = ?, name = ? WHERE id = ?")
+    sqlx::query("UPDATE users SET email = ?, name = ?, language = ? WHERE id = ?")
         .bind(&updated_email)
         .bind(&updated_name)
+        .bind(&updated_language)
         .bind(user_id.as_str())
         .execute(db.pool())
-        .await
-    {
-        Ok(_) => {
-            let user_info = UserInfo {
-                id: existing_user.id,
-                email: updated_email,
-                name: updated_name,
-            };
-            Ok(HttpResponse::Ok().json(user_info))
-        }
-        Err(_) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to update user"
-        }))),
-    }
+        .await?;
+
+    let user_info = UserInfo {
+        id: existing_user.id,
+        email: updated_email,
+        name: updated_name,
+        language: updated_language,
+    };
+    Ok(HttpResponse::Ok().json(user_info))
 }
 
 // Change user password
 pub async fn change_password(
     db: web::Data<Database>,
     user_id: web::Path<String>,
-    password_data: web::Json<ChangePasswordRequest>,
-) -> Result<HttpResponse> {
+    password_data: ValidatedJson<ChangePasswordRequest>,
+) -> Result<HttpResponse, AppError> {
     // Get current user
-    let user = match sqlx::query_as::<_, User>(
-        "SELECT id, email, password_hash, name, created_at FROM users WHERE id = ?",
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, email, password_hash, name, created_at, language FROM users WHERE id = ?",
     )
     .bind(user_id.as_str())
     .fetch_optional(db.pool())
-    .await
-    {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "User not found"
-            })));
-        }
-        Err(_) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Database error"
-            })));
-        }
-    };
+    .await?
+    .ok_or(ErrorCode::UserNotFound)?;
 
     // Verify current password
     if !verify(&password_data.current_password, &user.password_hash).unwrap_or(false) {
-        return Ok(HttpResponse::Unauthorized().json(serde_json::json!({
-            "error": "Current password is incorrect"
-        })));
+        return Err(ErrorCode::IncorrectPassword.into());
     }
 
     // Hash new password
-    let new_password_hash = match hash(&password_data.new_password, DEFAULT_COST) {
-        Ok(hash) => hash,
-        Err(_) => {
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to hash new password"
-            })));
-        }
-    };
+    let new_password_hash = hash(&password_data.new_password, DEFAULT_COST).map_err(anyhow::Error::from)?;
 
     // Update password
-    match sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+    sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
         .bind(&new_password_hash)
         .bind(user_id.as_str())
         .execute(db.pool())
-        .await
-    {
-        Ok(_) => Ok(HttpResponse::Ok().json(serde_json::json!({
-            "message": "Password updated successfully"
-        }))),
-        Err(_) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to update password"
-        }))),
-    }
+        .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Password updated successfully"
+    })))
 }
 
 // Delete user
 pub async fn delete_user(
     db: web::Data<Database>,
     user_id: web::Path<String>,
-) -> Result<HttpResponse> {
-    match sqlx::query("DELETE FROM users WHERE id = ?")
+) -> Result<HttpResponse, AppError> {
+    let result = sqlx::query("DELETE FROM users WHERE id = ?")
         .bind(user_id.as_str())
         .execute(db.pool())
-        .await
-    {
-        Ok(result) => {
-            if result.rows_affected() == 0 {
-                Ok(HttpResponse::NotFound().json(serde_json::json!({
-                    "error": "User not found"
-                })))
-            } else {
-                Ok(HttpResponse::Ok().json(serde_json::json!({
-                    "message": "User deleted successfully"
-                })))
-            }
-        }
-        Err(_) => Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to delete user"
-        }))),
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ErrorCode::UserNotFound.into());
     }
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "User deleted successfully"
+    })))
 }
 
 #[allow(dead_code)]
-pub async fn debug_test_password_verification(db: web::Data<Database>) -> Result<HttpResponse, Error> {
+pub async fn debug_test_password_verification(db: web::Data<Database>) -> Result<HttpResponse, AppError> {
     let default_email = env::var("DEFAULT_ADMIN_EMAIL").unwrap_or_default();
     let default_password = env::var("DEFAULT_ADMIN_PASSWORD").unwrap_or_default();
     
     // Get user from database
     let user_result = sqlx::query_as::<_, User>(
-        "SELECT id, email, password_hash, name, created_at FROM users WHERE email = ?",
+        "SELECT id, email, password_hash, name, created_at, language FROM users WHERE email = ?",
     )
     .bind(&default_email)
     .fetch_one(db.pool())
@@ -634,20 +543,18 @@ pub async fn debug_test_password_verification(db: web::Data<Database>) -> Result
         },
         Err(e) => {
             println!("User not found: {e:?}");
-            Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "User not found"
-            })))
+            Err(ErrorCode::UserNotFound.into())
         }
     }
 }
 
 #[allow(dead_code)]
-pub async fn debug_test_multiple_passwords(db: web::Data<Database>) -> Result<HttpResponse, Error> {
+pub async fn debug_test_multiple_passwords(db: web::Data<Database>) -> Result<HttpResponse, AppError> {
     let default_email = env::var("DEFAULT_ADMIN_EMAIL").unwrap_or_default();
     
     // Get user from database
     let user_result = sqlx::query_as::<_, User>(
-        "SELECT id, email, password_hash, name, created_at FROM users WHERE email = ?",
+        "SELECT id, email, password_hash, name, created_at, language FROM users WHERE email = ?",
     )
     .bind(&default_email)
     .fetch_one(db.pool())
@@ -693,9 +600,7 @@ pub async fn debug_test_multiple_passwords(db: web::Data<Database>) -> Result<Ht
         },
         Err(e) => {
             println!("User not found: {e:?}");
-            Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "User not found"
-            })))
+            Err(ErrorCode::UserNotFound.into())
         }
     }
 }
@@ -0,0 +1,399 @@
+use actix_multipart::Multipart;
+use actix_web::web::BytesMut;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web::http::header::{HeaderName, USER_AGENT};
+use futures_util::TryStreamExt;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+use crate::errors::{ApiError, AppError, ErrorCode};
+use crate::validation::{ValidatedJson, Validator};
+use crate::database::Database;
+use crate::documents::consent::{consent_context, render_consent};
+use crate::documents::template::Template;
+use crate::documents::{sanitize_filename, ClinicInfo};
+use crate::file_type;
+use crate::handlers::attachment_handler::read_text;
+use crate::i18n::Localizer;
+use crate::models::{
+    Claims, ConsentForm, CreateConsentFormRequest, UpdateConsentFormRequest, ConsentFormQuery, ConsentSignature,
+    ConsentSigning, SigningContext, RevokeConsentRequest, ConsentDocumentQuery, ConsentStatus, SIGNATURE_MAX_BYTES,
+};
+use crate::thumbnail;
+
+/// Proxies allowed to report the client address (`TRUSTED_PROXIES`, comma-separated IPs; none if unset)
+fn trusted_proxies() -> Vec<IpAddr> {
+    env::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect()
+}
+
+/// The client a trusted proxy forwarded the request for.
+///
+/// `X-Forwarded-For` is only read when the connection comes from a trusted
+/// proxy, and then from the right, past any further trusted proxies, since
+/// entries to the left of them are whatever the client chose to send.
+fn forwarded_client(req: &HttpRequest, peer: IpAddr) -> Option<IpAddr> {
+    let trusted = trusted_proxies();
+    if !trusted.contains(&peer) {
+        return None;
+    }
+    let header = req.headers().get(HeaderName::from_static("x-forwarded-for"))?.to_str().ok()?;
+    let mut client = None;
+    for entry in header.rsplit(',') {
+        let ip: IpAddr = entry.trim().parse().ok()?;
+        client = Some(ip);
+        if !trusted.contains(&ip) {
+            break;
+        }
+    }
+    client
+}
+
+/// The body is filled in for each patient at signing, so it must be a valid template
+fn check_body(form: &ConsentForm) -> Result<(), AppError> {
+    if let Err(e) = Template::parse(&form.body) {
+        return Err(ApiError::new(ErrorCode::InvalidTemplate).with("reason", e).into());
+    }
+    Ok(())
+}
+
+pub async fn create_consent_form(
+    data: ValidatedJson<CreateConsentFormRequest>,
+    claims: Option<web::ReqData<Claims>>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let form = ConsentForm::new(data.into_inner());
+    check_body(&form)?;
+
+    db.create_consent_form(&form, &form.current_version(claims.map(|c| c.into_inner().sub))).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Consent form created successfully",
+        "consent_form": form
+    })))
+}
+
+pub async fn get_consent_forms(
+    query: web::Query<ConsentFormQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let forms = db.get_consent_forms(query.include_inactive.unwrap_or(false)).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "consent_forms": forms,
+        "count": forms.len()
+    })))
+}
+
+pub async fn get_consent_form_by_id(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let form_id = path.into_inner();
+
+    let form = db
+        .get_consent_form_by_id(form_id)
+        .await?
+        .ok_or(ErrorCode::ConsentFormNotFound)?;
+    Ok(HttpResponse::Ok().json(form))
+}
+
+/// Update a form; a changed title or body becomes a new version that patients sign again
+pub async fn update_consent_form(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<UpdateConsentFormRequest>,
+    claims: Option<web::ReqData<Claims>>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let form_id = path.into_inner();
+
+    let mut form = db
+        .get_consent_form_by_id(form_id)
+        .await?
+        .ok_or(ErrorCode::ConsentFormNotFound)?;
+    let new_version = form.update(data.into_inner());
+    check_body(&form)?;
+
+    let version = new_version.then(|| form.current_version(claims.map(|c| c.into_inner().sub)));
+    if !db.update_consent_form(form_id, &form, version.as_ref()).await? {
+        return Err(ErrorCode::ConsentFormNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Consent form updated successfully",
+        "consent_form": form,
+        "new_version": new_version
+    })))
+}
+
+/// Delete a form nobody has signed; signed ones are deactivated instead
+pub async fn delete_consent_form(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let form_id = path.into_inner();
+
+    if db.consent_form_has_signatures(form_id).await? {
+        return Err(ErrorCode::ConsentFormInUse.into());
+    }
+    if !db.delete_consent_form(form_id).await? {
+        return Err(ErrorCode::ConsentFormNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Consent form deleted successfully"
+    })))
+}
+
+pub async fn get_consent_form_versions(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let form_id = path.into_inner();
+
+    if db.get_consent_form_by_id(form_id).await?.is_none() {
+        return Err(ErrorCode::ConsentFormNotFound.into());
+    }
+    let versions = db.get_consent_form_versions(form_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "versions": versions,
+        "count": versions.len()
+    })))
+}
+
+pub async fn get_consent_form_version(
+    path: web::Path<(Uuid, i64)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (form_id, version) = path.into_inner();
+
+    let version = db
+        .get_consent_form_version(form_id, version)
+        .await?
+        .ok_or(ErrorCode::ConsentFormNotFound)?;
+    Ok(HttpResponse::Ok().json(version))
+}
+
+/// Where the patient stands with every form they must sign before treatment
+pub async fn consent_statuses(db: &Database, patient_id: Uuid) -> Result<Vec<ConsentStatus>, AppError> {
+    let forms = db.get_required_consent_forms().await?;
+    if forms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let signatures = db.get_consent_signatures_for_patient(patient_id).await?;
+    Ok(forms.iter().map(|form| form.status(&signatures)).collect())
+}
+
+/// Load a signed consent and make sure it belongs to the patient in the path
+async fn load_signature(db: &Database, patient_id: Uuid, consent_id: Uuid) -> Result<ConsentSignature, AppError> {
+    db.get_consent_signature_by_id(consent_id)
+        .await?
+        .filter(|signature| signature.patient_id == patient_id)
+        .ok_or_else(|| ErrorCode::ConsentNotFound.into())
+}
+
+/// Sign a form as multipart form data: `form_id`, `signer_name`, the drawn `signature` image,
+/// and optionally `signer_contact_id` and `lang`
+pub async fn sign_consent(
+    path: web::Path<Uuid>,
+    mut payload: Multipart,
+    req: HttpRequest,
+    claims: Option<web::ReqData<Claims>>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    let patient = db
+        .get_patient_by_id(patient_id)
+        .await?
+        .ok_or(ErrorCode::PatientNotFound)?;
+
+    let mut image: Option<Vec<u8>> = None;
+    let mut form_id = None;
+    let mut signer_name = String::new();
+    let mut signer_contact_id = None;
+    let mut lang = None;
+
+    let parse_id = |field: &str, value: String| -> Result<Option<Uuid>, AppError> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(None);
+        }
+        Uuid::parse_str(value)
+            .map(Some)
+            .map_err(|e| AppError::InvalidRequest(format!("{field}: {e}")))
+    };
+
+    while let Some(mut field) = payload.try_next().await? {
+        match field.name().unwrap_or_default() {
+            "signature" => {
+                let mut data = BytesMut::new();
+                while let Some(chunk) = field.try_next().await? {
+                    if data.len() + chunk.len() > SIGNATURE_MAX_BYTES {
+                        return Err(ApiError::new(ErrorCode::FileTooLarge)
+                            .with("max_mb", SIGNATURE_MAX_BYTES / (1024 * 1024))
+                            .into());
+                    }
+                    data.extend_from_slice(&chunk);
+                }
+                image = Some(data.to_vec());
+            }
+            "form_id" => form_id = parse_id("form_id", read_text(&mut field).await?)?,
+            "signer_name" => signer_name = read_text(&mut field).await?.trim().to_string(),
+            "signer_contact_id" => signer_contact_id = parse_id("signer_contact_id", read_text(&mut field).await?)?,
+            "lang" => lang = Some(read_text(&mut field).await?.trim().to_string()).filter(|l| !l.is_empty()),
+            // Unknown fields are drained and ignored
+            _ => while field.try_next().await?.is_some() {},
+        }
+    }
+
+    let form_id = form_id.ok_or_else(|| AppError::InvalidRequest("form_id is required".to_string()))?;
+    let signing = ConsentSigning { form_id, signer_name, signer_contact_id, lang };
+    Validator::check(&signing).map_err(AppError::Validation)?;
+
+    let image = image.filter(|data| !data.is_empty()).ok_or(ErrorCode::SignatureRequired)?;
+    let content_type = file_type::sniff(&image, "")
+        .filter(|t| matches!(*t, "image/png" | "image/jpeg"))
+        .filter(|_| thumbnail::image_size(&image).is_some())
+        .ok_or(ErrorCode::InvalidSignatureImage)?;
+
+    let form = db
+        .get_consent_form_by_id(signing.form_id)
+        .await?
+        .ok_or(ErrorCode::ConsentFormNotFound)?;
+    if !form.active {
+        return Err(ErrorCode::ConsentFormInactive.into());
+    }
+    if let Some(contact_id) = signing.signer_contact_id {
+        let guardian = db
+            .get_contact_by_id(contact_id)
+            .await?
+            .is_some_and(|contact| contact.patient_id == patient_id && contact.legal_guardian);
+        if !guardian {
+            return Err(ErrorCode::SignerNotGuardian.into());
+        }
+    }
+
+    // The copy kept is the form as the patient saw it, with their details filled in
+    let localizer = Localizer::new(signing.lang.as_deref().or(patient.preferred_language.as_deref()).unwrap_or("en"));
+    let template = Template::parse(&form.body)
+        .map_err(|e| ApiError::new(ErrorCode::InvalidTemplate).with("reason", e))?;
+    let rendered_body = template.render(&consent_context(&patient, &signing.signer_name, &ClinicInfo::from_env(), &localizer));
+    let rendered_sha256 = format!("{:x}", Sha256::digest(rendered_body.as_bytes()));
+
+    let peer = req.peer_addr().map(|addr| addr.ip());
+    let context = SigningContext {
+        ip_address: peer.map(|ip| ip.to_string()),
+        forwarded_for: peer.and_then(|ip| forwarded_client(&req, ip)).map(|ip| ip.to_string()),
+        user_agent: req.headers().get(USER_AGENT).and_then(|v| v.to_str().ok()).map(str::to_string),
+        recorded_by: claims.map(|c| c.into_inner().sub),
+    };
+    let signature = ConsentSignature::new(patient_id, &form, signing, rendered_body, rendered_sha256, content_type, context);
+    db.create_consent_signature(&signature, &image).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Consent signed successfully",
+        "consent": signature
+    })))
+}
+
+/// A patient's signed consents, newest first, and whether each required form is signed in its current version
+pub async fn get_consents_for_patient(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    let signatures = db.get_consent_signatures_for_patient(patient_id).await?;
+    let required = consent_statuses(&db, patient_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "consents": signatures,
+        "count": signatures.len(),
+        "required": required,
+        "all_current": required.iter().all(|status| status.current)
+    })))
+}
+
+pub async fn get_consent_by_id(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, consent_id) = path.into_inner();
+
+    let signature = load_signature(&db, patient_id, consent_id).await?;
+    Ok(HttpResponse::Ok().json(signature))
+}
+
+/// The drawn signature as it was uploaded
+pub async fn get_consent_signature_image(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, consent_id) = path.into_inner();
+
+    let signature = load_signature(&db, patient_id, consent_id).await?;
+    let image = db
+        .get_consent_signature_image(consent_id)
+        .await?
+        .ok_or(ErrorCode::ConsentNotFound)?;
+    Ok(HttpResponse::Ok()
+        .content_type(signature.signature_content_type)
+        .insert_header(("Cache-Control", "private, max-age=31536000, immutable"))
+        .body(image))
+}
+
+/// The signed form as an RTF document, from the copy stored at signing
+pub async fn get_consent_document(
+    path: web::Path<(Uuid, Uuid)>,
+    query: web::Query<ConsentDocumentQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, consent_id) = path.into_inner();
+
+    let signature = load_signature(&db, patient_id, consent_id).await?;
+    let patient = db
+        .get_patient_by_id(patient_id)
+        .await?
+        .ok_or(ErrorCode::PatientNotFound)?;
+    let image = db.get_consent_signature_image(consent_id).await?.unwrap_or_default();
+    let language = query.lang.as_deref().or(patient.preferred_language.as_deref()).unwrap_or("en");
+
+    let content = render_consent(&signature, &image, thumbnail::image_size(&image), &ClinicInfo::from_env(), language);
+    let filename = format!(
+        "consent_{}_{}_v{}.rtf",
+        sanitize_filename(&patient.name),
+        sanitize_filename(&signature.title),
+        signature.form_version
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("application/rtf")
+        .append_header(("Content-Disposition", format!("attachment; filename=\"{filename}\"")))
+        .body(content))
+}
+
+/// Withdraw a consent; the signed copy is kept, marked revoked
+pub async fn revoke_consent(
+    path: web::Path<(Uuid, Uuid)>,
+    data: ValidatedJson<RevokeConsentRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, consent_id) = path.into_inner();
+
+    let mut signature = load_signature(&db, patient_id, consent_id).await?;
+    if signature.revoked_at.is_some() {
+        return Err(ErrorCode::ConsentAlreadyRevoked.into());
+    }
+
+    let revoked_at = chrono::Utc::now();
+    let reason = data.into_inner().reason.unwrap_or_default().trim().to_string();
+    if !db.revoke_consent_signature(consent_id, revoked_at, &reason).await? {
+        return Err(ErrorCode::ConsentAlreadyRevoked.into());
+    }
+    signature.revoked_at = Some(revoked_at);
+    signature.revoke_reason = reason;
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Consent revoked successfully",
+        "consent": signature
+    })))
+}
@@ -0,0 +1,93 @@
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::errors::{AppError, ErrorCode};
+use crate::validation::ValidatedJson;
+use crate::database::Database;
+use crate::models::{PatientContact, CreateContactRequest, UpdateContactRequest};
+
+/// Load a contact and make sure it belongs to the patient in the path
+async fn load_contact(db: &Database, patient_id: Uuid, contact_id: Uuid) -> Result<PatientContact, AppError> {
+    db.get_contact_by_id(contact_id)
+        .await?
+        .filter(|contact| contact.patient_id == patient_id)
+        .ok_or_else(|| ErrorCode::ContactNotFound.into())
+}
+
+pub async fn create_contact(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<CreateContactRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    if db.get_patient_by_id(patient_id).await?.is_none() {
+        return Err(ErrorCode::PatientNotFound.into());
+    }
+
+    let contact = PatientContact::new(patient_id, data.into_inner());
+    db.create_contact(&contact).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Contact created successfully",
+        "contact": contact
+    })))
+}
+
+pub async fn get_contacts_for_patient(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    let contacts = db.get_contacts_for_patient(patient_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "contacts": contacts,
+        "count": contacts.len()
+    })))
+}
+
+pub async fn get_contact_by_id(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, contact_id) = path.into_inner();
+
+    let contact = load_contact(&db, patient_id, contact_id).await?;
+    Ok(HttpResponse::Ok().json(contact))
+}
+
+pub async fn update_contact(
+    path: web::Path<(Uuid, Uuid)>,
+    data: ValidatedJson<UpdateContactRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, contact_id) = path.into_inner();
+
+    let mut contact = load_contact(&db, patient_id, contact_id).await?;
+    contact.update(data.into_inner());
+
+    if !db.update_contact(contact_id, &contact).await? {
+        return Err(ErrorCode::ContactNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Contact updated successfully",
+        "contact": contact
+    })))
+}
+
+pub async fn delete_contact(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, contact_id) = path.into_inner();
+
+    load_contact(&db, patient_id, contact_id).await?;
+
+    if !db.delete_contact(contact_id).await? {
+        return Err(ErrorCode::ContactNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Contact deleted successfully"
+    })))
+}
@@ -0,0 +1,103 @@
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::errors::{AppError, ErrorCode};
+use crate::validation::ValidatedJson;
+use crate::database::Database;
+use crate::documents::billing::{invoice_filename, receipt_filename, render_invoice, render_receipt};
+use crate::documents::letter::render_letter;
+use crate::documents::{sanitize_filename, ClinicInfo};
+use crate::handlers::invoice_handler::invoice_detail;
+use crate::i18n::request_localizer;
+use crate::models::{LetterRequest, Patient};
+
+#[derive(Debug, Deserialize)]
+pub struct DocumentQuery {
+    pub lang: Option<String>,
+}
+
+/// Language of a document about a patient: the one asked for, else the patient's own, else the request's
+fn document_language(requested: Option<&str>, patient: &Patient) -> String {
+    requested
+        .or(patient.preferred_language.as_deref())
+        .map_or_else(|| request_localizer().code().to_string(), str::to_string)
+}
+
+fn rtf_attachment(filename: &str, content: String) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/rtf")
+        .append_header(("Content-Disposition", format!("attachment; filename=\"{filename}\"")))
+        .body(content)
+}
+
+/// Download an invoice or credit note as an RTF document
+pub async fn invoice_document(
+    path: web::Path<Uuid>,
+    query: web::Query<DocumentQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let invoice_id = path.into_inner();
+
+    let invoice = db
+        .get_invoice_by_id(invoice_id)
+        .await?
+        .ok_or(ErrorCode::InvoiceNotFound)?;
+
+    let patient = db.get_patient_by_id(invoice.patient_id).await?
+        .ok_or_else(|| anyhow::anyhow!("Patient {} missing for invoice {}", invoice.patient_id, invoice.id))?;
+    let language = document_language(query.lang.as_deref(), &patient);
+    let credited_invoice = match invoice.credited_invoice_id {
+        Some(original_id) => db.get_invoice_by_id(original_id).await?,
+        None => None,
+    };
+    let detail = invoice_detail(&db, invoice).await?;
+    let filename = invoice_filename(&detail.invoice);
+
+    let content = render_invoice(&detail, credited_invoice.as_ref(), &patient, &ClinicInfo::from_env(), &language);
+    Ok(rtf_attachment(&filename, content))
+}
+
+/// Download the receipt for a payment as an RTF document
+pub async fn receipt_document(
+    path: web::Path<Uuid>,
+    query: web::Query<DocumentQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let payment_id = path.into_inner();
+
+    let payment = db
+        .get_payment_by_id(payment_id)
+        .await?
+        .ok_or(ErrorCode::PaymentNotFound)?;
+
+    let (Some(invoice), Some(patient)) = (
+        db.get_invoice_by_id(payment.invoice_id).await?,
+        db.get_patient_by_id(payment.patient_id).await?,
+    ) else {
+        return Err(ErrorCode::InvoiceNotFound.into());
+    };
+    let language = document_language(query.lang.as_deref(), &patient);
+
+    let content = render_receipt(&payment, &invoice, &patient, &ClinicInfo::from_env(), &language);
+    Ok(rtf_attachment(&receipt_filename(&payment), content))
+}
+
+/// Render a clinical letter about a patient on the clinic letterhead
+pub async fn create_patient_letter(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<LetterRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    let patient = db
+        .get_patient_by_id(patient_id)
+        .await?
+        .ok_or(ErrorCode::PatientNotFound)?;
+    let language = document_language(data.lang.as_deref(), &patient);
+
+    let content = render_letter(&data, &patient, &ClinicInfo::from_env(), &language);
+    let filename = format!("letter_{}_{}.rtf", sanitize_filename(&patient.name), sanitize_filename(&data.title));
+    Ok(rtf_attachment(&filename, content))
+}
@@ -1,281 +1,1428 @@
-use actix_web::{web, HttpResponse, Result};
-use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::io;
+use std::sync::Arc;
+
+use actix_web::{web, HttpResponse};
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::mpsc;
 use uuid::Uuid;
 
+use crate::errors::{ApiError, AppError, ErrorCode};
+use crate::validation::ValidatedJson;
 use crate::database::Database;
-use crate::models::patient::Patient;
+use crate::documents::docx::{self, DocxDocument};
+use crate::documents::pdf::{PdfDocument, PdfFonts};
+use crate::documents::rtf::RtfDocument;
+use crate::documents::template::{write_markup, Template};
+use crate::documents::zip::ZipWriter;
+use crate::documents::{sanitize_filename, ClinicInfo, DocumentWriter};
+use crate::i18n::{language_name, Calendar, Localizer};
+use crate::models::patient::{Gender, Patient};
+use crate::models::contact::{ContactRelationship, PatientContact};
+use crate::models::medical::{Allergy, AllergySeverity, Diagnosis, MedicalBackground, Medication};
 use crate::models::treatment::Treatment;
+use crate::models::body_chart::{BodyAnnotation, BodySide, ChartView, INTENSITY_MAX};
+use crate::body_region::region_name;
+use crate::models::treatment_plan::{GoalStatus, PlanGoal, PlanStatus, TreatmentPlan};
+use crate::models::{ExportFormat, ExportTemplatePreviewRequest};
+use crate::handlers::report_handler::csv_line;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PatientExportData {
     pub patient: Patient,
     pub treatments: Vec<Treatment>,
+    #[serde(default)]
+    pub contacts: Vec<PatientContact>,
+    #[serde(default)]
+    pub medical_background: MedicalBackground,
+    #[serde(default)]
+    pub treatment_plans: Vec<TreatmentPlan>,
 }
 
-struct FieldNames {
-    title: &'static str,
-    patient_info: &'static str,
-    name: &'static str,
-    email: &'static str,
-    phone: &'static str,
-    registration_date: &'static str,
-    status: &'static str,
-    active: &'static str,
-    inactive: &'static str,
-    description: &'static str,
-    treatments_history: &'static str,
-    no_treatments: &'static str,
-    total_treatments: &'static str,
-    treatment: &'static str,
-    exported_on: &'static str,
+#[derive(Serialize)]
+struct FieldNames<'a> {
+    title: &'a str,
+    patient_info: &'a str,
+    name: &'a str,
+    email: &'a str,
+    phone: &'a str,
+    date_of_birth: &'a str,
+    gender: &'a str,
+    gender_female: &'a str,
+    gender_male: &'a str,
+    gender_other: &'a str,
+    national_id: &'a str,
+    address: &'a str,
+    preferred_language: &'a str,
+    referral_source: &'a str,
+    contacts: &'a str,
+    relationship_parent: &'a str,
+    relationship_guardian: &'a str,
+    relationship_spouse: &'a str,
+    relationship_emergency: &'a str,
+    legal_guardian: &'a str,
+    may_receive_information: &'a str,
+    medical_background: &'a str,
+    diagnosis: &'a str,
+    allergy: &'a str,
+    medication: &'a str,
+    severity_mild: &'a str,
+    severity_moderate: &'a str,
+    severity_severe: &'a str,
+    severity_life_threatening: &'a str,
+    treatment_plans: &'a str,
+    plan_sessions: &'a str,
+    plan_frequency: &'a str,
+    goals_achieved: &'a str,
+    goal: &'a str,
+    target: &'a str,
+    plan_status_active: &'a str,
+    plan_status_on_hold: &'a str,
+    plan_status_completed: &'a str,
+    plan_status_discontinued: &'a str,
+    goal_status_not_started: &'a str,
+    goal_status_in_progress: &'a str,
+    goal_status_achieved: &'a str,
+    goal_status_discontinued: &'a str,
+    body_chart: &'a str,
+    side_left: &'a str,
+    side_right: &'a str,
+    side_bilateral: &'a str,
+    view_front: &'a str,
+    view_back: &'a str,
+    intensity: &'a str,
+    registration_date: &'a str,
+    status: &'a str,
+    active: &'a str,
+    inactive: &'a str,
+    description: &'a str,
+    treatments_history: &'a str,
+    no_treatments: &'a str,
+    total_treatments: &'a str,
+    treatment: &'a str,
+    date: &'a str,
+    duration: &'a str,
+    summary: &'a str,
+    period: &'a str,
+    exported_on: &'a str,
+    /// Page footer, with `{page}` and `{pages}` placeholders
+    page_of: &'a str,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ExportQuery {
     pub lang: Option<String>,
+    pub format: Option<ExportFormat>,
+    /// Name of an export template to lay the document out with
+    pub template: Option<String>,
+    /// Only treatments on or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Only treatments before this time
+    pub to: Option<DateTime<Utc>>,
+    /// Comma-separated IDs of the treatments to include
+    pub treatment_ids: Option<String>,
+    /// Leave out email, phone and address, for records shared with third parties
+    pub omit_contact: Option<bool>,
+    pub order: Option<ExportOrder>,
+    /// Calendar for long dates: `gregorian` (default), `hebrew` or `both`
+    pub calendar: Option<Calendar>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportOrder {
+    #[default]
+    Newest,
+    Oldest,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BulkExportFormat {
+    #[default]
+    Rtf,
+    Pdf,
+    Docx,
+    Json,
+}
+
+impl BulkExportFormat {
+    /// Document rendered for each patient; `None` for raw JSON data
+    fn document_format(self) -> Option<ExportFormat> {
+        match self {
+            BulkExportFormat::Rtf => Some(ExportFormat::Rtf),
+            BulkExportFormat::Pdf => Some(ExportFormat::Pdf),
+            BulkExportFormat::Docx => Some(ExportFormat::Docx),
+            BulkExportFormat::Json => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkExportQuery {
+    pub lang: Option<String>,
+    pub format: Option<BulkExportFormat>,
+    pub template: Option<String>,
+    /// Only active (`true`) or inactive (`false`) patients
+    pub active: Option<bool>,
+    /// Comma-separated IDs of the patients to include
+    pub patient_ids: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub omit_contact: Option<bool>,
+    pub order: Option<ExportOrder>,
+    pub calendar: Option<Calendar>,
+}
+
+impl BulkExportQuery {
+    /// Options applied to each patient, as for a single export
+    fn patient_query(&self) -> ExportQuery {
+        ExportQuery {
+            lang: self.lang.clone(),
+            format: self.format.unwrap_or_default().document_format(),
+            template: self.template.clone(),
+            from: self.from,
+            to: self.to,
+            treatment_ids: None,
+            omit_contact: self.omit_contact,
+            order: self.order,
+            calendar: self.calendar,
+        }
+    }
+}
+
+/// Patient with the treatments chosen for an export, in display order
+struct PatientRecord {
+    patient: Patient,
+    /// Relatives and guardians, legal guardians first
+    contacts: Vec<PatientContact>,
+    /// Full medical background, including entries that have ended
+    medical_background: MedicalBackground,
+    /// All plans, newest first, with their goals and session counts
+    treatment_plans: Vec<TreatmentPlan>,
+    /// Selected treatments with their session number in the full history
+    treatments: Vec<(usize, Treatment)>,
+    omit_contact: bool,
+    /// Date range the export is limited to, formatted for display
+    period: Option<String>,
+}
+
+impl From<&PatientRecord> for PatientExportData {
+    fn from(record: &PatientRecord) -> Self {
+        let mut patient = record.patient.clone();
+        if record.omit_contact {
+            patient.email = None;
+            patient.phone_number = String::new();
+            patient.phone_e164 = None;
+            patient.address = None;
+        }
+        let mut contacts = record.contacts.clone();
+        if record.omit_contact {
+            for contact in &mut contacts {
+                contact.phone_number = String::new();
+                contact.phone_e164 = None;
+                contact.email = None;
+            }
+        }
+
+        Self {
+            patient,
+            contacts,
+            medical_background: record.medical_background.clone(),
+            treatment_plans: record.treatment_plans.clone(),
+            treatments: record.treatments.iter().map(|(_, treatment)| treatment.clone()).collect(),
+        }
+    }
 }
 
-/// Export patient data and treatments to a Word document (RTF format)
+impl PatientRecord {
+    /// Full history, newest first, from treatments as loaded from the database
+    fn new(patient: Patient, treatments: Vec<Treatment>) -> Self {
+        let count = treatments.len();
+        Self {
+            patient,
+            contacts: Vec::new(),
+            medical_background: MedicalBackground::default(),
+            treatment_plans: Vec::new(),
+            treatments: treatments.into_iter().enumerate().map(|(index, t)| (count - index, t)).collect(),
+            omit_contact: false,
+            period: None,
+        }
+    }
+
+    /// The patient's full record with everything an export can show
+    async fn load(db: &Database, patient: Patient) -> anyhow::Result<Self> {
+        let treatments = db.get_treatments_for_patient(patient.id).await?;
+        let contacts = db.get_contacts_for_patient(patient.id).await?;
+        let medical_background = db.get_medical_background(patient.id).await?;
+        let treatment_plans = db.get_treatment_plans_for_patient(patient.id).await?;
+        Ok(Self { contacts, medical_background, treatment_plans, ..Self::new(patient, treatments) })
+    }
+}
+
+impl ExportQuery {
+    fn localizer(&self) -> Localizer {
+        Localizer::new(self.lang.as_deref().unwrap_or("en")).with_calendar(self.calendar.unwrap_or_default())
+    }
+
+    /// Narrow a patient's full record down to the requested treatments and details
+    fn select(&self, mut record: PatientRecord) -> Result<PatientRecord, AppError> {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from >= to {
+                return Err(ErrorCode::InvalidDateRange.into());
+            }
+        }
+
+        let ids = match &self.treatment_ids {
+            Some(list) => {
+                let parsed = list
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|id| !id.is_empty())
+                    .map(Uuid::parse_str)
+                    .collect::<Result<HashSet<_>, _>>();
+                let Ok(ids) = parsed else {
+                    return Err(ApiError::new(ErrorCode::InvalidIdList)
+                        .with("field", "treatment_ids")
+                        .into());
+                };
+                if let Some(unknown) = ids.iter().find(|id| !record.treatments.iter().any(|(_, t)| t.id == **id)) {
+                    return Err(ApiError::new(ErrorCode::TreatmentNotForPatient)
+                        .with("id", unknown)
+                        .into());
+                }
+                Some(ids)
+            }
+            None => None,
+        };
+
+        record.treatments.retain(|(_, treatment)| {
+            self.from.is_none_or(|from| treatment.date >= from)
+                && self.to.is_none_or(|to| treatment.date < to)
+                && ids.as_ref().is_none_or(|ids| ids.contains(&treatment.id))
+        });
+        if self.order.unwrap_or_default() == ExportOrder::Oldest {
+            record.treatments.reverse();
+        }
+
+        record.omit_contact = self.omit_contact.unwrap_or(false);
+        let localizer = self.localizer();
+        record.period = match (self.from, self.to) {
+            (None, None) => None,
+            (from, to) => Some(format!(
+                "{} – {}",
+                from.map(|d| localizer.format_date(&d)).unwrap_or_default(),
+                to.map(|d| localizer.format_date(&d)).unwrap_or_default()
+            ).trim().to_string()),
+        };
+
+        Ok(record)
+    }
+}
+
+/// The built-in layout written as a template, as a starting point for custom ones
+const DEFAULT_TEMPLATE: &str = "\
+# {{labels.title}}
+## {{labels.patient_info}}
+**{{labels.name}}:** {{patient.name}}
+{{#unless omit_contact}}
+**{{labels.email}}:** {{patient.email}}
+**{{labels.phone}}:** {{patient.phone_number}}
+{{#if patient.address}}
+**{{labels.address}}:** {{patient.address}}
+{{/if}}
+{{/unless}}
+{{#if patient.date_of_birth}}
+**{{labels.date_of_birth}}:** {{patient.date_of_birth}}
+{{/if}}
+{{#if patient.gender}}
+**{{labels.gender}}:** {{patient.gender}}
+{{/if}}
+{{#if patient.national_id}}
+**{{labels.national_id}}:** {{patient.national_id}}
+{{/if}}
+{{#if patient.preferred_language}}
+**{{labels.preferred_language}}:** {{patient.preferred_language_name}}
+{{/if}}
+{{#if patient.referral_source}}
+**{{labels.referral_source}}:** {{patient.referral_source}}
+{{/if}}
+**{{labels.registration_date}}:** {{patient.registration_date}}
+**{{labels.status}}:** {{patient.status}}
+{{#if patient.description}}
+**{{labels.description}}:** {{patient.description}}
+{{/if}}
+{{#if has_medical_background}}
+
+## {{labels.medical_background}}
+{{#each medical_background.allergies}}
+**{{../labels.allergy}}:** {{details}}
+{{/each}}
+{{#each medical_background.diagnoses}}
+**{{../labels.diagnosis}}:** {{details}}
+{{/each}}
+{{#each medical_background.medications}}
+**{{../labels.medication}}:** {{details}}
+{{/each}}
+{{/if}}
+{{#if contacts}}
+
+## {{labels.contacts}}
+{{#each contacts}}
+**{{relationship}}:** {{details}}
+{{/each}}
+{{/if}}
+{{#if treatment_plans}}
+
+## {{labels.treatment_plans}}
+{{#each treatment_plans}}
+**{{heading}}**
+**{{../labels.plan_sessions}}:** {{sessions}}
+{{#if frequency}}
+**{{../labels.plan_frequency}}:** {{frequency}}
+{{/if}}
+{{#each goals}}
+**{{../../labels.goal}}:** {{details}}
+{{/each}}
+{{/each}}
+{{/if}}
+
+## {{labels.treatments_history}}
+{{#if period}}
+**{{labels.period}}:** {{period}}
+{{/if}}
+{{#if treatments}}
+{{labels.total_treatments}}: {{treatment_count}}
+
+{{#each treatments}}
+**{{../labels.treatment}} #{{number}} - {{date}}**
+{{summary}}
+{{#each annotations}}
+**{{../../labels.body_chart}}:** {{details}}
+{{/each}}
+
+{{/each}}
+{{else}}
+_{{labels.no_treatments}}._
+{{/if}}
+---
+> {{labels.exported_on}}: {{today}}
+";
+
+/// Export patient data and treatments as an RTF, PDF or DOCX document, optionally laid out with a saved template
 pub async fn export_patient_to_word(
     path: web::Path<Uuid>,
     query: web::Query<ExportQuery>,
     db: web::Data<Database>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, AppError> {
     let patient_id = path.into_inner();
-    let language = query.lang.as_deref().unwrap_or("en");
-    
-    // Debug logging
-    eprintln!("Export request for patient ID: {patient_id} in language: {language}");
-    
-    // Debug: List all patients to see what exists
-    match db.get_all_patients().await {
-        Ok(all_patients) => {
-            eprintln!("Total patients in database: {}", all_patients.len());
-            for p in &all_patients {
-                eprintln!("  - {} (ID: {})", p.name, p.id);
+    let mut query = query.into_inner();
+
+    // Fetch patient data
+    let Some(patient) = db.get_patient_by_id(patient_id).await? else {
+        return Err(ErrorCode::PatientNotFound.into());
+    };
+
+    // Without an explicit language the patient's own is used
+    if query.lang.is_none() {
+        query.lang = patient.preferred_language.clone();
+    }
+    let localizer = query.localizer();
+
+    // Fetch treatments and related records for the patient
+    let record = query.select(PatientRecord::load(&db, patient).await?)?;
+
+    let format = query.format.unwrap_or_default();
+    let markup = match &query.template {
+        Some(name) => {
+            let template = db
+                .get_export_template_by_name(name, format)
+                .await?
+                .ok_or_else(|| ApiError::new(ErrorCode::ExportTemplateNotFound)
+                    .with("format", format.as_str())
+                    .with("name", name))?;
+            match Template::parse(&template.body) {
+                Ok(parsed) => Some(parsed.render(&template_context(&record, &localizer))),
+                Err(e) => {
+                    eprintln!("Stored export template '{name}' is invalid: {e}");
+                    return Err(ErrorCode::ExportTemplateInvalid.into());
+                }
             }
         }
-        Err(e) => eprintln!("Error fetching all patients: {e}"),
-    }
+        None => None,
+    };
 
-    // Fetch patient data
-    let patient = match db.get_patient_by_id(patient_id).await {
-        Ok(Some(patient)) => {
-            eprintln!("Found patient: {}", patient.name);
-            patient
-        },
-        Ok(None) => {
-            eprintln!("Patient not found in database for ID: {patient_id}");
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Patient not found"
-            })));
+    let (content, content_type, extension) = render_export(&record, &localizer, format, markup.as_deref())
+        .map_err(|e| export_failed(format, e))?;
+    let filename = format!("patient_{}_export.{extension}", sanitize_filename(&record.patient.name));
+
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .append_header(("Content-Disposition", format!("attachment; filename=\"{filename}\"")))
+        .body(content))
+}
+
+/// Export many patients as a ZIP with one document (or JSON file) per patient and a `manifest.csv`.
+///
+/// The archive is streamed: patients are loaded and rendered one at a time and
+/// each entry is sent as soon as it is written, so memory use does not grow
+/// with the size of the practice.
+pub async fn export_patients_bulk(
+    query: web::Query<BulkExportQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let query = query.into_inner();
+    let format = query.format.unwrap_or_default();
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return Err(ErrorCode::InvalidDateRange.into());
         }
-        Err(e) => {
-            eprintln!("Database error when fetching patient {patient_id}: {e}");
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to load patient"
-            })));
+    }
+
+    let template = match (&query.template, format.document_format()) {
+        (None, _) => None,
+        (Some(_), None) => {
+            return Err(ErrorCode::TemplateNotForJson.into());
         }
+        (Some(name), Some(document_format)) => match db.get_export_template_by_name(name, document_format).await? {
+            Some(template) => match Template::parse(&template.body) {
+                Ok(parsed) => Some(Arc::new(parsed)),
+                Err(e) => {
+                    eprintln!("Stored export template '{name}' is invalid: {e}");
+                    return Err(ErrorCode::ExportTemplateInvalid.into());
+                }
+            },
+            None => {
+                return Err(ApiError::new(ErrorCode::ExportTemplateNotFound)
+                    .with("format", document_format.as_str())
+                    .with("name", name)
+                    .into());
+            }
+        },
     };
 
-    // Fetch treatments for the patient
-    let treatments = match db.get_treatments_for_patient(patient_id).await {
-        Ok(treatments) => treatments,
-        Err(e) => {
-            eprintln!("Database error: {e}");
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to load treatments"
-            })));
+    let mut patients = db.get_all_patients(None).await?;
+    if let Some(active) = query.active {
+        patients.retain(|p| p.active == active);
+    }
+    if let Some(list) = &query.patient_ids {
+        let parsed = list
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(Uuid::parse_str)
+            .collect::<Result<HashSet<_>, _>>();
+        let Ok(ids) = parsed else {
+            return Err(ApiError::new(ErrorCode::InvalidIdList).with("field", "patient_ids").into());
+        };
+        if let Some(unknown) = ids.iter().find(|id| !patients.iter().any(|p| p.id == **id)) {
+            return Err(ApiError::new(ErrorCode::PatientNotFound).with("id", unknown).into());
         }
+        patients.retain(|p| ids.contains(&p.id));
+    }
+
+    // A couple of entries of buffer keeps rendering ahead of a slow client without piling up
+    let (tx, rx) = mpsc::channel::<io::Result<web::Bytes>>(2);
+    let selection = query.patient_query();
+    let job = BulkExportJob {
+        db: db.get_ref().clone(),
+        localizer: selection.localizer(),
+        selection,
+        format,
+        template,
     };
+    tokio::spawn(async move {
+        if let Err(e) = job.write_archive(patients, &tx).await {
+            eprintln!("Bulk export error: {e}");
+            let _ = tx.send(Err(io::Error::other(e.to_string()))).await;
+        }
+    });
+
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    let filename = format!("patients_export_{}.zip", Utc::now().format("%Y%m%d"));
 
-    // Generate RTF document
-    let rtf_content = generate_rtf_document(&patient, &treatments, language);
-    let filename = format!("patient_{}_export.rtf", sanitize_filename(&patient.name));
-    
     Ok(HttpResponse::Ok()
-        .content_type("application/rtf")
+        .content_type("application/zip")
         .append_header(("Content-Disposition", format!("attachment; filename=\"{filename}\"")))
-        .body(rtf_content))
-}
-
-fn get_field_names(language: &str) -> FieldNames {
-    match language {
-        "he" => FieldNames {
-            title: "תיק רפואי למטופל",
-            patient_info: "פרטי המטופל",
-            name: "שם",
-            email: "אימייל",
-            phone: "טלפון",
-            registration_date: "תאריך רישום",
-            status: "סטטוס",
-            active: "פעיל",
-            inactive: "לא פעיל",
-            description: "תיאור",
-            treatments_history: "היסטוריית טיפולים",
-            no_treatments: "לא נרשמו טיפולים",
-            total_treatments: "סה״כ טיפולים",
-            treatment: "טיפול",
-            exported_on: "המסמך יוצא בתאריך",
+        .streaming(stream))
+}
+
+struct BulkExportJob {
+    db: Database,
+    selection: ExportQuery,
+    format: BulkExportFormat,
+    localizer: Localizer,
+    template: Option<Arc<Template>>,
+}
+
+impl BulkExportJob {
+    async fn write_archive(&self, patients: Vec<Patient>, tx: &mpsc::Sender<io::Result<web::Bytes>>) -> anyhow::Result<()> {
+        let mut zip = ZipWriter::new(Vec::new());
+        let mut manifest = csv_line(&["patient_id", "name", "file", "treatments", "status"].map(String::from));
+
+        for patient in patients {
+            let (patient_id, name) = (patient.id.to_string(), patient.name.clone());
+            let row = match self.render_patient(patient).await {
+                Ok((file, content, treatments)) => {
+                    zip.add_file(&file, &content)?;
+                    [patient_id, name, file, treatments.to_string(), "ok".to_string()]
+                }
+                Err(e) => {
+                    eprintln!("Bulk export failed for patient {patient_id}: {e}");
+                    [patient_id, name, String::new(), String::new(), format!("error: {e}")]
+                }
+            };
+            manifest.push_str(&csv_line(&row));
+
+            let chunk = std::mem::take(zip.get_mut());
+            tx.send(Ok(chunk.into())).await.map_err(|_| anyhow!("client disconnected"))?;
+        }
+
+        zip.add_file("manifest.csv", manifest.as_bytes())?;
+        let rest = zip.finish()?;
+        tx.send(Ok(rest.into())).await.map_err(|_| anyhow!("client disconnected"))?;
+        Ok(())
+    }
+
+    /// Load one patient's record and render their file; returns the
+    /// archive path, the content and the number of treatments included
+    async fn render_patient(&self, patient: Patient) -> anyhow::Result<(String, Vec<u8>, usize)> {
+        let record = self
+            .selection
+            .select(PatientRecord::load(&self.db, patient).await?)
+            .map_err(|_| anyhow!("invalid export options"))?;
+
+        let format = self.format;
+        let localizer = self.localizer.clone();
+        let template = self.template.clone();
+
+        // Rendering (PDF especially) is CPU-bound, so keep it off the async workers
+        tokio::task::spawn_blocking(move || {
+            let (content, extension) = match format.document_format() {
+                Some(document_format) => {
+                    let markup = template.map(|t| t.render(&template_context(&record, &localizer)));
+                    let (content, _, extension) = render_export(&record, &localizer, document_format, markup.as_deref())?;
+                    (content, extension)
+                }
+                None => (serde_json::to_vec_pretty(&PatientExportData::from(&record))?, "json"),
+            };
+            let file = format!(
+                "patients/{}_{}.{extension}",
+                sanitize_filename(&record.patient.name),
+                &record.patient.id.simple().to_string()[..8]
+            );
+            Ok((file, content, record.treatments.len()))
+        })
+        .await?
+    }
+}
+
+/// Built-in layout as template source, to start a custom template from
+pub async fn get_default_export_template() -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(json!({ "body": DEFAULT_TEMPLATE })))
+}
+
+/// Render an unsaved template body against a patient, or sample data when none is given
+pub async fn preview_export_template(
+    data: ValidatedJson<ExportTemplatePreviewRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let request = data.into_inner();
+    let localizer = Localizer::new(request.lang.as_deref().unwrap_or("en"))
+        .with_calendar(request.calendar.unwrap_or_default());
+
+    let template = Template::parse(&request.body)
+        .map_err(|e| ApiError::new(ErrorCode::InvalidTemplate).with("reason", e))?;
+
+    let record = match request.patient_id {
+        Some(patient_id) => {
+            let patient = db
+                .get_patient_by_id(patient_id)
+                .await?
+                .ok_or(ErrorCode::PatientNotFound)?;
+            PatientRecord::load(&db, patient).await?
+        }
+        None => sample_patient(),
+    };
+
+    let markup = template.render(&template_context(&record, &localizer));
+    let Some(format) = request.format else {
+        return Ok(HttpResponse::Ok().json(json!({ "rendered": markup })));
+    };
+
+    let (content, content_type, extension) = render_export(&record, &localizer, format, Some(&markup))
+        .map_err(|e| export_failed(format, e))?;
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .append_header(("Content-Disposition", format!("inline; filename=\"template_preview.{extension}\"")))
+        .body(content))
+}
+
+/// Log a rendering failure and report it as `EXPORT_FAILED`
+fn export_failed(format: ExportFormat, e: anyhow::Error) -> ApiError {
+    eprintln!("Export error: {e}");
+    ApiError::new(ErrorCode::ExportFailed).with("format", format.as_str().to_uppercase())
+}
+
+/// Made-up record used to preview templates before any patient is picked
+fn sample_patient() -> PatientRecord {
+    let now = Utc::now();
+    let patient = Patient {
+        id: Uuid::nil(),
+        name: "Sample Patient".to_string(),
+        email: Some("patient@example.com".to_string()),
+        phone_number: "050-0000000".to_string(),
+        phone_e164: Some("+972500000000".to_string()),
+        description: "Lower back pain".to_string(),
+        date: now - Duration::days(90),
+        active: true,
+        date_of_birth: NaiveDate::from_ymd_opt(1985, 3, 14),
+        gender: Some(Gender::Female),
+        national_id: Some("000000018".to_string()),
+        address: Some("1 Herzl St, Tel Aviv".to_string()),
+        preferred_language: Some("he".to_string()),
+        referral_source: Some("Family doctor".to_string()),
+        merged_into: None,
+    };
+    let follow_up_chart = vec![BodyAnnotation {
+        region: "lumbar_spine".to_string(),
+        side: Some(BodySide::Left),
+        view: ChartView::Back,
+        x: 0.45,
+        y: 0.55,
+        intensity: Some(4),
+        note: "Less pain on bending".to_string(),
+    }];
+    let treatments = [
+        (14, "Follow-up session.\nMobility improved.", follow_up_chart),
+        (45, "Initial assessment.", Vec::new()),
+    ]
+    .into_iter()
+    .map(|(days_ago, summary, annotations)| Treatment {
+        id: Uuid::nil(),
+        patient_id: patient.id,
+        summary: summary.to_string(),
+        date: now - Duration::days(days_ago),
+        treatment_type_id: None,
+        duration_minutes: Some(45),
+        package_id: None,
+        plan_id: None,
+        therapist_id: None,
+        annotations,
+    })
+    .collect();
+
+    let contacts = vec![PatientContact {
+        id: Uuid::nil(),
+        patient_id: patient.id,
+        name: "Sample Contact".to_string(),
+        relationship: ContactRelationship::Spouse,
+        phone_number: "050-0000001".to_string(),
+        phone_e164: Some("+972500000001".to_string()),
+        email: None,
+        notes: String::new(),
+        may_receive_information: true,
+        legal_guardian: false,
+        created_at: now,
+        updated_at: now,
+    }];
+
+    let medical_background = MedicalBackground {
+        diagnoses: vec![Diagnosis {
+            id: Uuid::nil(),
+            patient_id: patient.id,
+            icd10_code: Some("M54.5".to_string()),
+            description: "Low back pain".to_string(),
+            start_date: Some((now - Duration::days(120)).date_naive()),
+            end_date: None,
+            notes: String::new(),
+            created_at: now,
+            updated_at: now,
+        }],
+        allergies: vec![Allergy {
+            id: Uuid::nil(),
+            patient_id: patient.id,
+            allergen: "Latex".to_string(),
+            reaction: "Rash".to_string(),
+            severity: AllergySeverity::Moderate,
+            start_date: None,
+            end_date: None,
+            notes: String::new(),
+            created_at: now,
+            updated_at: now,
+        }],
+        medications: vec![Medication {
+            id: Uuid::nil(),
+            patient_id: patient.id,
+            name: "Ibuprofen".to_string(),
+            dosage: "400 mg".to_string(),
+            frequency: "As needed".to_string(),
+            start_date: Some((now - Duration::days(100)).date_naive()),
+            end_date: None,
+            notes: String::new(),
+            created_at: now,
+            updated_at: now,
+        }],
+    };
+
+    let plan_id = Uuid::nil();
+    let treatment_plans = vec![TreatmentPlan {
+        id: plan_id,
+        patient_id: patient.id,
+        title: "Lower back rehabilitation".to_string(),
+        description: String::new(),
+        planned_sessions: Some(8),
+        frequency: "Once a week".to_string(),
+        start_date: Some((now - Duration::days(45)).date_naive()),
+        target_end_date: Some((now + Duration::days(15)).date_naive()),
+        status: PlanStatus::Active,
+        sessions_done: 2,
+        goals: vec![
+            PlanGoal {
+                id: Uuid::nil(),
+                plan_id,
+                description: "Sit for an hour without pain".to_string(),
+                target_date: None,
+                status: GoalStatus::Achieved,
+                progress: 100,
+                achieved_date: Some((now - Duration::days(14)).date_naive()),
+                notes: String::new(),
+                created_at: now,
+                updated_at: now,
+            },
+            PlanGoal {
+                id: Uuid::nil(),
+                plan_id,
+                description: "Walk 5 km".to_string(),
+                target_date: Some((now + Duration::days(15)).date_naive()),
+                status: GoalStatus::InProgress,
+                progress: 60,
+                achieved_date: None,
+                notes: String::new(),
+                created_at: now,
+                updated_at: now,
+            },
+        ],
+        created_at: now,
+        updated_at: now,
+    }];
+
+    PatientRecord { contacts, medical_background, treatment_plans, ..PatientRecord::new(patient, treatments) }
+}
+
+/// Values a template can refer to; treatments keep their session number in the full history
+fn template_context(record: &PatientRecord, localizer: &Localizer) -> serde_json::Value {
+    let field_names = get_field_names(localizer);
+    let clinic = ClinicInfo::from_env();
+    let patient = &record.patient;
+    let contact = |value: &str| if record.omit_contact { String::new() } else { value.to_string() };
+
+    json!({
+        "lang": localizer.code(),
+        "direction": localizer.direction(),
+        "today": localizer.format_date(&Utc::now()),
+        "omit_contact": record.omit_contact,
+        "period": record.period.as_deref().unwrap_or(""),
+        "labels": field_names,
+        "clinic": {
+            "name": clinic.name_for(localizer.code()),
+            "address": clinic.address_for(localizer.code()),
+            "phone": clinic.phone,
+            "email": clinic.email,
+            "tax_id": clinic.tax_id,
         },
-        _ => FieldNames {
-            title: "Patient Medical Record",
-            patient_info: "Patient Information",
-            name: "Name",
-            email: "Email",
-            phone: "Phone Number",
-            registration_date: "Registration Date",
-            status: "Status",
-            active: "Active",
-            inactive: "Inactive",
-            description: "Description",
-            treatments_history: "Treatment History",
-            no_treatments: "No treatments recorded",
-            total_treatments: "Total treatments",
-            treatment: "Treatment",
-            exported_on: "Document exported on",
+        "patient": {
+            "id": patient.id,
+            "name": patient.name,
+            "email": contact(patient.email.as_deref().unwrap_or("")),
+            "phone_number": contact(&patient.phone_number),
+            "address": contact(patient.address.as_deref().unwrap_or("")),
+            "date_of_birth": patient.date_of_birth.map(|d| localizer.format_date(&calendar_day(d))).unwrap_or_default(),
+            "date_of_birth_short": patient.date_of_birth.map(|d| localizer.format_short_date(&calendar_day(d))).unwrap_or_default(),
+            "gender": patient.gender.map(|g| gender_label(g, &field_names)).unwrap_or_default(),
+            "national_id": patient.national_id.as_deref().unwrap_or(""),
+            "preferred_language": patient.preferred_language.as_deref().unwrap_or(""),
+            "preferred_language_name": patient.preferred_language.as_deref().map(language_name).unwrap_or_default(),
+            "referral_source": patient.referral_source.as_deref().unwrap_or(""),
+            "description": patient.description,
+            "active": patient.active,
+            "status": if patient.active { field_names.active } else { field_names.inactive },
+            "registration_date": localizer.format_date(&patient.date),
+            "registration_date_short": localizer.format_short_date(&patient.date),
+            "registration_date_hebrew": localizer.format_hebrew_date(&patient.date),
         },
+        "contacts": record.contacts.iter().map(|c| json!({
+            "name": c.name,
+            "relationship": relationship_label(c.relationship, &field_names),
+            "phone_number": contact(&c.phone_number),
+            "email": contact(c.email.as_deref().unwrap_or("")),
+            "legal_guardian": c.legal_guardian,
+            "may_receive_information": c.may_receive_information,
+            "details": contact_details(c, record.omit_contact, &field_names),
+        })).collect::<Vec<_>>(),
+        "has_medical_background": !record.medical_background.is_empty(),
+        "medical_background": {
+            "diagnoses": record.medical_background.diagnoses.iter().map(|d| json!({
+                "icd10_code": d.icd10_code.as_deref().unwrap_or(""),
+                "description": d.description,
+                "period": period_label(d.start_date, d.end_date, localizer),
+                "details": diagnosis_details(d, localizer),
+            })).collect::<Vec<_>>(),
+            "allergies": record.medical_background.allergies.iter().map(|a| json!({
+                "allergen": a.allergen,
+                "reaction": a.reaction,
+                "severity": severity_label(a.severity, &field_names),
+                "period": period_label(a.start_date, a.end_date, localizer),
+                "details": allergy_details(a, localizer, &field_names),
+            })).collect::<Vec<_>>(),
+            "medications": record.medical_background.medications.iter().map(|m| json!({
+                "name": m.name,
+                "dosage": m.dosage,
+                "frequency": m.frequency,
+                "period": period_label(m.start_date, m.end_date, localizer),
+                "details": medication_details(m, localizer),
+            })).collect::<Vec<_>>(),
+        },
+        "treatment_plans": record.treatment_plans.iter().map(|p| json!({
+            "title": p.title,
+            "description": p.description,
+            "status": plan_status_label(p.status, &field_names),
+            "heading": plan_heading(p, localizer, &field_names),
+            "frequency": p.frequency,
+            "sessions_done": p.sessions_done,
+            "planned_sessions": p.planned_sessions,
+            "sessions": plan_sessions(p),
+            "goals_achieved": p.goals_achieved(),
+            "goals_total": p.goals.len(),
+            "goals": p.goals.iter().map(|g| json!({
+                "description": g.description,
+                "status": goal_status_label(g.status, &field_names),
+                "progress": g.progress,
+                "details": goal_details(g, localizer, &field_names),
+            })).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+        "treatment_count": record.treatments.len(),
+        "treatments": record.treatments.iter().map(|(number, treatment)| json!({
+            "number": number,
+            "date": localizer.format_date(&treatment.date),
+            "date_short": localizer.format_short_date(&treatment.date),
+            "date_hebrew": localizer.format_hebrew_date(&treatment.date),
+            "summary": treatment.summary,
+            "duration_minutes": treatment.duration_minutes,
+            "annotations": treatment.annotations.iter().map(|a| json!({
+                "region": a.region,
+                "region_name": region_name(&a.region, localizer),
+                "side": a.side.map(|side| side_label(side, &field_names)),
+                "view": view_label(a.view, &field_names),
+                "x": a.x,
+                "y": a.y,
+                "intensity": a.intensity,
+                "note": a.note,
+                "details": annotation_details(a, localizer, &field_names),
+            })).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// Render the export in `format`, from template markup when given, otherwise with the built-in layout.
+/// Returns the document bytes, content type and file extension.
+fn render_export(
+    record: &PatientRecord,
+    localizer: &Localizer,
+    format: ExportFormat,
+    markup: Option<&str>,
+) -> anyhow::Result<(Vec<u8>, &'static str, &'static str)> {
+    Ok(match format {
+        ExportFormat::Rtf => (
+            generate_rtf_document(record, localizer, markup).into_bytes(),
+            "application/rtf",
+            "rtf",
+        ),
+        ExportFormat::Pdf => (
+            generate_pdf_document(record, localizer, markup)?,
+            "application/pdf",
+            "pdf",
+        ),
+        ExportFormat::Docx => (
+            generate_docx_document(record, localizer, markup)?,
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            "docx",
+        ),
+    })
+}
+
+fn get_field_names(l: &Localizer) -> FieldNames<'_> {
+    FieldNames {
+        title: l.text("export.title"),
+        patient_info: l.text("export.patient_info"),
+        name: l.text("export.name"),
+        email: l.text("export.email"),
+        phone: l.text("export.phone"),
+        date_of_birth: l.text("export.date_of_birth"),
+        gender: l.text("export.gender"),
+        gender_female: l.text("export.gender_female"),
+        gender_male: l.text("export.gender_male"),
+        gender_other: l.text("export.gender_other"),
+        national_id: l.text("export.national_id"),
+        address: l.text("export.address"),
+        preferred_language: l.text("export.preferred_language"),
+        referral_source: l.text("export.referral_source"),
+        contacts: l.text("export.contacts"),
+        relationship_parent: l.text("export.relationship_parent"),
+        relationship_guardian: l.text("export.relationship_guardian"),
+        relationship_spouse: l.text("export.relationship_spouse"),
+        relationship_emergency: l.text("export.relationship_emergency"),
+        legal_guardian: l.text("export.legal_guardian"),
+        may_receive_information: l.text("export.may_receive_information"),
+        medical_background: l.text("export.medical_background"),
+        diagnosis: l.text("export.diagnosis"),
+        allergy: l.text("export.allergy"),
+        medication: l.text("export.medication"),
+        severity_mild: l.text("export.severity_mild"),
+        severity_moderate: l.text("export.severity_moderate"),
+        severity_severe: l.text("export.severity_severe"),
+        severity_life_threatening: l.text("export.severity_life_threatening"),
+        treatment_plans: l.text("export.treatment_plans"),
+        plan_sessions: l.text("export.plan_sessions"),
+        plan_frequency: l.text("export.plan_frequency"),
+        goals_achieved: l.text("export.goals_achieved"),
+        goal: l.text("export.goal"),
+        target: l.text("export.target"),
+        plan_status_active: l.text("export.plan_status_active"),
+        plan_status_on_hold: l.text("export.plan_status_on_hold"),
+        plan_status_completed: l.text("export.plan_status_completed"),
+        plan_status_discontinued: l.text("export.plan_status_discontinued"),
+        goal_status_not_started: l.text("export.goal_status_not_started"),
+        goal_status_in_progress: l.text("export.goal_status_in_progress"),
+        goal_status_achieved: l.text("export.goal_status_achieved"),
+        goal_status_discontinued: l.text("export.goal_status_discontinued"),
+        body_chart: l.text("export.body_chart"),
+        side_left: l.text("export.side_left"),
+        side_right: l.text("export.side_right"),
+        side_bilateral: l.text("export.side_bilateral"),
+        view_front: l.text("export.view_front"),
+        view_back: l.text("export.view_back"),
+        intensity: l.text("export.intensity"),
+        registration_date: l.text("export.registration_date"),
+        status: l.text("export.status"),
+        active: l.text("export.active"),
+        inactive: l.text("export.inactive"),
+        description: l.text("export.description"),
+        treatments_history: l.text("export.treatments_history"),
+        no_treatments: l.text("export.no_treatments"),
+        total_treatments: l.text("export.total_treatments"),
+        treatment: l.text("export.treatment"),
+        date: l.text("export.date"),
+        duration: l.text("export.duration"),
+        summary: l.text("export.summary"),
+        period: l.text("export.period"),
+        exported_on: l.text("export.exported_on"),
+        page_of: l.text("export.page_of"),
     }
 }
 
-fn generate_rtf_document(patient: &Patient, treatments: &[Treatment], language: &str) -> String {
-    let mut rtf = String::new();
-    
-    // RTF header with enhanced Hebrew support and RTL when needed
-    rtf.push_str("{\\rtf1\\ansi\\ansicpg1255\\deff0\\deflang1033\\deflangfe1037 ");
-    rtf.push_str("{\\fonttbl{\\f0\\fswiss\\fcharset177\\*\\fname Arial;}{\\f1\\froman\\fcharset0 Times New Roman;}}");
-    rtf.push_str("{\\colortbl;\\red0\\green0\\blue0;}");
-    
-    // Set document direction based on language
-    if language == "he" {
-        // Hebrew: Right-to-left document
-        rtf.push_str("\\uc1\\pard\\rtlpar\\qr\\cf1\\lang1037\\f0\\fs22 ");
-    } else {
-        // English/Default: Left-to-right document  
-        rtf.push_str("\\uc1\\pard\\ltrpar\\cf1\\lang1037\\f0\\fs22 ");
-    }
-    
-    // Get localized field names
-    let field_names = get_field_names(language);
-    
-    // Title with appropriate alignment
-    if language == "he" {
-        rtf.push_str(&format!("\\rtlpar\\qr\\f1\\fs28\\b {} \\b0\\fs24\\par\\par", escape_rtf_hebrew(field_names.title)));
-    } else {
-        rtf.push_str(&format!("\\ltrpar\\ql\\f1\\fs28\\b {} \\b0\\fs24\\par\\par", escape_rtf_hebrew(field_names.title)));
+fn generate_rtf_document(record: &PatientRecord, localizer: &Localizer, markup: Option<&str>) -> String {
+    let mut doc = RtfDocument::new(localizer.code());
+    match markup {
+        Some(markup) => write_markup(&mut doc, markup),
+        None => write_patient_record(&mut doc, record, localizer, &get_field_names(localizer)),
     }
-    
-    // Patient Information Section with appropriate alignment
-    if language == "he" {
-        rtf.push_str(&format!("\\rtlpar\\qr\\fs20\\b {} \\b0\\fs18\\par", escape_rtf_hebrew(field_names.patient_info)));
-    } else {
-        rtf.push_str(&format!("\\ltrpar\\ql\\fs20\\b {} \\b0\\fs18\\par", escape_rtf_hebrew(field_names.patient_info)));
-    }
-    
-    // Patient information fields with appropriate alignment
-    let field_format = if language == "he" { "\\rtlpar\\qr" } else { "\\ltrpar\\ql" };
-    
-    rtf.push_str(&format!("{}\\b {}: \\b0 {}\\par", field_format, escape_rtf_hebrew(field_names.name), escape_rtf_hebrew(&patient.name)));
-    rtf.push_str(&format!("{}\\b {}: \\b0 {}\\par", field_format, escape_rtf_hebrew(field_names.email), escape_rtf_hebrew(patient.email.as_deref().unwrap_or(""))));
-    rtf.push_str(&format!("{}\\b {}: \\b0 {}\\par", field_format, escape_rtf_hebrew(field_names.phone), escape_rtf_hebrew(&patient.phone_number)));
-    rtf.push_str(&format!("{}\\b {}: \\b0 {}\\par", field_format, escape_rtf_hebrew(field_names.registration_date), format_date(&patient.date)));
-    rtf.push_str(&format!("{}\\b {}: \\b0 {}\\par", field_format, escape_rtf_hebrew(field_names.status), escape_rtf_hebrew(if patient.active { field_names.active } else { field_names.inactive })));
-    
-    if !patient.description.is_empty() {
-        rtf.push_str(&format!("{}\\b {}: \\b0 {}\\par", field_format, escape_rtf_hebrew(field_names.description), escape_rtf_hebrew(&patient.description)));
-    }
-    
-    rtf.push_str("\\par");
-    
-    // Treatments Section with appropriate alignment
-    if language == "he" {
-        rtf.push_str(&format!("\\rtlpar\\qr\\fs20\\b {} \\b0\\fs18\\par", escape_rtf_hebrew(field_names.treatments_history)));
+    doc.finish()
+}
+
+fn generate_pdf_document(record: &PatientRecord, localizer: &Localizer, markup: Option<&str>) -> anyhow::Result<Vec<u8>> {
+    let field_names = get_field_names(localizer);
+    let clinic = ClinicInfo::from_env();
+    let patient = &record.patient;
+
+    let mut doc = PdfDocument::new(localizer.code(), PdfFonts::from_env()?);
+    doc.set_metadata(&format!("{} - {}", field_names.title, patient.name), clinic.name_for(localizer.code()));
+    doc.set_running_text(&format!("{} | {}", patient.name, field_names.title), field_names.page_of);
+    match markup {
+        Some(markup) => write_markup(&mut doc, markup),
+        None => write_patient_record(&mut doc, record, localizer, &field_names),
+    }
+    doc.finish()
+}
+
+fn generate_docx_document(record: &PatientRecord, localizer: &Localizer, markup: Option<&str>) -> anyhow::Result<Vec<u8>> {
+    let field_names = get_field_names(localizer);
+    let clinic = ClinicInfo::from_env();
+    let patient = &record.patient;
+
+    let mut doc = DocxDocument::new(localizer.code());
+    doc.set_metadata(&format!("{} - {}", field_names.title, patient.name), clinic.name_for(localizer.code()));
+    if let Some(markup) = markup {
+        write_markup(&mut doc, markup);
+        return doc.finish();
+    }
+
+    write_patient_details(&mut doc, record, localizer, &field_names);
+
+    // Treatments Section, as a table rather than one block per session
+    write_treatments_heading(&mut doc, record, &field_names);
+
+    if record.treatments.is_empty() {
+        doc.italic(&format!("{}.", field_names.no_treatments));
     } else {
-        rtf.push_str(&format!("\\ltrpar\\ql\\fs20\\b {} \\b0\\fs18\\par", escape_rtf_hebrew(field_names.treatments_history)));
+        doc.paragraph(&format!("{}: {}", field_names.total_treatments, record.treatments.len()));
+
+        let rows = record
+            .treatments
+            .iter()
+            .map(|(number, treatment)| {
+                vec![
+                    number.to_string(),
+                    localizer.format_date(&treatment.date),
+                    treatment.duration_minutes.map(|m| m.to_string()).unwrap_or_default(),
+                    treatment.summary.clone(),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let widths = [600, 2100, 1300];
+        let summary_width = docx::TEXT_WIDTH - widths.iter().sum::<u32>();
+        doc.table(
+            &["#", field_names.date, field_names.duration, field_names.summary],
+            &rows,
+            &[widths[0], widths[1], widths[2], summary_width],
+        );
+        doc.blank_line();
+
+        // Annotations don't fit the table; list them after it by treatment number
+        let charted: Vec<_> = record.treatments.iter().filter(|(_, t)| !t.annotations.is_empty()).collect();
+        if !charted.is_empty() {
+            doc.heading(field_names.body_chart);
+            for (number, treatment) in charted {
+                for annotation in &treatment.annotations {
+                    doc.field(&format!("#{number}"), &annotation_details(annotation, localizer, &field_names));
+                }
+            }
+            doc.blank_line();
+        }
     }
-    
-    if treatments.is_empty() {
-        rtf.push_str(&format!("{}\\i {}.\\i0\\par", field_format, escape_rtf_hebrew(field_names.no_treatments)));
+
+    write_export_footer(&mut doc, localizer, &field_names);
+    doc.finish()
+}
+
+/// Patient record layout shared by the RTF and PDF exports
+fn write_patient_record<W: DocumentWriter>(doc: &mut W, record: &PatientRecord, localizer: &Localizer, field_names: &FieldNames) {
+    write_patient_details(doc, record, localizer, field_names);
+
+    // Treatments Section
+    write_treatments_heading(doc, record, field_names);
+
+    if record.treatments.is_empty() {
+        doc.italic(&format!("{}.", field_names.no_treatments));
     } else {
-        rtf.push_str(&format!("{}{}: {}\\par\\par", field_format, escape_rtf_hebrew(field_names.total_treatments), treatments.len()));
-        
-        for (index, treatment) in treatments.iter().enumerate() {
-            // Treatment number and date with appropriate alignment
-            rtf.push_str(&format!(
-                "{}\\b {} #{} - {}\\b0\\par",
-                field_format,
-                escape_rtf_hebrew(field_names.treatment),
-                treatments.len() - index,
-                format_date(&treatment.date)
+        doc.paragraph(&format!("{}: {}", field_names.total_treatments, record.treatments.len()));
+        doc.blank_line();
+
+        for (number, treatment) in &record.treatments {
+            // Treatment number and date
+            doc.bold(&format!(
+                "{} #{} - {}",
+                field_names.treatment,
+                number,
+                localizer.format_date(&treatment.date)
             ));
-            
-            // Treatment summary with appropriate alignment
-            rtf.push_str(&format!("{}{}\\par\\par", field_format, escape_rtf_hebrew(&treatment.summary)));
+
+            // Treatment summary
+            doc.paragraph(&treatment.summary);
+            for annotation in &treatment.annotations {
+                doc.field(field_names.body_chart, &annotation_details(annotation, localizer, field_names));
+            }
+            doc.blank_line();
         }
     }
-    
-    // Footer with appropriate alignment
-    rtf.push_str("\\par---\\par");
-    rtf.push_str(&format!(
-        "{}\\fs16\\i {}: {}\\i0\\fs18\\par",
-        field_format,
-        escape_rtf_hebrew(field_names.exported_on),
-        format_date(&Utc::now())
-    ));
-    
-    // RTF footer
-    rtf.push('}');
-    
-    rtf
-}
-
-fn escape_rtf_hebrew(text: &str) -> String {
-    let mut result = String::new();
-    
-    for ch in text.chars() {
-        match ch {
-            '\\' => result.push_str("\\\\"),
-            '{' => result.push_str("\\{"),
-            '}' => result.push_str("\\}"),
-            '\n' => result.push_str("\\par "),
-            '\r' => {}, // Skip carriage returns
-            // Handle Hebrew characters (Unicode range 0x0590-0x05FF) with signed conversion
-            c if (c as u32) >= 0x0590 && (c as u32) <= 0x05FF => {
-                let unicode_val = c as u32;
-                if unicode_val > 32767 {
-                    // For values > 32767, use negative representation
-                    let signed_val = unicode_val as i32 - 65536;
-                    result.push_str(&format!("\\u{signed_val}?"));
-                } else {
-                    result.push_str(&format!("\\u{unicode_val}?"));
-                }
-            },
-            // Handle other Unicode characters
-            c if (c as u32) > 255 => {
-                let unicode_val = c as u32;
-                if unicode_val > 32767 {
-                    let signed_val = unicode_val as i32 - 65536;
-                    result.push_str(&format!("\\u{signed_val}?"));
-                } else {
-                    result.push_str(&format!("\\u{unicode_val}?"));
+
+    write_export_footer(doc, localizer, field_names);
+}
+
+fn write_patient_details<W: DocumentWriter>(doc: &mut W, record: &PatientRecord, localizer: &Localizer, field_names: &FieldNames) {
+    let patient = &record.patient;
+    doc.title(field_names.title);
+
+    // Patient Information Section
+    doc.heading(field_names.patient_info);
+    doc.field(field_names.name, &patient.name);
+    if !record.omit_contact {
+        doc.field(field_names.email, patient.email.as_deref().unwrap_or(""));
+        doc.field(field_names.phone, &patient.phone_number);
+        if let Some(address) = &patient.address {
+            doc.field(field_names.address, address);
+        }
+    }
+    if let Some(date_of_birth) = patient.date_of_birth {
+        doc.field(field_names.date_of_birth, &localizer.format_date(&calendar_day(date_of_birth)));
+    }
+    if let Some(gender) = patient.gender {
+        doc.field(field_names.gender, gender_label(gender, field_names));
+    }
+    if let Some(national_id) = &patient.national_id {
+        doc.field(field_names.national_id, national_id);
+    }
+    if let Some(preferred_language) = &patient.preferred_language {
+        doc.field(field_names.preferred_language, &language_name(preferred_language));
+    }
+    if let Some(referral_source) = &patient.referral_source {
+        doc.field(field_names.referral_source, referral_source);
+    }
+    doc.field(field_names.registration_date, &localizer.format_date(&patient.date));
+    doc.field(field_names.status, if patient.active { field_names.active } else { field_names.inactive });
+
+    if !patient.description.is_empty() {
+        doc.field(field_names.description, &patient.description);
+    }
+
+    doc.blank_line();
+
+    // Allergies first: they are what must not be missed before a session
+    let medical = &record.medical_background;
+    if !medical.is_empty() {
+        doc.heading(field_names.medical_background);
+        for allergy in &medical.allergies {
+            doc.field(field_names.allergy, &allergy_details(allergy, localizer, field_names));
+        }
+        for diagnosis in &medical.diagnoses {
+            doc.field(field_names.diagnosis, &diagnosis_details(diagnosis, localizer));
+        }
+        for medication in &medical.medications {
+            doc.field(field_names.medication, &medication_details(medication, localizer));
+        }
+        doc.blank_line();
+    }
+
+    if !record.contacts.is_empty() {
+        doc.heading(field_names.contacts);
+        for contact in &record.contacts {
+            doc.field(
+                relationship_label(contact.relationship, field_names),
+                &contact_details(contact, record.omit_contact, field_names),
+            );
+        }
+        doc.blank_line();
+    }
+
+    if !record.treatment_plans.is_empty() {
+        doc.heading(field_names.treatment_plans);
+        for plan in &record.treatment_plans {
+            doc.bold(&plan_heading(plan, localizer, field_names));
+            if !plan.description.is_empty() {
+                doc.paragraph(&plan.description);
+            }
+            doc.field(field_names.plan_sessions, &plan_sessions(plan));
+            if !plan.frequency.is_empty() {
+                doc.field(field_names.plan_frequency, &plan.frequency);
+            }
+            if !plan.goals.is_empty() {
+                doc.field(field_names.goals_achieved, &format!("{} / {}", plan.goals_achieved(), plan.goals.len()));
+                for goal in &plan.goals {
+                    doc.field(field_names.goal, &goal_details(goal, localizer, field_names));
                 }
-            },
-            // Regular ASCII characters
-            c => result.push(c),
+            }
+            doc.blank_line();
         }
     }
-    
-    result
 }
 
-fn format_date(date: &DateTime<Utc>) -> String {
-    date.format("%B %d, %Y").to_string()
+fn relationship_label<'a>(relationship: ContactRelationship, field_names: &FieldNames<'a>) -> &'a str {
+    match relationship {
+        ContactRelationship::Parent => field_names.relationship_parent,
+        ContactRelationship::Guardian => field_names.relationship_guardian,
+        ContactRelationship::Spouse => field_names.relationship_spouse,
+        ContactRelationship::Emergency => field_names.relationship_emergency,
+    }
+}
+
+/// "Name, phone, email (legal guardian, may receive information)" on one line
+fn contact_details(contact: &PatientContact, omit_contact: bool, field_names: &FieldNames) -> String {
+    let mut parts = vec![contact.name.as_str()];
+    if !omit_contact {
+        parts.push(&contact.phone_number);
+        parts.extend(contact.email.as_deref());
+    }
+    let mut line = parts.into_iter().filter(|part| !part.is_empty()).collect::<Vec<_>>().join(", ");
+
+    let flags: Vec<&str> = [
+        (contact.legal_guardian, field_names.legal_guardian),
+        (contact.may_receive_information, field_names.may_receive_information),
+    ]
+    .into_iter()
+    .filter_map(|(set, label)| set.then_some(label))
+    .collect();
+    if !flags.is_empty() {
+        line.push_str(&format!(" ({})", flags.join(", ")));
+    }
+    line
+}
+
+fn severity_label<'a>(severity: AllergySeverity, field_names: &FieldNames<'a>) -> &'a str {
+    match severity {
+        AllergySeverity::Mild => field_names.severity_mild,
+        AllergySeverity::Moderate => field_names.severity_moderate,
+        AllergySeverity::Severe => field_names.severity_severe,
+        AllergySeverity::LifeThreatening => field_names.severity_life_threatening,
+    }
+}
+
+fn plan_status_label<'a>(status: PlanStatus, field_names: &FieldNames<'a>) -> &'a str {
+    match status {
+        PlanStatus::Active => field_names.plan_status_active,
+        PlanStatus::OnHold => field_names.plan_status_on_hold,
+        PlanStatus::Completed => field_names.plan_status_completed,
+        PlanStatus::Discontinued => field_names.plan_status_discontinued,
+    }
+}
+
+fn goal_status_label<'a>(status: GoalStatus, field_names: &FieldNames<'a>) -> &'a str {
+    match status {
+        GoalStatus::NotStarted => field_names.goal_status_not_started,
+        GoalStatus::InProgress => field_names.goal_status_in_progress,
+        GoalStatus::Achieved => field_names.goal_status_achieved,
+        GoalStatus::Discontinued => field_names.goal_status_discontinued,
+    }
+}
+
+/// "Lower back rehabilitation (active, 01/09/2026 – 01/12/2026)"
+fn plan_heading(plan: &TreatmentPlan, localizer: &Localizer, field_names: &FieldNames) -> String {
+    let mut details = plan_status_label(plan.status, field_names).to_string();
+    let period = period_label(plan.start_date, plan.target_end_date, localizer);
+    if !period.is_empty() {
+        details.push_str(&format!(", {period}"));
+    }
+    format!("{} ({details})", plan.title)
+}
+
+/// Sessions done against planned, "5 / 8"; just the count when none were planned
+fn plan_sessions(plan: &TreatmentPlan) -> String {
+    match plan.planned_sessions {
+        Some(planned) => format!("{} / {planned}", plan.sessions_done),
+        None => plan.sessions_done.to_string(),
+    }
+}
+
+/// "Walk 5 km - in progress, 60% (target 01/12/2026)"
+fn goal_details(goal: &PlanGoal, localizer: &Localizer, field_names: &FieldNames) -> String {
+    let day = |date: NaiveDate| localizer.format_short_date(&calendar_day(date));
+    let mut line = format!("{} - {}", goal.description, goal_status_label(goal.status, field_names));
+    if goal.status == GoalStatus::InProgress {
+        line.push_str(&format!(", {}%", goal.progress));
+    }
+    if let Some(achieved_date) = goal.achieved_date {
+        line.push_str(&format!(" ({})", day(achieved_date)));
+    } else if let Some(target_date) = goal.target_date.filter(|_| goal.status != GoalStatus::Discontinued) {
+        line.push_str(&format!(" ({} {})", field_names.target, day(target_date)));
+    }
+    line
+}
+
+fn side_label<'a>(side: BodySide, field_names: &FieldNames<'a>) -> &'a str {
+    match side {
+        BodySide::Left => field_names.side_left,
+        BodySide::Right => field_names.side_right,
+        BodySide::Bilateral => field_names.side_bilateral,
+    }
+}
+
+fn view_label<'a>(view: ChartView, field_names: &FieldNames<'a>) -> &'a str {
+    match view {
+        ChartView::Front => field_names.view_front,
+        ChartView::Back => field_names.view_back,
+    }
+}
+
+/// "Lumbar spine, left (back) - intensity 4/10 - Less pain on bending"
+fn annotation_details(annotation: &BodyAnnotation, localizer: &Localizer, field_names: &FieldNames) -> String {
+    let mut line = region_name(&annotation.region, localizer).to_string();
+    if let Some(side) = annotation.side {
+        line.push_str(&format!(", {}", side_label(side, field_names)));
+    }
+    line.push_str(&format!(" ({})", view_label(annotation.view, field_names)));
+    if let Some(intensity) = annotation.intensity {
+        line.push_str(&format!(" - {} {intensity}/{INTENSITY_MAX}", field_names.intensity));
+    }
+    if !annotation.note.is_empty() {
+        line.push_str(&format!(" - {}", annotation.note));
+    }
+    line
+}
+
+/// "1/3/2024 – 15/4/2024", open-ended when the entry is ongoing; empty without dates
+fn period_label(start_date: Option<NaiveDate>, end_date: Option<NaiveDate>, localizer: &Localizer) -> String {
+    if start_date.is_none() && end_date.is_none() {
+        return String::new();
+    }
+    let day = |date: Option<NaiveDate>| date.map(|d| localizer.format_short_date(&calendar_day(d))).unwrap_or_default();
+    format!("{} – {}", day(start_date), day(end_date)).trim().to_string()
+}
+
+/// Append the period in parentheses when there is one
+fn with_period(mut line: String, start_date: Option<NaiveDate>, end_date: Option<NaiveDate>, localizer: &Localizer) -> String {
+    let period = period_label(start_date, end_date, localizer);
+    if !period.is_empty() {
+        line.push_str(&format!(" ({period})"));
+    }
+    line
+}
+
+/// "Penicillin - severe, hives"
+fn allergy_details(allergy: &Allergy, localizer: &Localizer, field_names: &FieldNames) -> String {
+    let mut line = format!("{} - {}", allergy.allergen, severity_label(allergy.severity, field_names));
+    if !allergy.reaction.is_empty() {
+        line.push_str(&format!(", {}", allergy.reaction));
+    }
+    with_period(line, allergy.start_date, allergy.end_date, localizer)
+}
+
+/// "M54.5 Low back pain"
+fn diagnosis_details(diagnosis: &Diagnosis, localizer: &Localizer) -> String {
+    let line = match &diagnosis.icd10_code {
+        Some(code) => format!("{code} {}", diagnosis.description),
+        None => diagnosis.description.clone(),
+    };
+    with_period(line, diagnosis.start_date, diagnosis.end_date, localizer)
+}
+
+/// "Ibuprofen 400 mg, as needed"
+fn medication_details(medication: &Medication, localizer: &Localizer) -> String {
+    let mut line = [medication.name.as_str(), medication.dosage.as_str()]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !medication.frequency.is_empty() {
+        line.push_str(&format!(", {}", medication.frequency));
+    }
+    with_period(line, medication.start_date, medication.end_date, localizer)
+}
+
+/// Dates of birth and other calendar days as a point in time, which the localizer formats
+fn calendar_day(date: NaiveDate) -> DateTime<Utc> {
+    date.and_time(NaiveTime::MIN).and_utc()
+}
+
+fn gender_label<'a>(gender: Gender, field_names: &FieldNames<'a>) -> &'a str {
+    match gender {
+        Gender::Female => field_names.gender_female,
+        Gender::Male => field_names.gender_male,
+        Gender::Other => field_names.gender_other,
+    }
+}
+
+fn write_treatments_heading<W: DocumentWriter>(doc: &mut W, record: &PatientRecord, field_names: &FieldNames) {
+    doc.heading(field_names.treatments_history);
+    if let Some(period) = &record.period {
+        doc.field(field_names.period, period);
+    }
 }
 
-fn sanitize_filename(name: &str) -> String {
-    name.chars()
-        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' { c } else { '_' })
-        .collect::<String>()
-        .replace(' ', "_")
+fn write_export_footer<W: DocumentWriter>(doc: &mut W, localizer: &Localizer, field_names: &FieldNames) {
+    doc.separator();
+    doc.footer(&format!("{}: {}", field_names.exported_on, localizer.format_date(&Utc::now())));
 }
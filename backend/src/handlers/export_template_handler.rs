@@ -0,0 +1,87 @@
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::errors::{ApiError, AppError, ErrorCode};
+use crate::validation::ValidatedJson;
+use crate::database::Database;
+use crate::documents::template::Template;
+use crate::models::{ExportTemplate, CreateExportTemplateRequest, UpdateExportTemplateRequest};
+
+/// Check the name and template syntax, and that no other template uses the same name and format
+async fn validate_template(db: &Database, template: &ExportTemplate) -> Result<(), AppError> {
+    if template.name.is_empty() {
+        return Err(ErrorCode::NameRequired.into());
+    }
+    if let Err(e) = Template::parse(&template.body) {
+        return Err(ApiError::new(ErrorCode::InvalidTemplate).with("reason", e).into());
+    }
+
+    match db.get_export_template_by_name(&template.name, template.format).await? {
+        Some(existing) if existing.id != template.id => Err(ApiError::new(ErrorCode::ExportTemplateExists)
+            .with("format", template.format.as_str())
+            .with("name", &template.name)
+            .into()),
+        _ => Ok(()),
+    }
+}
+
+pub async fn create_export_template(
+    data: ValidatedJson<CreateExportTemplateRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let template = ExportTemplate::new(data.into_inner());
+    validate_template(&db, &template).await?;
+
+    db.create_export_template(&template).await?;
+    Ok(HttpResponse::Created().json(template))
+}
+
+pub async fn get_export_templates(db: web::Data<Database>) -> Result<HttpResponse, AppError> {
+    let templates = db.get_export_templates().await?;
+    Ok(HttpResponse::Ok().json(templates))
+}
+
+pub async fn get_export_template_by_id(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let template = db
+        .get_export_template_by_id(path.into_inner())
+        .await?
+        .ok_or(ErrorCode::ExportTemplateNotFound)?;
+    Ok(HttpResponse::Ok().json(template))
+}
+
+pub async fn update_export_template(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<UpdateExportTemplateRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let template_id = path.into_inner();
+
+    let mut template = db
+        .get_export_template_by_id(template_id)
+        .await?
+        .ok_or(ErrorCode::ExportTemplateNotFound)?;
+
+    template.update(data.into_inner());
+    validate_template(&db, &template).await?;
+
+    if !db.update_export_template(template_id, &template).await? {
+        return Err(ErrorCode::ExportTemplateNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(template))
+}
+
+pub async fn delete_export_template(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    if !db.delete_export_template(path.into_inner()).await? {
+        return Err(ErrorCode::ExportTemplateNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Export template deleted successfully"
+    })))
+}
@@ -1,16 +1,17 @@
-use actix_web::{web, HttpResponse, Result};
+use actix_web::HttpResponse;
 use serde_json::json;
+use crate::errors::{ApiError, AppError, ErrorCode};
+use crate::validation::ValidatedJson;
 use crate::models::{CreateIssueRequest, GitHubIssueRequest, GitHubIssueResponse, CreateIssueResponse};
 
 pub async fn create_issue(
-    issue_data: web::Json<CreateIssueRequest>,
-) -> Result<HttpResponse> {
+    issue_data: ValidatedJson<CreateIssueRequest>,
+) -> Result<HttpResponse, AppError> {
     // Get GitHub configuration from environment variables
-    let github_token = std::env::var("GITHUB_TOKEN")
-        .map_err(|_| {
-            log::error!("GITHUB_TOKEN environment variable not set");
-            actix_web::error::ErrorInternalServerError("GitHub integration not configured")
-        })?;
+    let Ok(github_token) = std::env::var("GITHUB_TOKEN") else {
+        log::error!("GITHUB_TOKEN environment variable not set");
+        return Err(ErrorCode::GithubNotConfigured.into());
+    };
     
     let github_repo = std::env::var("GITHUB_REPO")
         .unwrap_or_else(|_| "ButterflyEA/treatments_manager".to_string());
@@ -73,9 +74,7 @@ pub async fn create_issue(
                     }
                     Err(e) => {
                         log::error!("Failed to parse GitHub response: {e}");
-                        Ok(HttpResponse::InternalServerError().json(json!({
-                            "error": "Failed to parse GitHub response"
-                        })))
+                        Err(ErrorCode::GithubUnavailable.into())
                     }
                 }
             } else {
@@ -83,22 +82,18 @@ pub async fn create_issue(
                 let error_body = response.text().await.unwrap_or_default();
                 log::error!("GitHub API error {status}: {error_body}");
                 
-                Ok(HttpResponse::InternalServerError().json(json!({
-                    "error": format!("GitHub API error: {}", status)
-                })))
+                Err(ApiError::new(ErrorCode::GithubUnavailable).with("status", status).into())
             }
         }
         Err(e) => {
             log::error!("Failed to connect to GitHub API: {e}");
-            Ok(HttpResponse::InternalServerError().json(json!({
-                "error": "Failed to connect to GitHub API"
-            })))
+            Err(ErrorCode::GithubUnavailable.into())
         }
     }
 }
 
 // Health check endpoint to verify GitHub integration
-pub async fn github_health() -> Result<HttpResponse> {
+pub async fn github_health() -> Result<HttpResponse, AppError> {
     let github_token = std::env::var("GITHUB_TOKEN");
     let github_repo = std::env::var("GITHUB_REPO")
         .unwrap_or_else(|_| "ButterflyEA/treatments_manager".to_string());
@@ -122,14 +117,13 @@ pub async fn github_health() -> Result<HttpResponse> {
 }
 
 // Fetch open GitHub issues
-pub async fn get_open_issues() -> Result<HttpResponse> {
+pub async fn get_open_issues() -> Result<HttpResponse, AppError> {
     log::info!("🔍 GitHub Issues: Starting to fetch open issues");
     
-    let github_token = std::env::var("GITHUB_TOKEN")
-        .map_err(|_| {
-            log::error!("❌ GITHUB_TOKEN environment variable not set");
-            actix_web::error::ErrorInternalServerError("GitHub integration not configured")
-        })?;
+    let Ok(github_token) = std::env::var("GITHUB_TOKEN") else {
+        log::error!("❌ GITHUB_TOKEN environment variable not set");
+        return Err(ErrorCode::GithubNotConfigured.into());
+    };
     
     let github_repo = std::env::var("GITHUB_REPO")
         .unwrap_or_else(|_| "ButterflyEA/treatments_manager".to_string());
@@ -207,9 +201,7 @@ pub async fn get_open_issues() -> Result<HttpResponse> {
                     }
                     Err(e) => {
                         log::error!("❌ Failed to parse GitHub issues response: {e}");
-                        Ok(HttpResponse::InternalServerError().json(json!({
-                            "error": "Failed to parse GitHub response"
-                        })))
+                        Err(ErrorCode::GithubUnavailable.into())
                     }
                 }
             } else {
@@ -217,16 +209,12 @@ pub async fn get_open_issues() -> Result<HttpResponse> {
                 let error_body = response.text().await.unwrap_or_default();
                 log::error!("❌ GitHub API error {status}: {error_body}");
                 
-                Ok(HttpResponse::InternalServerError().json(json!({
-                    "error": format!("GitHub API error: {}", status)
-                })))
+                Err(ApiError::new(ErrorCode::GithubUnavailable).with("status", status).into())
             }
         }
         Err(e) => {
             log::error!("❌ Failed to connect to GitHub API: {e}");
-            Ok(HttpResponse::InternalServerError().json(json!({
-                "error": "Failed to connect to GitHub API"
-            })))
+            Err(ErrorCode::GithubUnavailable.into())
         }
     }
 }
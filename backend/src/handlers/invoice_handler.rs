@@ -0,0 +1,450 @@
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use serde_json::json;
+use std::env;
+use uuid::Uuid;
+
+use crate::errors::{ApiError, AppError, ErrorCode};
+use crate::validation::ValidatedJson;
+use crate::database::Database;
+use crate::i18n::{request_localizer, Localizer};
+use crate::models::{
+    Invoice, InvoiceDetail, InvoiceKind, InvoiceLine, InvoiceLineRequest, InvoiceStatus, IssueOutcome, Payment, PaymentOutcome,
+    PatientBalance, CreateInvoiceRequest, UpdateInvoiceRequest, CreateCreditNoteRequest,
+    CreatePaymentRequest, InvoiceQuery,
+};
+
+/// VAT rate applied to new invoices, configurable through `VAT_RATE` (e.g. 0.18 for 18%)
+pub fn default_vat_rate() -> f64 {
+    env::var("VAT_RATE")
+        .ok()
+        .and_then(|rate| rate.parse::<f64>().ok())
+        .unwrap_or(0.18)
+}
+
+pub async fn create_invoice(
+    data: ValidatedJson<CreateInvoiceRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let data = data.into_inner();
+
+    if db.get_patient_by_id(data.patient_id).await?.is_none() {
+        return Err(ErrorCode::PatientNotFound.into());
+    }
+
+    let mut invoice = Invoice::new_draft(
+        InvoiceKind::Invoice,
+        data.patient_id,
+        data.vat_rate.unwrap_or_else(default_vat_rate),
+        data.notes.unwrap_or_default(),
+        data.due_date,
+    );
+
+    let lines = build_lines(&db, &invoice, data.lines).await?;
+    invoice.apply_totals(&lines);
+
+    db.create_invoice(&invoice, &lines).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Invoice created successfully",
+        "invoice": InvoiceDetail::draft(invoice, lines)
+    })))
+}
+
+pub async fn get_invoices(
+    query: web::Query<InvoiceQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let invoices = db.get_invoices(query.patient_id, query.status, query.kind).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "invoices": invoices,
+        "count": invoices.len()
+    })))
+}
+
+pub async fn get_invoice_by_id(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let invoice_id = path.into_inner();
+
+    let invoice = db
+        .get_invoice_by_id(invoice_id)
+        .await?
+        .ok_or(ErrorCode::InvoiceNotFound)?;
+    let detail = invoice_detail(&db, invoice).await?;
+    Ok(HttpResponse::Ok().json(detail))
+}
+
+/// Edit a draft invoice; issued invoices can only be corrected with a credit note
+pub async fn update_invoice(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<UpdateInvoiceRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let invoice_id = path.into_inner();
+    let data = data.into_inner();
+
+    let mut invoice = load_draft(&db, invoice_id).await?;
+
+    if let Some(vat_rate) = data.vat_rate {
+        invoice.vat_rate = vat_rate;
+    }
+    if let Some(notes) = data.notes {
+        invoice.notes = notes;
+    }
+    if let Some(due_date) = data.due_date {
+        invoice.due_date = Some(due_date);
+    }
+
+    let lines = match data.lines {
+        Some(requests) => Some(build_lines(&db, &invoice, requests).await?),
+        None => None,
+    };
+
+    let totals_from = match &lines {
+        Some(lines) => lines.clone(),
+        None => db.get_invoice_lines(invoice_id).await?,
+    };
+    invoice.apply_totals(&totals_from);
+
+    if !db.update_draft_invoice(&invoice, lines.as_deref()).await? {
+        return Err(ErrorCode::InvoiceNotDraft.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Invoice updated successfully",
+        "invoice": InvoiceDetail::draft(invoice, totals_from)
+    })))
+}
+
+pub async fn delete_invoice(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let invoice_id = path.into_inner();
+
+    load_draft(&db, invoice_id).await?;
+
+    if !db.delete_draft_invoice(invoice_id).await? {
+        return Err(ErrorCode::InvoiceNotDraft.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Invoice deleted successfully"
+    })))
+}
+
+/// Issue a draft invoice or credit note, assigning the next number in its series
+pub async fn issue_invoice(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let invoice_id = path.into_inner();
+
+    let invoice = load_draft(&db, invoice_id).await?;
+
+    if invoice.total_cents <= 0 {
+        return Err(ErrorCode::InvoiceEmpty.into());
+    }
+
+    if let Some(original_id) = invoice.credited_invoice_id {
+        if db.get_invoice_by_id(original_id).await?.is_none() {
+            return Err(ErrorCode::InvoiceNotFound.into());
+        }
+    }
+
+    // A credit note may not credit more than is left on the original invoice
+    let number = match db.issue_invoice(&invoice).await? {
+        IssueOutcome::Issued(number) => number,
+        IssueOutcome::NotDraft => return Err(ErrorCode::InvoiceNotDraft.into()),
+        IssueOutcome::ExceedsCredit => return Err(ErrorCode::CreditNoteExceedsInvoice.into()),
+    };
+    let issued = db
+        .get_invoice_by_id(invoice_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Issued invoice {invoice_id} disappeared"))?;
+    let detail = invoice_detail(&db, issued).await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": format!("Invoice issued with number {number}"),
+        "invoice": detail
+    })))
+}
+
+/// Void a draft that will never be issued; issued invoices are corrected with credit notes instead
+pub async fn void_invoice(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let invoice_id = path.into_inner();
+
+    load_draft(&db, invoice_id).await?;
+
+    if !db.void_draft_invoice(invoice_id).await? {
+        return Err(ErrorCode::InvoiceNotDraft.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Invoice voided successfully"
+    })))
+}
+
+/// Create a draft credit note against an issued invoice
+pub async fn create_credit_note(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<CreateCreditNoteRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let invoice_id = path.into_inner();
+    let data = data.into_inner();
+
+    let original = db
+        .get_invoice_by_id(invoice_id)
+        .await?
+        .ok_or(ErrorCode::InvoiceNotFound)?;
+
+    if original.kind != InvoiceKind::Invoice
+        || !matches!(original.status, InvoiceStatus::Issued | InvoiceStatus::Paid)
+    {
+        return Err(ErrorCode::CreditNoteNotAllowed.into());
+    }
+
+    let mut credit_note = Invoice::new_draft(
+        InvoiceKind::CreditNote,
+        original.patient_id,
+        original.vat_rate,
+        data.notes.unwrap_or_default(),
+        None,
+    );
+    credit_note.credited_invoice_id = Some(original.id);
+
+    let lines = match data.lines {
+        Some(requests) => build_lines(&db, &credit_note, requests).await?,
+        None => db
+            .get_invoice_lines(original.id)
+            .await?
+            .into_iter()
+            .map(|line| InvoiceLine {
+                id: Uuid::new_v4(),
+                invoice_id: credit_note.id,
+                ..line
+            })
+            .collect(),
+    };
+    credit_note.apply_totals(&lines);
+
+    db.create_invoice(&credit_note, &lines).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Credit note created successfully",
+        "invoice": InvoiceDetail::draft(credit_note, lines)
+    })))
+}
+
+/// Record a full or partial payment against an issued invoice
+pub async fn create_payment(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<CreatePaymentRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let invoice_id = path.into_inner();
+    let data = data.into_inner();
+
+    let invoice = db
+        .get_invoice_by_id(invoice_id)
+        .await?
+        .ok_or(ErrorCode::InvoiceNotFound)?;
+
+    if invoice.kind != InvoiceKind::Invoice || invoice.status != InvoiceStatus::Issued {
+        return Err(ErrorCode::PaymentNotAllowed.into());
+    }
+
+    if data.amount_cents <= 0 {
+        let detail = invoice_detail(&db, invoice).await?;
+        return Err(ApiError::new(ErrorCode::PaymentAmountOutOfRange)
+            .with("max", detail.amount_due_cents)
+            .into());
+    }
+
+    let mut payment = Payment {
+        id: Uuid::new_v4(),
+        invoice_id,
+        patient_id: invoice.patient_id,
+        receipt_number: 0,
+        amount_cents: data.amount_cents,
+        method: data.method,
+        reference: data.reference.unwrap_or_default(),
+        paid_at: data.paid_at.unwrap_or_else(Utc::now),
+        created_at: Utc::now(),
+    };
+
+    // The status and amount due are checked again as the payment is recorded
+    payment.receipt_number = match db.create_payment(&payment).await? {
+        PaymentOutcome::Recorded(receipt_number) => receipt_number,
+        PaymentOutcome::NotAllowed => return Err(ErrorCode::PaymentNotAllowed.into()),
+        PaymentOutcome::ExceedsDue(due) => {
+            return Err(ApiError::new(ErrorCode::PaymentAmountOutOfRange).with("max", due).into());
+        }
+    };
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Payment recorded successfully",
+        "payment": payment
+    })))
+}
+
+/// Outstanding balance for a patient across all issued invoices, credit notes and payments
+pub async fn get_patient_balance(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    if db.get_patient_by_id(patient_id).await?.is_none() {
+        return Err(ErrorCode::PatientNotFound.into());
+    }
+
+    let balance = patient_balance(&db, patient_id).await?;
+    Ok(HttpResponse::Ok().json(balance))
+}
+
+async fn patient_balance(db: &Database, patient_id: Uuid) -> anyhow::Result<PatientBalance> {
+    let invoices = db.get_invoices(Some(patient_id), None, None).await?;
+
+    let mut balance = PatientBalance {
+        patient_id,
+        invoiced_cents: 0,
+        credited_cents: 0,
+        paid_cents: 0,
+        balance_cents: 0,
+        open_invoices: Vec::new(),
+    };
+
+    for invoice in invoices {
+        match (invoice.kind, invoice.status) {
+            (InvoiceKind::Invoice, InvoiceStatus::Issued | InvoiceStatus::Paid) => {
+                let detail = invoice_detail(db, invoice).await?;
+                balance.invoiced_cents += detail.invoice.total_cents;
+                balance.paid_cents += detail.paid_cents;
+                if detail.amount_due_cents > 0 {
+                    balance.open_invoices.push(detail);
+                }
+            }
+            (InvoiceKind::CreditNote, InvoiceStatus::Issued) => {
+                balance.credited_cents += invoice.total_cents;
+            }
+            _ => {}
+        }
+    }
+
+    balance.balance_cents = balance.invoiced_cents - balance.credited_cents - balance.paid_cents;
+    Ok(balance)
+}
+
+pub(crate) async fn invoice_detail(db: &Database, invoice: Invoice) -> anyhow::Result<InvoiceDetail> {
+    let lines = db.get_invoice_lines(invoice.id).await?;
+    let payments = db.get_payments_for_invoice(invoice.id).await?;
+    let paid_cents: i64 = payments.iter().map(|p| p.amount_cents).sum();
+
+    let (credited_cents, amount_due_cents) = match (invoice.kind, invoice.status) {
+        (InvoiceKind::Invoice, InvoiceStatus::Issued | InvoiceStatus::Paid) => {
+            let credited = db.get_credited_cents(invoice.id).await?;
+            (credited, (invoice.total_cents - credited - paid_cents).max(0))
+        }
+        _ => (0, 0),
+    };
+
+    Ok(InvoiceDetail {
+        invoice,
+        lines,
+        payments,
+        credited_cents,
+        paid_cents,
+        amount_due_cents,
+    })
+}
+
+/// Fetch an invoice and make sure it is still a draft
+async fn load_draft(db: &Database, invoice_id: Uuid) -> Result<Invoice, AppError> {
+    match db.get_invoice_by_id(invoice_id).await? {
+        Some(invoice) if invoice.status == InvoiceStatus::Draft => Ok(invoice),
+        Some(_) => Err(ErrorCode::InvoiceImmutable.into()),
+        None => Err(ErrorCode::InvoiceNotFound.into()),
+    }
+}
+
+/// Localizer for the patient's preferred language, or the request's if they have none
+async fn patient_localizer(db: &Database, patient_id: Uuid) -> Result<Localizer, AppError> {
+    let language = db.get_patient_by_id(patient_id).await?.and_then(|patient| patient.preferred_language);
+    Ok(language.map_or_else(request_localizer, |language| Localizer::new(&language)))
+}
+
+/// Turn requested lines into invoice lines, filling description and price from linked treatments.
+///
+/// Descriptions filled in are kept as written, so they are in the patient's
+/// preferred language, the one their documents are printed in by default.
+async fn build_lines(
+    db: &Database,
+    invoice: &Invoice,
+    requests: Vec<InvoiceLineRequest>,
+) -> Result<Vec<InvoiceLine>, AppError> {
+    let mut lines = Vec::new();
+    let mut localizer: Option<Localizer> = None;
+
+    for (position, request) in requests.into_iter().enumerate() {
+        let mut description = request.description;
+        let mut unit_price_cents = request.unit_price_cents;
+
+        if let Some(treatment_id) = request.treatment_id {
+            let treatment = db
+                .get_treatment_by_id(treatment_id)
+                .await?
+                .filter(|treatment| treatment.patient_id == invoice.patient_id)
+                .ok_or(ErrorCode::TreatmentNotForPatient)?;
+
+            if invoice.kind == InvoiceKind::Invoice
+                && db.is_treatment_billed(treatment_id, Some(invoice.id)).await?
+            {
+                return Err(ErrorCode::TreatmentAlreadyBilled.into());
+            }
+
+            let treatment_type = match treatment.treatment_type_id {
+                Some(type_id) => db.get_treatment_type_by_id(type_id).await.ok().flatten(),
+                None => None,
+            };
+
+            if description.is_none() {
+                let localizer = match &mut localizer {
+                    Some(localizer) => localizer,
+                    None => localizer.insert(patient_localizer(db, invoice.patient_id).await?),
+                };
+                let session_date = localizer.format_short_date(&treatment.date);
+                let name = match &treatment_type {
+                    Some(treatment_type) => treatment_type.name(localizer.code()),
+                    None => localizer.text("billing.treatment_session"),
+                };
+                description = Some(format!("{name} - {session_date}"));
+            }
+            if unit_price_cents.is_none() {
+                unit_price_cents = treatment_type.map(|t| t.default_price_cents);
+            }
+        }
+
+        let (Some(description), Some(unit_price_cents)) = (description, unit_price_cents) else {
+            return Err(ApiError::new(ErrorCode::InvoiceLineIncomplete).with("line", position + 1).into());
+        };
+
+        let quantity = request.quantity.unwrap_or(1);
+        if quantity <= 0 || unit_price_cents < 0 {
+            return Err(ApiError::new(ErrorCode::InvoiceLineInvalid).with("line", position + 1).into());
+        }
+
+        lines.push(InvoiceLine {
+            id: Uuid::new_v4(),
+            invoice_id: invoice.id,
+            position: position as i64,
+            treatment_id: request.treatment_id,
+            description,
+            quantity,
+            unit_price_cents,
+            line_total_cents: quantity * unit_price_cents,
+        });
+    }
+
+    Ok(lines)
+}
@@ -0,0 +1,9 @@
+use actix_web::HttpResponse;
+
+use crate::errors::AppError;
+use crate::i18n;
+
+/// Languages documents can be exported in, for language pickers
+pub async fn get_locales() -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(i18n::available_locales()))
+}
@@ -0,0 +1,308 @@
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDate;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::errors::{AppError, ErrorCode};
+use crate::validation::{ValidatedJson, Validator};
+use crate::database::Database;
+use crate::icd10;
+use crate::models::{
+    Allergy, CreateAllergyRequest, UpdateAllergyRequest, Diagnosis, CreateDiagnosisRequest, UpdateDiagnosisRequest,
+    Medication, CreateMedicationRequest, UpdateMedicationRequest, Icd10Query, is_valid_period,
+};
+
+/// Results returned by an ICD-10 search without a `limit`
+const ICD10_SEARCH_DEFAULT: i64 = 20;
+
+async fn require_patient(db: &Database, patient_id: Uuid) -> Result<(), AppError> {
+    if db.get_patient_by_id(patient_id).await?.is_none() {
+        return Err(ErrorCode::PatientNotFound.into());
+    }
+    Ok(())
+}
+
+fn check_period(start_date: Option<NaiveDate>, end_date: Option<NaiveDate>) -> Result<(), AppError> {
+    if !is_valid_period(start_date, end_date) {
+        return Err(ErrorCode::InvalidDateRange.into());
+    }
+    Ok(())
+}
+
+/// Diagnoses, allergies and medications, including those that have ended
+pub async fn get_medical_background(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    require_patient(&db, patient_id).await?;
+    let background = db.get_medical_background(patient_id).await?;
+    Ok(HttpResponse::Ok().json(background))
+}
+
+/// Search the bundled ICD-10 list by code prefix or title words
+pub async fn search_icd10(query: web::Query<Icd10Query>) -> Result<HttpResponse, AppError> {
+    Validator::check(&*query).map_err(AppError::Validation)?;
+    let limit = query.limit.unwrap_or(ICD10_SEARCH_DEFAULT) as usize;
+    let codes = icd10::search(query.q.as_deref().unwrap_or(""), limit);
+    Ok(HttpResponse::Ok().json(json!({
+        "codes": codes,
+        "count": codes.len()
+    })))
+}
+
+/// Load a diagnosis and make sure it belongs to the patient in the path
+async fn load_diagnosis(db: &Database, patient_id: Uuid, diagnosis_id: Uuid) -> Result<Diagnosis, AppError> {
+    db.get_diagnosis_by_id(diagnosis_id)
+        .await?
+        .filter(|diagnosis| diagnosis.patient_id == patient_id)
+        .ok_or_else(|| ErrorCode::DiagnosisNotFound.into())
+}
+
+pub async fn create_diagnosis(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<CreateDiagnosisRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    require_patient(&db, patient_id).await?;
+    let diagnosis = Diagnosis::new(patient_id, data.into_inner());
+    check_period(diagnosis.start_date, diagnosis.end_date)?;
+
+    db.create_diagnosis(&diagnosis).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Diagnosis created successfully",
+        "diagnosis": diagnosis
+    })))
+}
+
+pub async fn get_diagnoses_for_patient(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    let diagnoses = db.get_diagnoses_for_patient(patient_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "diagnoses": diagnoses,
+        "count": diagnoses.len()
+    })))
+}
+
+pub async fn get_diagnosis_by_id(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, diagnosis_id) = path.into_inner();
+
+    let diagnosis = load_diagnosis(&db, patient_id, diagnosis_id).await?;
+    Ok(HttpResponse::Ok().json(diagnosis))
+}
+
+pub async fn update_diagnosis(
+    path: web::Path<(Uuid, Uuid)>,
+    data: ValidatedJson<UpdateDiagnosisRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, diagnosis_id) = path.into_inner();
+
+    let mut diagnosis = load_diagnosis(&db, patient_id, diagnosis_id).await?;
+    diagnosis.update(data.into_inner());
+    check_period(diagnosis.start_date, diagnosis.end_date)?;
+
+    if !db.update_diagnosis(diagnosis_id, &diagnosis).await? {
+        return Err(ErrorCode::DiagnosisNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Diagnosis updated successfully",
+        "diagnosis": diagnosis
+    })))
+}
+
+pub async fn delete_diagnosis(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, diagnosis_id) = path.into_inner();
+
+    load_diagnosis(&db, patient_id, diagnosis_id).await?;
+
+    if !db.delete_diagnosis(diagnosis_id).await? {
+        return Err(ErrorCode::DiagnosisNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Diagnosis deleted successfully"
+    })))
+}
+
+/// Load an allergy and make sure it belongs to the patient in the path
+async fn load_allergy(db: &Database, patient_id: Uuid, allergy_id: Uuid) -> Result<Allergy, AppError> {
+    db.get_allergy_by_id(allergy_id)
+        .await?
+        .filter(|allergy| allergy.patient_id == patient_id)
+        .ok_or_else(|| ErrorCode::AllergyNotFound.into())
+}
+
+pub async fn create_allergy(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<CreateAllergyRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    require_patient(&db, patient_id).await?;
+    let allergy = Allergy::new(patient_id, data.into_inner());
+    check_period(allergy.start_date, allergy.end_date)?;
+
+    db.create_allergy(&allergy).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Allergy created successfully",
+        "allergy": allergy
+    })))
+}
+
+pub async fn get_allergies_for_patient(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    let allergies = db.get_allergies_for_patient(patient_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "allergies": allergies,
+        "count": allergies.len()
+    })))
+}
+
+pub async fn get_allergy_by_id(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, allergy_id) = path.into_inner();
+
+    let allergy = load_allergy(&db, patient_id, allergy_id).await?;
+    Ok(HttpResponse::Ok().json(allergy))
+}
+
+pub async fn update_allergy(
+    path: web::Path<(Uuid, Uuid)>,
+    data: ValidatedJson<UpdateAllergyRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, allergy_id) = path.into_inner();
+
+    let mut allergy = load_allergy(&db, patient_id, allergy_id).await?;
+    allergy.update(data.into_inner());
+    check_period(allergy.start_date, allergy.end_date)?;
+
+    if !db.update_allergy(allergy_id, &allergy).await? {
+        return Err(ErrorCode::AllergyNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Allergy updated successfully",
+        "allergy": allergy
+    })))
+}
+
+pub async fn delete_allergy(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, allergy_id) = path.into_inner();
+
+    load_allergy(&db, patient_id, allergy_id).await?;
+
+    if !db.delete_allergy(allergy_id).await? {
+        return Err(ErrorCode::AllergyNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Allergy deleted successfully"
+    })))
+}
+
+/// Load a medication and make sure it belongs to the patient in the path
+async fn load_medication(db: &Database, patient_id: Uuid, medication_id: Uuid) -> Result<Medication, AppError> {
+    db.get_medication_by_id(medication_id)
+        .await?
+        .filter(|medication| medication.patient_id == patient_id)
+        .ok_or_else(|| ErrorCode::MedicationNotFound.into())
+}
+
+pub async fn create_medication(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<CreateMedicationRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    require_patient(&db, patient_id).await?;
+    let medication = Medication::new(patient_id, data.into_inner());
+    check_period(medication.start_date, medication.end_date)?;
+
+    db.create_medication(&medication).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Medication created successfully",
+        "medication": medication
+    })))
+}
+
+pub async fn get_medications_for_patient(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    let medications = db.get_medications_for_patient(patient_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "medications": medications,
+        "count": medications.len()
+    })))
+}
+
+pub async fn get_medication_by_id(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, medication_id) = path.into_inner();
+
+    let medication = load_medication(&db, patient_id, medication_id).await?;
+    Ok(HttpResponse::Ok().json(medication))
+}
+
+pub async fn update_medication(
+    path: web::Path<(Uuid, Uuid)>,
+    data: ValidatedJson<UpdateMedicationRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, medication_id) = path.into_inner();
+
+    let mut medication = load_medication(&db, patient_id, medication_id).await?;
+    medication.update(data.into_inner());
+    check_period(medication.start_date, medication.end_date)?;
+
+    if !db.update_medication(medication_id, &medication).await? {
+        return Err(ErrorCode::MedicationNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Medication updated successfully",
+        "medication": medication
+    })))
+}
+
+pub async fn delete_medication(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, medication_id) = path.into_inner();
+
+    load_medication(&db, patient_id, medication_id).await?;
+
+    if !db.delete_medication(medication_id).await? {
+        return Err(ErrorCode::MedicationNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Medication deleted successfully"
+    })))
+}
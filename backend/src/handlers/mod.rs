@@ -1,5 +1,18 @@
 pub mod patient_handler;
 pub mod treatment_handler;
+pub mod treatment_type_handler;
 pub mod auth;
 pub mod github;
 pub mod export_handler;
+pub mod invoice_handler;
+pub mod document_handler;
+pub mod package_handler;
+pub mod contact_handler;
+pub mod medical_handler;
+pub mod attachment_handler;
+pub mod questionnaire_handler;
+pub mod consent_handler;
+pub mod treatment_plan_handler;
+pub mod report_handler;
+pub mod export_template_handler;
+pub mod locale_handler;
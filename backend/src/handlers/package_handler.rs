@@ -0,0 +1,111 @@
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::errors::{AppError, ErrorCode};
+use crate::validation::ValidatedJson;
+use crate::database::Database;
+use crate::models::{SessionPackage, CreatePackageRequest, UpdatePackageRequest};
+
+fn validate_sessions(package: &SessionPackage) -> Result<(), AppError> {
+    if package.sessions_purchased <= 0 {
+        return Err(ErrorCode::InvalidSessionCount.into());
+    }
+    if package.sessions_used < 0 || package.sessions_used > package.sessions_purchased {
+        return Err(ErrorCode::SessionsBelowUsed.into());
+    }
+    if package.price_cents < 0 {
+        return Err(ErrorCode::NegativePrice.into());
+    }
+    Ok(())
+}
+
+/// Load a package and make sure it belongs to the patient in the path
+async fn load_package(db: &Database, patient_id: Uuid, package_id: Uuid) -> Result<SessionPackage, AppError> {
+    db.get_package_by_id(package_id)
+        .await?
+        .filter(|package| package.patient_id == patient_id)
+        .ok_or_else(|| ErrorCode::PackageNotFound.into())
+}
+
+pub async fn create_package(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<CreatePackageRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    if db.get_patient_by_id(patient_id).await?.is_none() {
+        return Err(ErrorCode::PatientNotFound.into());
+    }
+
+    let package = SessionPackage::new(patient_id, data.into_inner());
+    validate_sessions(&package)?;
+
+    db.create_package(&package).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Package created successfully",
+        "package": package.summary()
+    })))
+}
+
+pub async fn get_packages_for_patient(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    let packages = db.get_packages_for_patient(patient_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "count": packages.len(),
+        "packages": packages.into_iter().map(SessionPackage::summary).collect::<Vec<_>>()
+    })))
+}
+
+pub async fn get_package_by_id(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, package_id) = path.into_inner();
+
+    let package = load_package(&db, patient_id, package_id).await?;
+    Ok(HttpResponse::Ok().json(package.summary()))
+}
+
+pub async fn update_package(
+    path: web::Path<(Uuid, Uuid)>,
+    data: ValidatedJson<UpdatePackageRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, package_id) = path.into_inner();
+
+    let mut package = load_package(&db, patient_id, package_id).await?;
+
+    package.update(data.into_inner());
+    validate_sessions(&package)?;
+
+    if !db.update_package(package_id, &package).await? {
+        return Err(ErrorCode::PackageNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Package updated successfully",
+        "package": package.summary()
+    })))
+}
+
+/// Delete a package; treatments drawn from it stay on record without the link
+pub async fn delete_package(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, package_id) = path.into_inner();
+
+    load_package(&db, patient_id, package_id).await?;
+
+    if !db.delete_package(package_id).await? {
+        return Err(ErrorCode::PackageNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Package deleted successfully"
+    })))
+}
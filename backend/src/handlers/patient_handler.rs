@@ -1,172 +1,252 @@
-use actix_web::{web, HttpResponse, Result};
+use actix_web::{web, HttpResponse};
 use serde_json::json;
 use uuid::Uuid;
 
-use crate::models::{Patient, CreatePatientRequest, UpdatePatientRequest};
+use crate::errors::{ApiError, AppError, ErrorCode};
+use crate::validation::{ValidatedJson, Validator};
+use crate::models::{
+    Claims, Patient, PatientDetail, PatientQuery, CreatePatientRequest, UpdatePatientRequest,
+    DuplicateQuery, MergePatientRequest,
+};
+use crate::duplicates;
+use crate::phone;
 use crate::database::Database;
+use crate::handlers::attachment_handler::release_blobs;
+use crate::handlers::consent_handler::consent_statuses;
+use crate::storage::BlobStore;
+
+/// Candidate pairs returned by a duplicate search without a `limit`
+const DUPLICATES_DEFAULT_LIMIT: i64 = 100;
 
 pub async fn create_patient(
-    data: web::Json<CreatePatientRequest>,
+    data: ValidatedJson<CreatePatientRequest>,
     db: web::Data<Database>,
-) -> Result<HttpResponse> {
-    let patient = Patient::new(
-        data.name.clone(),
-        data.email.clone(),
-        data.phone_number.clone(),
-        data.description.clone(),
-        data.date,
-    );
-
-    match db.create_patient(&patient).await {
-        Ok(_) => Ok(HttpResponse::Created().json(json!({
-            "message": "Patient created successfully",
-            "patient": patient
-        }))),
-        Err(e) => {
-            eprintln!("Database error: {e}");
-            Ok(HttpResponse::InternalServerError().json("Failed to create patient"))
+) -> Result<HttpResponse, AppError> {
+    let patient = Patient::new(data.into_inner());
+    check_national_id(&db, &patient).await?;
+
+    db.create_patient(&patient).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Patient created successfully",
+        "patient": patient
+    })))
+}
+
+/// National IDs are unique; point the client at the patient that already has this one
+async fn check_national_id(db: &Database, patient: &Patient) -> Result<(), AppError> {
+    let Some(national_id) = &patient.national_id else {
+        return Ok(());
+    };
+    match db.find_patient_by_national_id(national_id).await? {
+        Some(existing) if existing != patient.id => {
+            Err(ApiError::new(ErrorCode::NationalIdTaken).with("patient_id", existing).into())
         }
+        _ => Ok(()),
     }
 }
 
 pub async fn get_all_patients(
+    query: web::Query<PatientQuery>,
     db: web::Data<Database>,
-) -> Result<HttpResponse> {
-    match db.get_all_patients().await {
-        Ok(patients) => Ok(HttpResponse::Ok().json(json!({
-            "patients": patients,
-            "count": patients.len()
-        }))),
-        Err(e) => {
-            eprintln!("Database error: {e}");
-            Ok(HttpResponse::InternalServerError().json(json!({
-                "error": "Failed to fetch patients"
-            })))
-        }
-    }
+) -> Result<HttpResponse, AppError> {
+    Validator::check(&*query).map_err(AppError::Validation)?;
+    // Validated above, so any given number normalizes
+    let phone_e164 = query.phone.as_deref().and_then(phone::normalize);
+    let patients = db.get_all_patients(phone_e164.as_deref()).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "patients": patients,
+        "count": patients.len()
+    })))
 }
 
 pub async fn get_patient_by_id(
     path: web::Path<Uuid>,
     db: web::Data<Database>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, AppError> {
     let patient_id = path.into_inner();
 
-    match db.get_patient_by_id(patient_id).await {
-        Ok(Some(patient)) => Ok(HttpResponse::Ok().json(patient)),
-        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
-            "error": "Patient not found"
-        }))),
-        Err(e) => {
-            eprintln!("Database error: {e}");
-            Ok(HttpResponse::InternalServerError().json(json!({
-                "error": "Failed to fetch patient"
-            })))
-        }
-    }
+    let patient = db
+        .get_patient_by_id(patient_id)
+        .await?
+        .ok_or(ErrorCode::PatientNotFound)?;
+    let packages = db.get_packages_for_patient(patient_id).await?;
+    let contacts = db.get_contacts_for_patient(patient_id).await?;
+    let medical_background = db.get_medical_background(patient_id).await?;
+    let attachments = db.get_attachments_for_patient(patient_id, None).await?;
+    let treatment_plans = db.get_treatment_plans_for_patient(patient_id).await?;
+    let consents = consent_statuses(&db, patient_id).await?;
+
+    Ok(HttpResponse::Ok().json(PatientDetail {
+        patient,
+        packages: packages.into_iter().map(|p| p.summary()).collect(),
+        contacts,
+        medical_background: medical_background.current(),
+        attachments,
+        treatment_plans: treatment_plans.into_iter().map(|p| p.summary()).collect(),
+        consents,
+    }))
 }
 
 pub async fn update_patient(
     path: web::Path<Uuid>,
-    data: web::Json<UpdatePatientRequest>,
+    data: ValidatedJson<UpdatePatientRequest>,
     db: web::Data<Database>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, AppError> {
     let patient_id = path.into_inner();
 
     // First, get the existing patient
-    let existing_patient = match db.get_patient_by_id(patient_id).await {
-        Ok(Some(patient)) => patient,
-        Ok(None) => return Ok(HttpResponse::NotFound().json(json!({
-            "error": "Patient not found"
-        }))),
-        Err(e) => {
-            eprintln!("Database error: {e}");
-            return Ok(HttpResponse::InternalServerError().json(json!({
-                "error": "Failed to fetch patient"
-            })));
-        }
-    };
-
-    // Create updated patient
-    let mut updated_patient = existing_patient;
+    let mut updated_patient = db
+        .get_patient_by_id(patient_id)
+        .await?
+        .ok_or(ErrorCode::PatientNotFound)?;
     updated_patient.update(data.into_inner());
+    check_national_id(&db, &updated_patient).await?;
 
-    match db.update_patient(patient_id, &updated_patient).await {
-        Ok(true) => Ok(HttpResponse::Ok().json(json!({
-            "message": "Patient updated successfully",
-            "patient": updated_patient
-        }))),
-        Ok(false) => Ok(HttpResponse::NotFound().json(json!({
-            "error": "Patient not found"
-        }))),
-        Err(e) => {
-            eprintln!("Database error: {e}");
-            Ok(HttpResponse::InternalServerError().json(json!({
-                "error": "Failed to update patient"
-            })))
-        }
+    if !db.update_patient(patient_id, &updated_patient).await? {
+        return Err(ErrorCode::PatientNotFound.into());
     }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Patient updated successfully",
+        "patient": updated_patient
+    })))
 }
 
 pub async fn delete_patient(
     path: web::Path<Uuid>,
     db: web::Data<Database>,
-) -> Result<HttpResponse> {
+    store: web::Data<dyn BlobStore>,
+) -> Result<HttpResponse, AppError> {
     let patient_id = path.into_inner();
 
-    match db.delete_patient(patient_id).await {
-        Ok(true) => Ok(HttpResponse::Ok().json(json!({
-            "message": "Patient deleted successfully"
-        }))),
-        Ok(false) => Ok(HttpResponse::NotFound().json(json!({
-            "error": "Patient not found"
-        }))),
-        Err(e) => {
-            eprintln!("Database error: {e}");
-            Ok(HttpResponse::InternalServerError().json(json!({
-                "error": "Failed to delete patient"
-            })))
-        }
+    // Issued invoices and receipts must be retained, so their patient cannot be removed
+    if db.patient_has_invoices(patient_id).await? {
+        return Err(ErrorCode::PatientHasInvoices.into());
+    }
+
+    // Attachment rows go with the patient; their content is released afterwards
+    let attachment_hashes = db.get_attachment_hashes_for_patient(patient_id).await?;
+    if !db.delete_patient(patient_id).await? {
+        return Err(ErrorCode::PatientNotFound.into());
     }
+    release_blobs(&db, store.get_ref(), &attachment_hashes).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Patient deleted successfully"
+    })))
 }
 
 pub async fn toggle_patient_status(
     path: web::Path<Uuid>,
     db: web::Data<Database>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, AppError> {
     let patient_id = path.into_inner();
 
     // First get the current patient
-    match db.get_patient_by_id(patient_id).await {
-        Ok(Some(mut patient)) => {
-            // Toggle the active status
-            patient.active = !patient.active;
-
-            // Update the patient in the database
-            match db.update_patient(patient_id, &patient).await {
-                Ok(true) => Ok(HttpResponse::Ok().json(json!({
-                    "message": format!("Patient status changed to {}", if patient.active { "active" } else { "inactive" }),
-                    "patient": patient
-                }))),
-                Ok(false) => Ok(HttpResponse::NotFound().json(json!({
-                    "error": "Patient not found"
-                }))),
-                Err(e) => {
-                    eprintln!("Database error: {e}");
-                    Ok(HttpResponse::InternalServerError().json(json!({
-                        "error": "Failed to update patient status"
-                    })))
-                }
-            }
-        },
-        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
-            "error": "Patient not found"
-        }))),
-        Err(e) => {
-            eprintln!("Database error: {e}");
-            Ok(HttpResponse::InternalServerError().json(json!({
-                "error": "Failed to retrieve patient"
-            })))
+    let mut patient = db
+        .get_patient_by_id(patient_id)
+        .await?
+        .ok_or(ErrorCode::PatientNotFound)?;
+
+    // Toggle the active status and update the patient in the database
+    patient.active = !patient.active;
+    if !db.update_patient(patient_id, &patient).await? {
+        return Err(ErrorCode::PatientNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": format!("Patient status changed to {}", if patient.active { "active" } else { "inactive" }),
+        "patient": patient
+    })))
+}
+
+pub async fn find_duplicate_patients(
+    query: web::Query<DuplicateQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    Validator::check(&*query).map_err(AppError::Validation)?;
+    let min_score = query.min_score.unwrap_or(duplicates::DEFAULT_MIN_SCORE);
+
+    let limit = query.limit.unwrap_or(DUPLICATES_DEFAULT_LIMIT) as usize;
+
+    let patients = db.get_all_patients(None).await?;
+    // Comparing names takes a while on a large practice, so it stays off the request threads
+    let (candidates, total) = web::block(move || duplicates::find_candidates(&patients, min_score, limit))
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    Ok(HttpResponse::Ok().json(json!({
+        "candidates": candidates,
+        "count": candidates.len(),
+        "total": total
+    })))
+}
+
+pub async fn get_patient_duplicates(
+    path: web::Path<Uuid>,
+    query: web::Query<DuplicateQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    Validator::check(&*query).map_err(AppError::Validation)?;
+    let min_score = query.min_score.unwrap_or(duplicates::DEFAULT_MIN_SCORE);
+
+    let patient = db
+        .get_patient_by_id(path.into_inner())
+        .await?
+        .ok_or(ErrorCode::PatientNotFound)?;
+    let others = db.get_all_patients(None).await?;
+    let candidates = web::block(move || duplicates::candidates_for(&patient, &others, min_score))
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    Ok(HttpResponse::Ok().json(json!({
+        "candidates": candidates,
+        "count": candidates.len()
+    })))
+}
+
+/// Fold the record in the body into the patient in the path
+pub async fn merge_patient(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<MergePatientRequest>,
+    claims: Option<web::ReqData<Claims>>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+    if data.duplicate_id == patient_id {
+        return Err(ErrorCode::MergeSamePatient.into());
+    }
+
+    let mut survivor = db
+        .get_patient_by_id(patient_id)
+        .await?
+        .ok_or(ErrorCode::PatientNotFound)?;
+    let duplicate = db
+        .get_patient_by_id(data.duplicate_id)
+        .await?
+        .ok_or(ErrorCode::PatientNotFound)?;
+    if survivor.merged_into.is_some() || duplicate.merged_into.is_some() {
+        return Err(ErrorCode::PatientMerged.into());
+    }
+    // Two different ID numbers mean two different people
+    if let (Some(a), Some(b)) = (&survivor.national_id, &duplicate.national_id) {
+        if a != b {
+            return Err(ErrorCode::NationalIdMismatch.into());
         }
     }
+
+    survivor.absorb(&duplicate);
+    let merged_by = claims.map(|c| c.into_inner().sub);
+    let merge = db.merge_patients(&survivor, &duplicate, merged_by).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Patients merged successfully",
+        "patient": survivor,
+        "merge": merge
+    })))
+}
+
+pub async fn get_patient_merges(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let merges = db.get_patient_merges(path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "merges": merges,
+        "count": merges.len()
+    })))
 }
@@ -0,0 +1,260 @@
+use std::collections::HashSet;
+
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::errors::{ApiError, AppError, ErrorCode};
+use crate::validation::{ValidatedJson, Validator};
+use crate::database::Database;
+use crate::models::{
+    Claims, Questionnaire, CreateQuestionnaireRequest, UpdateQuestionnaireRequest, QuestionnaireQuery,
+    QuestionnaireResponse, CreateQuestionnaireResponseRequest, QuestionnaireResponseQuery, QuestionKind,
+    AnswerCheck, ScoreSeriesQuery, ScoreSeries, ScorePoint,
+};
+
+/// Question IDs must be unique, choice questions need distinct options and scales a range
+fn check_questions(questionnaire: &Questionnaire) -> Result<(), AppError> {
+    let mut ids = HashSet::new();
+    for (index, question) in questionnaire.questions.iter().enumerate() {
+        if !ids.insert(question.id.as_str()) {
+            return Err(ApiError::new(ErrorCode::DuplicateQuestionId).with("id", &question.id).into());
+        }
+        let well_formed = match question.kind {
+            QuestionKind::SingleChoice | QuestionKind::MultiChoice => question.has_distinct_options(),
+            QuestionKind::Scale => question.has_valid_scale(),
+            QuestionKind::Text => true,
+        };
+        if !well_formed {
+            let code = if question.kind == QuestionKind::Scale {
+                ErrorCode::InvalidQuestionScale
+            } else {
+                ErrorCode::InvalidQuestionOptions
+            };
+            return Err(ApiError::new(code).with("question", index + 1).into());
+        }
+    }
+    Ok(())
+}
+
+pub async fn create_questionnaire(
+    data: ValidatedJson<CreateQuestionnaireRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let questionnaire = Questionnaire::new(data.into_inner());
+    check_questions(&questionnaire)?;
+
+    db.create_questionnaire(&questionnaire).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Questionnaire created successfully",
+        "questionnaire": questionnaire
+    })))
+}
+
+pub async fn get_questionnaires(
+    query: web::Query<QuestionnaireQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let questionnaires = db.get_questionnaires(query.include_inactive.unwrap_or(false)).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "questionnaires": questionnaires,
+        "count": questionnaires.len()
+    })))
+}
+
+pub async fn get_questionnaire_by_id(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let questionnaire_id = path.into_inner();
+
+    let questionnaire = db
+        .get_questionnaire_by_id(questionnaire_id)
+        .await?
+        .ok_or(ErrorCode::QuestionnaireNotFound)?;
+    Ok(HttpResponse::Ok().json(questionnaire))
+}
+
+/// Update a questionnaire; recorded responses keep their answers and scores
+pub async fn update_questionnaire(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<UpdateQuestionnaireRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let questionnaire_id = path.into_inner();
+
+    let mut questionnaire = db
+        .get_questionnaire_by_id(questionnaire_id)
+        .await?
+        .ok_or(ErrorCode::QuestionnaireNotFound)?;
+    questionnaire.update(data.into_inner());
+    check_questions(&questionnaire)?;
+
+    if !db.update_questionnaire(questionnaire_id, &questionnaire).await? {
+        return Err(ErrorCode::QuestionnaireNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Questionnaire updated successfully",
+        "questionnaire": questionnaire
+    })))
+}
+
+/// Delete a questionnaire nobody has answered; answered ones are deactivated instead
+pub async fn delete_questionnaire(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let questionnaire_id = path.into_inner();
+
+    if db.questionnaire_has_responses(questionnaire_id).await? {
+        return Err(ErrorCode::QuestionnaireInUse.into());
+    }
+    if !db.delete_questionnaire(questionnaire_id).await? {
+        return Err(ErrorCode::QuestionnaireNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Questionnaire deleted successfully"
+    })))
+}
+
+/// Load a response and make sure it belongs to the patient in the path
+async fn load_response(db: &Database, patient_id: Uuid, response_id: Uuid) -> Result<QuestionnaireResponse, AppError> {
+    db.get_questionnaire_response_by_id(response_id)
+        .await?
+        .filter(|response| response.patient_id == patient_id)
+        .ok_or_else(|| ErrorCode::QuestionnaireResponseNotFound.into())
+}
+
+/// Record a patient's answers; scored questionnaires get their score now
+pub async fn create_questionnaire_response(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<CreateQuestionnaireResponseRequest>,
+    claims: Option<web::ReqData<Claims>>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    if db.get_patient_by_id(patient_id).await?.is_none() {
+        return Err(ErrorCode::PatientNotFound.into());
+    }
+    let questionnaire = db
+        .get_questionnaire_by_id(data.questionnaire_id)
+        .await?
+        .ok_or(ErrorCode::QuestionnaireNotFound)?;
+    if !questionnaire.active {
+        return Err(ErrorCode::QuestionnaireInactive.into());
+    }
+    if let Some(treatment_id) = data.treatment_id {
+        let treatment = db
+            .get_treatment_by_id(treatment_id)
+            .await?
+            .ok_or(ErrorCode::TreatmentNotFound)?;
+        if treatment.patient_id != patient_id {
+            return Err(ErrorCode::TreatmentNotForPatient.into());
+        }
+    }
+    Validator::check(&AnswerCheck { questionnaire: &questionnaire, answers: &data.answers })
+        .map_err(AppError::Validation)?;
+
+    let response = QuestionnaireResponse::new(
+        patient_id,
+        &questionnaire,
+        data.into_inner(),
+        claims.map(|c| c.into_inner().sub),
+    );
+    db.create_questionnaire_response(&response).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Questionnaire response recorded successfully",
+        "response": response
+    })))
+}
+
+pub async fn get_questionnaire_responses_for_patient(
+    path: web::Path<Uuid>,
+    query: web::Query<QuestionnaireResponseQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    let responses = db
+        .get_questionnaire_responses_for_patient(patient_id, query.questionnaire_id)
+        .await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "responses": responses,
+        "count": responses.len()
+    })))
+}
+
+pub async fn get_questionnaire_response_by_id(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, response_id) = path.into_inner();
+
+    let response = load_response(&db, patient_id, response_id).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+pub async fn delete_questionnaire_response(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, response_id) = path.into_inner();
+
+    load_response(&db, patient_id, response_id).await?;
+    if !db.delete_questionnaire_response(response_id).await? {
+        return Err(ErrorCode::QuestionnaireResponseNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Questionnaire response deleted successfully"
+    })))
+}
+
+/// A patient's scores over time, one series per scored questionnaire
+pub async fn get_patient_scores(
+    path: web::Path<Uuid>,
+    query: web::Query<ScoreSeriesQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err(ErrorCode::InvalidDateRange.into());
+        }
+    }
+
+    let responses = db
+        .get_questionnaire_responses_for_patient(patient_id, query.questionnaire_id)
+        .await?;
+    let mut series: Vec<ScoreSeries> = Vec::new();
+    for response in responses {
+        let Some(score) = response.score else { continue };
+        if query.from.is_some_and(|from| response.completed_at < from)
+            || query.to.is_some_and(|to| response.completed_at > to)
+        {
+            continue;
+        }
+
+        let point = ScorePoint { response_id: response.id, completed_at: response.completed_at, score };
+        if let Some(existing) = series.iter_mut().find(|s| s.questionnaire_id == response.questionnaire_id) {
+            existing.points.push(point);
+            continue;
+        }
+        let Some(questionnaire) = db.get_questionnaire_by_id(response.questionnaire_id).await? else { continue };
+        let range = questionnaire.score_range();
+        series.push(ScoreSeries {
+            questionnaire_id: questionnaire.id,
+            name: questionnaire.name,
+            scoring: questionnaire.scoring,
+            higher_is_better: questionnaire.higher_is_better,
+            score_min: range.map(|(min, _)| min),
+            score_max: range.map(|(_, max)| max),
+            points: vec![point],
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "series": series,
+        "count": series.len()
+    })))
+}
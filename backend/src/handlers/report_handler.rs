@@ -0,0 +1,177 @@
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::errors::AppError;
+use crate::database::Database;
+use crate::models::{
+    ReportFormat, ReportQuery, SessionsReportRow, RevenueReportRow,
+    PatientActivityRow, TherapistWorkloadRow,
+};
+
+/// Report rows that can also be downloaded as CSV
+trait CsvRow {
+    const HEADER: &'static [&'static str];
+
+    fn fields(&self) -> Vec<String>;
+}
+
+impl CsvRow for SessionsReportRow {
+    const HEADER: &'static [&'static str] = &["period", "sessions", "patients", "total_minutes"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.period.clone(),
+            self.sessions.to_string(),
+            self.patients.to_string(),
+            self.total_minutes.to_string(),
+        ]
+    }
+}
+
+impl CsvRow for RevenueReportRow {
+    const HEADER: &'static [&'static str] =
+        &["period", "invoiced_cents", "credited_cents", "net_invoiced_cents", "collected_cents"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.period.clone(),
+            self.invoiced_cents.to_string(),
+            self.credited_cents.to_string(),
+            self.net_invoiced_cents.to_string(),
+            self.collected_cents.to_string(),
+        ]
+    }
+}
+
+impl CsvRow for PatientActivityRow {
+    const HEADER: &'static [&'static str] = &["period", "new_patients", "returning_patients"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.period.clone(),
+            self.new_patients.to_string(),
+            self.returning_patients.to_string(),
+        ]
+    }
+}
+
+impl CsvRow for TherapistWorkloadRow {
+    const HEADER: &'static [&'static str] =
+        &["therapist_id", "therapist_name", "sessions", "patients", "total_minutes"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.therapist_id.clone().unwrap_or_default(),
+            self.therapist_name.clone().unwrap_or_default(),
+            self.sessions.to_string(),
+            self.patients.to_string(),
+            self.total_minutes.to_string(),
+        ]
+    }
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub(crate) fn csv_line(fields: &[String]) -> String {
+    let escaped: Vec<String> = fields.iter().map(|f| csv_escape(f)).collect();
+    format!("{}\r\n", escaped.join(","))
+}
+
+fn csv_attachment(name: &str, content: String) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .append_header(("Content-Disposition", format!("attachment; filename=\"report_{name}.csv\"")))
+        .body(content)
+}
+
+fn report_response<T: Serialize + CsvRow>(name: &str, query: &ReportQuery, rows: Vec<T>) -> HttpResponse {
+    match query.format.unwrap_or_default() {
+        ReportFormat::Json => HttpResponse::Ok().json(json!({
+            "from": query.from,
+            "to": query.to,
+            "group_by": query.group_by.unwrap_or_default(),
+            "rows": rows
+        })),
+        ReportFormat::Csv => {
+            let header: Vec<String> = T::HEADER.iter().map(|h| h.to_string()).collect();
+            let mut content = csv_line(&header);
+            for row in &rows {
+                content.push_str(&csv_line(&row.fields()));
+            }
+            csv_attachment(name, content)
+        }
+    }
+}
+
+/// Sessions, distinct patients and minutes per period
+pub async fn sessions_report(
+    query: web::Query<ReportQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let rows = db.report_sessions(query.from, query.to, query.group_by.unwrap_or_default()).await?;
+    Ok(report_response("sessions", &query, rows))
+}
+
+/// Invoiced, credited and collected amounts per period
+pub async fn revenue_report(
+    query: web::Query<ReportQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let rows = db.report_revenue(query.from, query.to, query.group_by.unwrap_or_default()).await?;
+    Ok(report_response("revenue", &query, rows))
+}
+
+/// New versus returning patients per period
+pub async fn patient_activity_report(
+    query: web::Query<ReportQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let rows = db.report_patient_activity(query.from, query.to, query.group_by.unwrap_or_default()).await?;
+    Ok(report_response("patients", &query, rows))
+}
+
+/// Sessions and minutes logged by each therapist
+pub async fn therapist_workload_report(
+    query: web::Query<ReportQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let rows = db.report_therapist_workload(query.from, query.to).await?;
+    Ok(report_response("therapists", &query, rows))
+}
+
+/// Headline figures for the dashboard
+pub async fn summary_report(
+    query: web::Query<ReportQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let summary = db.report_summary(query.from, query.to).await?;
+
+    match query.format.unwrap_or_default() {
+        ReportFormat::Json => Ok(HttpResponse::Ok().json(summary)),
+        ReportFormat::Csv => {
+            let metrics = [
+                ("total_patients", summary.total_patients.to_string()),
+                ("active_patients", summary.active_patients.to_string()),
+                ("inactive_patients", summary.inactive_patients.to_string()),
+                ("sessions", summary.sessions.to_string()),
+                ("treated_patients", summary.treated_patients.to_string()),
+                ("average_sessions_per_patient", format!("{:.2}", summary.average_sessions_per_patient)),
+                ("net_invoiced_cents", summary.net_invoiced_cents.to_string()),
+                ("collected_cents", summary.collected_cents.to_string()),
+            ];
+
+            let mut content = csv_line(&["metric".to_string(), "value".to_string()]);
+            for (metric, value) in metrics {
+                content.push_str(&csv_line(&[metric.to_string(), value]));
+            }
+            Ok(csv_attachment("summary", content))
+        }
+    }
+}
@@ -1,155 +1,292 @@
-use actix_web::{web, HttpResponse, Result as ActixResult};
+use actix_web::{web, HttpResponse};
+use serde_json::json;
 use uuid::Uuid;
 
+use crate::errors::{AppError, ErrorCode};
+use crate::validation::{ValidatedJson, Validator};
 use crate::database::Database;
-use crate::models::treatment::{Treatment, CreateTreatmentRequest, UpdateTreatmentRequest};
+use crate::body_region::{self, REGIONS};
+use crate::handlers::consent_handler::consent_statuses;
+use crate::i18n::request_localizer;
+use crate::models::{BodyChartEntry, BodyChartQuery, Claims, ConsentStatus};
+use crate::models::treatment::{Treatment, CreateTreatmentRequest, UpdateTreatmentRequest, TreatmentOutcome, TreatmentQuery, TreatmentResponse};
 
-pub async fn create_treatment(
-    path: web::Path<Uuid>,
-    body: web::Json<CreateTreatmentRequest>,
-    data: web::Data<Database>,
-) -> ActixResult<HttpResponse> {
-    let patient_id = path.into_inner();
-
-    // Check if patient exists
-    match data.get_patient_by_id(patient_id).await {
-        Ok(Some(_)) => {
-            // Patient exists, proceed with treatment creation
-            let new_treatment = Treatment {
-                id: Uuid::new_v4(),
-                patient_id,
-                summary: body.summary.clone(),
-                date: body.date.unwrap_or_else(chrono::Utc::now),
-            };
-
-            match data.create_treatment(&new_treatment).await {
-                Ok(_) => Ok(HttpResponse::Created().json(&new_treatment)),
-                Err(e) => {
-                    eprintln!("Failed to create treatment: {e}");
-                    Ok(HttpResponse::InternalServerError().json("Failed to create treatment"))
-                }
+/// Make sure a package can take another session for this patient
+async fn check_package(data: &Database, patient_id: Uuid, package_id: Uuid) -> Result<(), AppError> {
+    match data.get_package_by_id(package_id).await? {
+        Some(package) if package.patient_id == patient_id => {
+            if package.sessions_remaining() == 0 {
+                Err(ErrorCode::PackageExhausted.into())
+            } else {
+                Ok(())
             }
         }
-        Ok(None) => Ok(HttpResponse::NotFound().json("Patient not found")),
+        _ => Err(ErrorCode::InvalidPackage.into()),
+    }
+}
+
+/// Package warnings to show once a session has been drawn from it
+async fn package_warnings(data: &Database, package_id: Option<Uuid>) -> Vec<String> {
+    let Some(package_id) = package_id else {
+        return Vec::new();
+    };
+
+    match data.get_package_by_id(package_id).await {
+        Ok(Some(package)) => package.summary().warnings,
+        Ok(None) => Vec::new(),
         Err(e) => {
-            eprintln!("Failed to check patient: {e}");
-            Ok(HttpResponse::InternalServerError().json("Failed to check patient"))
+            eprintln!("Failed to fetch package: {e}");
+            Vec::new()
         }
     }
 }
 
-pub async fn get_treatments_for_patient(
-    path: web::Path<Uuid>,
-    data: web::Data<Database>,
-) -> ActixResult<HttpResponse> {
-    let patient_id = path.into_inner();
+/// Make sure a treatment plan belongs to this patient
+async fn check_plan(data: &Database, patient_id: Uuid, plan_id: Uuid) -> Result<(), AppError> {
+    match data.get_treatment_plan_by_id(plan_id).await? {
+        Some(plan) if plan.patient_id == patient_id => Ok(()),
+        _ => Err(ErrorCode::InvalidTreatmentPlan.into()),
+    }
+}
+
+/// Plan warnings to show once a session has been linked to it, e.g. that it has used its planned sessions
+async fn plan_warnings(data: &Database, plan_id: Option<Uuid>) -> Vec<String> {
+    let Some(plan_id) = plan_id else {
+        return Vec::new();
+    };
 
-    match data.get_treatments_for_patient(patient_id).await {
-        Ok(treatments) => Ok(HttpResponse::Ok().json(treatments)),
+    match data.get_treatment_plan_by_id(plan_id).await {
+        Ok(Some(plan)) => plan.summary().warnings,
+        Ok(None) => Vec::new(),
         Err(e) => {
-            eprintln!("Failed to fetch treatments: {e}");
-            Ok(HttpResponse::InternalServerError().json("Failed to fetch treatments"))
+            eprintln!("Failed to fetch treatment plan: {e}");
+            Vec::new()
         }
     }
 }
 
-pub async fn get_all_treatments(data: web::Data<Database>) -> ActixResult<HttpResponse> {
-    match data.get_all_treatments().await {
-        Ok(treatments) => Ok(HttpResponse::Ok().json(treatments)),
+/// Warnings for required consent forms the patient has not signed in their current version
+async fn consent_warnings(data: &Database, patient_id: Uuid) -> Vec<String> {
+    match consent_statuses(data, patient_id).await {
+        Ok(statuses) => statuses.iter().filter_map(ConsentStatus::warning).collect(),
         Err(e) => {
-            eprintln!("Failed to fetch treatments: {e}");
-            Ok(HttpResponse::InternalServerError().json("Failed to fetch treatments"))
+            eprintln!("Failed to check consents: {e}");
+            Vec::new()
         }
     }
 }
 
+/// A treatment of the given patient, or `TREATMENT_NOT_FOUND`
+async fn patient_treatment(data: &Database, patient_id: Uuid, treatment_id: Uuid) -> Result<Treatment, AppError> {
+    data.get_treatment_by_id(treatment_id)
+        .await?
+        .filter(|treatment| treatment.patient_id == patient_id)
+        .ok_or_else(|| ErrorCode::TreatmentNotFound.into())
+}
+
+pub async fn create_treatment(
+    path: web::Path<Uuid>,
+    body: ValidatedJson<CreateTreatmentRequest>,
+    claims: Option<web::ReqData<Claims>>,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    // Check if patient exists
+    if data.get_patient_by_id(patient_id).await?.is_none() {
+        return Err(ErrorCode::PatientNotFound.into());
+    }
+
+    // Resolve the treatment type so the session length can default from the catalogue
+    let treatment_type = match body.treatment_type_id {
+        Some(type_id) => Some(
+            data.get_treatment_type_by_id(type_id)
+                .await?
+                .ok_or(ErrorCode::InvalidTreatmentType)?,
+        ),
+        None => None,
+    };
+
+    if let Some(package_id) = body.package_id {
+        check_package(&data, patient_id, package_id).await?;
+    }
+    if let Some(plan_id) = body.plan_id {
+        check_plan(&data, patient_id, plan_id).await?;
+    }
+
+    let new_treatment = Treatment {
+        id: Uuid::new_v4(),
+        patient_id,
+        summary: body.summary.clone(),
+        date: body.date.unwrap_or_else(chrono::Utc::now),
+        treatment_type_id: body.treatment_type_id,
+        duration_minutes: body.duration_minutes
+            .or(treatment_type.map(|t| t.default_duration_minutes)),
+        package_id: body.package_id,
+        plan_id: body.plan_id,
+        therapist_id: claims.map(|c| c.into_inner().sub),
+        annotations: body.annotations.clone().unwrap_or_default(),
+    };
+
+    // The package was checked above, but another session may have taken its last place since
+    if data.create_treatment(&new_treatment).await? == TreatmentOutcome::PackageExhausted {
+        return Err(ErrorCode::PackageExhausted.into());
+    }
+    let mut warnings = package_warnings(&data, new_treatment.package_id).await;
+    warnings.extend(plan_warnings(&data, new_treatment.plan_id).await);
+    warnings.extend(consent_warnings(&data, patient_id).await);
+    Ok(HttpResponse::Created().json(TreatmentResponse { treatment: new_treatment, warnings }))
+}
+
+pub async fn get_treatments_for_patient(
+    path: web::Path<Uuid>,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    let treatments = data.get_treatments_for_patient(patient_id).await?;
+    Ok(HttpResponse::Ok().json(treatments))
+}
+
+pub async fn get_all_treatments(
+    query: web::Query<TreatmentQuery>,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let treatments = data.get_all_treatments(query.treatment_type_id).await?;
+    Ok(HttpResponse::Ok().json(treatments))
+}
+
 pub async fn get_treatment_by_id(
     path: web::Path<(Uuid, Uuid)>,
     data: web::Data<Database>,
-) -> ActixResult<HttpResponse> {
+) -> Result<HttpResponse, AppError> {
     let (patient_id, treatment_id) = path.into_inner();
 
-    match data.get_treatment_by_id(treatment_id).await {
-        Ok(Some(treatment)) => {
-            // Verify that the treatment belongs to the specified patient
-            if treatment.patient_id == patient_id {
-                Ok(HttpResponse::Ok().json(treatment))
-            } else {
-                Ok(HttpResponse::NotFound().json("Treatment not found for this patient"))
-            }
-        }
-        Ok(None) => Ok(HttpResponse::NotFound().json("Treatment not found")),
-        Err(e) => {
-            eprintln!("Failed to fetch treatment: {e}");
-            Ok(HttpResponse::InternalServerError().json("Failed to fetch treatment"))
-        }
-    }
+    let treatment = patient_treatment(&data, patient_id, treatment_id).await?;
+    Ok(HttpResponse::Ok().json(treatment))
 }
 
 pub async fn update_treatment(
     path: web::Path<(Uuid, Uuid)>,
-    body: web::Json<UpdateTreatmentRequest>,
+    body: ValidatedJson<UpdateTreatmentRequest>,
     data: web::Data<Database>,
-) -> ActixResult<HttpResponse> {
+) -> Result<HttpResponse, AppError> {
     let (patient_id, treatment_id) = path.into_inner();
 
     // First, check if the treatment exists and belongs to the patient
-    match data.get_treatment_by_id(treatment_id).await {
-        Ok(Some(existing_treatment)) => {
-            if existing_treatment.patient_id != patient_id {
-                return Ok(HttpResponse::NotFound().json("Treatment not found for this patient"));
-            }
+    let existing_treatment = patient_treatment(&data, patient_id, treatment_id).await?;
 
-            let updated_treatment = Treatment {
-                id: treatment_id,
-                patient_id,
-                summary: body.summary.clone().unwrap_or(existing_treatment.summary),
-                date: body.date.unwrap_or(existing_treatment.date),
-            };
-
-            match data.update_treatment(treatment_id, &updated_treatment).await {
-                Ok(true) => Ok(HttpResponse::Ok().json(&updated_treatment)),
-                Ok(false) => Ok(HttpResponse::NotFound().json("Treatment not found")),
-                Err(e) => {
-                    eprintln!("Failed to update treatment: {e}");
-                    Ok(HttpResponse::InternalServerError().json("Failed to update treatment"))
-                }
-            }
-        }
-        Ok(None) => Ok(HttpResponse::NotFound().json("Treatment not found")),
-        Err(e) => {
-            eprintln!("Failed to fetch treatment: {e}");
-            Ok(HttpResponse::InternalServerError().json("Failed to fetch treatment"))
+    let treatment_type_id = body.treatment_type_id.unwrap_or(existing_treatment.treatment_type_id);
+    let package_id = body.package_id.unwrap_or(existing_treatment.package_id);
+    let plan_id = body.plan_id.unwrap_or(existing_treatment.plan_id);
+
+    if let Some(type_id) = body.treatment_type_id.flatten() {
+        if data.get_treatment_type_by_id(type_id).await?.is_none() {
+            return Err(ErrorCode::InvalidTreatmentType.into());
         }
     }
+
+    if let Some(package_id) = package_id.filter(|id| Some(*id) != existing_treatment.package_id) {
+        check_package(&data, patient_id, package_id).await?;
+    }
+    if let Some(plan_id) = body.plan_id.flatten() {
+        check_plan(&data, patient_id, plan_id).await?;
+    }
+
+    let updated_treatment = Treatment {
+        id: treatment_id,
+        patient_id,
+        summary: body.summary.clone().unwrap_or(existing_treatment.summary),
+        date: body.date.unwrap_or(existing_treatment.date),
+        treatment_type_id,
+        duration_minutes: body.duration_minutes.or(existing_treatment.duration_minutes),
+        package_id,
+        plan_id,
+        therapist_id: existing_treatment.therapist_id,
+        annotations: body.annotations.clone().unwrap_or(existing_treatment.annotations),
+    };
+
+    match data.update_treatment(treatment_id, &updated_treatment).await? {
+        TreatmentOutcome::Saved => {}
+        TreatmentOutcome::NotFound => return Err(ErrorCode::TreatmentNotFound.into()),
+        TreatmentOutcome::PackageExhausted => return Err(ErrorCode::PackageExhausted.into()),
+    }
+    let mut warnings = package_warnings(&data, updated_treatment.package_id).await;
+    warnings.extend(plan_warnings(&data, updated_treatment.plan_id).await);
+    Ok(HttpResponse::Ok().json(TreatmentResponse { treatment: updated_treatment, warnings }))
 }
 
 pub async fn delete_treatment(
     path: web::Path<(Uuid, Uuid)>,
     data: web::Data<Database>,
-) -> ActixResult<HttpResponse> {
+) -> Result<HttpResponse, AppError> {
     let (patient_id, treatment_id) = path.into_inner();
 
     // First, check if the treatment exists and belongs to the patient
-    match data.get_treatment_by_id(treatment_id).await {
-        Ok(Some(treatment)) => {
-            if treatment.patient_id != patient_id {
-                return Ok(HttpResponse::NotFound().json("Treatment not found for this patient"));
-            }
+    patient_treatment(&data, patient_id, treatment_id).await?;
 
-            match data.delete_treatment(treatment_id).await {
-                Ok(true) => Ok(HttpResponse::NoContent().finish()),
-                Ok(false) => Ok(HttpResponse::NotFound().json("Treatment not found")),
-                Err(e) => {
-                    eprintln!("Failed to delete treatment: {e}");
-                    Ok(HttpResponse::InternalServerError().json("Failed to delete treatment"))
-                }
-            }
-        }
-        Ok(None) => Ok(HttpResponse::NotFound().json("Treatment not found")),
-        Err(e) => {
-            eprintln!("Failed to fetch treatment: {e}");
-            Ok(HttpResponse::InternalServerError().json("Failed to fetch treatment"))
+    if !data.delete_treatment(treatment_id).await? {
+        return Err(ErrorCode::TreatmentNotFound.into());
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// The regions body chart annotations can be placed on, named in the request's language
+pub async fn get_body_regions() -> Result<HttpResponse, AppError> {
+    let localizer = request_localizer();
+    let regions: Vec<_> = REGIONS
+        .iter()
+        .map(|region| json!({
+            "code": region.code,
+            "name": region.name(&localizer),
+            "group": region.group,
+            "paired": region.paired,
+        }))
+        .collect();
+    Ok(HttpResponse::Ok().json(json!({
+        "regions": regions,
+        "count": regions.len()
+    })))
+}
+
+/// A patient's body chart annotations across all their treatments, oldest first
+pub async fn get_body_chart(
+    path: web::Path<Uuid>,
+    query: web::Query<BodyChartQuery>,
+    data: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+    Validator::check(&*query).map_err(AppError::Validation)?;
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err(ErrorCode::InvalidDateRange.into());
         }
     }
+    if data.get_patient_by_id(patient_id).await?.is_none() {
+        return Err(ErrorCode::PatientNotFound.into());
+    }
+
+    let localizer = request_localizer();
+    let treatments = data.get_annotated_treatments(patient_id, query.region.as_deref()).await?;
+    let entries: Vec<BodyChartEntry> = treatments
+        .into_iter()
+        .filter(|t| query.from.is_none_or(|from| t.date >= from) && query.to.is_none_or(|to| t.date <= to))
+        .flat_map(|t| {
+            let (treatment_id, date, summary) = (t.id, t.date, t.summary);
+            t.annotations.into_iter().map(move |annotation| (treatment_id, date, summary.clone(), annotation))
+        })
+        .filter(|(.., a)| query.region.as_ref().is_none_or(|region| &a.region == region))
+        .filter(|(.., a)| query.side.is_none_or(|side| a.side == Some(side)))
+        .map(|(treatment_id, date, summary, annotation)| BodyChartEntry {
+            treatment_id,
+            date,
+            summary,
+            region_name: body_region::region_name(&annotation.region, &localizer).to_string(),
+            annotation,
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(json!({
+        "entries": entries,
+        "count": entries.len()
+    })))
 }
@@ -0,0 +1,179 @@
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::errors::{ApiError, AppError, ErrorCode};
+use crate::validation::ValidatedJson;
+use crate::database::Database;
+use crate::models::{
+    TreatmentPlan, TreatmentPlanSummary, CreateTreatmentPlanRequest, UpdateTreatmentPlanRequest, PlanGoal, CreateGoalRequest,
+    UpdateGoalRequest, is_valid_period, GOALS_MAX,
+};
+
+fn check_dates(plan: &TreatmentPlan) -> Result<(), AppError> {
+    if !is_valid_period(plan.start_date, plan.target_end_date) {
+        return Err(ErrorCode::InvalidDateRange.into());
+    }
+    Ok(())
+}
+
+/// Load a plan and make sure it belongs to the patient in the path
+async fn load_plan(db: &Database, patient_id: Uuid, plan_id: Uuid) -> Result<TreatmentPlan, AppError> {
+    db.get_treatment_plan_by_id(plan_id)
+        .await?
+        .filter(|plan| plan.patient_id == patient_id)
+        .ok_or_else(|| ErrorCode::TreatmentPlanNotFound.into())
+}
+
+/// Load a goal of a plan that belongs to the patient in the path
+async fn load_goal(db: &Database, patient_id: Uuid, plan_id: Uuid, goal_id: Uuid) -> Result<PlanGoal, AppError> {
+    load_plan(db, patient_id, plan_id).await?;
+    db.get_plan_goal_by_id(goal_id)
+        .await?
+        .filter(|goal| goal.plan_id == plan_id)
+        .ok_or_else(|| ErrorCode::PlanGoalNotFound.into())
+}
+
+/// The plan as now saved, with its progress
+async fn plan_summary(db: &Database, patient_id: Uuid, plan_id: Uuid) -> Result<TreatmentPlanSummary, AppError> {
+    Ok(load_plan(db, patient_id, plan_id).await?.summary())
+}
+
+pub async fn create_treatment_plan(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<CreateTreatmentPlanRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    if db.get_patient_by_id(patient_id).await?.is_none() {
+        return Err(ErrorCode::PatientNotFound.into());
+    }
+
+    let plan = TreatmentPlan::new(patient_id, data.into_inner());
+    check_dates(&plan)?;
+
+    db.create_treatment_plan(&plan).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Treatment plan created successfully",
+        "treatment_plan": plan.summary()
+    })))
+}
+
+pub async fn get_treatment_plans_for_patient(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let patient_id = path.into_inner();
+
+    let plans = db.get_treatment_plans_for_patient(patient_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "count": plans.len(),
+        "treatment_plans": plans.into_iter().map(TreatmentPlan::summary).collect::<Vec<_>>()
+    })))
+}
+
+pub async fn get_treatment_plan_by_id(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, plan_id) = path.into_inner();
+
+    let plan = load_plan(&db, patient_id, plan_id).await?;
+    Ok(HttpResponse::Ok().json(plan.summary()))
+}
+
+pub async fn update_treatment_plan(
+    path: web::Path<(Uuid, Uuid)>,
+    data: ValidatedJson<UpdateTreatmentPlanRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, plan_id) = path.into_inner();
+
+    let mut plan = load_plan(&db, patient_id, plan_id).await?;
+    plan.update(data.into_inner());
+    check_dates(&plan)?;
+
+    if !db.update_treatment_plan(plan_id, &plan).await? {
+        return Err(ErrorCode::TreatmentPlanNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Treatment plan updated successfully",
+        "treatment_plan": plan.summary()
+    })))
+}
+
+/// Delete a plan and its goals; its treatments are kept, no longer linked to a plan
+pub async fn delete_treatment_plan(
+    path: web::Path<(Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, plan_id) = path.into_inner();
+
+    load_plan(&db, patient_id, plan_id).await?;
+
+    if !db.delete_treatment_plan(plan_id).await? {
+        return Err(ErrorCode::TreatmentPlanNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Treatment plan deleted successfully"
+    })))
+}
+
+pub async fn create_plan_goal(
+    path: web::Path<(Uuid, Uuid)>,
+    data: ValidatedJson<CreateGoalRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, plan_id) = path.into_inner();
+
+    let plan = load_plan(&db, patient_id, plan_id).await?;
+    if plan.goals.len() >= GOALS_MAX {
+        return Err(ApiError::new(ErrorCode::TooManyGoals).with("max", GOALS_MAX).into());
+    }
+
+    let goal = PlanGoal::new(plan_id, data.into_inner());
+    db.create_plan_goal(&goal).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Goal created successfully",
+        "goal": goal,
+        "treatment_plan": plan_summary(&db, patient_id, plan_id).await?
+    })))
+}
+
+/// Update a goal, e.g. to record progress towards it or mark it achieved
+pub async fn update_plan_goal(
+    path: web::Path<(Uuid, Uuid, Uuid)>,
+    data: ValidatedJson<UpdateGoalRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, plan_id, goal_id) = path.into_inner();
+
+    let mut goal = load_goal(&db, patient_id, plan_id, goal_id).await?;
+    goal.update(data.into_inner());
+
+    if !db.update_plan_goal(goal_id, &goal).await? {
+        return Err(ErrorCode::PlanGoalNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Goal updated successfully",
+        "goal": goal,
+        "treatment_plan": plan_summary(&db, patient_id, plan_id).await?
+    })))
+}
+
+pub async fn delete_plan_goal(
+    path: web::Path<(Uuid, Uuid, Uuid)>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let (patient_id, plan_id, goal_id) = path.into_inner();
+
+    load_goal(&db, patient_id, plan_id, goal_id).await?;
+
+    if !db.delete_plan_goal(goal_id).await? {
+        return Err(ErrorCode::PlanGoalNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Goal deleted successfully"
+    })))
+}
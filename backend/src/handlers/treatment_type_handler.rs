@@ -0,0 +1,98 @@
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::errors::{AppError, ErrorCode};
+use crate::validation::ValidatedJson;
+use crate::database::Database;
+use crate::models::{
+    TreatmentType, CreateTreatmentTypeRequest, UpdateTreatmentTypeRequest,
+    TreatmentTypeQuery, TreatmentTypeBreakdownQuery,
+};
+
+pub async fn create_treatment_type(
+    data: ValidatedJson<CreateTreatmentTypeRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let treatment_type = TreatmentType::new(data.into_inner());
+
+    db.create_treatment_type(&treatment_type).await?;
+    Ok(HttpResponse::Created().json(json!({
+        "message": "Treatment type created successfully",
+        "treatment_type": treatment_type
+    })))
+}
+
+pub async fn get_treatment_types(
+    query: web::Query<TreatmentTypeQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let treatment_types = db.get_treatment_types(query.include_inactive.unwrap_or(false)).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "treatment_types": treatment_types,
+        "count": treatment_types.len()
+    })))
+}
+
+pub async fn get_treatment_type_by_id(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let type_id = path.into_inner();
+
+    let treatment_type = db
+        .get_treatment_type_by_id(type_id)
+        .await?
+        .ok_or(ErrorCode::TreatmentTypeNotFound)?;
+    Ok(HttpResponse::Ok().json(treatment_type))
+}
+
+pub async fn update_treatment_type(
+    path: web::Path<Uuid>,
+    data: ValidatedJson<UpdateTreatmentTypeRequest>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let type_id = path.into_inner();
+
+    let mut treatment_type = db
+        .get_treatment_type_by_id(type_id)
+        .await?
+        .ok_or(ErrorCode::TreatmentTypeNotFound)?;
+
+    treatment_type.update(data.into_inner());
+
+    if !db.update_treatment_type(type_id, &treatment_type).await? {
+        return Err(ErrorCode::TreatmentTypeNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Treatment type updated successfully",
+        "treatment_type": treatment_type
+    })))
+}
+
+/// Delete a treatment type; treatments that used it keep their data but lose the link
+pub async fn delete_treatment_type(
+    path: web::Path<Uuid>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let type_id = path.into_inner();
+
+    if !db.delete_treatment_type(type_id).await? {
+        return Err(ErrorCode::TreatmentTypeNotFound.into());
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Treatment type deleted successfully"
+    })))
+}
+
+/// Sessions and minutes per treatment type, optionally limited to a date range
+pub async fn get_treatment_type_breakdown(
+    query: web::Query<TreatmentTypeBreakdownQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let breakdown = db.get_treatment_type_breakdown(query.from, query.to).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "breakdown": breakdown,
+        "total_sessions": breakdown.iter().map(|b| b.sessions).sum::<i64>()
+    })))
+}
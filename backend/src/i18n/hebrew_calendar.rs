@@ -0,0 +1,126 @@
+use chrono::{Datelike, NaiveDate};
+
+/// Fixed day number (days since 1 January 1 CE, counted from 1) of 1 Tishrei, year 1
+const HEBREW_EPOCH: i64 = -1_373_427;
+
+/// Date in the Hebrew calendar; `month` is a key into the locale's `hebrew_months`
+pub struct HebrewDate {
+    pub year: i64,
+    pub month: &'static str,
+    pub day: i64,
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (7 * year + 1).rem_euclid(19) < 7
+}
+
+/// Days from the epoch to the molad of Tishrei, with the first postponement applied
+fn elapsed_days(year: i64) -> i64 {
+    let months = (235 * year - 234).div_euclid(19);
+    let parts = 12_084 + 13_753 * months;
+    let day = 29 * months + parts.div_euclid(25_920);
+    if (3 * (day + 1)).rem_euclid(7) < 3 {
+        day + 1
+    } else {
+        day
+    }
+}
+
+/// Further postponement keeping year lengths within the allowed 353-355 / 383-385 days
+fn year_length_correction(year: i64) -> i64 {
+    let previous = elapsed_days(year - 1);
+    let current = elapsed_days(year);
+    let next = elapsed_days(year + 1);
+
+    if next - current == 356 {
+        2
+    } else if current - previous == 382 {
+        1
+    } else {
+        0
+    }
+}
+
+fn new_year(year: i64) -> i64 {
+    HEBREW_EPOCH + elapsed_days(year) + year_length_correction(year)
+}
+
+/// Months of a year in civil order, starting from Tishrei, with their lengths
+fn months(year: i64) -> Vec<(&'static str, i64)> {
+    let length = new_year(year + 1) - new_year(year);
+    let cheshvan = if length % 10 == 5 { 30 } else { 29 };
+    let kislev = if length % 10 == 3 { 29 } else { 30 };
+
+    let mut months = vec![("tishrei", 30), ("cheshvan", cheshvan), ("kislev", kislev), ("tevet", 29), ("shevat", 30)];
+    if is_leap_year(year) {
+        months.extend([("adar_1", 30), ("adar_2", 29)]);
+    } else {
+        months.push(("adar", 29));
+    }
+    months.extend([("nisan", 30), ("iyar", 29), ("sivan", 30), ("tammuz", 29), ("av", 30), ("elul", 29)]);
+    months
+}
+
+impl HebrewDate {
+    pub fn from_gregorian(date: NaiveDate) -> Self {
+        let fixed = i64::from(date.num_days_from_ce());
+
+        // 1 Tishrei falls in September or October, so the year is one of two candidates
+        let mut year = i64::from(date.year()) + 3760;
+        if fixed >= new_year(year + 1) {
+            year += 1;
+        }
+
+        let mut day = fixed - new_year(year) + 1;
+        for (month, length) in months(year) {
+            if day <= length {
+                return Self { year, month, day };
+            }
+            day -= length;
+        }
+        unreachable!("day {fixed} lies outside Hebrew year {year}")
+    }
+}
+
+/// Write a number in Hebrew numerals (gematria), e.g. 15 as ט״ו and 5787 as תשפ״ז.
+/// Thousands are dropped, as is customary for years.
+pub fn hebrew_numerals(number: i64) -> String {
+    const HUNDREDS: [(i64, char); 4] = [(400, 'ת'), (300, 'ש'), (200, 'ר'), (100, 'ק')];
+    const TENS: [char; 9] = ['י', 'כ', 'ל', 'מ', 'נ', 'ס', 'ע', 'פ', 'צ'];
+    const UNITS: [char; 9] = ['א', 'ב', 'ג', 'ד', 'ה', 'ו', 'ז', 'ח', 'ט'];
+
+    let mut rest = number.rem_euclid(1000);
+    let mut letters = Vec::new();
+    for (value, letter) in HUNDREDS {
+        while rest >= value {
+            letters.push(letter);
+            rest -= value;
+        }
+    }
+    // 15 and 16 are written 9+6 and 9+7 to avoid spelling the divine name
+    match rest {
+        15 => letters.extend(['ט', 'ו']),
+        16 => letters.extend(['ט', 'ז']),
+        _ => {
+            if rest >= 10 {
+                letters.push(TENS[(rest / 10 - 1) as usize]);
+            }
+            if rest % 10 > 0 {
+                letters.push(UNITS[(rest % 10 - 1) as usize]);
+            }
+        }
+    }
+
+    let mut result: String = letters.iter().collect();
+    match letters.len() {
+        0 => {}
+        1 => result.push('׳'),
+        n => {
+            let last = letters[n - 1];
+            result = letters[..n - 1].iter().collect();
+            result.push('״');
+            result.push(last);
+        }
+    }
+    result
+}
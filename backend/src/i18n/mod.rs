@@ -0,0 +1,435 @@
+mod hebrew_calendar;
+
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::fs;
+use std::sync::{Arc, OnceLock};
+
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use hebrew_calendar::{hebrew_numerals, HebrewDate};
+
+use crate::request_context;
+
+/// Locales shipped with the application; files in `LOCALES_DIR` extend or override them
+const BUILT_IN_LOCALES: [(&str, &str); 4] = [
+    ("en", include_str!("../../locales/en.json")),
+    ("he", include_str!("../../locales/he.json")),
+    ("ar", include_str!("../../locales/ar.json")),
+    ("ru", include_str!("../../locales/ru.json")),
+];
+
+/// Last resort of every fallback chain
+const DEFAULT_LOCALE: &str = "en";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+/// Calendar used for long dates in documents
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Calendar {
+    #[default]
+    Gregorian,
+    Hebrew,
+    /// Gregorian date followed by the Hebrew date in parentheses
+    Both,
+}
+
+/// Date and number patterns; `{day}`, `{day2}`, `{month}`, `{month2}` and
+/// `{year}` are replaced in dates, `{symbol}` and `{amount}` in money
+#[derive(Debug, Default, Deserialize)]
+struct Formats {
+    date: Option<String>,
+    short_date: Option<String>,
+    hebrew_date: Option<String>,
+    decimal_separator: Option<String>,
+    group_separator: Option<String>,
+    money: Option<String>,
+}
+
+/// One locale resource file. Everything is optional so a file can cover only
+/// part of a language and leave the rest to its fallback chain.
+#[derive(Debug, Deserialize)]
+struct LocaleFile {
+    name: Option<String>,
+    tag: Option<String>,
+    direction: Option<Direction>,
+    fallback: Option<String>,
+    #[serde(default)]
+    formats: Formats,
+    hebrew_numerals: Option<bool>,
+    months: Option<Vec<String>>,
+    #[serde(default)]
+    hebrew_months: HashMap<String, String>,
+    #[serde(default)]
+    messages: Value,
+}
+
+#[derive(Debug)]
+struct Locale {
+    code: String,
+    file: LocaleFile,
+    /// `messages` flattened to dotted keys, e.g. `export.title`
+    messages: HashMap<String, String>,
+}
+
+impl Locale {
+    fn parse(code: &str, source: &str) -> Result<Self, serde_json::Error> {
+        let mut file: LocaleFile = serde_json::from_str(source)?;
+        if file.months.as_ref().is_some_and(|months| months.len() != 12) {
+            eprintln!("Locale {code}: `months` must list 12 names, ignoring them");
+            file.months = None;
+        }
+
+        let mut messages = HashMap::new();
+        flatten_messages("", &file.messages, &mut messages);
+        file.messages = Value::Null;
+
+        Ok(Self { code: code.to_string(), file, messages })
+    }
+
+    /// Layer an override file on top of this locale
+    fn merge(&mut self, other: Locale) {
+        let file = other.file;
+        self.file.name = file.name.or(self.file.name.take());
+        self.file.tag = file.tag.or(self.file.tag.take());
+        self.file.direction = file.direction.or(self.file.direction);
+        self.file.fallback = file.fallback.or(self.file.fallback.take());
+        self.file.hebrew_numerals = file.hebrew_numerals.or(self.file.hebrew_numerals);
+        self.file.months = file.months.or(self.file.months.take());
+        self.file.hebrew_months.extend(file.hebrew_months);
+
+        let (formats, theirs) = (&mut self.file.formats, file.formats);
+        formats.date = theirs.date.or(formats.date.take());
+        formats.short_date = theirs.short_date.or(formats.short_date.take());
+        formats.hebrew_date = theirs.hebrew_date.or(formats.hebrew_date.take());
+        formats.decimal_separator = theirs.decimal_separator.or(formats.decimal_separator.take());
+        formats.group_separator = theirs.group_separator.or(formats.group_separator.take());
+        formats.money = theirs.money.or(formats.money.take());
+
+        self.messages.extend(other.messages);
+    }
+}
+
+fn flatten_messages(prefix: &str, value: &Value, out: &mut HashMap<String, String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() { key.clone() } else { format!("{prefix}.{key}") };
+                flatten_messages(&key, value, out);
+            }
+        }
+        Value::String(text) => {
+            out.insert(prefix.to_string(), text.clone());
+        }
+        _ => {}
+    }
+}
+
+fn registry() -> &'static HashMap<String, Arc<Locale>> {
+    static LOCALES: OnceLock<HashMap<String, Arc<Locale>>> = OnceLock::new();
+
+    LOCALES.get_or_init(|| {
+        let mut locales: HashMap<String, Locale> = HashMap::new();
+        for (code, source) in BUILT_IN_LOCALES {
+            match Locale::parse(code, source) {
+                Ok(locale) => {
+                    locales.insert(code.to_string(), locale);
+                }
+                Err(e) => eprintln!("Invalid built-in locale {code}: {e}"),
+            }
+        }
+
+        if let Ok(dir) = env::var("LOCALES_DIR") {
+            match fs::read_dir(&dir) {
+                Ok(entries) => {
+                    for path in entries.flatten().map(|entry| entry.path()) {
+                        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                            continue;
+                        }
+                        let Some(code) = path.file_stem().and_then(|s| s.to_str()).map(str::to_lowercase) else {
+                            continue;
+                        };
+                        let parsed = fs::read_to_string(&path)
+                            .map_err(|e| e.to_string())
+                            .and_then(|source| Locale::parse(&code, &source).map_err(|e| e.to_string()));
+                        match (parsed, locales.get_mut(&code)) {
+                            (Ok(locale), Some(existing)) => existing.merge(locale),
+                            (Ok(locale), None) => {
+                                locales.insert(code, locale);
+                            }
+                            (Err(e), _) => eprintln!("Skipping locale file {}: {e}", path.display()),
+                        }
+                    }
+                }
+                Err(e) => eprintln!("Cannot read LOCALES_DIR {dir}: {e}"),
+            }
+        }
+
+        locales.into_iter().map(|(code, locale)| (code, Arc::new(locale))).collect()
+    })
+}
+
+/// Summary of an available locale, for language pickers
+#[derive(Debug, Serialize)]
+pub struct LocaleInfo {
+    pub code: String,
+    pub name: String,
+    pub tag: String,
+    pub direction: Direction,
+}
+
+/// Every loaded locale, sorted by code
+pub fn available_locales() -> Vec<LocaleInfo> {
+    let mut locales: Vec<LocaleInfo> = registry()
+        .keys()
+        .map(|code| {
+            let localizer = Localizer::new(code);
+            LocaleInfo {
+                code: code.clone(),
+                name: localizer.name().to_string(),
+                tag: localizer.tag().to_string(),
+                direction: localizer.direction(),
+            }
+        })
+        .collect();
+    locales.sort_by(|a, b| a.code.cmp(&b.code));
+    locales
+}
+
+/// Name of a language in its own locale, e.g. `עברית` for `he`; unsupported codes are returned as they are
+pub fn language_name(language: &str) -> String {
+    if is_supported(language) {
+        Localizer::new(language).name().to_string()
+    } else {
+        language.to_string()
+    }
+}
+
+pub fn is_rtl(language: &str) -> bool {
+    Localizer::new(language).direction() == Direction::Rtl
+}
+
+/// Lowercase a language tag and use `-` as separator, e.g. `he_IL` → `he-il`
+fn normalize(language: &str) -> String {
+    language.trim().replace('_', "-").to_lowercase()
+}
+
+/// Whether `language` or its primary language has a locale of its own
+pub fn is_supported(language: &str) -> bool {
+    let code = normalize(language);
+    let primary = code.split('-').next().unwrap_or_default();
+    registry().contains_key(&code) || registry().contains_key(primary)
+}
+
+/// Best supported locale code for an `Accept-Language` header, by quality then order
+pub fn negotiate(accept_language: &str) -> Option<String> {
+    let mut ranges: Vec<(f32, &str)> = accept_language
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let tag = parts.next()?.trim();
+            let quality = match parts.find_map(|p| p.trim().strip_prefix("q=")) {
+                Some(q) => q.trim().parse().ok()?,
+                None => 1.0,
+            };
+            (!tag.is_empty() && tag != "*" && quality > 0.0).then_some((quality, tag))
+        })
+        .collect();
+    // Stable sort, so equally weighted languages keep the client's order
+    ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    ranges
+        .into_iter()
+        .find(|(_, tag)| is_supported(tag))
+        .map(|(_, tag)| Localizer::new(tag).code().to_string())
+}
+
+/// Localizer for the current request; English outside of one
+pub fn request_localizer() -> Localizer {
+    let language = request_context::language().unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+    Localizer::new(&language)
+}
+
+/// Translations and formats for one requested language.
+///
+/// Lookups walk a fallback chain: the exact code (`he-il`), its primary
+/// language (`he`), that locale's declared `fallback`, and finally English.
+/// Missing messages render as their key so gaps are visible rather than blank.
+#[derive(Clone)]
+pub struct Localizer {
+    chain: Vec<Arc<Locale>>,
+    calendar: Calendar,
+}
+
+impl Localizer {
+    pub fn new(language: &str) -> Self {
+        let registry = registry();
+        let requested = normalize(language);
+
+        let mut pending = VecDeque::from([requested.clone()]);
+        if let Some((primary, _)) = requested.split_once('-') {
+            pending.push_back(primary.to_string());
+        }
+        pending.push_back(DEFAULT_LOCALE.to_string());
+
+        let mut chain: Vec<Arc<Locale>> = Vec::new();
+        while let Some(code) = pending.pop_front() {
+            if chain.iter().any(|l| l.code == code) {
+                continue;
+            }
+            if let Some(locale) = registry.get(&code) {
+                if let Some(fallback) = &locale.file.fallback {
+                    pending.push_front(fallback.to_lowercase());
+                }
+                chain.push(locale.clone());
+            }
+        }
+
+        Self { chain, calendar: Calendar::default() }
+    }
+
+    pub fn with_calendar(mut self, calendar: Calendar) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    fn find<'a, T>(&'a self, get: impl Fn(&'a Locale) -> Option<T>) -> Option<T> {
+        self.chain.iter().find_map(|locale| get(locale))
+    }
+
+    /// Code of the best matching locale, e.g. `he` for a request of `he-IL`
+    pub fn code(&self) -> &str {
+        self.chain.first().map_or(DEFAULT_LOCALE, |l| l.code.as_str())
+    }
+
+    /// The locale's own name for its language, e.g. `עברית`
+    pub fn name(&self) -> &str {
+        self.find(|l| l.file.name.as_deref()).unwrap_or(self.code())
+    }
+
+    /// BCP 47 language tag, e.g. `he-IL`
+    pub fn tag(&self) -> &str {
+        self.find(|l| l.file.tag.as_deref()).unwrap_or(self.code())
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.find(|l| l.file.direction).unwrap_or_default()
+    }
+
+    pub fn text<'a>(&'a self, key: &'a str) -> &'a str {
+        self.find(|l| l.messages.get(key).map(String::as_str)).unwrap_or(key)
+    }
+
+    /// Message with `{name}` placeholders filled in
+    pub fn format_message(&self, key: &str, values: &[(&str, String)]) -> String {
+        fill(self.text(key), values)
+    }
+
+    /// Long date in the selected calendar, e.g. `October 19, 2026` or `19 באוקטובר 2026`
+    pub fn format_date(&self, date: &DateTime<Utc>) -> String {
+        match self.calendar {
+            Calendar::Gregorian => self.format_gregorian_date(date),
+            Calendar::Hebrew => self.format_hebrew_date(date),
+            Calendar::Both => format!("{} ({})", self.format_gregorian_date(date), self.format_hebrew_date(date)),
+        }
+    }
+
+    pub fn format_gregorian_date(&self, date: &DateTime<Utc>) -> String {
+        let pattern = self.find(|l| l.file.formats.date.as_deref()).unwrap_or("{month} {day2}, {year}");
+        let month = self
+            .find(|l| l.file.months.as_ref().map(|months| months[date.month0() as usize].as_str()))
+            .map(str::to_string)
+            .unwrap_or_else(|| date.format("%B").to_string());
+
+        fill(pattern, &[
+            ("day", date.day().to_string()),
+            ("day2", format!("{:02}", date.day())),
+            ("month", month),
+            ("month2", format!("{:02}", date.month())),
+            ("year", date.year().to_string()),
+        ])
+    }
+
+    /// Date in the Hebrew calendar, in Hebrew numerals where the locale asks for them
+    pub fn format_hebrew_date(&self, date: &DateTime<Utc>) -> String {
+        let hebrew = HebrewDate::from_gregorian(date.date_naive());
+        let pattern = self.find(|l| l.file.formats.hebrew_date.as_deref()).unwrap_or("{day} {month} {year}");
+        let month = self.find(|l| l.file.hebrew_months.get(hebrew.month).map(String::as_str)).unwrap_or(hebrew.month);
+
+        let (day, year) = if self.find(|l| l.file.hebrew_numerals).unwrap_or(false) {
+            (hebrew_numerals(hebrew.day), hebrew_numerals(hebrew.year))
+        } else {
+            (hebrew.day.to_string(), hebrew.year.to_string())
+        };
+
+        fill(pattern, &[
+            ("day", day.clone()),
+            ("day2", day),
+            ("month", month.to_string()),
+            ("month2", month.to_string()),
+            ("year", year),
+        ])
+    }
+
+    /// Numeric date, e.g. `19/10/2026`; always Gregorian
+    pub fn format_short_date(&self, date: &DateTime<Utc>) -> String {
+        let pattern = self.find(|l| l.file.formats.short_date.as_deref()).unwrap_or("{day2}/{month2}/{year}");
+
+        fill(pattern, &[
+            ("day", date.day().to_string()),
+            ("day2", format!("{:02}", date.day())),
+            ("month", date.month().to_string()),
+            ("month2", format!("{:02}", date.month())),
+            ("year", date.year().to_string()),
+        ])
+    }
+
+    /// Amount in cents with the currency symbol, e.g. `₪1,234.50` or `1 234,50 ₪`
+    pub fn format_money(&self, cents: i64, currency: &str) -> String {
+        let symbol = match currency {
+            "ILS" => "₪",
+            "USD" => "$",
+            "EUR" => "€",
+            _ => "",
+        };
+        let decimal = self.find(|l| l.file.formats.decimal_separator.as_deref()).unwrap_or(".");
+        let group = self.find(|l| l.file.formats.group_separator.as_deref()).unwrap_or(",");
+
+        let sign = if cents < 0 { "-" } else { "" };
+        let cents = cents.abs();
+        let units = (cents / 100).to_string();
+        let mut grouped = String::new();
+        for (i, ch) in units.chars().enumerate() {
+            if i > 0 && (units.len() - i).is_multiple_of(3) {
+                grouped.push_str(group);
+            }
+            grouped.push(ch);
+        }
+        let amount = format!("{grouped}{decimal}{:02}", cents % 100);
+
+        if symbol.is_empty() {
+            format!("{sign}{amount} {currency}")
+        } else {
+            let pattern = self.find(|l| l.file.formats.money.as_deref()).unwrap_or("{symbol}{amount}");
+            format!("{sign}{}", fill(pattern, &[("symbol", symbol.to_string()), ("amount", amount)]))
+        }
+    }
+}
+
+/// Replace `{name}` placeholders; unknown placeholders are left as they are
+fn fill(pattern: &str, values: &[(&str, String)]) -> String {
+    let mut result = pattern.to_string();
+    for (name, value) in values {
+        result = result.replace(&format!("{{{name}}}"), value);
+    }
+    result
+}
@@ -0,0 +1,68 @@
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+/// Bundled subset of ICD-10, one `code<TAB>title` per line
+const CODE_LIST: &str = include_str!("../data/icd10.tsv");
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Icd10Code {
+    pub code: &'static str,
+    pub title: &'static str,
+}
+
+fn codes() -> &'static [Icd10Code] {
+    static CODES: OnceLock<Vec<Icd10Code>> = OnceLock::new();
+    CODES.get_or_init(|| {
+        CODE_LIST
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(code, title)| Icd10Code { code: code.trim(), title: title.trim() })
+            .collect()
+    })
+}
+
+/// Canonical form of an ICD-10 code, e.g. `m545` becomes `M54.5`, or `None` if it is malformed.
+///
+/// A code is a letter and two digits, optionally followed by a dot and one to
+/// four more characters (national extensions such as ICD-10-CM use the longer ones).
+pub fn normalize(input: &str) -> Option<String> {
+    let compact: String = input
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '.')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let (category, subdivision) = compact.split_at_checked(3)?;
+    let mut chars = category.chars();
+    let category_ok = chars.next().is_some_and(|c| c.is_ascii_uppercase())
+        && chars.all(|c| c.is_ascii_digit());
+    let subdivision_ok = subdivision.len() <= 4 && subdivision.chars().all(|c| c.is_ascii_alphanumeric());
+    if !category_ok || !subdivision_ok || input.matches('.').count() > 1 {
+        return None;
+    }
+    Some(if subdivision.is_empty() { category.to_string() } else { format!("{category}.{subdivision}") })
+}
+
+/// The bundled entry for `code`, in any spelling `normalize` accepts
+pub fn lookup(code: &str) -> Option<&'static Icd10Code> {
+    let code = normalize(code)?;
+    codes().iter().find(|entry| entry.code == code)
+}
+
+/// Codes starting with `query`, then codes whose title contains every word of it
+pub fn search(query: &str, limit: usize) -> Vec<&'static Icd10Code> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return codes().iter().take(limit).collect();
+    }
+    let prefix = query.replace('.', "").to_uppercase();
+    let words: Vec<&str> = query.split_whitespace().collect();
+
+    let by_code = codes().iter().filter(|entry| entry.code.replace('.', "").starts_with(&prefix));
+    let by_title = codes().iter().filter(|entry| {
+        let title = entry.title.to_lowercase();
+        !entry.code.replace('.', "").starts_with(&prefix) && words.iter().all(|word| title.contains(word))
+    });
+    by_code.chain(by_title).take(limit).collect()
+}
@@ -3,17 +3,33 @@ mod handlers;
 mod routes;
 mod database;
 mod auth;
+mod body_region;
 mod middleware;
+mod documents;
+mod duplicates;
+mod errors;
+mod exif;
+mod file_type;
+mod i18n;
+mod icd10;
+mod national_id;
+mod phone;
+mod request_context;
+mod storage;
+mod thumbnail;
+mod validation;
 
 use actix_web::{web, App, HttpServer, middleware::Logger};
 use actix_cors::Cors;
 use actix_files::Files;
 use dotenv::dotenv;
 use std::env;
+use std::sync::Arc;
 
 use database::Database;
 use routes::configure_routes;
 use handlers::auth::create_default_user;
+use storage::{BlobStore, LocalBlobStore};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -44,6 +60,10 @@ async fn main() -> std::io::Result<()> {
 
     let db_data = web::Data::new(db);
 
+    // Attachment content lives outside the database
+    let blob_store: Arc<dyn BlobStore> = Arc::new(LocalBlobStore::from_env());
+    let blob_store_data: web::Data<dyn BlobStore> = web::Data::from(blob_store);
+
     // Configure host and port based on environment
     let host = env::var("SERVER_HOST")
         .unwrap_or_else(|_| {
@@ -76,6 +96,7 @@ async fn main() -> std::io::Result<()> {
         if cfg!(debug_assertions) { "debug build" } else { "release build" }
     );
     println!("📝 GitHub Issues: {}", if std::env::var("GITHUB_TOKEN").is_ok() { "✅ Configured" } else { "❌ Not configured" });
+    println!("🌍 Locales: {}", i18n::available_locales().iter().map(|l| l.code.as_str()).collect::<Vec<_>>().join(", "));
     
     // Show environment variable status for debugging
     println!("🔧 Environment Variables Status:");
@@ -93,8 +114,13 @@ async fn main() -> std::io::Result<()> {
 
         App::new()
             .app_data(db_data.clone())
+            .app_data(blob_store_data.clone())
+            // Malformed input gets the same error body as handler errors
+            .app_data(web::JsonConfig::default().error_handler(errors::extractor_error))
+            .app_data(web::QueryConfig::default().error_handler(errors::extractor_error))
+            .app_data(web::PathConfig::default().error_handler(errors::extractor_error))
             .wrap(cors)
-            .wrap(Logger::default())
+            .wrap(Logger::new(r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T %{X-Request-Id}o"#))
             // Configure API routes FIRST (highest priority)
             .configure(configure_routes)
             // Serve static assets
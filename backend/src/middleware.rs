@@ -1,5 +1,7 @@
 use actix_web::{
+    body::EitherBody,
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderName, HeaderValue, ACCEPT_LANGUAGE},
     Error, HttpMessage,
 };
 use futures_util::future::LocalBoxFuture;
@@ -7,7 +9,13 @@ use std::{
     future::{ready, Ready},
     rc::Rc,
 };
+use uuid::Uuid;
 use crate::auth::JwtUtils;
+use crate::errors::{AppError, ErrorCode};
+use crate::i18n;
+use crate::request_context;
+
+const X_REQUEST_ID: &str = "x-request-id";
 
 pub struct AuthMiddleware;
 
@@ -17,7 +25,7 @@ where
     S::Future: 'static,
     B: 'static,
 {
-    type Response = ServiceResponse<B>;
+    type Response = ServiceResponse<EitherBody<B>>;
     type Error = Error;
     type InitError = ();
     type Transform = AuthMiddlewareService<S>;
@@ -40,7 +48,7 @@ where
     S::Future: 'static,
     B: 'static,
 {
-    type Response = ServiceResponse<B>;
+    type Response = ServiceResponse<EitherBody<B>>;
     type Error = Error;
     type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
 
@@ -53,7 +61,7 @@ where
             // Skip auth for login endpoint and OPTIONS requests
             let path = req.path();
             if path == "/api/auth/login" || req.method().as_str() == "OPTIONS" {
-                return service.call(req).await;
+                return service.call(req).await.map(ServiceResponse::map_into_left_body);
             }
 
             // Check for Authorization header
@@ -62,9 +70,13 @@ where
                     if let Some(token) = auth_str.strip_prefix("Bearer ") {
                         match JwtUtils::verify_token(token) {
                             Ok(claims) => {
+                                // The user's own language preference beats Accept-Language
+                                if let Some(language) = claims.lang.as_deref().filter(|l| i18n::is_supported(l)) {
+                                    request_context::set_language(language);
+                                }
                                 // Add user info to request extensions
                                 req.extensions_mut().insert(claims);
-                                return service.call(req).await;
+                                return service.call(req).await.map(ServiceResponse::map_into_left_body);
                             }
                             Err(_) => {
                                 // Invalid token
@@ -74,8 +86,78 @@ where
                 }
             }
 
-            // No valid token found
-            Err(actix_web::error::ErrorUnauthorized("Authentication required"))
+            // No valid token found; rendered here so it carries the request's language and id
+            let error = AppError::from(ErrorCode::AuthenticationRequired);
+            Ok(req.error_response(error).map_into_right_body())
         })
     }
 }
+
+/// Sets up the per-request context: an id for correlating responses with
+/// server logs, and the response language picked from `Accept-Language` so
+/// error messages and other server text come back in the client's language.
+/// `AuthMiddleware` replaces the language with the user's preference when they
+/// have one. The id is taken from an incoming `X-Request-Id` when it is sane,
+/// generated otherwise, and echoed on every response.
+pub struct RequestContextMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestContextMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestContextMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestContextMiddlewareService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RequestContextMiddlewareService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestContextMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let request_id = req
+            .headers()
+            .get(X_REQUEST_ID)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| !id.is_empty() && id.len() <= 64 && id.bytes().all(|b| b.is_ascii_graphic()))
+            .map(str::to_string)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let language = req
+            .headers()
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(i18n::negotiate)
+            .unwrap_or_else(|| "en".to_string());
+
+        Box::pin(request_context::scope(request_id.clone(), language, async move {
+            let mut res = service.call(req).await?;
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                res.headers_mut().insert(HeaderName::from_static(X_REQUEST_ID), value);
+            }
+            Ok(res)
+        }))
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use std::env;
+
+use crate::thumbnail::ThumbnailSize;
+use crate::validation::{Validate, Validator, NAME_MAX, TEXT_MAX};
+
+/// A file attached to a patient, such as a referral letter, scan or signed form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    /// The treatment the file belongs to, if any
+    pub treatment_id: Option<Uuid>,
+    pub file_name: String,
+    /// Detected from the content when uploaded
+    pub content_type: String,
+    pub size_bytes: i64,
+    /// Hex SHA-256 of the content, also its key in the blob store
+    pub sha256: String,
+    pub description: String,
+    pub uploaded_by: Option<String>,
+    /// A thumbnail can be fetched from the thumbnail endpoint
+    pub has_thumbnail: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What a multipart upload told about a new file, once its content is stored
+#[derive(Debug)]
+pub struct AttachmentUpload {
+    pub treatment_id: Option<Uuid>,
+    pub file_name: String,
+    pub content_type: &'static str,
+    pub size_bytes: usize,
+    pub sha256: String,
+    pub description: String,
+    pub has_thumbnail: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttachmentQuery {
+    /// Only files attached to this treatment
+    pub treatment_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttachmentDownloadQuery {
+    /// Always send as a download, even for types a browser could show
+    pub download: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ThumbnailQuery {
+    /// `small` (default) or `large`
+    #[serde(default)]
+    pub size: ThumbnailSize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateAttachmentRequest {
+    pub file_name: Option<String>,
+    pub description: Option<String>,
+    /// Attach to another treatment of the same patient
+    pub treatment_id: Option<Uuid>,
+}
+
+/// Largest accepted upload (`ATTACHMENT_MAX_MB`, 20 MB if unset)
+pub fn max_upload_bytes() -> usize {
+    let megabytes = env::var("ATTACHMENT_MAX_MB")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|mb| *mb > 0)
+        .unwrap_or(20);
+    megabytes * 1024 * 1024
+}
+
+/// The last path component of an uploaded file name, without control characters
+pub fn clean_file_name(name: &str) -> String {
+    let name: String = name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(NAME_MAX)
+        .collect();
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." { "attachment".to_string() } else { name.to_string() }
+}
+
+impl Attachment {
+    pub fn new(patient_id: Uuid, upload: AttachmentUpload, uploaded_by: Option<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            patient_id,
+            treatment_id: upload.treatment_id,
+            file_name: clean_file_name(&upload.file_name),
+            content_type: upload.content_type.to_string(),
+            size_bytes: upload.size_bytes as i64,
+            sha256: upload.sha256,
+            description: upload.description,
+            uploaded_by,
+            has_thumbnail: upload.has_thumbnail,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn update(&mut self, update_req: UpdateAttachmentRequest) {
+        if let Some(file_name) = update_req.file_name {
+            self.file_name = clean_file_name(&file_name);
+        }
+        if let Some(description) = update_req.description {
+            self.description = description;
+        }
+        if let Some(treatment_id) = update_req.treatment_id {
+            self.treatment_id = Some(treatment_id);
+        }
+    }
+}
+
+impl Validate for UpdateAttachmentRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("file_name", &self.file_name).required().max_chars(NAME_MAX);
+        v.text("description", &self.description).max_chars(TEXT_MAX);
+    }
+}
+
+impl Validate for AttachmentUpload {
+    fn validate(&self, v: &mut Validator) {
+        v.text("description", &self.description).max_chars(TEXT_MAX);
+    }
+}
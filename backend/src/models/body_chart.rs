@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use crate::validation::{Validate, Validator, TEXT_MAX};
+
+/// Most annotations one treatment can have
+pub const ANNOTATIONS_MAX: usize = 50;
+
+/// Highest pain or treatment intensity, as on a 0–10 rating scale
+pub const INTENSITY_MAX: i64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodySide {
+    Left,
+    Right,
+    Bilateral,
+}
+
+/// Which diagram of the body an annotation was placed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChartView {
+    Front,
+    Back,
+}
+
+/// A mark on the body chart of a treatment, e.g. where the patient felt pain or was treated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodyAnnotation {
+    /// Code from the fixed list of body regions, e.g. `lumbar_spine`
+    pub region: String,
+    pub side: Option<BodySide>,
+    pub view: ChartView,
+    /// Position on the diagram as a fraction of its width, from the left edge
+    pub x: f64,
+    /// Position on the diagram as a fraction of its height, from the top
+    pub y: f64,
+    /// 0–10
+    pub intensity: Option<i64>,
+    #[serde(default)]
+    pub note: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BodyChartQuery {
+    /// Only annotations on this region
+    pub region: Option<String>,
+    pub side: Option<BodySide>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// One annotation in a patient's history, with the session it was made in
+#[derive(Debug, Serialize)]
+pub struct BodyChartEntry {
+    pub treatment_id: Uuid,
+    pub date: DateTime<Utc>,
+    pub summary: String,
+    /// Name of the region in the request's language
+    pub region_name: String,
+    #[serde(flatten)]
+    pub annotation: BodyAnnotation,
+}
+
+impl Validate for BodyAnnotation {
+    fn validate(&self, v: &mut Validator) {
+        v.text("region", &self.region).required().body_region();
+        v.number("x", self.x).range(0.0, 1.0);
+        v.number("y", self.y).range(0.0, 1.0);
+        v.number("intensity", self.intensity).range(0, INTENSITY_MAX);
+        v.text("note", &self.note).max_chars(TEXT_MAX);
+    }
+}
+
+impl Validate for BodyChartQuery {
+    fn validate(&self, v: &mut Validator) {
+        v.text("region", &self.region).body_region();
+    }
+}
+
+/// Check a treatment's annotations, reported as `annotations[0].region` and so on
+pub fn validate_annotations(v: &mut Validator, annotations: &Option<Vec<BodyAnnotation>>) {
+    let Some(annotations) = annotations else {
+        return;
+    };
+    v.number("annotations", annotations.len()).range(0, ANNOTATIONS_MAX);
+    for (index, annotation) in annotations.iter().enumerate() {
+        v.nested(&format!("annotations[{index}]"), annotation);
+    }
+}
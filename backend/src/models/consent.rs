@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use crate::validation::{Validate, Validator, DOCUMENT_MAX, NAME_MAX, TEXT_MAX};
+
+/// Largest accepted signature image
+pub const SIGNATURE_MAX_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentKind {
+    Treatment,
+    Privacy,
+    Other,
+}
+
+impl ConsentKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConsentKind::Treatment => "treatment",
+            ConsentKind::Privacy => "privacy",
+            ConsentKind::Other => "other",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "treatment" => Some(ConsentKind::Treatment),
+            "privacy" => Some(ConsentKind::Privacy),
+            "other" => Some(ConsentKind::Other),
+            _ => None,
+        }
+    }
+}
+
+/// A consent form patients sign; `title`, `body` and `version` are those of the current text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentForm {
+    pub id: Uuid,
+    pub kind: ConsentKind,
+    pub title: String,
+    /// Template text, in export template markup; `{{patient.name}}` and the like are filled in at signing
+    pub body: String,
+    pub version: i64,
+    /// Patients must have signed the current version before they are treated
+    pub required: bool,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One text a consent form has had
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentFormVersion {
+    pub form_id: Uuid,
+    pub version: i64,
+    pub title: String,
+    pub body: String,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateConsentFormRequest {
+    pub kind: ConsentKind,
+    pub title: String,
+    pub body: String,
+    pub required: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateConsentFormRequest {
+    /// A new title or body makes a new version, which patients have to sign again
+    pub title: Option<String>,
+    pub body: Option<String>,
+    pub required: Option<bool>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConsentFormQuery {
+    pub include_inactive: Option<bool>,
+}
+
+/// A patient's signature on one version of a consent form.
+///
+/// Only `revoked_at` and `revoke_reason` may change once it is recorded; the
+/// signature image is kept with it but loaded on its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentSignature {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub form_id: Uuid,
+    pub form_version: i64,
+    pub kind: ConsentKind,
+    pub title: String,
+    /// The form as the patient signed it, with their details filled in
+    pub rendered_body: String,
+    /// Hex SHA-256 of `rendered_body`
+    pub rendered_sha256: String,
+    pub signer_name: String,
+    /// The contact who signed on the patient's behalf, e.g. a parent of a minor
+    pub signer_contact_id: Option<Uuid>,
+    pub signature_content_type: String,
+    pub signed_at: DateTime<Utc>,
+    /// Address the request came from
+    pub ip_address: Option<String>,
+    /// Client address given in `X-Forwarded-For` by a trusted proxy
+    pub forwarded_for: Option<String>,
+    pub user_agent: Option<String>,
+    pub recorded_by: Option<String>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub revoke_reason: String,
+}
+
+/// What the signing form told, once the signature image has been read
+#[derive(Debug)]
+pub struct ConsentSigning {
+    pub form_id: Uuid,
+    pub signer_name: String,
+    pub signer_contact_id: Option<Uuid>,
+    /// Language of the dates filled into the form; the patient's preferred language if unset
+    pub lang: Option<String>,
+}
+
+/// Where a signature was made from, taken from the request
+#[derive(Debug, Default)]
+pub struct SigningContext {
+    pub ip_address: Option<String>,
+    pub forwarded_for: Option<String>,
+    pub user_agent: Option<String>,
+    pub recorded_by: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevokeConsentRequest {
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConsentDocumentQuery {
+    pub lang: Option<String>,
+}
+
+/// Whether a patient has signed the current version of a required form
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsentStatus {
+    pub form_id: Uuid,
+    pub kind: ConsentKind,
+    pub title: String,
+    pub version: i64,
+    /// The latest version the patient signed and has not revoked
+    pub signed_version: Option<i64>,
+    pub signature_id: Option<Uuid>,
+    pub current: bool,
+}
+
+impl ConsentForm {
+    pub fn new(req: CreateConsentFormRequest) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            kind: req.kind,
+            title: req.title.trim().to_string(),
+            body: req.body,
+            version: 1,
+            required: req.required.unwrap_or(true),
+            active: true,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Apply an update; returns whether the text changed, making a new version
+    pub fn update(&mut self, update_req: UpdateConsentFormRequest) -> bool {
+        let mut changed = false;
+        if let Some(title) = update_req.title.map(|t| t.trim().to_string()) {
+            changed |= title != self.title;
+            self.title = title;
+        }
+        if let Some(body) = update_req.body {
+            changed |= body != self.body;
+            self.body = body;
+        }
+        if changed {
+            self.version += 1;
+        }
+        if let Some(required) = update_req.required {
+            self.required = required;
+        }
+        if let Some(active) = update_req.active {
+            self.active = active;
+        }
+        self.updated_at = Utc::now();
+        changed
+    }
+
+    /// The current text, as kept in the version history
+    pub fn current_version(&self, created_by: Option<String>) -> ConsentFormVersion {
+        ConsentFormVersion {
+            form_id: self.id,
+            version: self.version,
+            title: self.title.clone(),
+            body: self.body.clone(),
+            created_by,
+            created_at: self.updated_at,
+        }
+    }
+
+    /// Where a patient stands with this form, given all their signatures
+    pub fn status(&self, signatures: &[ConsentSignature]) -> ConsentStatus {
+        let signed = signatures
+            .iter()
+            .filter(|s| s.form_id == self.id && s.revoked_at.is_none())
+            .max_by_key(|s| (s.form_version, s.signed_at));
+        ConsentStatus {
+            form_id: self.id,
+            kind: self.kind,
+            title: self.title.clone(),
+            version: self.version,
+            signed_version: signed.map(|s| s.form_version),
+            signature_id: signed.map(|s| s.id),
+            current: signed.is_some_and(|s| s.form_version == self.version),
+        }
+    }
+}
+
+impl ConsentStatus {
+    pub fn warning(&self) -> Option<String> {
+        match self.signed_version {
+            _ if self.current => None,
+            Some(signed) => Some(format!(
+                "Consent \"{}\" was signed for version {signed}; version {} has not been signed",
+                self.title, self.version
+            )),
+            None => Some(format!("Consent \"{}\" has not been signed", self.title)),
+        }
+    }
+}
+
+impl ConsentSignature {
+    pub fn new(
+        patient_id: Uuid,
+        form: &ConsentForm,
+        signing: ConsentSigning,
+        rendered_body: String,
+        rendered_sha256: String,
+        signature_content_type: &str,
+        context: SigningContext,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            patient_id,
+            form_id: form.id,
+            form_version: form.version,
+            kind: form.kind,
+            title: form.title.clone(),
+            rendered_body,
+            rendered_sha256,
+            signer_name: signing.signer_name.trim().to_string(),
+            signer_contact_id: signing.signer_contact_id,
+            signature_content_type: signature_content_type.to_string(),
+            signed_at: Utc::now(),
+            ip_address: context.ip_address,
+            forwarded_for: context.forwarded_for,
+            user_agent: context.user_agent,
+            recorded_by: context.recorded_by,
+            revoked_at: None,
+            revoke_reason: String::new(),
+        }
+    }
+}
+
+impl Validate for CreateConsentFormRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("title", &self.title).required().max_chars(NAME_MAX);
+        v.text("body", &self.body).required().max_chars(DOCUMENT_MAX);
+    }
+}
+
+impl Validate for UpdateConsentFormRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("title", &self.title).required().max_chars(NAME_MAX);
+        v.text("body", &self.body).required().max_chars(DOCUMENT_MAX);
+    }
+}
+
+impl Validate for ConsentSigning {
+    fn validate(&self, v: &mut Validator) {
+        v.text("signer_name", &self.signer_name).required().max_chars(NAME_MAX);
+    }
+}
+
+impl Validate for RevokeConsentRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("reason", &self.reason).max_chars(TEXT_MAX);
+    }
+}
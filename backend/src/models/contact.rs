@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use crate::phone;
+use crate::validation::{Validate, Validator, EMAIL_MAX, NAME_MAX, TEXT_MAX};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContactRelationship {
+    Parent,
+    Guardian,
+    Spouse,
+    Emergency,
+}
+
+impl ContactRelationship {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContactRelationship::Parent => "parent",
+            ContactRelationship::Guardian => "guardian",
+            ContactRelationship::Spouse => "spouse",
+            ContactRelationship::Emergency => "emergency",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "parent" => Some(ContactRelationship::Parent),
+            "guardian" => Some(ContactRelationship::Guardian),
+            "spouse" => Some(ContactRelationship::Spouse),
+            "emergency" => Some(ContactRelationship::Emergency),
+            _ => None,
+        }
+    }
+}
+
+/// Someone related to a patient, e.g. the parent of a minor or an emergency contact
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatientContact {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub name: String,
+    pub relationship: ContactRelationship,
+    pub phone_number: String,
+    pub phone_e164: Option<String>,
+    pub email: Option<String>,
+    pub notes: String,
+    /// Clinical information may be shared with this contact
+    pub may_receive_information: bool,
+    /// Legally entitled to consent on the patient's behalf
+    pub legal_guardian: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateContactRequest {
+    pub name: String,
+    pub relationship: ContactRelationship,
+    pub phone_number: Option<String>,
+    pub email: Option<String>,
+    pub notes: Option<String>,
+    pub may_receive_information: Option<bool>,
+    pub legal_guardian: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateContactRequest {
+    pub name: Option<String>,
+    pub relationship: Option<ContactRelationship>,
+    pub phone_number: Option<String>,
+    pub email: Option<String>,
+    pub notes: Option<String>,
+    pub may_receive_information: Option<bool>,
+    pub legal_guardian: Option<bool>,
+}
+
+impl PatientContact {
+    pub fn new(patient_id: Uuid, req: CreateContactRequest) -> Self {
+        let now = Utc::now();
+        let phone_number = req.phone_number.unwrap_or_default();
+        Self {
+            id: Uuid::new_v4(),
+            patient_id,
+            name: req.name,
+            relationship: req.relationship,
+            phone_e164: phone::normalize(&phone_number),
+            phone_number,
+            email: req.email.filter(|email| !email.trim().is_empty()),
+            notes: req.notes.unwrap_or_default(),
+            may_receive_information: req.may_receive_information.unwrap_or(false),
+            legal_guardian: req.legal_guardian.unwrap_or(false),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn update(&mut self, update_req: UpdateContactRequest) {
+        if let Some(name) = update_req.name {
+            self.name = name;
+        }
+        if let Some(relationship) = update_req.relationship {
+            self.relationship = relationship;
+        }
+        if let Some(phone_number) = update_req.phone_number {
+            self.phone_e164 = phone::normalize(&phone_number);
+            self.phone_number = phone_number;
+        }
+        if let Some(email) = update_req.email {
+            self.email = Some(email).filter(|email| !email.trim().is_empty());
+        }
+        if let Some(notes) = update_req.notes {
+            self.notes = notes;
+        }
+        if let Some(may_receive_information) = update_req.may_receive_information {
+            self.may_receive_information = may_receive_information;
+        }
+        if let Some(legal_guardian) = update_req.legal_guardian {
+            self.legal_guardian = legal_guardian;
+        }
+        self.updated_at = Utc::now();
+    }
+}
+
+impl Validate for CreateContactRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name).required().max_chars(NAME_MAX);
+        v.text("phone_number", &self.phone_number).max_chars(NAME_MAX).phone();
+        v.text("email", &self.email).max_chars(EMAIL_MAX).email();
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+    }
+}
+
+impl Validate for UpdateContactRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name).required().max_chars(NAME_MAX);
+        v.text("phone_number", &self.phone_number).max_chars(NAME_MAX).phone();
+        v.text("email", &self.email).max_chars(EMAIL_MAX).email();
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+    }
+}
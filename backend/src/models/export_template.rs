@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use crate::i18n::Calendar;
+use crate::validation::{Validate, Validator, DOCUMENT_MAX, NAME_MAX, TEXT_MAX};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Rtf,
+    Pdf,
+    Docx,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Rtf => "rtf",
+            ExportFormat::Pdf => "pdf",
+            ExportFormat::Docx => "docx",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "rtf" => Some(ExportFormat::Rtf),
+            "pdf" => Some(ExportFormat::Pdf),
+            "docx" => Some(ExportFormat::Docx),
+            _ => None,
+        }
+    }
+}
+
+/// Admin-edited layout for patient exports, selected by `name` and output `format`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportTemplate {
+    pub id: Uuid,
+    pub name: String,
+    pub format: ExportFormat,
+    pub description: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateExportTemplateRequest {
+    pub name: String,
+    pub format: ExportFormat,
+    pub description: Option<String>,
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateExportTemplateRequest {
+    pub name: Option<String>,
+    pub format: Option<ExportFormat>,
+    pub description: Option<String>,
+    pub body: Option<String>,
+}
+
+/// Render a template body without saving it; without `format` the rendered
+/// markup is returned as text instead of a document
+#[derive(Debug, Deserialize)]
+pub struct ExportTemplatePreviewRequest {
+    pub body: String,
+    pub patient_id: Option<Uuid>,
+    pub lang: Option<String>,
+    pub format: Option<ExportFormat>,
+    pub calendar: Option<Calendar>,
+}
+
+impl ExportTemplate {
+    pub fn new(req: CreateExportTemplateRequest) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            name: req.name.trim().to_string(),
+            format: req.format,
+            description: req.description.unwrap_or_default(),
+            body: req.body,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn update(&mut self, update_req: UpdateExportTemplateRequest) {
+        if let Some(name) = update_req.name {
+            self.name = name.trim().to_string();
+        }
+        if let Some(format) = update_req.format {
+            self.format = format;
+        }
+        if let Some(description) = update_req.description {
+            self.description = description;
+        }
+        if let Some(body) = update_req.body {
+            self.body = body;
+        }
+        self.updated_at = Utc::now();
+    }
+}
+
+impl Validate for CreateExportTemplateRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name).required().max_chars(NAME_MAX);
+        v.text("description", &self.description).max_chars(TEXT_MAX);
+        v.text("body", &self.body).max_chars(DOCUMENT_MAX);
+    }
+}
+
+impl Validate for UpdateExportTemplateRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name).required().max_chars(NAME_MAX);
+        v.text("description", &self.description).max_chars(TEXT_MAX);
+        v.text("body", &self.body).max_chars(DOCUMENT_MAX);
+    }
+}
+
+impl Validate for ExportTemplatePreviewRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("body", &self.body).max_chars(DOCUMENT_MAX);
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::validation::{Validate, Validator, NAME_MAX};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateIssueRequest {
     pub title: String,
@@ -41,3 +43,15 @@ pub struct CreateIssueResponse {
     pub html_url: Option<String>,
     pub issue_number: Option<u32>,
 }
+
+/// GitHub caps issue bodies at 65536 characters; leave room for the metadata footer
+const DESCRIPTION_MAX: usize = 60_000;
+
+impl Validate for CreateIssueRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("title", &self.title).required().max_chars(NAME_MAX);
+        v.text("description", &self.description).max_chars(DESCRIPTION_MAX);
+        v.text("type", &self.issue_type).one_of(&["bug", "feature", "enhancement"]);
+        v.text("priority", &self.priority).one_of(&["low", "medium", "high"]);
+    }
+}
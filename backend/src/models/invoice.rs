@@ -0,0 +1,314 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use crate::validation::{deadline_horizon, event_horizon, Validate, Validator, NAME_MAX, PRICE_MAX_CENTS, TEXT_MAX};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceKind {
+    Invoice,
+    CreditNote,
+}
+
+/// What came of issuing a draft
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueOutcome {
+    /// Issued with this number
+    Issued(i64),
+    /// The invoice was no longer a draft
+    NotDraft,
+    /// A credit note that would credit more than is left on the original invoice
+    ExceedsCredit,
+}
+
+/// What came of recording a payment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentOutcome {
+    /// Recorded with this receipt number
+    Recorded(i64),
+    /// The invoice is not an issued invoice that can take payments
+    NotAllowed,
+    /// More than is due; carries the amount due
+    ExceedsDue(i64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceStatus {
+    Draft,
+    Issued,
+    Paid,
+    Void,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentMethod {
+    Cash,
+    Card,
+    BankTransfer,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invoice {
+    pub id: Uuid,
+    pub kind: InvoiceKind,
+    pub number: Option<i64>, // assigned when the invoice is issued
+    pub patient_id: Uuid,
+    pub status: InvoiceStatus,
+    pub credited_invoice_id: Option<Uuid>,
+    pub currency: String,
+    pub vat_rate: f64,
+    pub subtotal_cents: i64,
+    pub vat_cents: i64,
+    pub total_cents: i64,
+    pub notes: String,
+    pub due_date: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub issued_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceLine {
+    pub id: Uuid,
+    pub invoice_id: Uuid,
+    pub position: i64,
+    pub treatment_id: Option<Uuid>,
+    pub description: String,
+    pub quantity: i64,
+    pub unit_price_cents: i64,
+    pub line_total_cents: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payment {
+    pub id: Uuid,
+    pub invoice_id: Uuid,
+    pub patient_id: Uuid,
+    pub receipt_number: i64,
+    pub amount_cents: i64,
+    pub method: PaymentMethod,
+    pub reference: String,
+    pub paid_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An invoice together with its lines, payments and what is still owed on it
+#[derive(Debug, Serialize)]
+pub struct InvoiceDetail {
+    #[serde(flatten)]
+    pub invoice: Invoice,
+    pub lines: Vec<InvoiceLine>,
+    pub payments: Vec<Payment>,
+    pub credited_cents: i64,
+    pub paid_cents: i64,
+    pub amount_due_cents: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PatientBalance {
+    pub patient_id: Uuid,
+    pub invoiced_cents: i64,
+    pub credited_cents: i64,
+    pub paid_cents: i64,
+    pub balance_cents: i64,
+    pub open_invoices: Vec<InvoiceDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InvoiceLineRequest {
+    pub treatment_id: Option<Uuid>,
+    pub description: Option<String>,
+    pub quantity: Option<i64>,
+    pub unit_price_cents: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInvoiceRequest {
+    pub patient_id: Uuid,
+    pub lines: Vec<InvoiceLineRequest>,
+    pub vat_rate: Option<f64>,
+    pub notes: Option<String>,
+    pub due_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateInvoiceRequest {
+    pub lines: Option<Vec<InvoiceLineRequest>>,
+    pub vat_rate: Option<f64>,
+    pub notes: Option<String>,
+    pub due_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCreditNoteRequest {
+    /// Lines to credit; when omitted the whole invoice is credited
+    pub lines: Option<Vec<InvoiceLineRequest>>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePaymentRequest {
+    pub amount_cents: i64,
+    pub method: PaymentMethod,
+    pub reference: Option<String>,
+    pub paid_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InvoiceQuery {
+    pub patient_id: Option<Uuid>,
+    pub status: Option<InvoiceStatus>,
+    pub kind: Option<InvoiceKind>,
+}
+
+impl InvoiceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvoiceKind::Invoice => "invoice",
+            InvoiceKind::CreditNote => "credit_note",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "invoice" => Some(InvoiceKind::Invoice),
+            "credit_note" => Some(InvoiceKind::CreditNote),
+            _ => None,
+        }
+    }
+}
+
+impl InvoiceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvoiceStatus::Draft => "draft",
+            InvoiceStatus::Issued => "issued",
+            InvoiceStatus::Paid => "paid",
+            InvoiceStatus::Void => "void",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "draft" => Some(InvoiceStatus::Draft),
+            "issued" => Some(InvoiceStatus::Issued),
+            "paid" => Some(InvoiceStatus::Paid),
+            "void" => Some(InvoiceStatus::Void),
+            _ => None,
+        }
+    }
+}
+
+impl PaymentMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentMethod::Cash => "cash",
+            PaymentMethod::Card => "card",
+            PaymentMethod::BankTransfer => "bank_transfer",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "cash" => Some(PaymentMethod::Cash),
+            "card" => Some(PaymentMethod::Card),
+            "bank_transfer" => Some(PaymentMethod::BankTransfer),
+            _ => None,
+        }
+    }
+}
+
+impl InvoiceDetail {
+    /// Detail for a draft, which has no payments or credits and owes nothing yet
+    pub fn draft(invoice: Invoice, lines: Vec<InvoiceLine>) -> Self {
+        Self {
+            invoice,
+            lines,
+            payments: Vec::new(),
+            credited_cents: 0,
+            paid_cents: 0,
+            amount_due_cents: 0,
+        }
+    }
+}
+
+impl Invoice {
+    pub fn new_draft(kind: InvoiceKind, patient_id: Uuid, vat_rate: f64, notes: String, due_date: Option<DateTime<Utc>>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            number: None,
+            patient_id,
+            status: InvoiceStatus::Draft,
+            credited_invoice_id: None,
+            currency: "ILS".to_string(),
+            vat_rate,
+            subtotal_cents: 0,
+            vat_cents: 0,
+            total_cents: 0,
+            notes,
+            due_date,
+            created_at: Utc::now(),
+            issued_at: None,
+        }
+    }
+
+    /// Recalculate subtotal, VAT and total from the given lines
+    pub fn apply_totals(&mut self, lines: &[InvoiceLine]) {
+        self.subtotal_cents = lines.iter().map(|l| l.line_total_cents).sum();
+        self.vat_cents = (self.subtotal_cents as f64 * self.vat_rate).round() as i64;
+        self.total_cents = self.subtotal_cents + self.vat_cents;
+    }
+}
+
+/// Upper bound on a line's quantity, to catch typos
+const QUANTITY_MAX: i64 = 10_000;
+const LINE_DESCRIPTION_MAX: usize = 500;
+
+impl Validate for InvoiceLineRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("description", &self.description).max_chars(LINE_DESCRIPTION_MAX);
+        v.number("quantity", self.quantity).range(1, QUANTITY_MAX);
+        v.number("unit_price_cents", self.unit_price_cents).range(0, PRICE_MAX_CENTS);
+    }
+}
+
+fn validate_lines(v: &mut Validator, lines: &[InvoiceLineRequest]) {
+    for (index, line) in lines.iter().enumerate() {
+        v.nested(&format!("lines[{index}]"), line);
+    }
+}
+
+impl Validate for CreateInvoiceRequest {
+    fn validate(&self, v: &mut Validator) {
+        validate_lines(v, &self.lines);
+        v.number("vat_rate", self.vat_rate).range(0.0, 1.0);
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+        v.date("due_date", self.due_date).plausible(deadline_horizon());
+    }
+}
+
+impl Validate for UpdateInvoiceRequest {
+    fn validate(&self, v: &mut Validator) {
+        validate_lines(v, self.lines.as_deref().unwrap_or_default());
+        v.number("vat_rate", self.vat_rate).range(0.0, 1.0);
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+        v.date("due_date", self.due_date).plausible(deadline_horizon());
+    }
+}
+
+impl Validate for CreateCreditNoteRequest {
+    fn validate(&self, v: &mut Validator) {
+        validate_lines(v, self.lines.as_deref().unwrap_or_default());
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+    }
+}
+
+impl Validate for CreatePaymentRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("reference", &self.reference).max_chars(NAME_MAX);
+        v.date("paid_at", self.paid_at).plausible(event_horizon());
+    }
+}
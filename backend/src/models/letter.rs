@@ -0,0 +1,22 @@
+use serde::Deserialize;
+
+use crate::validation::{Validate, Validator, DOCUMENT_MAX, NAME_MAX, TEXT_MAX};
+
+/// Free-form clinical letter (referral, summary, attendance confirmation, ...)
+#[derive(Debug, Deserialize)]
+pub struct LetterRequest {
+    pub title: String,
+    pub recipient: Option<String>,
+    pub body: String,
+    pub signature: Option<String>,
+    pub lang: Option<String>,
+}
+
+impl Validate for LetterRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("title", &self.title).required().max_chars(NAME_MAX);
+        v.text("recipient", &self.recipient).max_chars(TEXT_MAX);
+        v.text("body", &self.body).required().max_chars(DOCUMENT_MAX);
+        v.text("signature", &self.signature).max_chars(TEXT_MAX);
+    }
+}
@@ -0,0 +1,382 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+
+use crate::icd10;
+use crate::validation::{deadline_horizon, event_horizon, Validate, Validator, NAME_MAX, TEXT_MAX};
+
+/// Most results an ICD-10 search returns
+pub const ICD10_SEARCH_MAX: i64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllergySeverity {
+    Mild,
+    Moderate,
+    Severe,
+    LifeThreatening,
+}
+
+impl AllergySeverity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AllergySeverity::Mild => "mild",
+            AllergySeverity::Moderate => "moderate",
+            AllergySeverity::Severe => "severe",
+            AllergySeverity::LifeThreatening => "life_threatening",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "mild" => Some(AllergySeverity::Mild),
+            "moderate" => Some(AllergySeverity::Moderate),
+            "severe" => Some(AllergySeverity::Severe),
+            "life_threatening" => Some(AllergySeverity::LifeThreatening),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnosis {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    /// Canonical ICD-10 code, e.g. `M54.5`
+    pub icd10_code: Option<String>,
+    pub description: String,
+    pub start_date: Option<NaiveDate>,
+    /// Resolved on this day; `None` while the condition is ongoing
+    pub end_date: Option<NaiveDate>,
+    pub notes: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Allergy {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    /// Substance the patient reacts to, e.g. a drug, latex or a massage oil
+    pub allergen: String,
+    pub reaction: String,
+    pub severity: AllergySeverity,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub notes: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Medication {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub name: String,
+    pub dosage: String,
+    pub frequency: String,
+    pub start_date: Option<NaiveDate>,
+    /// Stopped on this day; `None` while the patient still takes it
+    pub end_date: Option<NaiveDate>,
+    pub notes: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Diagnoses, allergies and medications of one patient; allergies are listed most severe first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MedicalBackground {
+    pub diagnoses: Vec<Diagnosis>,
+    pub allergies: Vec<Allergy>,
+    pub medications: Vec<Medication>,
+}
+
+impl MedicalBackground {
+    /// Only the entries that have not ended by today, as a therapist needs to see them before a session
+    pub fn current(mut self) -> Self {
+        let today = Utc::now().date_naive();
+        let ongoing = |end_date: Option<NaiveDate>| end_date.is_none_or(|end| end >= today);
+        self.diagnoses.retain(|d| ongoing(d.end_date));
+        self.allergies.retain(|a| ongoing(a.end_date));
+        self.medications.retain(|m| ongoing(m.end_date));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnoses.is_empty() && self.allergies.is_empty() && self.medications.is_empty()
+    }
+}
+
+/// A start date after the end date is rejected as `INVALID_DATE_RANGE`
+pub fn is_valid_period(start_date: Option<NaiveDate>, end_date: Option<NaiveDate>) -> bool {
+    match (start_date, end_date) {
+        (Some(start), Some(end)) => start <= end,
+        _ => true,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateDiagnosisRequest {
+    pub icd10_code: Option<String>,
+    /// May be left out for codes in the bundled list, whose title is used instead
+    pub description: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateDiagnosisRequest {
+    /// An empty value removes the code
+    pub icd10_code: Option<String>,
+    pub description: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAllergyRequest {
+    pub allergen: String,
+    pub reaction: Option<String>,
+    pub severity: AllergySeverity,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateAllergyRequest {
+    pub allergen: Option<String>,
+    pub reaction: Option<String>,
+    pub severity: Option<AllergySeverity>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateMedicationRequest {
+    pub name: String,
+    pub dosage: Option<String>,
+    pub frequency: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMedicationRequest {
+    pub name: Option<String>,
+    pub dosage: Option<String>,
+    pub frequency: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Icd10Query {
+    /// Code prefix (`M54`) or words of the title (`back pain`)
+    pub q: Option<String>,
+    pub limit: Option<i64>,
+}
+
+impl Diagnosis {
+    pub fn new(patient_id: Uuid, req: CreateDiagnosisRequest) -> Self {
+        let now = Utc::now();
+        let icd10_code = req.icd10_code.as_deref().and_then(icd10::normalize);
+        let description = req
+            .description
+            .map(|d| d.trim().to_string())
+            .filter(|d| !d.is_empty())
+            .or_else(|| icd10_code.as_deref().and_then(icd10::lookup).map(|entry| entry.title.to_string()))
+            .unwrap_or_default();
+        Self {
+            id: Uuid::new_v4(),
+            patient_id,
+            icd10_code,
+            description,
+            start_date: req.start_date,
+            end_date: req.end_date,
+            notes: req.notes.unwrap_or_default(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn update(&mut self, update_req: UpdateDiagnosisRequest) {
+        if let Some(code) = update_req.icd10_code {
+            self.icd10_code = icd10::normalize(&code);
+        }
+        if let Some(description) = update_req.description {
+            self.description = description;
+        }
+        if let Some(start_date) = update_req.start_date {
+            self.start_date = Some(start_date);
+        }
+        if let Some(end_date) = update_req.end_date {
+            self.end_date = Some(end_date);
+        }
+        if let Some(notes) = update_req.notes {
+            self.notes = notes;
+        }
+        self.updated_at = Utc::now();
+    }
+}
+
+impl Allergy {
+    pub fn new(patient_id: Uuid, req: CreateAllergyRequest) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            patient_id,
+            allergen: req.allergen,
+            reaction: req.reaction.unwrap_or_default(),
+            severity: req.severity,
+            start_date: req.start_date,
+            end_date: req.end_date,
+            notes: req.notes.unwrap_or_default(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn update(&mut self, update_req: UpdateAllergyRequest) {
+        if let Some(allergen) = update_req.allergen {
+            self.allergen = allergen;
+        }
+        if let Some(reaction) = update_req.reaction {
+            self.reaction = reaction;
+        }
+        if let Some(severity) = update_req.severity {
+            self.severity = severity;
+        }
+        if let Some(start_date) = update_req.start_date {
+            self.start_date = Some(start_date);
+        }
+        if let Some(end_date) = update_req.end_date {
+            self.end_date = Some(end_date);
+        }
+        if let Some(notes) = update_req.notes {
+            self.notes = notes;
+        }
+        self.updated_at = Utc::now();
+    }
+}
+
+impl Medication {
+    pub fn new(patient_id: Uuid, req: CreateMedicationRequest) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            patient_id,
+            name: req.name,
+            dosage: req.dosage.unwrap_or_default(),
+            frequency: req.frequency.unwrap_or_default(),
+            start_date: req.start_date,
+            end_date: req.end_date,
+            notes: req.notes.unwrap_or_default(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn update(&mut self, update_req: UpdateMedicationRequest) {
+        if let Some(name) = update_req.name {
+            self.name = name;
+        }
+        if let Some(dosage) = update_req.dosage {
+            self.dosage = dosage;
+        }
+        if let Some(frequency) = update_req.frequency {
+            self.frequency = frequency;
+        }
+        if let Some(start_date) = update_req.start_date {
+            self.start_date = Some(start_date);
+        }
+        if let Some(end_date) = update_req.end_date {
+            self.end_date = Some(end_date);
+        }
+        if let Some(notes) = update_req.notes {
+            self.notes = notes;
+        }
+        self.updated_at = Utc::now();
+    }
+}
+
+/// Start dates may lie up to a year ahead (e.g. a planned medication), end dates further
+fn validate_period(v: &mut Validator, start_date: Option<NaiveDate>, end_date: Option<NaiveDate>) {
+    let day = |date: Option<NaiveDate>| date.map(|date| date.and_time(NaiveTime::MIN).and_utc());
+    v.date("start_date", day(start_date)).plausible(event_horizon());
+    v.date("end_date", day(end_date)).plausible(deadline_horizon());
+}
+
+impl Validate for CreateDiagnosisRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("icd10_code", &self.icd10_code).icd10();
+        if self.icd10_code.as_deref().and_then(icd10::lookup).is_some() {
+            v.text("description", &self.description).max_chars(NAME_MAX);
+        } else {
+            // Without a title to fall back on the description must be given
+            let description = self.description.clone().unwrap_or_default();
+            v.text("description", &description).required().max_chars(NAME_MAX);
+        }
+        validate_period(v, self.start_date, self.end_date);
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+    }
+}
+
+impl Validate for UpdateDiagnosisRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("icd10_code", &self.icd10_code).icd10();
+        v.text("description", &self.description).required().max_chars(NAME_MAX);
+        validate_period(v, self.start_date, self.end_date);
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+    }
+}
+
+impl Validate for CreateAllergyRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("allergen", &self.allergen).required().max_chars(NAME_MAX);
+        v.text("reaction", &self.reaction).max_chars(NAME_MAX);
+        validate_period(v, self.start_date, self.end_date);
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+    }
+}
+
+impl Validate for UpdateAllergyRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("allergen", &self.allergen).required().max_chars(NAME_MAX);
+        v.text("reaction", &self.reaction).max_chars(NAME_MAX);
+        validate_period(v, self.start_date, self.end_date);
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+    }
+}
+
+impl Validate for CreateMedicationRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name).required().max_chars(NAME_MAX);
+        v.text("dosage", &self.dosage).max_chars(NAME_MAX);
+        v.text("frequency", &self.frequency).max_chars(NAME_MAX);
+        validate_period(v, self.start_date, self.end_date);
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+    }
+}
+
+impl Validate for UpdateMedicationRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name).required().max_chars(NAME_MAX);
+        v.text("dosage", &self.dosage).max_chars(NAME_MAX);
+        v.text("frequency", &self.frequency).max_chars(NAME_MAX);
+        validate_period(v, self.start_date, self.end_date);
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+    }
+}
+
+impl Validate for Icd10Query {
+    fn validate(&self, v: &mut Validator) {
+        v.text("q", &self.q).max_chars(NAME_MAX);
+        v.number("limit", self.limit).range(1, ICD10_SEARCH_MAX);
+    }
+}
@@ -1,9 +1,37 @@
 pub mod patient;
+pub mod patient_merge;
+pub mod contact;
+pub mod medical;
+pub mod attachment;
+pub mod questionnaire;
+pub mod consent;
+pub mod treatment_plan;
 pub mod treatment;
+pub mod body_chart;
+pub mod treatment_type;
 pub mod user;
 pub mod github;
+pub mod invoice;
+pub mod letter;
+pub mod package;
+pub mod report;
+pub mod export_template;
 
 pub use patient::*;
+pub use patient_merge::*;
+pub use contact::*;
+pub use medical::*;
+pub use attachment::*;
+pub use questionnaire::*;
+pub use consent::*;
+pub use treatment_plan::*;
 pub use treatment::*;
+pub use body_chart::*;
+pub use treatment_type::*;
 pub use user::*;
 pub use github::*;
+pub use invoice::*;
+pub use letter::*;
+pub use package::*;
+pub use report::*;
+pub use export_template::*;
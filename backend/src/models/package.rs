@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Duration, Utc};
+use std::env;
+
+use crate::validation::{deadline_horizon, event_horizon, Validate, Validator, NAME_MAX, PRICE_MAX_CENTS, TEXT_MAX};
+
+/// Prepaid bundle of sessions; `sessions_used` is maintained by the database as treatments are linked
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionPackage {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub name: String,
+    pub sessions_purchased: i64,
+    pub sessions_used: i64,
+    pub price_cents: i64,
+    pub purchased_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub notes: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageStatus {
+    Active,
+    NearlyExhausted,
+    Exhausted,
+    Expired,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PackageSummary {
+    #[serde(flatten)]
+    pub package: SessionPackage,
+    pub sessions_remaining: i64,
+    pub status: PackageStatus,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePackageRequest {
+    pub name: String,
+    pub sessions_purchased: i64,
+    pub price_cents: Option<i64>,
+    pub purchased_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Sessions already consumed, for packages carried over from another system
+    pub sessions_used: Option<i64>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePackageRequest {
+    pub name: Option<String>,
+    pub sessions_purchased: Option<i64>,
+    pub price_cents: Option<i64>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>,
+}
+
+/// Remaining sessions at or below which a package counts as nearly exhausted (`PACKAGE_LOW_SESSIONS`)
+fn low_sessions_threshold() -> i64 {
+    env::var("PACKAGE_LOW_SESSIONS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(2)
+}
+
+/// Days before expiry from which a package warns (`PACKAGE_EXPIRY_WARNING_DAYS`)
+fn expiry_warning_days() -> i64 {
+    env::var("PACKAGE_EXPIRY_WARNING_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(14)
+}
+
+impl SessionPackage {
+    pub fn new(patient_id: Uuid, req: CreatePackageRequest) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            patient_id,
+            name: req.name,
+            sessions_purchased: req.sessions_purchased,
+            sessions_used: req.sessions_used.unwrap_or(0),
+            price_cents: req.price_cents.unwrap_or(0),
+            purchased_at: req.purchased_at.unwrap_or_else(Utc::now),
+            expires_at: req.expires_at,
+            notes: req.notes.unwrap_or_default(),
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn update(&mut self, update_req: UpdatePackageRequest) {
+        if let Some(name) = update_req.name {
+            self.name = name;
+        }
+        if let Some(sessions_purchased) = update_req.sessions_purchased {
+            self.sessions_purchased = sessions_purchased;
+        }
+        if let Some(price_cents) = update_req.price_cents {
+            self.price_cents = price_cents;
+        }
+        if let Some(expires_at) = update_req.expires_at {
+            self.expires_at = Some(expires_at);
+        }
+        if let Some(notes) = update_req.notes {
+            self.notes = notes;
+        }
+    }
+
+    pub fn sessions_remaining(&self) -> i64 {
+        (self.sessions_purchased - self.sessions_used).max(0)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+
+    pub fn summary(self) -> PackageSummary {
+        let remaining = self.sessions_remaining();
+        let mut warnings = Vec::new();
+
+        let status = if self.is_expired() {
+            warnings.push(format!("Package \"{}\" has expired", self.name));
+            PackageStatus::Expired
+        } else if remaining == 0 {
+            warnings.push(format!("Package \"{}\" has no sessions left", self.name));
+            PackageStatus::Exhausted
+        } else if remaining <= low_sessions_threshold() {
+            warnings.push(format!("Package \"{}\" has only {remaining} session(s) left", self.name));
+            PackageStatus::NearlyExhausted
+        } else {
+            PackageStatus::Active
+        };
+
+        if let Some(expires_at) = self.expires_at {
+            if status != PackageStatus::Expired
+                && expires_at <= Utc::now() + Duration::days(expiry_warning_days())
+            {
+                warnings.push(format!(
+                    "Package \"{}\" expires on {}",
+                    self.name,
+                    expires_at.format("%Y-%m-%d")
+                ));
+            }
+        }
+
+        PackageSummary {
+            package: self,
+            sessions_remaining: remaining,
+            status,
+            warnings,
+        }
+    }
+}
+
+/// Upper bound on sessions in one package, to catch typos
+const SESSIONS_MAX: i64 = 1000;
+
+impl Validate for CreatePackageRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name).required().max_chars(NAME_MAX);
+        v.number("sessions_purchased", self.sessions_purchased).range(1, SESSIONS_MAX);
+        v.number("price_cents", self.price_cents).range(0, PRICE_MAX_CENTS);
+        v.date("purchased_at", self.purchased_at).plausible(event_horizon());
+        v.date("expires_at", self.expires_at).plausible(deadline_horizon());
+        v.number("sessions_used", self.sessions_used).range(0, SESSIONS_MAX);
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+    }
+}
+
+impl Validate for UpdatePackageRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name).required().max_chars(NAME_MAX);
+        v.number("sessions_purchased", self.sessions_purchased).range(1, SESSIONS_MAX);
+        v.number("price_cents", self.price_cents).range(0, PRICE_MAX_CENTS);
+        v.date("expires_at", self.expires_at).plausible(deadline_horizon());
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+    }
+}
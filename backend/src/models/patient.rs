@@ -1,6 +1,39 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+
+use super::{Attachment, ConsentStatus, MedicalBackground, PackageSummary, PatientContact, TreatmentPlanSummary};
+use crate::{i18n, national_id, phone};
+use crate::validation::{event_horizon, Validate, Validator, EMAIL_MAX, NAME_MAX, TEXT_MAX};
+
+const ADDRESS_MAX: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Gender {
+    Female,
+    Male,
+    Other,
+}
+
+impl Gender {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Gender::Female => "female",
+            Gender::Male => "male",
+            Gender::Other => "other",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "female" => Some(Gender::Female),
+            "male" => Some(Gender::Male),
+            "other" => Some(Gender::Other),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Patient {
@@ -8,9 +41,39 @@ pub struct Patient {
     pub name: String,
     pub email: Option<String>,
     pub phone_number: String,
+    /// `phone_number` in E.164 form, used for lookups; `None` if it could not be parsed
+    pub phone_e164: Option<String>,
     pub description: String,
     pub date: DateTime<Utc>,
     pub active: bool,
+    pub date_of_birth: Option<NaiveDate>,
+    pub gender: Option<Gender>,
+    /// Israeli ID number, 9 digits with a valid check digit; unique among patients
+    pub national_id: Option<String>,
+    pub address: Option<String>,
+    /// Locale code for letters and exports, e.g. `he`
+    pub preferred_language: Option<String>,
+    /// How the patient found the clinic, e.g. a referring doctor
+    pub referral_source: Option<String>,
+    /// Set once this record has been merged into another patient but had to be kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merged_into: Option<Uuid>,
+}
+
+/// Patient with the related records shown on the patient detail view
+#[derive(Debug, Serialize)]
+pub struct PatientDetail {
+    #[serde(flatten)]
+    pub patient: Patient,
+    pub packages: Vec<PackageSummary>,
+    pub contacts: Vec<PatientContact>,
+    /// Diagnoses, allergies and medications that have not ended, to check before a session
+    pub medical_background: MedicalBackground,
+    pub attachments: Vec<Attachment>,
+    /// Plans with sessions done against planned and goals achieved
+    pub treatment_plans: Vec<TreatmentPlanSummary>,
+    /// Forms the patient must sign before treatment, and whether the current version is signed
+    pub consents: Vec<ConsentStatus>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -20,6 +83,18 @@ pub struct CreatePatientRequest {
     pub phone_number: String,
     pub description: String,
     pub date: Option<DateTime<Utc>>,
+    pub date_of_birth: Option<NaiveDate>,
+    pub gender: Option<Gender>,
+    pub national_id: Option<String>,
+    pub address: Option<String>,
+    pub preferred_language: Option<String>,
+    pub referral_source: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PatientQuery {
+    /// Phone number in any format, matched on its E.164 form
+    pub phone: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -30,18 +105,33 @@ pub struct UpdatePatientRequest {
     pub description: Option<String>,
     pub date: Option<DateTime<Utc>>,
     pub active: Option<bool>,
+    pub date_of_birth: Option<NaiveDate>,
+    pub gender: Option<Gender>,
+    /// An empty value clears it, as for the other optional text fields below
+    pub national_id: Option<String>,
+    pub address: Option<String>,
+    pub preferred_language: Option<String>,
+    pub referral_source: Option<String>,
 }
 
 impl Patient {
-    pub fn new(name: String, email: Option<String>, phone_number: String, description: String, date: Option<DateTime<Utc>>) -> Self {
+    pub fn new(req: CreatePatientRequest) -> Self {
         Self {
             id: Uuid::new_v4(),
-            name,
-            email,
-            phone_number,
-            description,
-            date: date.unwrap_or_else(Utc::now),
+            name: req.name,
+            email: req.email,
+            phone_e164: phone::normalize(&req.phone_number),
+            phone_number: req.phone_number,
+            description: req.description,
+            date: req.date.unwrap_or_else(Utc::now),
             active: true, // New patients are active by default
+            date_of_birth: req.date_of_birth,
+            gender: req.gender,
+            national_id: req.national_id.as_deref().and_then(national_id::normalize),
+            address: non_blank(req.address),
+            preferred_language: req.preferred_language.as_deref().and_then(language_code),
+            referral_source: non_blank(req.referral_source),
+            merged_into: None,
         }
     }
 
@@ -53,6 +143,7 @@ impl Patient {
             self.email = Some(email);
         }
         if let Some(phone_number) = update_req.phone_number {
+            self.phone_e164 = phone::normalize(&phone_number);
             self.phone_number = phone_number;
         }
         if let Some(description) = update_req.description {
@@ -64,5 +155,102 @@ impl Patient {
         if let Some(active) = update_req.active {
             self.active = active;
         }
+        if let Some(date_of_birth) = update_req.date_of_birth {
+            self.date_of_birth = Some(date_of_birth);
+        }
+        if let Some(gender) = update_req.gender {
+            self.gender = Some(gender);
+        }
+        if let Some(id) = update_req.national_id {
+            self.national_id = national_id::normalize(&id);
+        }
+        if let Some(address) = update_req.address {
+            self.address = non_blank(Some(address));
+        }
+        if let Some(language) = update_req.preferred_language {
+            self.preferred_language = language_code(&language);
+        }
+        if let Some(referral_source) = update_req.referral_source {
+            self.referral_source = non_blank(Some(referral_source));
+        }
+    }
+
+    /// Take over what `duplicate` knows and this record is missing
+    pub fn absorb(&mut self, duplicate: &Patient) {
+        if self.email.as_deref().is_none_or(|email| email.trim().is_empty()) {
+            self.email = duplicate.email.clone();
+        }
+        if self.phone_number.trim().is_empty() {
+            self.phone_number = duplicate.phone_number.clone();
+            self.phone_e164 = duplicate.phone_e164.clone();
+        }
+        if self.description.trim().is_empty() {
+            self.description = duplicate.description.clone();
+        }
+        self.date_of_birth = self.date_of_birth.or(duplicate.date_of_birth);
+        self.gender = self.gender.or(duplicate.gender);
+        self.national_id = self.national_id.take().or_else(|| duplicate.national_id.clone());
+        self.address = self.address.take().or_else(|| duplicate.address.clone());
+        self.preferred_language = self.preferred_language.take().or_else(|| duplicate.preferred_language.clone());
+        self.referral_source = self.referral_source.take().or_else(|| duplicate.referral_source.clone());
+        // The earliest record tells when the patient first came
+        self.date = self.date.min(duplicate.date);
+    }
+}
+
+fn non_blank(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+fn language_code(value: &str) -> Option<String> {
+    Some(value.trim().to_lowercase()).filter(|code| !code.is_empty())
+}
+
+/// Rules for the demographic fields, shared by create and update requests
+fn validate_demographics(
+    v: &mut Validator,
+    date_of_birth: Option<NaiveDate>,
+    id: &Option<String>,
+    address: &Option<String>,
+    preferred_language: &Option<String>,
+    referral_source: &Option<String>,
+) {
+    // Born on or before today
+    let birth = date_of_birth.map(|date| date.and_time(NaiveTime::MIN).and_utc());
+    v.date("date_of_birth", birth).plausible(Duration::zero());
+    v.text("national_id", id).national_id();
+    v.text("address", address).max_chars(ADDRESS_MAX);
+    let languages: Vec<String> = i18n::available_locales().into_iter().map(|locale| locale.code).collect();
+    let languages: Vec<&str> = languages.iter().map(String::as_str).collect();
+    let language = preferred_language.as_deref().and_then(language_code);
+    v.text("preferred_language", &language).one_of(&languages);
+    v.text("referral_source", referral_source).max_chars(NAME_MAX);
+}
+
+impl Validate for CreatePatientRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name).required().max_chars(NAME_MAX);
+        v.text("email", &self.email).max_chars(EMAIL_MAX).email();
+        v.text("phone_number", &self.phone_number).required().max_chars(NAME_MAX).phone();
+        v.text("description", &self.description).max_chars(TEXT_MAX);
+        v.date("date", self.date).plausible(event_horizon());
+        validate_demographics(v, self.date_of_birth, &self.national_id, &self.address, &self.preferred_language, &self.referral_source);
+    }
+}
+
+impl Validate for UpdatePatientRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name).required().max_chars(NAME_MAX);
+        v.text("email", &self.email).max_chars(EMAIL_MAX).email();
+        v.text("phone_number", &self.phone_number).required().max_chars(NAME_MAX).phone();
+        v.text("description", &self.description).max_chars(TEXT_MAX);
+        v.date("date", self.date).plausible(event_horizon());
+        validate_demographics(v, self.date_of_birth, &self.national_id, &self.address, &self.preferred_language, &self.referral_source);
+    }
+}
+
+impl Validate for PatientQuery {
+    fn validate(&self, v: &mut Validator) {
+        v.text("phone", &self.phone).required().phone();
     }
 }
@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use super::Patient;
+use crate::duplicates::MIN_SCORE_FLOOR;
+use crate::validation::{Validate, Validator};
+
+/// Most candidate pairs a duplicate search returns
+pub const DUPLICATES_MAX: i64 = 500;
+
+/// Two records that may describe the same person, with the evidence for it
+#[derive(Debug, Serialize)]
+pub struct DuplicateCandidate {
+    pub patient: Patient,
+    pub duplicate: Patient,
+    /// 0 to 1; pairs with the same name score 0.5, a shared phone or email adds to it
+    pub score: f64,
+    pub name_similarity: f64,
+    pub same_phone: bool,
+    pub same_email: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DuplicateQuery {
+    pub min_score: Option<f64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergePatientRequest {
+    /// Record to fold into the patient in the path
+    pub duplicate_id: Uuid,
+}
+
+/// Audit entry for one merge; `merged_patient` is the duplicate as it was before the merge
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientMerge {
+    pub id: Uuid,
+    pub surviving_patient_id: Uuid,
+    pub merged_patient_id: Uuid,
+    pub merged_patient: Patient,
+    pub treatments_moved: i64,
+    pub packages_moved: i64,
+    pub invoices_moved: i64,
+    /// Rows moved from the other patient tables, by table name
+    pub records_moved: BTreeMap<String, i64>,
+    /// The duplicate is kept, inactive, when it has issued invoices, which cannot change patient
+    pub merged_patient_retained: bool,
+    pub merged_by: Option<String>,
+    pub merged_at: DateTime<Utc>,
+}
+
+impl Validate for DuplicateQuery {
+    fn validate(&self, v: &mut Validator) {
+        v.number("min_score", self.min_score).range(MIN_SCORE_FLOOR, 1.0);
+        v.number("limit", self.limit).range(1, DUPLICATES_MAX);
+    }
+}
+
+impl Validate for MergePatientRequest {
+    fn validate(&self, _v: &mut Validator) {}
+}
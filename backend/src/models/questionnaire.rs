@@ -0,0 +1,522 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use crate::validation::{event_horizon, Validate, Validator, NAME_MAX, TEXT_MAX};
+
+/// Most questions one questionnaire may have
+pub const QUESTIONS_MAX: usize = 100;
+
+/// Most options one choice question may have
+pub const OPTIONS_MAX: usize = 50;
+
+/// Longest question ID or option value
+const KEY_MAX: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestionKind {
+    Text,
+    SingleChoice,
+    MultiChoice,
+    /// A number between `scale_min` and `scale_max`, e.g. a 0–10 pain rating
+    Scale,
+}
+
+/// How the answers to scored questions add up to the questionnaire's score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoringMethod {
+    /// Not scored, e.g. an intake form
+    #[default]
+    None,
+    Sum,
+    Mean,
+    /// Sum as a percentage of the range of the questions answered, as in disability indexes
+    Percent,
+}
+
+impl ScoringMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScoringMethod::None => "none",
+            ScoringMethod::Sum => "sum",
+            ScoringMethod::Mean => "mean",
+            ScoringMethod::Percent => "percent",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "none" => Some(ScoringMethod::None),
+            "sum" => Some(ScoringMethod::Sum),
+            "mean" => Some(ScoringMethod::Mean),
+            "percent" => Some(ScoringMethod::Percent),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestionOption {
+    /// Stored in answers; labels may be reworded later
+    pub value: String,
+    pub label: String,
+    /// Points for choosing this option in a scored question
+    pub score: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Question {
+    /// Key of the answer; assigned when a new question is saved without one
+    #[serde(default)]
+    pub id: String,
+    pub text: String,
+    pub kind: QuestionKind,
+    #[serde(default)]
+    pub required: bool,
+    /// Counts toward the score: the chosen options' scores, or the scale value
+    #[serde(default)]
+    pub scored: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<QuestionOption>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale_min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale_max: Option<f64>,
+    /// What the ends of the scale mean, e.g. "No pain" and "Worst imaginable pain"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale_min_label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale_max_label: Option<String>,
+}
+
+/// A form patients fill in: an intake questionnaire, a pain scale or an outcome measure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Questionnaire {
+    pub id: Uuid,
+    pub name: String,
+    pub description: String,
+    pub questions: Vec<Question>,
+    pub scoring: ScoringMethod,
+    /// For charts: whether a rising score means the patient is improving
+    pub higher_is_better: bool,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateQuestionnaireRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub questions: Vec<Question>,
+    pub scoring: Option<ScoringMethod>,
+    pub higher_is_better: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateQuestionnaireRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Replaces all questions; keep a question's `id` so earlier answers still match it
+    pub questions: Option<Vec<Question>>,
+    pub scoring: Option<ScoringMethod>,
+    pub higher_is_better: Option<bool>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuestionnaireQuery {
+    pub include_inactive: Option<bool>,
+}
+
+/// An answer: text or a chosen option's value, several chosen values, or a scale value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AnswerValue {
+    Number(f64),
+    Text(String),
+    Choices(Vec<String>),
+}
+
+impl AnswerValue {
+    /// A single value as text; numbers are accepted for option values such as `"3"`
+    fn as_text(&self) -> Option<String> {
+        match self {
+            AnswerValue::Text(text) => Some(text.clone()),
+            AnswerValue::Number(number) => Some(number.to_string()),
+            AnswerValue::Choices(_) => None,
+        }
+    }
+
+    /// Chosen values; a single value counts as one choice
+    fn as_choices(&self) -> Vec<String> {
+        match self {
+            AnswerValue::Choices(values) => values.clone(),
+            other => other.as_text().into_iter().collect(),
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            AnswerValue::Number(number) => Some(*number),
+            AnswerValue::Text(text) => text.trim().parse().ok(),
+            AnswerValue::Choices(_) => None,
+        }
+    }
+
+    /// Whether the patient left the question unanswered
+    fn is_blank(&self) -> bool {
+        match self {
+            AnswerValue::Number(_) => false,
+            AnswerValue::Text(text) => text.trim().is_empty(),
+            AnswerValue::Choices(values) => values.is_empty(),
+        }
+    }
+}
+
+/// A patient's answers to a questionnaire at one point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestionnaireResponse {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub questionnaire_id: Uuid,
+    pub questionnaire_name: String,
+    /// The session it was filled in at, if any
+    pub treatment_id: Option<Uuid>,
+    /// Answers by question ID
+    pub answers: BTreeMap<String, AnswerValue>,
+    /// Calculated when the response is recorded, so later edits to the questionnaire do not change it
+    pub score: Option<f64>,
+    pub notes: String,
+    pub completed_at: DateTime<Utc>,
+    pub recorded_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateQuestionnaireResponseRequest {
+    pub questionnaire_id: Uuid,
+    pub treatment_id: Option<Uuid>,
+    pub answers: BTreeMap<String, AnswerValue>,
+    pub notes: Option<String>,
+    /// When the patient filled it in, if not now
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuestionnaireResponseQuery {
+    pub questionnaire_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScoreSeriesQuery {
+    /// Only this questionnaire's scores
+    pub questionnaire_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScorePoint {
+    pub response_id: Uuid,
+    pub completed_at: DateTime<Utc>,
+    pub score: f64,
+}
+
+/// A patient's scores on one questionnaire, oldest first, for charting progress
+#[derive(Debug, Serialize)]
+pub struct ScoreSeries {
+    pub questionnaire_id: Uuid,
+    pub name: String,
+    pub scoring: ScoringMethod,
+    pub higher_is_better: bool,
+    /// Lowest and highest possible score, for the chart's axis
+    pub score_min: Option<f64>,
+    pub score_max: Option<f64>,
+    pub points: Vec<ScorePoint>,
+}
+
+/// A short random ID for a question saved without one
+fn new_question_id() -> String {
+    format!("q{}", &Uuid::new_v4().simple().to_string()[..8])
+}
+
+/// Scores are kept to two decimals
+fn round_score(score: f64) -> f64 {
+    (score * 100.0).round() / 100.0
+}
+
+impl Question {
+    /// Lowest and highest points this question can add to a score
+    fn score_range(&self) -> Option<(f64, f64)> {
+        if !self.scored {
+            return None;
+        }
+        let scores = || self.options.iter().filter_map(|option| option.score);
+        match self.kind {
+            QuestionKind::Text => None,
+            QuestionKind::Scale => Some((self.scale_min?, self.scale_max?)),
+            QuestionKind::SingleChoice => Some((scores().reduce(f64::min)?, scores().reduce(f64::max)?)),
+            QuestionKind::MultiChoice => Some((
+                scores().filter(|score| *score < 0.0).sum(),
+                scores().filter(|score| *score > 0.0).sum(),
+            )),
+        }
+    }
+
+    /// Points for an answer to this question, if it is scored and answered
+    fn score(&self, answer: &AnswerValue) -> Option<f64> {
+        if !self.scored || answer.is_blank() {
+            return None;
+        }
+        let option_score = |value: &str| self.options.iter().find(|option| option.value == value).and_then(|o| o.score);
+        match self.kind {
+            QuestionKind::Text => None,
+            QuestionKind::Scale => answer.as_number(),
+            QuestionKind::SingleChoice => option_score(&answer.as_text()?),
+            QuestionKind::MultiChoice => Some(answer.as_choices().iter().filter_map(|value| option_score(value)).sum()),
+        }
+    }
+
+    /// At least two options, each with its own value
+    pub fn has_distinct_options(&self) -> bool {
+        let values: HashSet<&str> = self.options.iter().map(|option| option.value.as_str()).collect();
+        self.options.len() >= 2 && values.len() == self.options.len()
+    }
+
+    /// Both ends of the scale given, the minimum below the maximum
+    pub fn has_valid_scale(&self) -> bool {
+        matches!((self.scale_min, self.scale_max), (Some(min), Some(max)) if min < max)
+    }
+}
+
+impl Questionnaire {
+    pub fn new(req: CreateQuestionnaireRequest) -> Self {
+        let now = Utc::now();
+        let mut questionnaire = Self {
+            id: Uuid::new_v4(),
+            name: req.name.trim().to_string(),
+            description: req.description.unwrap_or_default(),
+            questions: req.questions,
+            scoring: req.scoring.unwrap_or_default(),
+            higher_is_better: req.higher_is_better.unwrap_or(false),
+            active: true,
+            created_at: now,
+            updated_at: now,
+        };
+        questionnaire.assign_question_ids();
+        questionnaire
+    }
+
+    pub fn update(&mut self, update_req: UpdateQuestionnaireRequest) {
+        if let Some(name) = update_req.name {
+            self.name = name.trim().to_string();
+        }
+        if let Some(description) = update_req.description {
+            self.description = description;
+        }
+        if let Some(questions) = update_req.questions {
+            self.questions = questions;
+            self.assign_question_ids();
+        }
+        if let Some(scoring) = update_req.scoring {
+            self.scoring = scoring;
+        }
+        if let Some(higher_is_better) = update_req.higher_is_better {
+            self.higher_is_better = higher_is_better;
+        }
+        if let Some(active) = update_req.active {
+            self.active = active;
+        }
+        self.updated_at = Utc::now();
+    }
+
+    fn assign_question_ids(&mut self) {
+        for question in &mut self.questions {
+            question.id = question.id.trim().to_string();
+            if question.id.is_empty() {
+                question.id = new_question_id();
+            }
+        }
+    }
+
+    /// The score of a set of answers, or `None` if the questionnaire is not scored or
+    /// no scored question was answered
+    pub fn score(&self, answers: &BTreeMap<String, AnswerValue>) -> Option<f64> {
+        let scored: Vec<(f64, (f64, f64))> = self
+            .questions
+            .iter()
+            .filter_map(|question| {
+                let points = question.score(answers.get(&question.id)?)?;
+                Some((points, question.score_range()?))
+            })
+            .collect();
+        if scored.is_empty() {
+            return None;
+        }
+
+        let total: f64 = scored.iter().map(|(points, _)| points).sum();
+        let score = match self.scoring {
+            ScoringMethod::None => return None,
+            ScoringMethod::Sum => total,
+            ScoringMethod::Mean => total / scored.len() as f64,
+            ScoringMethod::Percent => {
+                let lowest: f64 = scored.iter().map(|(_, (min, _))| min).sum();
+                let highest: f64 = scored.iter().map(|(_, (_, max))| max).sum();
+                if highest <= lowest {
+                    return None;
+                }
+                (total - lowest) / (highest - lowest) * 100.0
+            }
+        };
+        Some(round_score(score))
+    }
+
+    /// Lowest and highest possible score when every scored question is answered
+    pub fn score_range(&self) -> Option<(f64, f64)> {
+        let ranges: Vec<(f64, f64)> = self.questions.iter().filter_map(Question::score_range).collect();
+        if ranges.is_empty() {
+            return None;
+        }
+        let lowest: f64 = ranges.iter().map(|(min, _)| min).sum();
+        let highest: f64 = ranges.iter().map(|(_, max)| max).sum();
+        match self.scoring {
+            ScoringMethod::None => None,
+            ScoringMethod::Sum => Some((lowest, highest)),
+            ScoringMethod::Mean => Some((lowest / ranges.len() as f64, highest / ranges.len() as f64)),
+            ScoringMethod::Percent => Some((0.0, 100.0)),
+        }
+    }
+}
+
+impl QuestionnaireResponse {
+    pub fn new(
+        patient_id: Uuid,
+        questionnaire: &Questionnaire,
+        req: CreateQuestionnaireResponseRequest,
+        recorded_by: Option<String>,
+    ) -> Self {
+        // Blank answers are not kept, so they do not count toward mean scores
+        let answers: BTreeMap<String, AnswerValue> =
+            req.answers.into_iter().filter(|(_, answer)| !answer.is_blank()).collect();
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            patient_id,
+            questionnaire_id: questionnaire.id,
+            questionnaire_name: questionnaire.name.clone(),
+            treatment_id: req.treatment_id,
+            score: questionnaire.score(&answers),
+            answers,
+            notes: req.notes.unwrap_or_default(),
+            completed_at: req.completed_at.unwrap_or(now),
+            recorded_by,
+            created_at: now,
+        }
+    }
+}
+
+/// A set of answers checked against the questions they answer
+pub struct AnswerCheck<'a> {
+    pub questionnaire: &'a Questionnaire,
+    pub answers: &'a BTreeMap<String, AnswerValue>,
+}
+
+impl Validate for AnswerCheck<'_> {
+    fn validate(&self, v: &mut Validator) {
+        let ids: Vec<&str> = self.questionnaire.questions.iter().map(|q| q.id.as_str()).collect();
+        for id in self.answers.keys() {
+            v.text("answers", id).one_of(&ids);
+        }
+
+        for question in &self.questionnaire.questions {
+            let field = format!("answers.{}", question.id);
+            let answer = self.answers.get(&question.id).filter(|answer| !answer.is_blank());
+            if question.required && answer.is_none() {
+                v.text(&field, &String::new()).required();
+                continue;
+            }
+            let Some(answer) = answer else { continue };
+
+            let allowed: Vec<&str> = question.options.iter().map(|option| option.value.as_str()).collect();
+            match question.kind {
+                QuestionKind::Text => {
+                    v.text(&field, &answer.as_text()).max_chars(TEXT_MAX);
+                }
+                QuestionKind::SingleChoice => {
+                    v.text(&field, &answer.as_text()).required().one_of(&allowed);
+                }
+                QuestionKind::MultiChoice => {
+                    for choice in answer.as_choices() {
+                        v.text(&field, &choice).one_of(&allowed);
+                    }
+                }
+                QuestionKind::Scale => match answer.as_number() {
+                    Some(value) => {
+                        let (min, max) = (question.scale_min.unwrap_or(value), question.scale_max.unwrap_or(value));
+                        v.number(&field, value).range(min, max);
+                    }
+                    None => {
+                        v.text(&field, &answer.as_text()).numeric();
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl Validate for QuestionOption {
+    fn validate(&self, v: &mut Validator) {
+        v.text("value", &self.value).required().max_chars(KEY_MAX);
+        v.text("label", &self.label).required().max_chars(NAME_MAX);
+    }
+}
+
+impl Validate for Question {
+    fn validate(&self, v: &mut Validator) {
+        v.text("id", &self.id).max_chars(KEY_MAX);
+        v.text("text", &self.text).required().max_chars(TEXT_MAX);
+        v.number("options", self.options.len()).range(0, OPTIONS_MAX);
+        for (index, option) in self.options.iter().enumerate() {
+            v.nested(&format!("options[{index}]"), option);
+        }
+        v.text("scale_min_label", &self.scale_min_label).max_chars(NAME_MAX);
+        v.text("scale_max_label", &self.scale_max_label).max_chars(NAME_MAX);
+    }
+}
+
+fn validate_questions(v: &mut Validator, questions: &[Question]) {
+    v.number("questions", questions.len()).range(1, QUESTIONS_MAX);
+    for (index, question) in questions.iter().enumerate() {
+        v.nested(&format!("questions[{index}]"), question);
+    }
+}
+
+impl Validate for CreateQuestionnaireRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name).required().max_chars(NAME_MAX);
+        v.text("description", &self.description).max_chars(TEXT_MAX);
+        validate_questions(v, &self.questions);
+    }
+}
+
+impl Validate for UpdateQuestionnaireRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name).required().max_chars(NAME_MAX);
+        v.text("description", &self.description).max_chars(TEXT_MAX);
+        if let Some(questions) = &self.questions {
+            validate_questions(v, questions);
+        }
+    }
+}
+
+impl Validate for CreateQuestionnaireResponseRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+        v.date("completed_at", self.completed_at).plausible(event_horizon());
+    }
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+/// Period that report rows are bucketed into
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportGrouping {
+    Day,
+    Week,
+    #[default]
+    Month,
+    Year,
+}
+
+impl ReportGrouping {
+    /// SQLite `strftime` pattern producing the period label
+    pub fn strftime_format(&self) -> &'static str {
+        match self {
+            ReportGrouping::Day => "%Y-%m-%d",
+            ReportGrouping::Week => "%Y-W%W",
+            ReportGrouping::Month => "%Y-%m",
+            ReportGrouping::Year => "%Y",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// Common parameters for every report; `from` is inclusive, `to` exclusive
+#[derive(Debug, Deserialize)]
+pub struct ReportQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub group_by: Option<ReportGrouping>,
+    pub format: Option<ReportFormat>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionsReportRow {
+    pub period: String,
+    pub sessions: i64,
+    pub patients: i64,
+    pub total_minutes: i64,
+}
+
+/// Issued invoices and credit notes by issue date, payments by payment date
+#[derive(Debug, Default, Serialize)]
+pub struct RevenueReportRow {
+    pub period: String,
+    pub invoiced_cents: i64,
+    pub credited_cents: i64,
+    pub net_invoiced_cents: i64,
+    pub collected_cents: i64,
+}
+
+/// Patients seen in a period, split by whether it was their first period with a treatment
+#[derive(Debug, Serialize)]
+pub struct PatientActivityRow {
+    pub period: String,
+    pub new_patients: i64,
+    pub returning_patients: i64,
+}
+
+/// Sessions logged per user; treatments without a recorded therapist are grouped under `None`
+#[derive(Debug, Serialize)]
+pub struct TherapistWorkloadRow {
+    pub therapist_id: Option<String>,
+    pub therapist_name: Option<String>,
+    pub sessions: i64,
+    pub patients: i64,
+    pub total_minutes: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportSummary {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub total_patients: i64,
+    pub active_patients: i64,
+    pub inactive_patients: i64,
+    pub sessions: i64,
+    pub treated_patients: i64,
+    pub average_sessions_per_patient: f64,
+    pub net_invoiced_cents: i64,
+    pub collected_cents: i64,
+}
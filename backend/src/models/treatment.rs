@@ -2,22 +2,91 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+use super::body_chart::{validate_annotations, BodyAnnotation};
+use crate::validation::{event_horizon, Validate, Validator, MINUTES_PER_DAY, TEXT_MAX};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Treatment {
     pub id: Uuid,
     pub patient_id: Uuid,
     pub summary: String,
     pub date: DateTime<Utc>,
+    pub treatment_type_id: Option<Uuid>,
+    pub duration_minutes: Option<i64>,
+    pub package_id: Option<Uuid>,
+    /// Treatment plan the session was given under
+    pub plan_id: Option<Uuid>,
+    /// User who logged the session
+    pub therapist_id: Option<String>,
+    /// Marks on the body chart, in the order they were placed
+    #[serde(default)]
+    pub annotations: Vec<BodyAnnotation>,
+}
+
+/// What came of saving a treatment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreatmentOutcome {
+    Saved,
+    NotFound,
+    /// The session package it draws from has no sessions left
+    PackageExhausted,
+}
+
+/// Treatment as returned after saving, with any warnings raised on the way
+#[derive(Debug, Serialize)]
+pub struct TreatmentResponse {
+    #[serde(flatten)]
+    pub treatment: Treatment,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateTreatmentRequest {
     pub summary: String,
     pub date: Option<DateTime<Utc>>,
+    pub treatment_type_id: Option<Uuid>,
+    pub duration_minutes: Option<i64>,
+    pub package_id: Option<Uuid>,
+    pub plan_id: Option<Uuid>,
+    pub annotations: Option<Vec<BodyAnnotation>>,
 }
 
+/// Fields left out are kept; the links to a type, package or plan are removed by sending null
 #[derive(Debug, Deserialize)]
 pub struct UpdateTreatmentRequest {
     pub summary: Option<String>,
     pub date: Option<DateTime<Utc>>,
-}
\ No newline at end of file
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub treatment_type_id: Option<Option<Uuid>>,
+    pub duration_minutes: Option<i64>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub package_id: Option<Option<Uuid>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub plan_id: Option<Option<Uuid>>,
+    /// Replaces all annotations
+    pub annotations: Option<Vec<BodyAnnotation>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TreatmentQuery {
+    pub treatment_type_id: Option<Uuid>,
+}
+
+impl Validate for CreateTreatmentRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("summary", &self.summary).max_chars(TEXT_MAX);
+        v.date("date", self.date).plausible(event_horizon());
+        v.number("duration_minutes", self.duration_minutes).range(1, MINUTES_PER_DAY);
+        validate_annotations(v, &self.annotations);
+    }
+}
+
+impl Validate for UpdateTreatmentRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("summary", &self.summary).max_chars(TEXT_MAX);
+        v.date("date", self.date).plausible(event_horizon());
+        v.number("duration_minutes", self.duration_minutes).range(1, MINUTES_PER_DAY);
+        validate_annotations(v, &self.annotations);
+    }
+}
@@ -0,0 +1,358 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+
+use crate::validation::{deadline_horizon, event_horizon, Validate, Validator, NAME_MAX, TEXT_MAX};
+
+/// Upper bound on planned sessions, to catch typos
+const PLANNED_SESSIONS_MAX: i64 = 1000;
+
+/// Most goals one plan can have
+pub const GOALS_MAX: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanStatus {
+    #[default]
+    Active,
+    OnHold,
+    Completed,
+    Discontinued,
+}
+
+impl PlanStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlanStatus::Active => "active",
+            PlanStatus::OnHold => "on_hold",
+            PlanStatus::Completed => "completed",
+            PlanStatus::Discontinued => "discontinued",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "active" => Some(PlanStatus::Active),
+            "on_hold" => Some(PlanStatus::OnHold),
+            "completed" => Some(PlanStatus::Completed),
+            "discontinued" => Some(PlanStatus::Discontinued),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalStatus {
+    #[default]
+    NotStarted,
+    InProgress,
+    Achieved,
+    Discontinued,
+}
+
+impl GoalStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GoalStatus::NotStarted => "not_started",
+            GoalStatus::InProgress => "in_progress",
+            GoalStatus::Achieved => "achieved",
+            GoalStatus::Discontinued => "discontinued",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "not_started" => Some(GoalStatus::NotStarted),
+            "in_progress" => Some(GoalStatus::InProgress),
+            "achieved" => Some(GoalStatus::Achieved),
+            "discontinued" => Some(GoalStatus::Discontinued),
+            _ => None,
+        }
+    }
+}
+
+/// Course of treatment set for a patient; sessions are counted from the treatments linked to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreatmentPlan {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub title: String,
+    pub description: String,
+    pub planned_sessions: Option<i64>,
+    /// e.g. "twice a week"
+    pub frequency: String,
+    pub start_date: Option<NaiveDate>,
+    pub target_end_date: Option<NaiveDate>,
+    pub status: PlanStatus,
+    /// Treatments linked to the plan, maintained by the database
+    pub sessions_done: i64,
+    pub goals: Vec<PlanGoal>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanGoal {
+    pub id: Uuid,
+    pub plan_id: Uuid,
+    pub description: String,
+    pub target_date: Option<NaiveDate>,
+    pub status: GoalStatus,
+    /// Percent of the way to the goal
+    pub progress: i64,
+    /// Set when the goal is marked achieved
+    pub achieved_date: Option<NaiveDate>,
+    pub notes: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Plan with its progress: sessions done against planned and goals achieved
+#[derive(Debug, Serialize)]
+pub struct TreatmentPlanSummary {
+    #[serde(flatten)]
+    pub plan: TreatmentPlan,
+    pub sessions_remaining: Option<i64>,
+    pub goals_achieved: usize,
+    pub goals_total: usize,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTreatmentPlanRequest {
+    pub title: String,
+    pub description: Option<String>,
+    pub planned_sessions: Option<i64>,
+    pub frequency: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub target_end_date: Option<NaiveDate>,
+    pub status: Option<PlanStatus>,
+    pub goals: Option<Vec<CreateGoalRequest>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateTreatmentPlanRequest {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub planned_sessions: Option<i64>,
+    pub frequency: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub target_end_date: Option<NaiveDate>,
+    pub status: Option<PlanStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateGoalRequest {
+    pub description: String,
+    pub target_date: Option<NaiveDate>,
+    pub status: Option<GoalStatus>,
+    pub progress: Option<i64>,
+    pub notes: Option<String>,
+}
+
+/// Marks progress on a goal; achieving it sets the progress to 100%
+#[derive(Debug, Deserialize)]
+pub struct UpdateGoalRequest {
+    pub description: Option<String>,
+    pub target_date: Option<NaiveDate>,
+    pub status: Option<GoalStatus>,
+    pub progress: Option<i64>,
+    /// Defaults to today when the goal is marked achieved
+    pub achieved_date: Option<NaiveDate>,
+    pub notes: Option<String>,
+}
+
+impl TreatmentPlan {
+    pub fn new(patient_id: Uuid, req: CreateTreatmentPlanRequest) -> Self {
+        let now = Utc::now();
+        let id = Uuid::new_v4();
+        Self {
+            id,
+            patient_id,
+            title: req.title,
+            description: req.description.unwrap_or_default(),
+            planned_sessions: req.planned_sessions,
+            frequency: req.frequency.unwrap_or_default(),
+            start_date: req.start_date.or(Some(now.date_naive())),
+            target_end_date: req.target_end_date,
+            status: req.status.unwrap_or_default(),
+            sessions_done: 0,
+            goals: req
+                .goals
+                .unwrap_or_default()
+                .into_iter()
+                .map(|goal| PlanGoal::new(id, goal))
+                .collect(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn update(&mut self, update_req: UpdateTreatmentPlanRequest) {
+        if let Some(title) = update_req.title {
+            self.title = title;
+        }
+        if let Some(description) = update_req.description {
+            self.description = description;
+        }
+        if let Some(planned_sessions) = update_req.planned_sessions {
+            self.planned_sessions = Some(planned_sessions);
+        }
+        if let Some(frequency) = update_req.frequency {
+            self.frequency = frequency;
+        }
+        if let Some(start_date) = update_req.start_date {
+            self.start_date = Some(start_date);
+        }
+        if let Some(target_end_date) = update_req.target_end_date {
+            self.target_end_date = Some(target_end_date);
+        }
+        if let Some(status) = update_req.status {
+            self.status = status;
+        }
+        self.updated_at = Utc::now();
+    }
+
+    pub fn sessions_remaining(&self) -> Option<i64> {
+        self.planned_sessions.map(|planned| (planned - self.sessions_done).max(0))
+    }
+
+    pub fn goals_achieved(&self) -> usize {
+        self.goals.iter().filter(|goal| goal.status == GoalStatus::Achieved).count()
+    }
+
+    pub fn summary(self) -> TreatmentPlanSummary {
+        let mut warnings = Vec::new();
+        match self.status {
+            PlanStatus::Active => {
+                if self.sessions_remaining() == Some(0) {
+                    warnings.push(format!(
+                        "Treatment plan \"{}\" has reached its {} planned session(s)",
+                        self.title,
+                        self.planned_sessions.unwrap_or_default()
+                    ));
+                }
+                if let Some(target_end_date) = self.target_end_date.filter(|date| *date < Utc::now().date_naive()) {
+                    warnings.push(format!(
+                        "Treatment plan \"{}\" was due to end on {}",
+                        self.title,
+                        target_end_date.format("%Y-%m-%d")
+                    ));
+                }
+            }
+            PlanStatus::OnHold => warnings.push(format!("Treatment plan \"{}\" is on hold", self.title)),
+            PlanStatus::Completed => warnings.push(format!("Treatment plan \"{}\" is completed", self.title)),
+            PlanStatus::Discontinued => warnings.push(format!("Treatment plan \"{}\" was discontinued", self.title)),
+        }
+
+        TreatmentPlanSummary {
+            sessions_remaining: self.sessions_remaining(),
+            goals_achieved: self.goals_achieved(),
+            goals_total: self.goals.len(),
+            warnings,
+            plan: self,
+        }
+    }
+}
+
+impl PlanGoal {
+    pub fn new(plan_id: Uuid, req: CreateGoalRequest) -> Self {
+        let now = Utc::now();
+        let mut goal = Self {
+            id: Uuid::new_v4(),
+            plan_id,
+            description: req.description,
+            target_date: req.target_date,
+            status: GoalStatus::NotStarted,
+            progress: req.progress.unwrap_or(0),
+            achieved_date: None,
+            notes: req.notes.unwrap_or_default(),
+            created_at: now,
+            updated_at: now,
+        };
+        goal.set_status(req.status.unwrap_or_default(), None);
+        goal
+    }
+
+    pub fn update(&mut self, update_req: UpdateGoalRequest) {
+        if let Some(description) = update_req.description {
+            self.description = description;
+        }
+        if let Some(target_date) = update_req.target_date {
+            self.target_date = Some(target_date);
+        }
+        if let Some(progress) = update_req.progress {
+            self.progress = progress;
+        }
+        if let Some(notes) = update_req.notes {
+            self.notes = notes;
+        }
+        if update_req.status.is_some() || update_req.achieved_date.is_some() {
+            self.set_status(update_req.status.unwrap_or(self.status), update_req.achieved_date);
+        }
+        self.updated_at = Utc::now();
+    }
+
+    /// Achieved goals are complete and dated; goals that are not lose their achieved date
+    fn set_status(&mut self, status: GoalStatus, achieved_date: Option<NaiveDate>) {
+        self.status = status;
+        if status == GoalStatus::Achieved {
+            self.progress = 100;
+            self.achieved_date = achieved_date.or(self.achieved_date).or(Some(Utc::now().date_naive()));
+        } else {
+            self.achieved_date = None;
+        }
+    }
+}
+
+fn day(date: Option<NaiveDate>) -> Option<DateTime<Utc>> {
+    date.map(|date| date.and_time(NaiveTime::MIN).and_utc())
+}
+
+impl Validate for CreateTreatmentPlanRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("title", &self.title).required().max_chars(NAME_MAX);
+        v.text("description", &self.description).max_chars(TEXT_MAX);
+        v.number("planned_sessions", self.planned_sessions).range(1, PLANNED_SESSIONS_MAX);
+        v.text("frequency", &self.frequency).max_chars(NAME_MAX);
+        v.date("start_date", day(self.start_date)).plausible(event_horizon());
+        v.date("target_end_date", day(self.target_end_date)).plausible(deadline_horizon());
+        let goals = self.goals.as_deref().unwrap_or_default();
+        v.number("goals", goals.len()).range(0, GOALS_MAX);
+        for (index, goal) in goals.iter().enumerate() {
+            v.nested(&format!("goals[{index}]"), goal);
+        }
+    }
+}
+
+impl Validate for UpdateTreatmentPlanRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("title", &self.title).required().max_chars(NAME_MAX);
+        v.text("description", &self.description).max_chars(TEXT_MAX);
+        v.number("planned_sessions", self.planned_sessions).range(1, PLANNED_SESSIONS_MAX);
+        v.text("frequency", &self.frequency).max_chars(NAME_MAX);
+        v.date("start_date", day(self.start_date)).plausible(event_horizon());
+        v.date("target_end_date", day(self.target_end_date)).plausible(deadline_horizon());
+    }
+}
+
+impl Validate for CreateGoalRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("description", &self.description).required().max_chars(NAME_MAX);
+        v.date("target_date", day(self.target_date)).plausible(deadline_horizon());
+        v.number("progress", self.progress).range(0, 100);
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+    }
+}
+
+impl Validate for UpdateGoalRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("description", &self.description).required().max_chars(NAME_MAX);
+        v.date("target_date", day(self.target_date)).plausible(deadline_horizon());
+        v.number("progress", self.progress).range(0, 100);
+        // A day's grace, as a local "today" may already be tomorrow in UTC
+        v.date("achieved_date", day(self.achieved_date)).plausible(Duration::days(1));
+        v.text("notes", &self.notes).max_chars(TEXT_MAX);
+    }
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use crate::validation::{Validate, Validator, MINUTES_PER_DAY, NAME_MAX, PRICE_MAX_CENTS};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreatmentType {
+    pub id: Uuid,
+    pub name_he: String,
+    pub name_en: String,
+    pub default_duration_minutes: i64,
+    pub default_price_cents: i64,
+    pub color: String,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTreatmentTypeRequest {
+    pub name_he: String,
+    pub name_en: String,
+    pub default_duration_minutes: i64,
+    pub default_price_cents: Option<i64>,
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateTreatmentTypeRequest {
+    pub name_he: Option<String>,
+    pub name_en: Option<String>,
+    pub default_duration_minutes: Option<i64>,
+    pub default_price_cents: Option<i64>,
+    pub color: Option<String>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TreatmentTypeQuery {
+    pub include_inactive: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TreatmentTypeBreakdownQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Session counts for one treatment type; `treatment_type_id` is `None` for untyped sessions
+#[derive(Debug, Serialize)]
+pub struct TreatmentTypeBreakdown {
+    pub treatment_type_id: Option<Uuid>,
+    pub name_he: Option<String>,
+    pub name_en: Option<String>,
+    pub color: Option<String>,
+    pub sessions: i64,
+    pub total_minutes: i64,
+}
+
+impl TreatmentType {
+    pub fn new(req: CreateTreatmentTypeRequest) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name_he: req.name_he,
+            name_en: req.name_en,
+            default_duration_minutes: req.default_duration_minutes,
+            default_price_cents: req.default_price_cents.unwrap_or(0),
+            color: req.color.unwrap_or_else(|| "#3b82f6".to_string()),
+            active: true,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Name in the given language; Hebrew has its own, every other language gets the English one
+    pub fn name(&self, language: &str) -> &str {
+        if language == "he" {
+            &self.name_he
+        } else {
+            &self.name_en
+        }
+    }
+
+    pub fn update(&mut self, update_req: UpdateTreatmentTypeRequest) {
+        if let Some(name_he) = update_req.name_he {
+            self.name_he = name_he;
+        }
+        if let Some(name_en) = update_req.name_en {
+            self.name_en = name_en;
+        }
+        if let Some(duration) = update_req.default_duration_minutes {
+            self.default_duration_minutes = duration;
+        }
+        if let Some(price) = update_req.default_price_cents {
+            self.default_price_cents = price;
+        }
+        if let Some(color) = update_req.color {
+            self.color = color;
+        }
+        if let Some(active) = update_req.active {
+            self.active = active;
+        }
+    }
+}
+
+impl Validate for CreateTreatmentTypeRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name_he", &self.name_he).required().max_chars(NAME_MAX);
+        v.text("name_en", &self.name_en).required().max_chars(NAME_MAX);
+        v.number("default_duration_minutes", self.default_duration_minutes).range(1, MINUTES_PER_DAY);
+        v.number("default_price_cents", self.default_price_cents).range(0, PRICE_MAX_CENTS);
+        v.text("color", &self.color).max_chars(32);
+    }
+}
+
+impl Validate for UpdateTreatmentTypeRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name_he", &self.name_he).required().max_chars(NAME_MAX);
+        v.text("name_en", &self.name_en).required().max_chars(NAME_MAX);
+        v.number("default_duration_minutes", self.default_duration_minutes).range(1, MINUTES_PER_DAY);
+        v.number("default_price_cents", self.default_price_cents).range(0, PRICE_MAX_CENTS);
+        v.text("color", &self.color).max_chars(32);
+    }
+}
@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use chrono::{DateTime, Utc};
 
+use crate::validation::{Validate, Validator, EMAIL_MAX, NAME_MAX, PASSWORD_MAX, PASSWORD_MIN};
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct User {
     pub id: String,  // Store UUID as string for SQLite
@@ -9,6 +11,7 @@ pub struct User {
     pub password_hash: String,
     pub name: String,
     pub created_at: DateTime<Utc>,
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -28,14 +31,18 @@ pub struct UserInfo {
     pub id: String,  // Store UUID as string
     pub email: String,
     pub name: String,
+    pub language: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // user id
     pub email: String,
     pub exp: usize,
     pub iat: usize,
+    /// The user's preferred language, when they have set one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -49,6 +56,8 @@ pub struct CreateUserRequest {
 pub struct UpdateUserRequest {
     pub email: Option<String>,
     pub name: Option<String>,
+    /// Preferred language for API messages; an empty string clears it
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -63,6 +72,30 @@ impl From<User> for UserInfo {
             id: user.id,
             email: user.email,
             name: user.name,
+            language: user.language,
         }
     }
 }
+
+impl Validate for CreateUserRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("email", &self.email).required().max_chars(EMAIL_MAX).email();
+        v.text("password", &self.password).min_chars(PASSWORD_MIN).max_chars(PASSWORD_MAX);
+        v.text("name", &self.name).required().max_chars(NAME_MAX);
+    }
+}
+
+impl Validate for UpdateUserRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("email", &self.email).required().max_chars(EMAIL_MAX).email();
+        v.text("name", &self.name).required().max_chars(NAME_MAX);
+        v.text("language", &self.language).max_chars(35);
+    }
+}
+
+impl Validate for ChangePasswordRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("current_password", &self.current_password).required();
+        v.text("new_password", &self.new_password).min_chars(PASSWORD_MIN).max_chars(PASSWORD_MAX);
+    }
+}
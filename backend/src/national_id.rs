@@ -0,0 +1,33 @@
+/// Digits in an Israeli ID number (Teudat Zehut), including the check digit
+const ID_DIGITS: usize = 9;
+
+/// Canonical 9-digit form of an Israeli ID number, or `None` if it is malformed or fails its check digit.
+///
+/// Spaces and dashes are ignored and shorter numbers are padded with leading
+/// zeros, as the Population Authority does.
+pub fn normalize(input: &str) -> Option<String> {
+    let input = input.trim();
+    if !input.chars().all(|c| c.is_ascii_digit() || c == ' ' || c == '-') {
+        return None;
+    }
+    let digits: String = input.chars().filter(char::is_ascii_digit).collect();
+    if digits.is_empty() || digits.len() > ID_DIGITS {
+        return None;
+    }
+    let padded = format!("{digits:0>ID_DIGITS$}");
+    (padded != "000000000" && has_valid_check_digit(&padded)).then_some(padded)
+}
+
+/// Luhn-style check: digits are weighted 1, 2, 1, 2, ..., two-digit products are
+/// reduced to their digit sum, and the total must be a multiple of 10
+fn has_valid_check_digit(id: &str) -> bool {
+    let sum: u32 = id
+        .bytes()
+        .enumerate()
+        .map(|(i, b)| {
+            let product = u32::from(b - b'0') * if i % 2 == 0 { 1 } else { 2 };
+            if product > 9 { product - 9 } else { product }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
@@ -0,0 +1,85 @@
+use std::env;
+
+/// Numbering rules for a country whose numbers may be written in national form
+pub struct Country {
+    /// ISO 3166-1 alpha-2 code, e.g. `IL`
+    pub code: &'static str,
+    pub calling_code: &'static str,
+    /// Prefix dialled before national numbers, dropped in international form
+    pub trunk_prefix: &'static str,
+    /// Digits in a national number without the trunk prefix
+    pub national_digits: (usize, usize),
+}
+
+const COUNTRIES: &[Country] = &[
+    Country { code: "IL", calling_code: "972", trunk_prefix: "0", national_digits: (8, 9) },
+    Country { code: "PS", calling_code: "970", trunk_prefix: "0", national_digits: (8, 9) },
+    Country { code: "US", calling_code: "1", trunk_prefix: "1", national_digits: (10, 10) },
+    Country { code: "CA", calling_code: "1", trunk_prefix: "1", national_digits: (10, 10) },
+    Country { code: "GB", calling_code: "44", trunk_prefix: "0", national_digits: (9, 10) },
+    Country { code: "DE", calling_code: "49", trunk_prefix: "0", national_digits: (6, 11) },
+    Country { code: "FR", calling_code: "33", trunk_prefix: "0", national_digits: (9, 9) },
+    Country { code: "RU", calling_code: "7", trunk_prefix: "8", national_digits: (10, 10) },
+    Country { code: "UA", calling_code: "380", trunk_prefix: "0", national_digits: (9, 9) },
+];
+
+/// E.164 allows at most 15 digits including the country code
+const E164_MAX_DIGITS: usize = 15;
+const E164_MIN_DIGITS: usize = 8;
+
+/// Country assumed for numbers written without a country code (`DEFAULT_PHONE_COUNTRY`, Israel if unset)
+pub fn default_country() -> &'static Country {
+    env::var("DEFAULT_PHONE_COUNTRY")
+        .ok()
+        .and_then(|code| country(code.trim()))
+        .unwrap_or(&COUNTRIES[0])
+}
+
+pub fn country(code: &str) -> Option<&'static Country> {
+    COUNTRIES.iter().find(|c| c.code.eq_ignore_ascii_case(code))
+}
+
+/// E.164 form (`+972501234567`) of a number in any common notation, using the default country
+pub fn normalize(input: &str) -> Option<String> {
+    to_e164(input, default_country())
+}
+
+/// E.164 form of `input`, reading national numbers as belonging to `country`.
+///
+/// Accepts `+` or `00` international prefixes and the separators ` -.()`;
+/// returns `None` for anything that is not a plausible number.
+pub fn to_e164(input: &str, country: &Country) -> Option<String> {
+    let input = input.trim();
+    if input.is_empty() || !input.chars().all(|c| c.is_ascii_digit() || " -.()+".contains(c)) {
+        return None;
+    }
+    let (international, rest) = match input.strip_prefix('+') {
+        Some(rest) => (true, rest),
+        None => (false, input),
+    };
+    if rest.contains('+') {
+        return None;
+    }
+    let digits: String = rest.chars().filter(char::is_ascii_digit).collect();
+
+    let full = if international {
+        digits
+    } else if let Some(number) = digits.strip_prefix("00") {
+        number.to_string()
+    } else {
+        let (min, max) = country.national_digits;
+        let national = match digits.strip_prefix(country.trunk_prefix) {
+            Some(number) if (min..=max).contains(&number.len()) => number,
+            _ if (min..=max).contains(&digits.len()) => digits.as_str(),
+            // Country code written without the `+`, e.g. 972501234567
+            _ => match digits.strip_prefix(country.calling_code) {
+                Some(number) if (min..=max).contains(&number.len()) => number,
+                _ => return None,
+            },
+        };
+        format!("{}{national}", country.calling_code)
+    };
+
+    let plausible = (E164_MIN_DIGITS..=E164_MAX_DIGITS).contains(&full.len()) && !full.starts_with('0');
+    plausible.then(|| format!("+{full}"))
+}
@@ -0,0 +1,34 @@
+use std::cell::RefCell;
+use std::future::Future;
+
+/// Per-request state that code without access to the `HttpRequest` needs,
+/// such as error responses built from `ResponseError`.
+struct RequestContext {
+    id: String,
+    language: RefCell<String>,
+}
+
+tokio::task_local! {
+    static CURRENT: RequestContext;
+}
+
+/// Run a request's handling with its id and negotiated response language
+pub async fn scope<F: Future>(id: String, language: String, future: F) -> F::Output {
+    let context = RequestContext { id, language: RefCell::new(language) };
+    CURRENT.scope(context, future).await
+}
+
+/// Id of the request being handled, as echoed in `X-Request-Id`
+pub fn request_id() -> Option<String> {
+    CURRENT.try_with(|context| context.id.clone()).ok()
+}
+
+/// Response language of the request being handled
+pub fn language() -> Option<String> {
+    CURRENT.try_with(|context| context.language.borrow().clone()).ok()
+}
+
+/// Change the current request's language, e.g. once the user's preference is known
+pub fn set_language(language: &str) {
+    let _ = CURRENT.try_with(|context| *context.language.borrow_mut() = language.to_string());
+}
@@ -1,14 +1,28 @@
 use actix_web::web;
 use crate::handlers::patient_handler;
 use crate::handlers::treatment_handler;
+use crate::handlers::treatment_type_handler;
 use crate::handlers::auth;
 use crate::handlers::github;
 use crate::handlers::export_handler;
-use crate::middleware::AuthMiddleware;
+use crate::handlers::invoice_handler;
+use crate::handlers::document_handler;
+use crate::handlers::package_handler;
+use crate::handlers::contact_handler;
+use crate::handlers::medical_handler;
+use crate::handlers::attachment_handler;
+use crate::handlers::questionnaire_handler;
+use crate::handlers::consent_handler;
+use crate::handlers::treatment_plan_handler;
+use crate::handlers::report_handler;
+use crate::handlers::export_template_handler;
+use crate::handlers::locale_handler;
+use crate::middleware::{AuthMiddleware, RequestContextMiddleware};
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api")
+            .wrap(RequestContextMiddleware)
             .service(
                 web::scope("/auth")
                     .route("/login", web::post().to(auth::login))
@@ -26,12 +40,86 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
                         web::scope("/patients")
                             .route("", web::post().to(patient_handler::create_patient))
                             .route("", web::get().to(patient_handler::get_all_patients))
+                            .route("/export", web::get().to(export_handler::export_patients_bulk))
+                            .route("/duplicates", web::get().to(patient_handler::find_duplicate_patients))
                             .route("/{id}", web::get().to(patient_handler::get_patient_by_id))
                             .route("/{id}", web::put().to(patient_handler::update_patient))
                             .route("/{id}", web::delete().to(patient_handler::delete_patient))
                             .route("/{id}/toggle-status", web::patch().to(patient_handler::toggle_patient_status))
                             .route("/{id}/export", web::get().to(export_handler::export_patient_to_word))
+                            .route("/{id}/balance", web::get().to(invoice_handler::get_patient_balance))
+                            .route("/{id}/letters", web::post().to(document_handler::create_patient_letter))
+                            .route("/{id}/duplicates", web::get().to(patient_handler::get_patient_duplicates))
+                            .route("/{id}/merge", web::post().to(patient_handler::merge_patient))
+                            .route("/{id}/merges", web::get().to(patient_handler::get_patient_merges))
+
+                            // Prepaid session packages
+                            .route("/{id}/packages", web::post().to(package_handler::create_package))
+                            .route("/{id}/packages", web::get().to(package_handler::get_packages_for_patient))
+                            .route("/{patient_id}/packages/{package_id}", web::get().to(package_handler::get_package_by_id))
+                            .route("/{patient_id}/packages/{package_id}", web::put().to(package_handler::update_package))
+                            .route("/{patient_id}/packages/{package_id}", web::delete().to(package_handler::delete_package))
                             
+                            // Relatives, guardians and emergency contacts
+                            .route("/{id}/contacts", web::post().to(contact_handler::create_contact))
+                            .route("/{id}/contacts", web::get().to(contact_handler::get_contacts_for_patient))
+                            .route("/{patient_id}/contacts/{contact_id}", web::get().to(contact_handler::get_contact_by_id))
+                            .route("/{patient_id}/contacts/{contact_id}", web::put().to(contact_handler::update_contact))
+                            .route("/{patient_id}/contacts/{contact_id}", web::delete().to(contact_handler::delete_contact))
+
+                            // Medical background: diagnoses, allergies and medications
+                            .route("/{id}/medical-background", web::get().to(medical_handler::get_medical_background))
+                            .route("/{id}/diagnoses", web::post().to(medical_handler::create_diagnosis))
+                            .route("/{id}/diagnoses", web::get().to(medical_handler::get_diagnoses_for_patient))
+                            .route("/{patient_id}/diagnoses/{diagnosis_id}", web::get().to(medical_handler::get_diagnosis_by_id))
+                            .route("/{patient_id}/diagnoses/{diagnosis_id}", web::put().to(medical_handler::update_diagnosis))
+                            .route("/{patient_id}/diagnoses/{diagnosis_id}", web::delete().to(medical_handler::delete_diagnosis))
+                            .route("/{id}/allergies", web::post().to(medical_handler::create_allergy))
+                            .route("/{id}/allergies", web::get().to(medical_handler::get_allergies_for_patient))
+                            .route("/{patient_id}/allergies/{allergy_id}", web::get().to(medical_handler::get_allergy_by_id))
+                            .route("/{patient_id}/allergies/{allergy_id}", web::put().to(medical_handler::update_allergy))
+                            .route("/{patient_id}/allergies/{allergy_id}", web::delete().to(medical_handler::delete_allergy))
+                            .route("/{id}/medications", web::post().to(medical_handler::create_medication))
+                            .route("/{id}/medications", web::get().to(medical_handler::get_medications_for_patient))
+                            .route("/{patient_id}/medications/{medication_id}", web::get().to(medical_handler::get_medication_by_id))
+                            .route("/{patient_id}/medications/{medication_id}", web::put().to(medical_handler::update_medication))
+                            .route("/{patient_id}/medications/{medication_id}", web::delete().to(medical_handler::delete_medication))
+
+                            // Attached files: referral letters, scans, photos
+                            .route("/{id}/attachments", web::post().to(attachment_handler::upload_attachment))
+                            .route("/{id}/attachments", web::get().to(attachment_handler::get_attachments_for_patient))
+                            .route("/{patient_id}/attachments/{attachment_id}", web::get().to(attachment_handler::get_attachment_by_id))
+                            .route("/{patient_id}/attachments/{attachment_id}", web::put().to(attachment_handler::update_attachment))
+                            .route("/{patient_id}/attachments/{attachment_id}", web::delete().to(attachment_handler::delete_attachment))
+                            .route("/{patient_id}/attachments/{attachment_id}/content", web::get().to(attachment_handler::download_attachment))
+                            .route("/{patient_id}/attachments/{attachment_id}/thumbnail", web::get().to(attachment_handler::get_attachment_thumbnail))
+
+                            // Treatment plans and their goals
+                            .route("/{id}/treatment-plans", web::post().to(treatment_plan_handler::create_treatment_plan))
+                            .route("/{id}/treatment-plans", web::get().to(treatment_plan_handler::get_treatment_plans_for_patient))
+                            .route("/{patient_id}/treatment-plans/{plan_id}", web::get().to(treatment_plan_handler::get_treatment_plan_by_id))
+                            .route("/{patient_id}/treatment-plans/{plan_id}", web::put().to(treatment_plan_handler::update_treatment_plan))
+                            .route("/{patient_id}/treatment-plans/{plan_id}", web::delete().to(treatment_plan_handler::delete_treatment_plan))
+                            .route("/{patient_id}/treatment-plans/{plan_id}/goals", web::post().to(treatment_plan_handler::create_plan_goal))
+                            .route("/{patient_id}/treatment-plans/{plan_id}/goals/{goal_id}", web::put().to(treatment_plan_handler::update_plan_goal))
+                            .route("/{patient_id}/treatment-plans/{plan_id}/goals/{goal_id}", web::delete().to(treatment_plan_handler::delete_plan_goal))
+
+                            // Questionnaire responses and outcome scores
+                            .route("/{id}/questionnaire-responses", web::post().to(questionnaire_handler::create_questionnaire_response))
+                            .route("/{id}/questionnaire-responses", web::get().to(questionnaire_handler::get_questionnaire_responses_for_patient))
+                            .route("/{patient_id}/questionnaire-responses/{response_id}", web::get().to(questionnaire_handler::get_questionnaire_response_by_id))
+                            .route("/{patient_id}/questionnaire-responses/{response_id}", web::delete().to(questionnaire_handler::delete_questionnaire_response))
+                            .route("/{id}/scores", web::get().to(questionnaire_handler::get_patient_scores))
+                            .route("/{id}/body-chart", web::get().to(treatment_handler::get_body_chart))
+
+                            // Signed consent forms
+                            .route("/{id}/consents", web::post().to(consent_handler::sign_consent))
+                            .route("/{id}/consents", web::get().to(consent_handler::get_consents_for_patient))
+                            .route("/{patient_id}/consents/{consent_id}", web::get().to(consent_handler::get_consent_by_id))
+                            .route("/{patient_id}/consents/{consent_id}/signature", web::get().to(consent_handler::get_consent_signature_image))
+                            .route("/{patient_id}/consents/{consent_id}/document", web::get().to(consent_handler::get_consent_document))
+                            .route("/{patient_id}/consents/{consent_id}/revoke", web::post().to(consent_handler::revoke_consent))
+
                             // Treatment routes nested under patients
                             .route("/{id}/treatments", web::post().to(treatment_handler::create_treatment))
                             .route("/{id}/treatments", web::get().to(treatment_handler::get_treatments_for_patient))
@@ -39,10 +127,81 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
                             .route("/{patient_id}/treatments/{treatment_id}", web::put().to(treatment_handler::update_treatment))
                             .route("/{patient_id}/treatments/{treatment_id}", web::delete().to(treatment_handler::delete_treatment))
                     )
+                    .service(
+                        web::scope("/questionnaires")
+                            .route("", web::get().to(questionnaire_handler::get_questionnaires))
+                            .route("", web::post().to(questionnaire_handler::create_questionnaire))
+                            .route("/{id}", web::get().to(questionnaire_handler::get_questionnaire_by_id))
+                            .route("/{id}", web::put().to(questionnaire_handler::update_questionnaire))
+                            .route("/{id}", web::delete().to(questionnaire_handler::delete_questionnaire))
+                    )
+                    .service(
+                        web::scope("/consent-forms")
+                            .route("", web::get().to(consent_handler::get_consent_forms))
+                            .route("", web::post().to(consent_handler::create_consent_form))
+                            .route("/{id}", web::get().to(consent_handler::get_consent_form_by_id))
+                            .route("/{id}", web::put().to(consent_handler::update_consent_form))
+                            .route("/{id}", web::delete().to(consent_handler::delete_consent_form))
+                            .route("/{id}/versions", web::get().to(consent_handler::get_consent_form_versions))
+                            .route("/{id}/versions/{version}", web::get().to(consent_handler::get_consent_form_version))
+                    )
+                    .service(
+                        web::scope("/icd10")
+                            .route("", web::get().to(medical_handler::search_icd10))
+                    )
+                    .service(
+                        web::scope("/body-regions")
+                            .route("", web::get().to(treatment_handler::get_body_regions))
+                    )
                     .service(
                         web::scope("/treatments")
                             .route("", web::get().to(treatment_handler::get_all_treatments))
                     )
+                    .service(
+                        web::scope("/treatment-types")
+                            .route("", web::get().to(treatment_type_handler::get_treatment_types))
+                            .route("", web::post().to(treatment_type_handler::create_treatment_type))
+                            .route("/breakdown", web::get().to(treatment_type_handler::get_treatment_type_breakdown))
+                            .route("/{id}", web::get().to(treatment_type_handler::get_treatment_type_by_id))
+                            .route("/{id}", web::put().to(treatment_type_handler::update_treatment_type))
+                            .route("/{id}", web::delete().to(treatment_type_handler::delete_treatment_type))
+                    )
+                    .service(
+                        web::scope("/invoices")
+                            .route("", web::get().to(invoice_handler::get_invoices))
+                            .route("", web::post().to(invoice_handler::create_invoice))
+                            .route("/{id}", web::get().to(invoice_handler::get_invoice_by_id))
+                            .route("/{id}", web::put().to(invoice_handler::update_invoice))
+                            .route("/{id}", web::delete().to(invoice_handler::delete_invoice))
+                            .route("/{id}/issue", web::post().to(invoice_handler::issue_invoice))
+                            .route("/{id}/void", web::post().to(invoice_handler::void_invoice))
+                            .route("/{id}/credit-notes", web::post().to(invoice_handler::create_credit_note))
+                            .route("/{id}/payments", web::post().to(invoice_handler::create_payment))
+                            .route("/{id}/document", web::get().to(document_handler::invoice_document))
+                    )
+                    .service(
+                        web::scope("/payments")
+                            .route("/{id}/receipt", web::get().to(document_handler::receipt_document))
+                    )
+                    .service(
+                        web::scope("/reports")
+                            .route("/summary", web::get().to(report_handler::summary_report))
+                            .route("/sessions", web::get().to(report_handler::sessions_report))
+                            .route("/revenue", web::get().to(report_handler::revenue_report))
+                            .route("/patients", web::get().to(report_handler::patient_activity_report))
+                            .route("/therapists", web::get().to(report_handler::therapist_workload_report))
+                    )
+                    .service(
+                        web::scope("/export-templates")
+                            .route("", web::post().to(export_template_handler::create_export_template))
+                            .route("", web::get().to(export_template_handler::get_export_templates))
+                            .route("/default", web::get().to(export_handler::get_default_export_template))
+                            .route("/preview", web::post().to(export_handler::preview_export_template))
+                            .route("/{id}", web::get().to(export_template_handler::get_export_template_by_id))
+                            .route("/{id}", web::put().to(export_template_handler::update_export_template))
+                            .route("/{id}", web::delete().to(export_template_handler::delete_export_template))
+                    )
+                    .route("/locales", web::get().to(locale_handler::get_locales))
                     .service(
                         web::scope("/users")
                             .route("", web::get().to(auth::get_users))
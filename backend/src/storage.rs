@@ -0,0 +1,100 @@
+use std::env;
+use std::io;
+use std::path::PathBuf;
+
+use actix_web::web::Bytes;
+use futures_util::future::BoxFuture;
+use futures_util::stream::{self, BoxStream};
+use tokio::io::AsyncReadExt;
+
+/// Content of a stored blob, read in chunks
+pub type BlobStream = BoxStream<'static, io::Result<Bytes>>;
+
+/// Bytes read per chunk when streaming a blob
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Where uploaded file content lives, addressed by key (the SHA-256 of the content).
+///
+/// Metadata stays in the database; a store only keeps bytes, so a
+/// S3-compatible store can be added by implementing these three calls.
+pub trait BlobStore: Send + Sync {
+    /// Store `data` under `key`; a key that already exists is left as it is
+    fn put<'a>(&'a self, key: &'a str, data: Bytes) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Stream the blob, or `None` if there is no blob under `key`
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, anyhow::Result<Option<BlobStream>>>;
+
+    /// Remove the blob; removing a missing key is not an error
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+/// Blobs as files under a root directory, spread over subdirectories by key prefix
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Store under `ATTACHMENTS_DIR`, `./attachments` if unset
+    pub fn from_env() -> Self {
+        Self::new(env::var("ATTACHMENTS_DIR").unwrap_or_else(|_| "./attachments".to_string()))
+    }
+
+    /// `root/ab/cd/abcd...`; keys are lowercase hex, which also keeps them inside the root
+    fn path(&self, key: &str) -> anyhow::Result<PathBuf> {
+        if key.len() < 8 || !key.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) {
+            anyhow::bail!("invalid blob key: {key}");
+        }
+        Ok(self.root.join(&key[..2]).join(&key[2..4]).join(key))
+    }
+}
+
+impl BlobStore for LocalBlobStore {
+    fn put<'a>(&'a self, key: &'a str, data: Bytes) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let path = self.path(key)?;
+            if tokio::fs::try_exists(&path).await? {
+                return Ok(());
+            }
+            let dir = path.parent().expect("blob paths have a parent");
+            tokio::fs::create_dir_all(dir).await?;
+            // Written under a temporary name first, so a blob is never seen half-written
+            let partial = dir.join(format!("{key}.{}.partial", uuid::Uuid::new_v4().simple()));
+            tokio::fs::write(&partial, &data).await?;
+            tokio::fs::rename(&partial, &path).await?;
+            Ok(())
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, anyhow::Result<Option<BlobStream>>> {
+        Box::pin(async move {
+            let file = match tokio::fs::File::open(self.path(key)?).await {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            let chunks = stream::try_unfold(file, |mut file| async move {
+                let mut buffer = vec![0; CHUNK_SIZE];
+                let read = file.read(&mut buffer).await?;
+                if read == 0 {
+                    return Ok(None);
+                }
+                buffer.truncate(read);
+                Ok(Some((Bytes::from(buffer), file)))
+            });
+            Ok(Some(Box::pin(chunks) as BlobStream))
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(self.path(key)?).await {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            }
+        })
+    }
+}
@@ -0,0 +1,168 @@
+use std::io::{Cursor, Read};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, GrayImage, ImageDecoder, ImageReader, RgbImage};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Thumbnails are always JPEG
+pub const CONTENT_TYPE: &str = "image/jpeg";
+
+/// JPEG quality of generated thumbnails
+const JPEG_QUALITY: u8 = 80;
+
+/// Largest image area decoded for a thumbnail, against decompression bombs
+const MAX_DECODE_PIXELS: u64 = 100_000_000;
+
+/// Thumbnail sizes, by the longest side in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThumbnailSize {
+    /// For lists, such as the patient's attachments
+    #[default]
+    Small,
+    /// For previewing a file before opening it
+    Large,
+}
+
+impl ThumbnailSize {
+    pub const ALL: [ThumbnailSize; 2] = [ThumbnailSize::Small, ThumbnailSize::Large];
+
+    pub fn pixels(self) -> u32 {
+        match self {
+            ThumbnailSize::Small => 200,
+            ThumbnailSize::Large => 800,
+        }
+    }
+
+    /// Blob store key of the thumbnail of the content stored under `sha256`
+    pub fn key(self, sha256: &str) -> String {
+        format!("{:x}", Sha256::digest(format!("thumbnail:{}:{sha256}", self.pixels())))
+    }
+}
+
+/// Whether thumbnails are attempted for this type of file
+pub fn is_supported(content_type: &str) -> bool {
+    matches!(content_type, "image/jpeg" | "image/png" | "image/webp" | "application/pdf")
+}
+
+/// A JPEG thumbnail of an image, or of the first page of a scanned PDF.
+///
+/// `None` when the file has no preview: it is of another type, cannot be
+/// decoded, or is a PDF whose first page is not an image. Rendering text and
+/// vector pages would need a full PDF renderer, which the server does not include.
+pub fn generate(content_type: &str, content: &[u8], size: ThumbnailSize) -> Option<Vec<u8>> {
+    let image = match content_type {
+        "image/jpeg" | "image/png" | "image/webp" => decode_image(content)?,
+        "application/pdf" => pdf_page_image(content)?,
+        _ => return None,
+    };
+
+    let max_side = size.pixels();
+    let image = if image.width() > max_side || image.height() > max_side {
+        image.thumbnail(max_side, max_side)
+    } else {
+        image
+    };
+
+    let mut out = Cursor::new(Vec::new());
+    on_white(&image)
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY))
+        .ok()?;
+    Some(out.into_inner())
+}
+
+/// Transparent areas become white, as on a page, since JPEG has no transparency
+fn on_white(image: &DynamicImage) -> RgbImage {
+    if !image.color().has_alpha() {
+        return image.to_rgb8();
+    }
+    let mut rgb = RgbImage::new(image.width(), image.height());
+    for (pixel, source) in rgb.pixels_mut().zip(image.to_rgba8().pixels()) {
+        let alpha = u16::from(source[3]);
+        for channel in 0..3 {
+            pixel[channel] = ((u16::from(source[channel]) * alpha + 255 * (255 - alpha)) / 255) as u8;
+        }
+    }
+    rgb
+}
+
+/// Width and height of an image, read from its header without decoding it
+pub fn image_size(content: &[u8]) -> Option<(u32, u32)> {
+    ImageReader::new(Cursor::new(content)).with_guessed_format().ok()?.into_dimensions().ok()
+}
+
+/// Decode an image upright, as the camera's orientation tag says it was taken
+fn decode_image(content: &[u8]) -> Option<DynamicImage> {
+    let mut decoder = ImageReader::new(Cursor::new(content))
+        .with_guessed_format()
+        .ok()?
+        .into_decoder()
+        .ok()?;
+    let (width, height) = decoder.dimensions();
+    if u64::from(width) * u64::from(height) > MAX_DECODE_PIXELS {
+        return None;
+    }
+    let orientation = decoder.orientation().ok()?;
+    let mut image = DynamicImage::from_decoder(decoder).ok()?;
+    image.apply_orientation(orientation);
+    Some(image)
+}
+
+/// The scanned image making up the first page of a PDF.
+///
+/// A page counts as scanned when its largest image has the page's proportions;
+/// a logo or photo on a text page does not represent the page.
+fn pdf_page_image(content: &[u8]) -> Option<DynamicImage> {
+    let document = lopdf::Document::load_mem(content).ok()?;
+    let page_id = *document.get_pages().values().next()?;
+    let [x0, y0, x1, y1] = media_box(&document, page_id)?;
+    let page_ratio = (x1 - x0).abs() / (y1 - y0).abs();
+
+    let images = document.get_page_images(page_id).ok()?;
+    let scan = images.iter().max_by_key(|image| image.width * image.height)?;
+    if scan.width <= 0 || scan.height <= 0 {
+        return None;
+    }
+    let image_ratio = scan.width as f32 / scan.height as f32;
+    // Scanners often store pages turned a quarter, so either proportion will do
+    let matches_page = |ratio: f32| (ratio - page_ratio).abs() / page_ratio < 0.1;
+    if !matches_page(image_ratio) && !matches_page(1.0 / image_ratio) {
+        return None;
+    }
+
+    let (width, height) = (u32::try_from(scan.width).ok()?, u32::try_from(scan.height).ok()?);
+    if u64::from(width) * u64::from(height) > MAX_DECODE_PIXELS {
+        return None;
+    }
+
+    let filters = scan.filters.clone().unwrap_or_default();
+    match filters.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["DCTDecode"] => decode_image(scan.content),
+        ["FlateDecode"] if scan.bits_per_component == Some(8) => {
+            let expected = u64::from(width) * u64::from(height) * 3;
+            let mut pixels = Vec::new();
+            flate2::read::ZlibDecoder::new(scan.content).take(expected).read_to_end(&mut pixels).ok()?;
+            match scan.color_space.as_deref() {
+                Some("DeviceRGB") => RgbImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8),
+                Some("DeviceGray") => GrayImage::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The page's `MediaBox`, which may be inherited from the page tree
+fn media_box(document: &lopdf::Document, page_id: lopdf::ObjectId) -> Option<[f32; 4]> {
+    let mut node = document.get_dictionary(page_id).ok()?;
+    // Page trees are shallow; a deep one is malformed or loops
+    for _ in 0..32 {
+        if let Ok(media_box) = node.get(b"MediaBox").and_then(|b| b.as_array()) {
+            let corners: Vec<f32> = media_box.iter().filter_map(|v| v.as_float().ok()).collect();
+            return corners.try_into().ok();
+        }
+        node = document.get_dictionary(node.get(b"Parent").ok()?.as_reference().ok()?).ok()?;
+    }
+    None
+}
@@ -0,0 +1,296 @@
+use std::fmt::Display;
+use std::ops::Deref;
+
+use actix_web::dev::Payload;
+use actix_web::{web, FromRequest, HttpRequest};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use futures_util::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+
+use crate::errors::AppError;
+use crate::i18n;
+use crate::{body_region, icd10, national_id, phone};
+
+/// Limits shared by the request models
+pub const NAME_MAX: usize = 200;
+pub const EMAIL_MAX: usize = 254;
+pub const TEXT_MAX: usize = 10_000;
+pub const DOCUMENT_MAX: usize = 100_000;
+pub const PASSWORD_MIN: usize = 6;
+pub const PASSWORD_MAX: usize = 128;
+pub const PRICE_MAX_CENTS: i64 = 100_000_000;
+pub const MINUTES_PER_DAY: i64 = 24 * 60;
+
+/// Request bodies that declare rules for their fields.
+///
+/// Rules are checked by [`ValidatedJson`] before the handler runs; every
+/// failing field is reported at once, with one error per field.
+pub trait Validate {
+    fn validate(&self, v: &mut Validator);
+}
+
+/// One field that broke a rule, e.g. `TOO_LONG` with `max = 200`
+#[derive(Debug, Clone)]
+pub struct FieldError {
+    field: String,
+    rule: &'static str,
+    params: Vec<(&'static str, String)>,
+}
+
+impl FieldError {
+    /// `{"field": ..., "code": ..., "message": ..., "details": {...}}` in the request's language
+    pub fn to_json(&self) -> Value {
+        let key = format!("validation.{}", self.rule.to_ascii_lowercase());
+        let mut error = json!({
+            "field": self.field,
+            "code": self.rule,
+            "message": i18n::request_localizer().format_message(&key, &self.params),
+        });
+        if !self.params.is_empty() {
+            let details: Map<String, Value> = self
+                .params
+                .iter()
+                .map(|(name, value)| (name.to_string(), Value::String(value.clone())))
+                .collect();
+            error["details"] = Value::Object(details);
+        }
+        error
+    }
+}
+
+/// Collects the field errors of one request body
+#[derive(Default)]
+pub struct Validator {
+    prefix: String,
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn check<T: Validate>(value: &T) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::default();
+        value.validate(&mut v);
+        if v.errors.is_empty() { Ok(()) } else { Err(v.errors) }
+    }
+
+    /// Rules for a text field; a missing optional value passes everything but `required`
+    pub fn text<'v, 'a>(&'v mut self, field: &str, value: impl Into<TextValue<'a>>) -> TextRules<'v, 'a> {
+        let value = value.into().0;
+        TextRules { field: self.field(field), validator: self, value, failed: false }
+    }
+
+    pub fn number<'v, N: PartialOrd + Display + Copy>(&'v mut self, field: &str, value: impl Into<Option<N>>) -> NumberRules<'v, N> {
+        NumberRules { field: self.field(field), validator: self, value: value.into() }
+    }
+
+    pub fn date<'v>(&'v mut self, field: &str, value: Option<DateTime<Utc>>) -> DateRules<'v> {
+        DateRules { field: self.field(field), validator: self, value }
+    }
+
+    /// Validate a nested item, naming its fields `field.inner` (e.g. `lines[2].quantity`)
+    pub fn nested<T: Validate>(&mut self, field: &str, value: &T) {
+        let inner = self.field(field);
+        let outer = std::mem::replace(&mut self.prefix, inner);
+        value.validate(self);
+        self.prefix = outer;
+    }
+
+    fn field(&self, name: &str) -> String {
+        if self.prefix.is_empty() { name.to_string() } else { format!("{}.{name}", self.prefix) }
+    }
+
+    fn fail(&mut self, field: &str, rule: &'static str, params: Vec<(&'static str, String)>) {
+        self.errors.push(FieldError { field: field.to_string(), rule, params });
+    }
+}
+
+/// Borrowed text from a required (`String`) or optional (`Option<String>`) field
+pub struct TextValue<'a>(Option<&'a str>);
+
+impl<'a> From<&'a String> for TextValue<'a> {
+    fn from(value: &'a String) -> Self {
+        TextValue(Some(value))
+    }
+}
+
+impl<'a> From<&'a Option<String>> for TextValue<'a> {
+    fn from(value: &'a Option<String>) -> Self {
+        TextValue(value.as_deref())
+    }
+}
+
+pub struct TextRules<'v, 'a> {
+    validator: &'v mut Validator,
+    field: String,
+    value: Option<&'a str>,
+    failed: bool,
+}
+
+impl TextRules<'_, '_> {
+    fn rule(mut self, rule: &'static str, params: Vec<(&'static str, String)>, ok: impl FnOnce(&str) -> bool) -> Self {
+        if let (false, Some(value)) = (self.failed, self.value) {
+            if !ok(value) {
+                self.validator.fail(&self.field, rule, params);
+                self.failed = true;
+            }
+        }
+        self
+    }
+
+    /// Present and not blank; on update requests only a given value must be non-blank
+    pub fn required(self) -> Self {
+        self.rule("REQUIRED", Vec::new(), |value| !value.trim().is_empty())
+    }
+
+    pub fn max_chars(self, max: usize) -> Self {
+        self.rule("TOO_LONG", vec![("max", max.to_string())], |value| value.chars().count() <= max)
+    }
+
+    pub fn min_chars(self, min: usize) -> Self {
+        self.rule("TOO_SHORT", vec![("min", min.to_string())], |value| value.chars().count() >= min)
+    }
+
+    /// Blank values count as "no email"; use `required` as well when one is needed
+    pub fn email(self) -> Self {
+        self.rule("INVALID_EMAIL", Vec::new(), |value| value.trim().is_empty() || is_email(value.trim()))
+    }
+
+    /// A number that can be stored in E.164 form; national numbers are read for `DEFAULT_PHONE_COUNTRY`
+    pub fn phone(self) -> Self {
+        self.rule("INVALID_PHONE", Vec::new(), |value| value.trim().is_empty() || phone::normalize(value).is_some())
+    }
+
+    /// Israeli ID number with a valid check digit; blank values pass
+    pub fn national_id(self) -> Self {
+        self.rule("INVALID_NATIONAL_ID", Vec::new(), |value| value.trim().is_empty() || national_id::normalize(value).is_some())
+    }
+
+    /// Well-formed ICD-10 code; codes outside the bundled list are accepted. Blank values pass
+    pub fn icd10(self) -> Self {
+        self.rule("INVALID_ICD10_CODE", Vec::new(), |value| value.trim().is_empty() || icd10::normalize(value).is_some())
+    }
+
+    /// A code from the fixed list of body regions
+    pub fn body_region(self) -> Self {
+        self.rule("UNKNOWN_BODY_REGION", Vec::new(), |value| body_region::lookup(value).is_some())
+    }
+
+    /// A number written as text, e.g. a rating entered in a form
+    pub fn numeric(self) -> Self {
+        self.rule("NOT_A_NUMBER", Vec::new(), |value| value.trim().parse::<f64>().is_ok_and(f64::is_finite))
+    }
+
+    pub fn one_of(self, allowed: &[&str]) -> Self {
+        self.rule("NOT_ALLOWED", vec![("allowed", allowed.join(", "))], |value| allowed.contains(&value))
+    }
+}
+
+pub struct NumberRules<'v, N> {
+    validator: &'v mut Validator,
+    field: String,
+    value: Option<N>,
+}
+
+impl<N: PartialOrd + Display + Copy> NumberRules<'_, N> {
+    pub fn range(self, min: N, max: N) -> Self {
+        if let Some(value) = self.value {
+            if value < min || value > max {
+                let params = vec![("min", min.to_string()), ("max", max.to_string())];
+                self.validator.fail(&self.field, "OUT_OF_RANGE", params);
+            }
+        }
+        self
+    }
+}
+
+pub struct DateRules<'v> {
+    validator: &'v mut Validator,
+    field: String,
+    value: Option<DateTime<Utc>>,
+}
+
+impl DateRules<'_> {
+    /// Between 1900 and `ahead` from now, which catches typos in the year
+    pub fn plausible(self, ahead: Duration) -> Self {
+        if let Some(value) = self.value {
+            let earliest = Utc.with_ymd_and_hms(1900, 1, 1, 0, 0, 0).unwrap();
+            let latest = Utc::now() + ahead;
+            if value < earliest {
+                let params = vec![("min", earliest.format("%Y-%m-%d").to_string())];
+                self.validator.fail(&self.field, "DATE_TOO_EARLY", params);
+            } else if value > latest {
+                let params = vec![("max", latest.format("%Y-%m-%d").to_string())];
+                self.validator.fail(&self.field, "DATE_TOO_LATE", params);
+            }
+        }
+        self
+    }
+}
+
+/// Dates of things that happen, such as sessions and payments, may be booked up to a year ahead
+pub fn event_horizon() -> Duration {
+    Duration::days(366)
+}
+
+/// Due and expiry dates may lie further ahead
+pub fn deadline_horizon() -> Duration {
+    Duration::days(10 * 366)
+}
+
+/// `local@domain.tld` with a plausible domain; deliberately simpler than RFC 5322
+pub fn is_email(value: &str) -> bool {
+    let Some((local, domain)) = value.rsplit_once('@') else {
+        return false;
+    };
+    let local_ok = !local.is_empty()
+        && local.len() <= 64
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local.chars().all(|c| c.is_alphanumeric() || "!#$%&'*+-/=?^_`{|}~.".contains(c));
+    let labels: Vec<&str> = domain.split('.').collect();
+    let domain_ok = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        })
+        && labels.last().is_some_and(|tld| tld.chars().count() >= 2 && tld.chars().all(char::is_alphabetic));
+    local_ok && domain_ok
+}
+
+/// JSON body that has passed its [`Validate`] rules.
+///
+/// Use in place of `web::Json`: malformed bodies are rejected with
+/// `INVALID_REQUEST` and invalid ones with `VALIDATION_FAILED`, listing each
+/// failing field, before the handler runs.
+pub struct ValidatedJson<T>(pub T);
+
+impl<T> ValidatedJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ValidatedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + Validate + 'static> FromRequest for ValidatedJson<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = web::Json::<T>::from_request(req, payload);
+        Box::pin(async move {
+            let value = json.await?.into_inner();
+            Validator::check(&value).map_err(AppError::Validation)?;
+            Ok(ValidatedJson(value))
+        })
+    }
+}